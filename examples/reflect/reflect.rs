@@ -3,9 +3,8 @@ use clap::{App, AppSettings, Arg};
 use std::sync::Arc;
 use tokio::time::Duration;
 
-use interceptor::registry::Registry;
 use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
-use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::interceptor_registry::{register_default_interceptors, Registry};
 use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_VP8};
 use webrtc::api::APIBuilder;
 use webrtc::media::rtp::rtp_codec::{RTPCodecCapability, RTPCodecParameters, RTPCodecType};
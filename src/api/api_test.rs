@@ -24,3 +24,68 @@ fn test_new_api() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_api_certificate_rotation() -> Result<()> {
+    let api = APIBuilder::new()
+        .with_certificate_lifetime(Duration::from_secs(3600), Duration::from_secs(60))
+        .build();
+
+    let rotated = Arc::new(Mutex::new(vec![]));
+    let rotated2 = Arc::clone(&rotated);
+    api.on_certificate_rotated(Box::new(move |certificate: Certificate| {
+        let rotated3 = Arc::clone(&rotated2);
+        Box::pin(async move {
+            let mut rotated = rotated3.lock().await;
+            rotated.push(certificate.pem().unwrap());
+        })
+    }))
+    .await;
+
+    // the certificate is generated once and then cached
+    let certificate = api.certificate().await?;
+    assert!(certificate == api.certificate().await?);
+    assert!(certificate.expires() > SystemTime::now() + Duration::from_secs(3500));
+    assert!(certificate.expires() <= SystemTime::now() + Duration::from_secs(3600));
+    {
+        let rotated = rotated.lock().await;
+        assert_eq!(vec![certificate.pem()?], *rotated);
+    }
+
+    // a certificate expiring within the renewal period is rotated
+    let api = APIBuilder::new()
+        .with_certificate(certificate.clone())
+        .with_certificate_lifetime(Duration::from_secs(3600), Duration::from_secs(7200))
+        .build();
+    let rotated_certificate = api.certificate().await?;
+    assert!(certificate != rotated_certificate);
+
+    // a persisted certificate is used while it is valid
+    let api = APIBuilder::new()
+        .with_certificate(Certificate::from_pem_with_key(&certificate.pem()?)?)
+        .with_certificate_lifetime(Duration::from_secs(3600), Duration::from_secs(60))
+        .build();
+    assert!(certificate == api.certificate().await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_new_peer_connection_expired_certificate() -> Result<()> {
+    let mut params = CertificateParams::new(vec!["expired".to_owned()]);
+    params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+    params.not_before = chrono::Utc::now() - chrono::Duration::days(2);
+    params.not_after = chrono::Utc::now() - chrono::Duration::days(1);
+    let expired = Certificate::from_params(params)?;
+
+    let api = APIBuilder::new().build();
+    let result = api
+        .new_peer_connection(Configuration {
+            certificates: vec![expired],
+            ..Default::default()
+        })
+        .await;
+    assert!(Error::ErrCertificateExpired.equal(&result.err().unwrap()));
+
+    Ok(())
+}
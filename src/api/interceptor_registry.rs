@@ -1,50 +1,172 @@
 use crate::api::media_engine::MediaEngine;
+use crate::media::interceptor::gcc::send_side_bwe::SendSideBWE;
+use crate::media::interceptor::nack::generator_interceptor::GeneratorInterceptor;
+use crate::media::interceptor::nack::responder_interceptor::ResponderInterceptor;
+use crate::media::interceptor::report::receiver_interceptor::ReceiverInterceptor;
+use crate::media::interceptor::report::sender_interceptor::SenderInterceptor;
+use crate::media::interceptor::twcc;
+use crate::media::interceptor::twcc::header_extension_interceptor::HeaderExtensionInterceptor;
+use crate::media::interceptor::InterceptorBuilder;
+use crate::media::rtp::rtp_codec::{RTPCodecType, RTPHeaderExtensionCapability};
+use crate::media::rtp::RTCPFeedback;
+
 use anyhow::Result;
-use interceptor::registry::Registry;
+use interceptor::chain::Chain;
+use interceptor::noop::NoOp;
+use interceptor::Interceptor;
+use std::sync::Arc;
+
+/// Registry is a collector for interceptors. It builds a chain of them for each
+/// PeerConnection of an API, since the state of an interceptor, such as the RTCP writer
+/// it sends with, belongs to a single PeerConnection.
+#[derive(Default)]
+pub struct Registry {
+    builders: Vec<Box<dyn InterceptorBuilder + Send + Sync>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// with_interceptor adds a new Interceptor to the registry. The same interceptor is
+    /// used by every PeerConnection, see with_interceptor_builder to get one per PeerConnection.
+    pub fn with_interceptor(self, icpr: Arc<dyn Interceptor + Send + Sync>) -> Self {
+        self.with_interceptor_builder(SharedInterceptor(icpr))
+    }
+
+    /// with_interceptor_builder adds a new Interceptor to the registry, built anew for
+    /// each PeerConnection.
+    pub fn with_interceptor_builder<B>(mut self, builder: B) -> Self
+    where
+        B: InterceptorBuilder + Send + Sync + 'static,
+    {
+        self.builders.push(Box::new(builder));
+        self
+    }
+
+    /// build constructs the interceptors of a PeerConnection as a single Interceptor
+    pub fn build(&self) -> Arc<dyn Interceptor + Send + Sync> {
+        if self.builders.is_empty() {
+            return Arc::new(NoOp {});
+        }
+
+        Arc::new(Chain::new(
+            self.builders
+                .iter()
+                .map(|builder| builder.build())
+                .collect(),
+        ))
+    }
+}
+
+/// SharedInterceptor builds the same interceptor for every PeerConnection
+struct SharedInterceptor(Arc<dyn Interceptor + Send + Sync>);
+
+impl InterceptorBuilder for SharedInterceptor {
+    fn build(&self) -> Arc<dyn Interceptor + Send + Sync> {
+        Arc::clone(&self.0)
+    }
+}
 
 /// register_default_interceptors will register some useful interceptors.
 /// If you want to customize which interceptors are loaded, you should copy the
 /// code from this method and remove unwanted interceptors.
 pub fn register_default_interceptors(
-    registry: Registry,
-    _media_engine: &mut MediaEngine,
+    mut registry: Registry,
+    media_engine: &mut MediaEngine,
 ) -> Result<Registry> {
+    registry = configure_nack(registry, media_engine)?;
+
+    registry = configure_rtcp_reports(registry)?;
+
     Ok(registry)
 }
 
-/*TODO:
-// ConfigureRTCPReports will setup everything necessary for generating Sender and Receiver Reports
-func ConfigureRTCPReports(interceptorRegistry *interceptor.Registry) error {
-    reciver, err := report.NewReceiverInterceptor()
-    if err != nil {
-        return err
-    }
+/// configure_rtcp_reports will setup everything necessary for generating Sender and Receiver Reports
+pub fn configure_rtcp_reports(registry: Registry) -> Result<Registry> {
+    Ok(registry
+        .with_interceptor_builder(|| Arc::new(ReceiverInterceptor::new()))
+        .with_interceptor_builder(|| Arc::new(SenderInterceptor::new())))
+}
 
-    sender, err := report.NewSenderInterceptor()
-    if err != nil {
-        return err
-    }
+/// configure_nack will setup everything necessary for handling generating/responding to nack messages.
+/// Retransmissions only happen while the application reads the RTCP of its RTPSenders, since the
+/// incoming nack messages are processed on the way to RTPSender::read_rtcp.
+pub fn configure_nack(registry: Registry, media_engine: &mut MediaEngine) -> Result<Registry> {
+    media_engine.register_feedback(
+        RTCPFeedback {
+            typ: "nack".to_owned(),
+            parameter: "".to_owned(),
+        },
+        RTPCodecType::Video,
+    );
+    media_engine.register_feedback(
+        RTCPFeedback {
+            typ: "nack".to_owned(),
+            parameter: "pli".to_owned(),
+        },
+        RTPCodecType::Video,
+    );
 
-    interceptorRegistry.Add(reciver)
-    interceptorRegistry.Add(sender)
-    return nil
+    Ok(registry
+        .with_interceptor_builder(|| Arc::new(ResponderInterceptor::new()))
+        .with_interceptor_builder(|| Arc::new(GeneratorInterceptor::new())))
 }
 
-// ConfigureNack will setup everything necessary for handling generating/responding to nack messages.
-func ConfigureNack(mediaEngine *MediaEngine, interceptorRegistry *interceptor.Registry) error {
-    generator, err := nack.NewGeneratorInterceptor()
-    if err != nil {
-        return err
-    }
+/// configure_twcc will setup everything necessary for generating transport-wide congestion
+/// control feedback about the packets received with a transport-wide sequence number.
+pub async fn configure_twcc(
+    registry: Registry,
+    media_engine: &mut MediaEngine,
+) -> Result<Registry> {
+    register_twcc(media_engine).await?;
 
-    responder, err := nack.NewResponderInterceptor()
-    if err != nil {
-        return err
+    Ok(registry.with_interceptor_builder(|| {
+        Arc::new(twcc::generator_interceptor::GeneratorInterceptor::new())
+    }))
+}
+
+/// configure_congestion_control will setup everything necessary for estimating the bandwidth
+/// available to the outgoing streams, from the transport-wide congestion control feedback of the
+/// remote. The estimate is surfaced through RTPSender::on_target_bitrate_change.
+///
+/// It should be called before register_default_interceptors, so that retransmissions get a
+/// transport-wide sequence number of their own.
+pub async fn configure_congestion_control(
+    registry: Registry,
+    media_engine: &mut MediaEngine,
+) -> Result<Registry> {
+    register_twcc(media_engine).await?;
+
+    // the header extension interceptor wraps the estimator, so that the estimator sees
+    // the transport-wide sequence number of every packet it sends
+    Ok(registry
+        .with_interceptor_builder(|| Arc::new(SendSideBWE::new()))
+        .with_interceptor_builder(|| Arc::new(HeaderExtensionInterceptor::new())))
+}
+
+/// register_twcc negotiates the transport-wide sequence number header extension and
+/// the transport-cc feedback for audio and video.
+async fn register_twcc(media_engine: &mut MediaEngine) -> Result<()> {
+    for typ in &[RTPCodecType::Video, RTPCodecType::Audio] {
+        media_engine
+            .register_header_extension(
+                RTPHeaderExtensionCapability {
+                    uri: twcc::TRANSPORT_CC_URI.to_owned(),
+                },
+                *typ,
+                vec![],
+            )
+            .await?;
+        media_engine.register_feedback(
+            RTCPFeedback {
+                typ: "transport-cc".to_owned(),
+                parameter: "".to_owned(),
+            },
+            *typ,
+        );
     }
 
-    mediaEngine.RegisterFeedback(RTCPFeedback{Type: "nack"}, RTPCodecTypeVideo)
-    mediaEngine.RegisterFeedback(RTCPFeedback{Type: "nack", Parameter: "pli"}, RTPCodecTypeVideo)
-    interceptorRegistry.Add(responder)
-    interceptorRegistry.Add(generator)
-    return nil
-}*/
+    Ok(())
+}
@@ -13,6 +13,7 @@ use crate::error::Error;
 use crate::media::rtp::fmtp::parse_fmtp;
 use crate::media::rtp::{PayloadType, RTCPFeedback};
 use crate::peer::sdp::{codecs_from_media_description, rtp_extensions_from_media_description};
+use crate::stats::{stats_timestamp_now, CodecStats, StatsCollector, StatsReportType, StatsType};
 use anyhow::Result;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -40,6 +41,9 @@ pub const MIME_TYPE_PCMU: &str = "audio/PCMU";
 /// MIME_TYPE_PCMA PCMA MIME type
 /// Note: Matching should be case insensitive.
 pub const MIME_TYPE_PCMA: &str = "audio/PCMA";
+/// MIME_TYPE_RTX RTX (RFC 4588) MIME type.
+/// Note: Matching should be case insensitive.
+pub const MIME_TYPE_RTX: &str = "video/rtx";
 
 #[derive(Default, Clone)]
 pub(crate) struct MediaEngineHeaderExtension {
@@ -154,7 +158,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=96".to_owned(),
@@ -176,7 +180,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=98".to_owned(),
@@ -198,7 +202,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=100".to_owned(),
@@ -222,7 +226,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=102".to_owned(),
@@ -246,7 +250,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=127".to_owned(),
@@ -270,7 +274,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=125".to_owned(),
@@ -294,7 +298,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=108".to_owned(),
@@ -318,7 +322,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=127".to_owned(),
@@ -342,7 +346,7 @@ impl MediaEngine {
             },
             RTPCodecParameters {
                 capability: RTPCodecCapability {
-                    mime_type: "video/rtx".to_owned(),
+                    mime_type: MIME_TYPE_RTX.to_owned(),
                     clock_rate: 90000,
                     channels: 0,
                     sdp_fmtp_line: "apt=123".to_owned(),
@@ -457,19 +461,18 @@ impl MediaEngine {
     }
 
     /// register_feedback adds feedback mechanism to already registered codecs.
+    /// Codecs which already have the feedback are left untouched.
     pub fn register_feedback(&mut self, feedback: RTCPFeedback, typ: RTPCodecType) {
-        match typ {
-            RTPCodecType::Video => {
-                for v in &mut self.video_codecs {
-                    v.capability.rtcp_feedback.push(feedback.clone());
-                }
-            }
-            RTPCodecType::Audio => {
-                for a in &mut self.audio_codecs {
-                    a.capability.rtcp_feedback.push(feedback.clone());
-                }
+        let codecs = match typ {
+            RTPCodecType::Video => &mut self.video_codecs,
+            RTPCodecType::Audio => &mut self.audio_codecs,
+            _ => return,
+        };
+
+        for c in codecs {
+            if !c.capability.rtcp_feedback.contains(&feedback) {
+                c.capability.rtcp_feedback.push(feedback.clone());
             }
-            _ => {}
         }
     }
 
@@ -528,30 +531,33 @@ impl MediaEngine {
         Err(Error::ErrCodecNotFound.into())
     }
 
-    /*TODO: func (m *MediaEngine) collectStats(collector *statsReportCollector) {
-
-            statsLoop := func(codecs []RTPCodecParameters) {
-                for _, codec := range codecs {
-                    collector.Collecting()
-                    stats := CodecStats{
-                        Timestamp:   statsTimestampFrom(time.Now()),
-                        Type:        StatsTypeCodec,
-                        ID:          codec.statsID,
-                        PayloadType: codec.PayloadType,
-                        mime_type:    codec.mime_type,
-                        clock_rate:   codec.clock_rate,
-                        channels:    uint8(codec.channels),
-                        sdpfmtp_line: codec.sdpfmtp_line,
-                    }
-
-                    collector.Collect(stats.ID, stats)
-                }
-            }
-
-            statsLoop(m.videoCodecs)
-            statsLoop(m.audioCodecs)
+    /// collect_stats reports a CodecStats object for every registered codec
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector) {
+        for codec in self.video_codecs.iter().chain(self.audio_codecs.iter()) {
+            collector
+                .collect(StatsReportType::Codec(CodecStats {
+                    timestamp: stats_timestamp_now(),
+                    stats_type: StatsType::Codec,
+                    id: codec.stats_id.clone(),
+                    payload_type: codec.payload_type,
+                    mime_type: codec.capability.mime_type.clone(),
+                    clock_rate: codec.capability.clock_rate,
+                    channels: codec.capability.channels,
+                    sdp_fmtp_line: codec.capability.sdp_fmtp_line.clone(),
+                }))
+                .await;
         }
-    */
+    }
+
+    /// get_codec_stats_id returns the id of the CodecStats object describing the
+    /// registered codec with the given payload type, or an empty string if none.
+    pub(crate) fn get_codec_stats_id(&self, payload_type: PayloadType) -> String {
+        self.video_codecs
+            .iter()
+            .chain(self.audio_codecs.iter())
+            .find(|codec| codec.payload_type == payload_type)
+            .map_or_else(String::new, |codec| codec.stats_id.clone())
+    }
 
     /// Look up a codec and enable if it exists
     pub(crate) fn match_remote_codec(
@@ -765,6 +771,25 @@ impl MediaEngine {
         }
     }
 
+    /// is_rtx_enabled returns whether a RTX codec was registered, or negotiated
+    /// if negotiation happened, for the given kind and directions.
+    pub(crate) async fn is_rtx_enabled(
+        &self,
+        typ: RTPCodecType,
+        directions: &[RTPTransceiverDirection],
+    ) -> bool {
+        self.get_rtp_parameters_by_kind(typ, directions)
+            .await
+            .codecs
+            .iter()
+            .any(|codec| {
+                codec
+                    .capability
+                    .mime_type
+                    .eq_ignore_ascii_case(MIME_TYPE_RTX)
+            })
+    }
+
     pub(crate) async fn get_rtp_parameters_by_payload_type(
         &self,
         payload_type: PayloadType,
@@ -1,6 +1,7 @@
 use super::*;
 use crate::api::media_engine::MIME_TYPE_OPUS;
 use crate::api::APIBuilder;
+use crate::media::rtp::rtp_codec::{find_apt_payload_type, find_rtx_payload_type};
 use crate::peer::configuration::Configuration;
 use regex::Regex;
 use std::io::Cursor;
@@ -723,3 +724,27 @@ async fn test_update_header_extenstion_to_cloned_media_engine() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_media_engine_rtx() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+
+    assert!(
+        m.is_rtx_enabled(RTPCodecType::Video, &[RTPTransceiverDirection::Sendonly])
+            .await
+    );
+    assert!(
+        !m.is_rtx_enabled(RTPCodecType::Audio, &[RTPTransceiverDirection::Sendonly])
+            .await
+    );
+
+    let codecs = m.get_codecs_by_kind(RTPCodecType::Video).await;
+    assert_eq!(Some(97), find_rtx_payload_type(96, &codecs));
+    assert_eq!(Some(99), find_rtx_payload_type(98, &codecs));
+    assert_eq!(None, find_rtx_payload_type(97, &codecs));
+    assert_eq!(Some(96), find_apt_payload_type(97, &codecs));
+    assert_eq!(None, find_apt_payload_type(96, &codecs));
+
+    Ok(())
+}
@@ -14,6 +14,7 @@ use crate::peer::ice::ice_gather::ICEGatherOptions;
 use media_engine::*;
 use setting_engine::*;
 
+use crate::api::interceptor_registry::Registry;
 use crate::data::data_channel::data_channel_parameters::DataChannelParameters;
 use crate::data::data_channel::DataChannel;
 use crate::data::sctp_transport::SCTPTransport;
@@ -24,12 +25,27 @@ use crate::media::rtp::rtp_sender::RTPSender;
 use crate::media::track::track_local::TrackLocal;
 use crate::peer::configuration::Configuration;
 use crate::peer::peer_connection::PeerConnection;
-use interceptor::{noop::NoOp, registry::Registry, Interceptor};
+use crate::util::math_rand_alpha;
+use interceptor::Interceptor;
 
 use anyhow::Result;
-use rcgen::KeyPair;
+use rcgen::{CertificateParams, KeyPair};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+
+/// DEFAULT_CERTIFICATE_LIFETIME is how long the certificates generated by
+/// API::certificate are valid, unless set with the APIBuilder.
+pub const DEFAULT_CERTIFICATE_LIFETIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// DEFAULT_CERTIFICATE_RENEWAL is how long before they expire the certificates
+/// returned by API::certificate are rotated, unless set with the APIBuilder.
+pub const DEFAULT_CERTIFICATE_RENEWAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub type OnCertificateRotatedHdlrFn = Box<
+    dyn (FnMut(Certificate) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>) + Send + Sync,
+>;
 
 /// API bundles the global functions of the WebRTC and ORTC API.
 /// Some of these functions are also exported globally using the
@@ -39,6 +55,12 @@ pub struct API {
     pub(crate) setting_engine: Arc<SettingEngine>,
     pub(crate) media_engine: Arc<MediaEngine>,
     pub(crate) interceptor: Arc<dyn Interceptor + Send + Sync>,
+    pub(crate) interceptor_registry: Registry,
+
+    pub(crate) certificate: Mutex<Option<Certificate>>,
+    pub(crate) certificate_lifetime: Duration,
+    pub(crate) certificate_renewal: Duration,
+    pub(crate) on_certificate_rotated_handler: Mutex<Option<OnCertificateRotatedHdlrFn>>,
 }
 
 impl API {
@@ -50,6 +72,46 @@ impl API {
         PeerConnection::new(self, configuration).await
     }
 
+    /// certificate returns the certificate cached by the API, to be shared by the
+    /// PeerConnections it is configured for. A new certificate is generated when
+    /// none is cached yet or when the cached one expires within the renewal period,
+    /// and handed to the on_certificate_rotated handler.
+    pub async fn certificate(&self) -> Result<Certificate> {
+        let certificate = {
+            let mut certificate = self.certificate.lock().await;
+            if let Some(certificate) = &*certificate {
+                if certificate.expires() > SystemTime::now() + self.certificate_renewal {
+                    return Ok(certificate.clone());
+                }
+            }
+
+            let mut params = CertificateParams::new(vec![math_rand_alpha(16)]);
+            params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+            // Allow for the clock of the remote peer to be a bit behind
+            params.not_before = chrono::Utc::now() - chrono::Duration::days(1);
+            params.not_after =
+                chrono::Utc::now() + chrono::Duration::from_std(self.certificate_lifetime)?;
+
+            let new_certificate = Certificate::from_params(params)?;
+            *certificate = Some(new_certificate.clone());
+            new_certificate
+        };
+
+        let mut handler = self.on_certificate_rotated_handler.lock().await;
+        if let Some(f) = &mut *handler {
+            f(certificate.clone()).await;
+        }
+
+        Ok(certificate)
+    }
+
+    /// on_certificate_rotated sets an event handler which is invoked when
+    /// API::certificate generates a new certificate, to persist its pem for example.
+    pub async fn on_certificate_rotated(&self, f: OnCertificateRotatedHdlrFn) {
+        let mut handler = self.on_certificate_rotated_handler.lock().await;
+        *handler = Some(f);
+    }
+
     /// new_ice_gatherer creates a new ice gatherer.
     /// This constructor is part of the ORTC API. It is not
     /// meant to be used together with the basic WebRTC API.
@@ -167,7 +229,9 @@ impl API {
 pub struct APIBuilder {
     setting_engine: Option<Arc<SettingEngine>>,
     media_engine: Option<Arc<MediaEngine>>,
-    interceptor: Option<Arc<dyn Interceptor + Send + Sync>>,
+    interceptor_registry: Option<Registry>,
+    certificate: Option<Certificate>,
+    certificate_lifetime: Option<(Duration, Duration)>,
 }
 
 impl APIBuilder {
@@ -176,6 +240,8 @@ impl APIBuilder {
     }
 
     pub fn build(mut self) -> API {
+        let interceptor_registry = self.interceptor_registry.take().unwrap_or_default();
+
         API {
             setting_engine: if let Some(setting_engine) = self.setting_engine.take() {
                 setting_engine
@@ -187,11 +253,16 @@ impl APIBuilder {
             } else {
                 Arc::new(MediaEngine::default())
             },
-            interceptor: if let Some(interceptor) = self.interceptor.take() {
-                interceptor
-            } else {
-                Arc::new(NoOp {})
-            },
+            interceptor: interceptor_registry.build(),
+            interceptor_registry,
+            certificate: Mutex::new(self.certificate.take()),
+            certificate_lifetime: self
+                .certificate_lifetime
+                .map_or(DEFAULT_CERTIFICATE_LIFETIME, |(lifetime, _)| lifetime),
+            certificate_renewal: self
+                .certificate_lifetime
+                .map_or(DEFAULT_CERTIFICATE_RENEWAL, |(_, renewal)| renewal),
+            on_certificate_rotated_handler: Mutex::new(None),
         }
     }
 
@@ -210,9 +281,23 @@ impl APIBuilder {
     }
 
     /// with_interceptor_registry allows providing Interceptors to the API.
-    /// Settings should not be changed after passing the registry to an API.
+    /// Each PeerConnection built from the API gets its own chain of interceptors from the registry.
     pub fn with_interceptor_registry(mut self, interceptor_registry: Registry) -> Self {
-        self.interceptor = Some(interceptor_registry.build());
+        self.interceptor_registry = Some(interceptor_registry);
+        self
+    }
+
+    /// with_certificate provides the certificate API::certificate returns until it
+    /// is rotated, such as a certificate persisted by a previous run.
+    pub fn with_certificate(mut self, certificate: Certificate) -> Self {
+        self.certificate = Some(certificate);
+        self
+    }
+
+    /// with_certificate_lifetime sets how long the certificates generated by
+    /// API::certificate are valid, and how long before they expire they are rotated.
+    pub fn with_certificate_lifetime(mut self, lifetime: Duration, renewal: Duration) -> Self {
+        self.certificate_lifetime = Some((lifetime, renewal));
         self
     }
 }
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod setting_engine_test;
 
+use crate::data::sctp_transport::DEFAULT_SCTP_MAX_MESSAGE_SIZE;
+use crate::media::dtls_transport::dtls_certificate_verifier::DTLSCertificateVerifier;
 use crate::media::dtls_transport::dtls_role::DTLSRole;
 use crate::peer::ice::ice_candidate::ice_candidate_type::ICECandidateType;
 use dtls::extension::extension_use_srtp::SrtpProtectionProfile;
@@ -69,6 +71,7 @@ pub struct SettingEngine {
     pub(crate) sdp_media_level_fingerprints: bool,
     pub(crate) answering_dtls_role: DTLSRole,
     pub(crate) disable_certificate_fingerprint_verification: bool,
+    pub(crate) dtls_certificate_verifier: Option<Arc<dyn DTLSCertificateVerifier + Send + Sync>>,
     pub(crate) disable_srtp_replay_protection: bool,
     pub(crate) disable_srtcp_replay_protection: bool,
     pub(crate) vnet: Option<Arc<Net>>,
@@ -78,6 +81,7 @@ pub struct SettingEngine {
     //iceProxyDialer                            :proxy.Dialer,?
     pub(crate) disable_media_engine_copy: bool,
     pub(crate) srtp_protection_profiles: Vec<SrtpProtectionProfile>,
+    pub(crate) sctp_max_message_size: u32,
 }
 
 impl SettingEngine {
@@ -234,10 +238,21 @@ impl SettingEngine {
     }
 
     /// disable_certificate_fingerprint_verification disables fingerprint verification after dtls_transport Handshake has finished
+    /// The verifier set with set_dtls_certificate_verifier still checks the remote certificate.
     pub fn disable_certificate_fingerprint_verification(&mut self, is_disabled: bool) {
         self.disable_certificate_fingerprint_verification = is_disabled;
     }
 
+    /// set_dtls_certificate_verifier sets a verifier which checks the certificate chain
+    /// of the remote peer after the dtls_transport Handshake has finished, in addition
+    /// to the fingerprint verification.
+    pub fn set_dtls_certificate_verifier(
+        &mut self,
+        verifier: Arc<dyn DTLSCertificateVerifier + Send + Sync>,
+    ) {
+        self.dtls_certificate_verifier = Some(verifier);
+    }
+
     /// set_dtls_replay_protection_window sets a replay attack protection window size of dtls_transport connection.
     pub fn set_dtls_replay_protection_window(&mut self, n: usize) {
         self.replay_protection.dtls = n;
@@ -291,6 +306,22 @@ impl SettingEngine {
     //    self.iceProxyDialer = d
     //}
 
+    /// set_sctp_max_message_size sets the size of the largest DataChannel message that
+    /// can be received, advertised to the remote peer with a=max-message-size.
+    /// Messages are buffered whole, so the SCTP receive window is grown to fit them.
+    /// The default is DEFAULT_SCTP_MAX_MESSAGE_SIZE.
+    pub fn set_sctp_max_message_size(&mut self, max_message_size: u32) {
+        self.sctp_max_message_size = max_message_size;
+    }
+
+    pub(crate) fn get_sctp_max_message_size(&self) -> u32 {
+        if self.sctp_max_message_size == 0 {
+            DEFAULT_SCTP_MAX_MESSAGE_SIZE
+        } else {
+            self.sctp_max_message_size
+        }
+    }
+
     /// disable_media_engine_copy stops the MediaEngine from being copied. This allows a user to modify
     /// the MediaEngine after the PeerConnection has been constructed. This is useful if you wish to
     /// modify codecs after signaling. Make sure not to share MediaEngines between PeerConnections.
@@ -0,0 +1,185 @@
+use crate::data::sctp_transport::{
+    DEFAULT_SCTP_MAX_MESSAGE_SIZE, SCTP_MAX_MESSAGE_SIZE_UNSET_VALUE,
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use futures::ready;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::Notify;
+
+type PendingFuture<T> = Pin<Box<dyn Future<Output = Result<T>> + Send>>;
+
+/// PendingRead hands the read buffer back along with the result, to be reused
+type PendingRead = Pin<Box<dyn Future<Output = (Vec<u8>, Result<usize>)> + Send>>;
+
+/// DataChannelIO adapts a detached DataChannel to tokio's AsyncRead and
+/// AsyncWrite, so that codecs and protocols working on byte streams can run
+/// over it. Message boundaries are not preserved: writes are split in messages
+/// of at most 64KiB, the size every peer accepts, and reads return the content
+/// of the received messages in order.
+///
+/// It takes over the on_buffered_amount_low handler of the DataChannel. A write
+/// is only accepted once buffered_amount is at or below
+/// buffered_amount_low_threshold, raising the threshold keeps more data in flight.
+pub struct DataChannelIO {
+    data_channel: Arc<data::data_channel::DataChannel>,
+    buffered_amount_low: Arc<Notify>,
+    read_buffer_size: usize,
+    /// read_buffer holds the last message read, from read_start to read_end
+    read_buffer: Vec<u8>,
+    read_start: usize,
+    read_end: usize,
+    read: Option<PendingRead>,
+    write: Option<PendingFuture<usize>>,
+    shutdown: Option<PendingFuture<()>>,
+}
+
+impl DataChannelIO {
+    pub async fn new(data_channel: Arc<data::data_channel::DataChannel>) -> Self {
+        let buffered_amount_low = Arc::new(Notify::new());
+        let buffered_amount_low2 = Arc::clone(&buffered_amount_low);
+        data_channel
+            .on_buffered_amount_low(Box::new(move || {
+                buffered_amount_low2.notify_one();
+                Box::pin(async {})
+            }))
+            .await;
+
+        DataChannelIO {
+            data_channel,
+            buffered_amount_low,
+            read_buffer_size: DEFAULT_SCTP_MAX_MESSAGE_SIZE as usize,
+            read_buffer: vec![],
+            read_start: 0,
+            read_end: 0,
+            read: None,
+            write: None,
+            shutdown: None,
+        }
+    }
+
+    /// set_read_buffer_size sets the size of the buffer messages are read into,
+    /// which must fit the largest message received. It defaults to
+    /// DEFAULT_SCTP_MAX_MESSAGE_SIZE.
+    pub fn set_read_buffer_size(&mut self, read_buffer_size: usize) {
+        self.read_buffer_size = read_buffer_size;
+    }
+
+    /// data_channel returns the detached DataChannel read from and written to.
+    pub fn data_channel(&self) -> &Arc<data::data_channel::DataChannel> {
+        &self.data_channel
+    }
+}
+
+fn to_io_error(err: anyhow::Error) -> io::Error {
+    io::Error::other(err.to_string())
+}
+
+impl AsyncRead for DataChannelIO {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.read_start < this.read_end {
+                let n = std::cmp::min(buf.remaining(), this.read_end - this.read_start);
+                buf.put_slice(&this.read_buffer[this.read_start..this.read_start + n]);
+                this.read_start += n;
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.read.is_none() {
+                let data_channel = Arc::clone(&this.data_channel);
+                // The buffer is only allocated by the first read, or after its size changed
+                let mut buffer = std::mem::take(&mut this.read_buffer);
+                buffer.resize(this.read_buffer_size, 0);
+                this.read = Some(Box::pin(async move {
+                    let result = data_channel.read(&mut buffer).await;
+                    (buffer, result)
+                }));
+            }
+
+            let (buffer, result) = match this.read.as_mut() {
+                Some(read) => ready!(read.as_mut().poll(cx)),
+                None => (vec![], Ok(0)),
+            };
+            this.read = None;
+            this.read_buffer = buffer;
+            this.read_start = 0;
+            this.read_end = 0;
+            match result {
+                // Empty messages carry no bytes, the loop reads the next one
+                Ok(n) => this.read_end = n,
+                // The remote closing the DataChannel is the end of the byte stream
+                Err(err) if sctp::error::Error::ErrStreamClosed.equal(&err) => {
+                    return Poll::Ready(Ok(()));
+                }
+                Err(err) => return Poll::Ready(Err(to_io_error(err))),
+            }
+        }
+    }
+}
+
+impl AsyncWrite for DataChannelIO {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.write.is_none() {
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let data_channel = Arc::clone(&this.data_channel);
+            let buffered_amount_low = Arc::clone(&this.buffered_amount_low);
+            let n = std::cmp::min(buf.len(), SCTP_MAX_MESSAGE_SIZE_UNSET_VALUE as usize);
+            let data = Bytes::copy_from_slice(&buf[..n]);
+            this.write = Some(Box::pin(async move {
+                // buffered_amount_low is signaled when the buffered amount drops to the
+                // threshold, a notification received before waiting is kept as a permit
+                while data_channel.buffered_amount() > data_channel.buffered_amount_low_threshold()
+                {
+                    buffered_amount_low.notified().await;
+                }
+                data_channel.write(&data).await
+            }));
+        }
+
+        let result = match this.write.as_mut() {
+            Some(write) => ready!(write.as_mut().poll(cx)),
+            None => Ok(0),
+        };
+        this.write = None;
+        Poll::Ready(result.map_err(to_io_error))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Messages are handed to the SCTP stream as soon as they are written
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.shutdown.is_none() {
+            let data_channel = Arc::clone(&this.data_channel);
+            this.shutdown = Some(Box::pin(async move { data_channel.close().await }));
+        }
+
+        let result = match this.shutdown.as_mut() {
+            Some(shutdown) => ready!(shutdown.as_mut().poll(cx)),
+            None => Ok(()),
+        };
+        this.shutdown = None;
+        Poll::Ready(result.map_err(to_io_error))
+    }
+}
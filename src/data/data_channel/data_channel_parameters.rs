@@ -1,13 +1,15 @@
 use serde::{Deserialize, Serialize};
 
 /// DataChannelParameters describes the configuration of the DataChannel.
+/// The channel is reliable unless one of max_packet_life_time or max_retransmits
+/// is set, a max_retransmits of 0 meaning that messages are never retransmitted.
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct DataChannelParameters {
     pub label: String,
     pub protocol: String,
     pub id: u16,
     pub ordered: bool,
-    pub max_packet_life_time: u16,
-    pub max_retransmits: u16,
+    pub max_packet_life_time: Option<u16>,
+    pub max_retransmits: Option<u16>,
     pub negotiated: bool,
 }
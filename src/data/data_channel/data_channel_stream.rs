@@ -0,0 +1,152 @@
+use super::data_channel_message::DataChannelMessage;
+use super::DataChannel;
+
+use anyhow::Result;
+use futures::{ready, Sink, Stream};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, Notify};
+
+/// DATA_CHANNEL_STREAM_QUEUE_SIZE is the number of received messages queued
+/// before the DataChannel stops reading from the SCTP stream.
+const DATA_CHANNEL_STREAM_QUEUE_SIZE: usize = 16;
+
+type PendingFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// DataChannelStream adapts a DataChannel to a futures Stream of the received
+/// messages and a Sink of the messages to send.
+///
+/// It takes over the on_message and on_buffered_amount_low handlers of the
+/// DataChannel, which must not be detached. The stream ends once the DataChannel
+/// is closed. The sink only accepts a message once buffered_amount is at or below
+/// buffered_amount_low_threshold, raising the threshold keeps more data in flight.
+pub struct DataChannelStream {
+    data_channel: Arc<DataChannel>,
+    messages: mpsc::Receiver<DataChannelMessage>,
+    buffered_amount_low: Arc<Notify>,
+    ready: Option<PendingFuture>,
+    write: Option<PendingFuture>,
+    close: Option<PendingFuture>,
+}
+
+impl DataChannelStream {
+    pub async fn new(data_channel: Arc<DataChannel>) -> Self {
+        let (messages_tx, messages) = mpsc::channel(DATA_CHANNEL_STREAM_QUEUE_SIZE);
+        data_channel
+            .on_message(Box::new(move |msg: DataChannelMessage| {
+                let messages_tx2 = messages_tx.clone();
+                Box::pin(async move {
+                    // Waiting for room in the queue holds the read loop of the DataChannel
+                    let _ = messages_tx2.send(msg).await;
+                })
+            }))
+            .await;
+
+        let buffered_amount_low = Arc::new(Notify::new());
+        let buffered_amount_low2 = Arc::clone(&buffered_amount_low);
+        data_channel
+            .on_buffered_amount_low(Box::new(move || {
+                buffered_amount_low2.notify_one();
+                Box::pin(async {})
+            }))
+            .await;
+
+        DataChannelStream {
+            data_channel,
+            messages,
+            buffered_amount_low,
+            ready: None,
+            write: None,
+            close: None,
+        }
+    }
+
+    /// data_channel returns the DataChannel the stream reads from and writes to.
+    pub fn data_channel(&self) -> &Arc<DataChannel> {
+        &self.data_channel
+    }
+}
+
+impl Stream for DataChannelStream {
+    type Item = DataChannelMessage;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<DataChannelMessage>> {
+        self.get_mut().messages.poll_recv(cx)
+    }
+}
+
+impl Sink<DataChannelMessage> for DataChannelStream {
+    type Error = anyhow::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+
+        let this = self.get_mut();
+        if this.ready.is_none() {
+            let data_channel = Arc::clone(&this.data_channel);
+            let buffered_amount_low = Arc::clone(&this.buffered_amount_low);
+            this.ready = Some(Box::pin(async move {
+                // buffered_amount_low is signaled when the buffered amount drops to the
+                // threshold, a notification received before waiting is kept as a permit
+                while data_channel.buffered_amount().await
+                    > data_channel.buffered_amount_low_threshold().await
+                {
+                    buffered_amount_low.notified().await;
+                }
+                Ok(())
+            }));
+        }
+
+        let result = match this.ready.as_mut() {
+            Some(ready) => ready!(ready.as_mut().poll(cx)),
+            None => Ok(()),
+        };
+        this.ready = None;
+        Poll::Ready(result)
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: DataChannelMessage) -> Result<()> {
+        let this = self.get_mut();
+        let data_channel = Arc::clone(&this.data_channel);
+        this.write = Some(Box::pin(async move {
+            if msg.is_string {
+                data_channel
+                    .send_text(String::from_utf8(msg.data.to_vec())?)
+                    .await?;
+            } else {
+                data_channel.send(&msg.data).await?;
+            }
+            Ok(())
+        }));
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        let result = match this.write.as_mut() {
+            Some(write) => ready!(write.as_mut().poll(cx)),
+            None => Ok(()),
+        };
+        this.write = None;
+        Poll::Ready(result)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+
+        let this = self.get_mut();
+        if this.close.is_none() {
+            let data_channel = Arc::clone(&this.data_channel);
+            this.close = Some(Box::pin(async move { data_channel.close().await }));
+        }
+
+        let result = match this.close.as_mut() {
+            Some(close) => ready!(close.as_mut().poll(cx)),
+            None => Ok(()),
+        };
+        this.close = None;
+        Poll::Ready(result)
+    }
+}
@@ -2,6 +2,8 @@ use super::*;
 use crate::api::media_engine::MediaEngine;
 use crate::api::{APIBuilder, API};
 use crate::data::data_channel::data_channel_config::DataChannelConfig;
+use crate::data::data_channel::data_channel_io::DataChannelIO;
+use crate::data::data_channel::data_channel_stream::DataChannelStream;
 use crate::peer::peer_connection::peer_connection_test::*;
 use crate::peer::peer_connection::PeerConnection;
 
@@ -19,7 +21,9 @@ use crate::peer::ice::ice_gather::ICEGatherOptions;
 use crate::peer::ice::ice_role::ICERole;
 use crate::peer::ice::ICEParameters;
 use crate::util::flatten_errs;
+use futures::{SinkExt, StreamExt};
 use regex::Regex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::mpsc;
 use tokio::time::Duration;
 use waitgroup::WaitGroup;
@@ -343,6 +347,80 @@ async fn test_data_channel_send_after_connected() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_data_channel_max_message_size() -> Result<()> {
+    const MAX_MESSAGE_SIZE: u32 = 3 * 1024 * 1024;
+    const MESSAGE_SIZE: usize = 2 * 1024 * 1024;
+
+    let mut s = SettingEngine::default();
+    s.set_sctp_max_message_size(MAX_MESSAGE_SIZE);
+    let api = APIBuilder::new().with_setting_engine(s).build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    let (done_tx, done_rx) = mpsc::channel::<()>(1);
+    let done_tx = Arc::new(Mutex::new(Some(done_tx)));
+    answer_pc
+        .on_data_channel(Box::new(move |d: Arc<DataChannel>| {
+            if d.label() != EXPECTED_LABEL {
+                return Box::pin(async {});
+            }
+            let done_tx1 = Arc::clone(&done_tx);
+            Box::pin(async move {
+                d.on_message(Box::new(move |msg: DataChannelMessage| {
+                    let done_tx2 = Arc::clone(&done_tx1);
+                    Box::pin(async move {
+                        assert_eq!(msg.data.len(), MESSAGE_SIZE);
+                        assert!(msg.data.iter().all(|b| *b == 0xAB));
+                        let mut done = done_tx2.lock().await;
+                        done.take();
+                    })
+                }))
+                .await;
+            })
+        }))
+        .await;
+
+    let dc = offer_pc.create_data_channel(EXPECTED_LABEL, None).await?;
+    let dc2 = Arc::clone(&dc);
+    dc.on_open(Box::new(move || {
+        Box::pin(async move {
+            assert_eq!(
+                dc2.max_message_size.load(Ordering::SeqCst),
+                MAX_MESSAGE_SIZE
+            );
+
+            let too_large = Bytes::from(vec![0xAB; MAX_MESSAGE_SIZE as usize + 1]);
+            if let Err(err) = dc2.send(&too_large).await {
+                assert!(
+                    Error::ErrDataChannelMessageTooLarge.equal(&err),
+                    "expected ErrDataChannelMessageTooLarge, but got {}",
+                    err
+                );
+            } else {
+                assert!(false, "message larger than max-message-size was sent");
+            }
+
+            let result = dc2.send(&Bytes::from(vec![0xAB; MESSAGE_SIZE])).await;
+            assert!(
+                result.is_ok(),
+                "Failed to send large message on data channel"
+            );
+        })
+    }))
+    .await;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    // A multi-megabyte message takes longer than close_pair waits for
+    let mut done_rx = done_rx;
+    let received = tokio::time::timeout(Duration::from_secs(20), done_rx.recv()).await;
+    assert!(received.is_ok(), "timed out waiting for the large message");
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_data_channel_close() -> Result<()> {
     let mut m = MediaEngine::default();
@@ -397,7 +475,7 @@ async fn test_data_channel_parameters_max_packet_life_time_exchange() -> Result<
     );
     assert_eq!(
         dc.max_packet_lifetime(),
-        max_packet_life_time,
+        Some(max_packet_life_time),
         "should match"
     );
 
@@ -415,7 +493,7 @@ async fn test_data_channel_parameters_max_packet_life_time_exchange() -> Result<
             );
             assert_eq!(
                 d.max_packet_lifetime(),
-                max_packet_life_time,
+                Some(max_packet_life_time),
                 "should match"
             );
             let done_tx2 = Arc::clone(&done_tx);
@@ -450,7 +528,7 @@ async fn test_data_channel_parameters_max_retransmits_exchange() -> Result<()> {
 
     // Check if parameters are correctly set
     assert!(!dc.ordered(), "Ordered should be set to false");
-    assert_eq!(dc.max_retransmits(), max_retransmits, "should match");
+    assert_eq!(dc.max_retransmits(), Some(max_retransmits), "should match");
 
     let done_tx = Arc::new(Mutex::new(Some(done_tx)));
     answer_pc
@@ -463,7 +541,7 @@ async fn test_data_channel_parameters_max_retransmits_exchange() -> Result<()> {
 
             // Check if parameters are correctly set
             assert!(!d.ordered(), "Ordered should be set to false");
-            assert_eq!(max_retransmits, d.max_retransmits(), "should match");
+            assert_eq!(Some(max_retransmits), d.max_retransmits(), "should match");
             let done_tx2 = Arc::clone(&done_tx);
             Box::pin(async move {
                 let mut done = done_tx2.lock().await;
@@ -477,6 +555,125 @@ async fn test_data_channel_parameters_max_retransmits_exchange() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_data_channel_parameters_zero_retransmits_exchange() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    const EXPECTED_MESSAGE: &str = "game state";
+
+    let options = DataChannelConfig {
+        ordered: Some(false),
+        max_retransmits: Some(0),
+        ..Default::default()
+    };
+
+    let (mut offer_pc, mut answer_pc, dc, done_tx, done_rx) =
+        set_up_data_channel_parameters_test(&api, Some(options)).await?;
+
+    // Check if parameters are correctly set
+    assert!(!dc.ordered(), "Ordered should be set to false");
+    assert_eq!(dc.max_retransmits(), Some(0), "should match");
+    assert_eq!(dc.max_packet_lifetime(), None, "should match");
+
+    let dc2 = Arc::clone(&dc);
+    dc.on_open(Box::new(move || {
+        Box::pin(async move {
+            let _ = dc2.send_text(EXPECTED_MESSAGE.to_owned()).await;
+        })
+    }))
+    .await;
+
+    let done_tx = Arc::new(Mutex::new(Some(done_tx)));
+    answer_pc
+        .on_data_channel(Box::new(move |d: Arc<DataChannel>| {
+            if d.label() != EXPECTED_LABEL {
+                return Box::pin(async {});
+            }
+
+            // Check if the negotiated parameters are exposed
+            assert!(!d.ordered(), "Ordered should be set to false");
+            assert_eq!(d.max_retransmits(), Some(0), "should match");
+            assert_eq!(d.max_packet_lifetime(), None, "should match");
+
+            let done_tx2 = Arc::clone(&done_tx);
+            Box::pin(async move {
+                d.on_message(Box::new(move |msg: DataChannelMessage| {
+                    assert_eq!(msg.data, EXPECTED_MESSAGE);
+                    let done_tx3 = Arc::clone(&done_tx2);
+                    Box::pin(async move {
+                        let mut done = done_tx3.lock().await;
+                        done.take();
+                    })
+                }))
+                .await;
+            })
+        }))
+        .await;
+
+    close_reliability_param_test(&mut offer_pc, &mut answer_pc, done_rx).await?;
+
+    Ok(())
+}
+
+#[test]
+fn test_data_channel_reliability() {
+    let tests = vec![
+        (true, None, None, ChannelType::Reliable, 0),
+        (false, None, None, ChannelType::ReliableUnordered, 0),
+        (true, None, Some(3), ChannelType::PartialReliableRexmit, 3),
+        (
+            false,
+            None,
+            Some(0),
+            ChannelType::PartialReliableRexmitUnordered,
+            0,
+        ),
+        (
+            true,
+            Some(500),
+            None,
+            ChannelType::PartialReliableTimed,
+            500,
+        ),
+        (
+            false,
+            Some(500),
+            None,
+            ChannelType::PartialReliableTimedUnordered,
+            500,
+        ),
+    ];
+
+    for (ordered, max_packet_lifetime, max_retransmits, expected_type, expected_param) in tests {
+        let (typ, param) = channel_type(ordered, max_packet_lifetime, max_retransmits);
+        assert_eq!(expected_type, typ);
+        assert_eq!(expected_param, param);
+        assert_eq!(
+            (ordered, max_packet_lifetime, max_retransmits),
+            reliability(typ, param),
+        );
+
+        let (unordered, reliability_type) = sctp_reliability_params(typ);
+        assert_eq!(!ordered, unordered);
+        let expected_reliability_type = if max_retransmits.is_some() {
+            ReliabilityType::Rexmit
+        } else if max_packet_lifetime.is_some() {
+            ReliabilityType::Timed
+        } else {
+            ReliabilityType::Reliable
+        };
+        assert_eq!(expected_reliability_type, reliability_type);
+    }
+
+    // reliability parameters beyond the range of the settings are clamped
+    assert_eq!(
+        (true, None, Some(u16::MAX)),
+        reliability(ChannelType::PartialReliableRexmit, 100_000)
+    );
+}
+
 #[tokio::test]
 async fn test_data_channel_parameters_protocol_exchange() -> Result<()> {
     let mut m = MediaEngine::default();
@@ -793,7 +990,7 @@ async fn test_data_channel_parameters_go() -> Result<()> {
         // Check if parameters are correctly set
         assert!(dc.ordered(), "Ordered should be set to true");
         assert_eq!(
-            max_packet_life_time,
+            Some(max_packet_life_time),
             dc.max_packet_lifetime(),
             "should match"
         );
@@ -810,7 +1007,7 @@ async fn test_data_channel_parameters_go() -> Result<()> {
                 // Check if parameters are correctly set
                 assert!(d.ordered, "Ordered should be set to true");
                 assert_eq!(
-                    max_packet_life_time,
+                    Some(max_packet_life_time),
                     d.max_packet_lifetime(),
                     "should match"
                 );
@@ -966,6 +1163,87 @@ async fn test_data_channel_buffered_amount_set_before_open() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_data_channel_buffered_amount_set_on_data_channel() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let n_cbs = Arc::new(AtomicU16::new(0));
+    let buf = Bytes::from_static(&[0u8; 1000]);
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    // The DataChannel opened by the remote peer is set up before it gets opened
+    let n_cbs2 = Arc::clone(&n_cbs);
+    answer_pc
+        .on_data_channel(Box::new(move |d: Arc<DataChannel>| {
+            if d.label() != EXPECTED_LABEL {
+                return Box::pin(async {});
+            }
+
+            let n_cbs3 = Arc::clone(&n_cbs2);
+            let buf2 = buf.clone();
+            Box::pin(async move {
+                d.set_buffered_amount_low_threshold(1500).await;
+                d.on_buffered_amount_low(Box::new(move || {
+                    n_cbs3.fetch_add(1, Ordering::SeqCst);
+                    Box::pin(async {})
+                }))
+                .await;
+
+                let d2 = Arc::clone(&d);
+                d.on_open(Box::new(move || {
+                    Box::pin(async move {
+                        for _ in 0..10 {
+                            assert!(
+                                d2.send(&buf2).await.is_ok(),
+                                "Failed to send on data channel"
+                            );
+                            assert_eq!(
+                                1500,
+                                d2.buffered_amount_low_threshold().await,
+                                "value mismatch"
+                            );
+                        }
+                    })
+                }))
+                .await;
+            })
+        }))
+        .await;
+
+    let (done_tx, done_rx) = mpsc::channel::<()>(1);
+    let done_tx = Arc::new(Mutex::new(Some(done_tx)));
+    let n_packets_received = Arc::new(AtomicU16::new(0));
+
+    let dc = offer_pc.create_data_channel(EXPECTED_LABEL, None).await?;
+    dc.on_message(Box::new(move |_msg: DataChannelMessage| {
+        let n = n_packets_received.fetch_add(1, Ordering::SeqCst);
+        if n == 9 {
+            let done_tx2 = Arc::clone(&done_tx);
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                let mut done = done_tx2.lock().await;
+                done.take();
+            });
+        }
+        Box::pin(async {})
+    }))
+    .await;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    close_pair(&offer_pc, &answer_pc, done_rx).await;
+
+    assert!(
+        n_cbs.load(Ordering::SeqCst) > 0,
+        "callback should be made at least once"
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_data_channel_buffered_amount_set_after_open() -> Result<()> {
     let mut m = MediaEngine::default();
@@ -1340,6 +1618,141 @@ async fn test_data_channel_non_standard_session_description() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_data_channel_stream() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    // The answerer echoes every message back
+    answer_pc
+        .on_data_channel(Box::new(move |d: Arc<DataChannel>| {
+            if d.label() != EXPECTED_LABEL {
+                return Box::pin(async {});
+            }
+            Box::pin(async move {
+                let mut stream = DataChannelStream::new(d).await;
+                tokio::spawn(async move {
+                    while let Some(msg) = stream.next().await {
+                        stream.send(msg).await?;
+                    }
+                    Result::<()>::Ok(())
+                });
+            })
+        }))
+        .await;
+
+    let dc = offer_pc.create_data_channel(EXPECTED_LABEL, None).await?;
+    let mut stream = DataChannelStream::new(Arc::clone(&dc)).await;
+
+    let (open_tx, mut open_rx) = mpsc::channel::<()>(1);
+    dc.on_open(Box::new(move || {
+        Box::pin(async move {
+            let _ = open_tx.send(()).await;
+        })
+    }))
+    .await;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+    let _ = open_rx.recv().await;
+
+    stream
+        .send(DataChannelMessage {
+            is_string: true,
+            data: Bytes::from_static(b"Ping"),
+        })
+        .await?;
+    stream
+        .send(DataChannelMessage {
+            is_string: false,
+            data: Bytes::from_static(&[1, 2, 3]),
+        })
+        .await?;
+
+    let msg = stream.next().await.unwrap();
+    assert!(msg.is_string);
+    assert_eq!(msg.data, Bytes::from_static(b"Ping"));
+    let msg = stream.next().await.unwrap();
+    assert!(!msg.is_string);
+    assert_eq!(msg.data, Bytes::from_static(&[1, 2, 3]));
+
+    // Closing the DataChannel ends the stream
+    stream.close().await?;
+    let end = tokio::time::timeout(Duration::from_secs(5), stream.next()).await;
+    assert!(
+        matches!(end, Ok(None)),
+        "stream should end once the DataChannel is closed"
+    );
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_channel_io() -> Result<()> {
+    let mut s = SettingEngine::default();
+    s.detach_data_channels();
+    let api = APIBuilder::new().with_setting_engine(s).build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    // More than a message, so that the writes are split
+    let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+
+    let (received_tx, mut received_rx) = mpsc::channel::<Vec<u8>>(1);
+    let received_tx = Arc::new(received_tx);
+    answer_pc
+        .on_data_channel(Box::new(move |d: Arc<DataChannel>| {
+            if d.label() != EXPECTED_LABEL {
+                return Box::pin(async {});
+            }
+            let received_tx2 = Arc::clone(&received_tx);
+            let d2 = Arc::clone(&d);
+            Box::pin(async move {
+                d.on_open(Box::new(move || {
+                    Box::pin(async move {
+                        let detached = d2.detach().await.unwrap();
+                        tokio::spawn(async move {
+                            let mut io = DataChannelIO::new(detached).await;
+                            let mut received = vec![];
+                            io.read_to_end(&mut received).await?;
+                            let _ = received_tx2.send(received).await;
+                            Result::<()>::Ok(())
+                        });
+                    })
+                }))
+                .await;
+            })
+        }))
+        .await;
+
+    let dc = offer_pc.create_data_channel(EXPECTED_LABEL, None).await?;
+    let (open_tx, mut open_rx) = mpsc::channel::<()>(1);
+    dc.on_open(Box::new(move || {
+        Box::pin(async move {
+            let _ = open_tx.send(()).await;
+        })
+    }))
+    .await;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+    let _ = open_rx.recv().await;
+
+    let mut io = DataChannelIO::new(dc.detach().await?).await;
+    io.write_all(&data).await?;
+    io.shutdown().await?;
+
+    let received = tokio::time::timeout(Duration::from_secs(5), received_rx.recv()).await;
+    assert_eq!(received.ok().flatten(), Some(data));
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
 struct TestOrtcStack {
     //api      *API
     gatherer: Arc<ICEGatherer>,
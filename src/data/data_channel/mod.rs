@@ -2,9 +2,11 @@
 mod data_channel_test;
 
 pub mod data_channel_config;
+pub mod data_channel_io;
 pub mod data_channel_message;
 pub mod data_channel_parameters;
 pub mod data_channel_state;
+pub mod data_channel_stream;
 
 use data_channel_message::*;
 use data_channel_parameters::*;
@@ -12,23 +14,24 @@ use data_channel_parameters::*;
 use bytes::Bytes;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU8, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
 
 use anyhow::Result;
 use data::message::message_channel_open::ChannelType;
-use sctp::stream::OnBufferedAmountLowFn;
+use sctp::chunk::chunk_payload_data::PayloadProtocolIdentifier;
+use sctp::stream::{OnBufferedAmountLowFn, ReliabilityType};
 use tokio::sync::Mutex;
 
 use data_channel_state::DataChannelState;
 
 use crate::api::setting_engine::SettingEngine;
-use crate::data::sctp_transport::SCTPTransport;
+use crate::data::sctp_transport::{SCTPTransport, SCTP_TRANSPORT_STATS_ID};
 use crate::error::{Error, OnErrorHdlrFn};
-
-/// message size limit for Chromium
-const DATA_CHANNEL_BUFFER_SIZE: u16 = u16::MAX;
+use crate::stats::{
+    stats_timestamp_now, DataChannelStats, StatsCollector, StatsReportType, StatsType,
+};
 
 pub type OnMessageHdlrFn = Box<
     dyn (FnMut(DataChannelMessage) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
@@ -50,14 +53,16 @@ pub struct DataChannel {
     pub(crate) stats_id: String,
     pub(crate) label: String,
     pub(crate) ordered: bool,
-    pub(crate) max_packet_lifetime: u16,
-    pub(crate) max_retransmits: u16,
+    pub(crate) max_packet_lifetime: Option<u16>,
+    pub(crate) max_retransmits: Option<u16>,
     pub(crate) protocol: String,
     pub(crate) negotiated: bool,
     pub(crate) id: AtomicU16,
     pub(crate) ready_state: Arc<AtomicU8>, // DataChannelState
     pub(crate) buffered_amount_low_threshold: AtomicUsize,
     pub(crate) detach_called: Arc<AtomicBool>,
+    /// max_message_size is the size of the largest message the remote peer accepts
+    pub(crate) max_message_size: AtomicU32,
 
     // The binaryType represents attribute MUST, on getting, return the value to
     // which it was last set. On setting, if the new value is either the string
@@ -116,31 +121,8 @@ impl DataChannel {
                 }
             }
 
-            let channel_type;
-            let reliability_parameter;
-
-            if self.max_packet_lifetime == 0 && self.max_retransmits == 0 {
-                reliability_parameter = 0u32;
-                if self.ordered {
-                    channel_type = ChannelType::Reliable;
-                } else {
-                    channel_type = ChannelType::ReliableUnordered;
-                }
-            } else if self.max_retransmits != 0 {
-                reliability_parameter = self.max_retransmits as u32;
-                if self.ordered {
-                    channel_type = ChannelType::PartialReliableRexmit;
-                } else {
-                    channel_type = ChannelType::PartialReliableRexmitUnordered;
-                }
-            } else {
-                reliability_parameter = self.max_packet_lifetime as u32;
-                if self.ordered {
-                    channel_type = ChannelType::PartialReliableTimed;
-                } else {
-                    channel_type = ChannelType::PartialReliableTimedUnordered;
-                }
-            }
+            let (channel_type, reliability_parameter) =
+                channel_type(self.ordered, self.max_packet_lifetime, self.max_retransmits);
 
             let cfg = data::data_channel::Config {
                 channel_type,
@@ -162,20 +144,19 @@ impl DataChannel {
                 );
             }
 
-            let dc = data::data_channel::DataChannel::dial(&association, self.id(), cfg).await?;
+            let stream = association
+                .open_stream(self.id(), PayloadProtocolIdentifier::Binary)
+                .await?;
+            // The PR-SCTP policy is set before any message is sent: the remote only
+            // acknowledges the DCEP open after the first messages may have been sent,
+            // and negotiated channels don't exchange DCEP messages at all.
+            let (unordered, reliability_type) = sctp_reliability_params(channel_type);
+            stream.set_reliability_params(unordered, reliability_type, reliability_parameter);
 
-            // buffered_amount_low_threshold and on_buffered_amount_low might be set earlier
-            dc.set_buffered_amount_low_threshold(
-                self.buffered_amount_low_threshold.load(Ordering::SeqCst),
-            );
-            {
-                let mut on_buffered_amount_low = self.on_buffered_amount_low.lock().await;
-                if let Some(f) = on_buffered_amount_low.take() {
-                    dc.on_buffered_amount_low(f).await;
-                }
-            }
+            let dc = data::data_channel::DataChannel::client(stream, cfg).await?;
 
-            self.handle_open(Arc::new(dc)).await;
+            self.handle_open(Arc::new(dc), association.max_message_size())
+                .await;
 
             Ok(())
         } else {
@@ -248,11 +229,30 @@ impl DataChannel {
         }
     }
 
-    pub(crate) async fn handle_open(&self, dc: Arc<data::data_channel::DataChannel>) {
+    pub(crate) async fn handle_open(
+        &self,
+        dc: Arc<data::data_channel::DataChannel>,
+        max_message_size: u32,
+    ) {
         {
             let mut data_channel = self.data_channel.lock().await;
+
+            // buffered_amount_low_threshold and on_buffered_amount_low might be set earlier,
+            // including from on_data_channel for the DataChannels opened by the remote peer
+            dc.set_buffered_amount_low_threshold(
+                self.buffered_amount_low_threshold.load(Ordering::SeqCst),
+            );
+            {
+                let mut on_buffered_amount_low = self.on_buffered_amount_low.lock().await;
+                if let Some(f) = on_buffered_amount_low.take() {
+                    dc.on_buffered_amount_low(f).await;
+                }
+            }
+
             *data_channel = Some(Arc::clone(&dc));
         }
+        self.max_message_size
+            .store(max_message_size, Ordering::SeqCst);
         self.set_ready_state(DataChannelState::Open);
 
         self.do_open().await;
@@ -262,9 +262,11 @@ impl DataChannel {
             let on_message_handler = Arc::clone(&self.on_message_handler);
             let on_close_handler = Arc::clone(&self.on_close_handler);
             let on_error_handler = Arc::clone(&self.on_error_handler);
+            let buffer_size = self.setting_engine.get_sctp_max_message_size() as usize;
             tokio::spawn(async move {
                 DataChannel::read_loop(
                     dc,
+                    buffer_size,
                     ready_state,
                     on_message_handler,
                     on_close_handler,
@@ -284,12 +286,14 @@ impl DataChannel {
 
     async fn read_loop(
         data_channel: Arc<data::data_channel::DataChannel>,
+        buffer_size: usize,
         ready_state: Arc<AtomicU8>,
         on_message_handler: Arc<Mutex<Option<OnMessageHdlrFn>>>,
         on_close_handler: Arc<Mutex<Option<OnCloseHdlrFn>>>,
         on_error_handler: Arc<Mutex<Option<OnErrorHdlrFn>>>,
     ) {
-        let mut buffer = vec![0u8; DATA_CHANNEL_BUFFER_SIZE as usize];
+        // Messages are read whole, the buffer must fit the largest message accepted
+        let mut buffer = vec![0u8; buffer_size];
         loop {
             //TODO: add cancellation handling
             let (n, is_string) = match data_channel.read_data_channel(&mut buffer).await {
@@ -329,11 +333,16 @@ impl DataChannel {
                 }
             }
         }
+
+        // Nothing is received anymore, releasing the handler ends a DataChannelStream
+        let mut handler = on_message_handler.lock().await;
+        handler.take();
     }
 
     /// send sends the binary message to the DataChannel peer
     pub async fn send(&self, data: &Bytes) -> Result<usize> {
         self.ensure_open()?;
+        self.ensure_message_size(data.len())?;
 
         let data_channel = self.data_channel.lock().await;
         if let Some(dc) = &*data_channel {
@@ -346,6 +355,7 @@ impl DataChannel {
     /// send_text sends the text message to the DataChannel peer
     pub async fn send_text(&self, s: String) -> Result<usize> {
         self.ensure_open()?;
+        self.ensure_message_size(s.len())?;
 
         let data_channel = self.data_channel.lock().await;
         if let Some(dc) = &*data_channel {
@@ -363,6 +373,14 @@ impl DataChannel {
         }
     }
 
+    fn ensure_message_size(&self, len: usize) -> Result<()> {
+        if len > self.max_message_size.load(Ordering::SeqCst) as usize {
+            Err(Error::ErrDataChannelMessageTooLarge.into())
+        } else {
+            Ok(())
+        }
+    }
+
     /// detach allows you to detach the underlying datachannel. This provides
     /// an idiomatic API to work with, however it disables the OnMessage callback.
     /// Before calling Detach you have to enable this behavior by calling
@@ -370,7 +388,8 @@ impl DataChannel {
     /// is not supported.
     /// Please refer to the data-channels-detach example and the
     /// pion/datachannel documentation for the correct way to handle the
-    /// resulting DataChannel object.
+    /// resulting DataChannel object. DataChannelIO adapts it to tokio's
+    /// AsyncRead and AsyncWrite.
     pub async fn detach(&self) -> Result<Arc<data::data_channel::DataChannel>> {
         if !self.setting_engine.detach.data_channels {
             return Err(Error::ErrDetachNotEnabled.into());
@@ -417,14 +436,16 @@ impl DataChannel {
     }
 
     /// max_packet_lifetime represents the length of the time window (msec) during
-    /// which transmissions and retransmissions may occur in unreliable mode.
-    pub fn max_packet_lifetime(&self) -> u16 {
+    /// which transmissions and retransmissions may occur in unreliable mode, or
+    /// None if the channel isn't limited by time.
+    pub fn max_packet_lifetime(&self) -> Option<u16> {
         self.max_packet_lifetime
     }
 
     /// max_retransmits represents the maximum number of retransmissions that are
-    /// attempted in unreliable mode.
-    pub fn max_retransmits(&self) -> u16 {
+    /// attempted in unreliable mode, or None if the channel isn't limited by
+    /// retransmissions.
+    pub fn max_retransmits(&self) -> Option<u16> {
         self.max_retransmits
     }
 
@@ -517,39 +538,109 @@ impl DataChannel {
         self.stats_id.as_str()
     }
 
-    /*TODO: func (d *DataChannel) collectStats(collector *statsReportCollector) {
-        collector.Collecting()
+    /// collect_stats reports the DataChannelStats of this DataChannel
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector) {
+        let (messages_sent, bytes_sent, messages_received, bytes_received) = {
+            let data_channel = self.data_channel.lock().await;
+            if let Some(dc) = &*data_channel {
+                (
+                    dc.messages_sent(),
+                    dc.bytes_sent(),
+                    dc.messages_received(),
+                    dc.bytes_received(),
+                )
+            } else {
+                (0, 0, 0, 0)
+            }
+        };
 
-        d.mu.Lock()
-        defer d.mu.Unlock()
+        collector
+            .collect(StatsReportType::DataChannel(DataChannelStats {
+                timestamp: stats_timestamp_now(),
+                stats_type: StatsType::DataChannel,
+                id: self.stats_id.clone(),
+                label: self.label.clone(),
+                protocol: self.protocol.clone(),
+                data_channel_identifier: self.id(),
+                transport_id: SCTP_TRANSPORT_STATS_ID.to_owned(),
+                state: self.ready_state(),
+                messages_sent,
+                bytes_sent,
+                messages_received,
+                bytes_received,
+            }))
+            .await;
+    }
 
-        stats := DataChannelStats{
-            Timestamp: statsTimestampNow(),
-            Type:      StatsTypeDataChannel,
-            ID:        d.stats_id,
-            Label:     d.label,
-            Protocol:  d.protocol,
-            // TransportID string `json:"transportId"`
-            State: d.ready_state(),
-        }
+    pub(crate) fn set_ready_state(&self, r: DataChannelState) {
+        self.ready_state.store(r as u8, Ordering::SeqCst);
+    }
+}
 
-        if d.id != nil {
-            stats.DataChannelIdentifier = int32(*d.id)
+/// channel_type returns the DCEP channel type and reliability parameter announcing
+/// the reliability of a DataChannel, as defined in
+/// https://tools.ietf.org/html/rfc8832#section-5.1
+pub(crate) fn channel_type(
+    ordered: bool,
+    max_packet_lifetime: Option<u16>,
+    max_retransmits: Option<u16>,
+) -> (ChannelType, u32) {
+    if let Some(max_retransmits) = max_retransmits {
+        if ordered {
+            (ChannelType::PartialReliableRexmit, max_retransmits as u32)
+        } else {
+            (
+                ChannelType::PartialReliableRexmitUnordered,
+                max_retransmits as u32,
+            )
         }
-
-        if d.dataChannel != nil {
-            stats.MessagesSent = d.dataChannel.MessagesSent()
-            stats.BytesSent = d.dataChannel.BytesSent()
-            stats.MessagesReceived = d.dataChannel.MessagesReceived()
-            stats.BytesReceived = d.dataChannel.BytesReceived()
+    } else if let Some(max_packet_lifetime) = max_packet_lifetime {
+        if ordered {
+            (
+                ChannelType::PartialReliableTimed,
+                max_packet_lifetime as u32,
+            )
+        } else {
+            (
+                ChannelType::PartialReliableTimedUnordered,
+                max_packet_lifetime as u32,
+            )
         }
-
-        collector.Collect(stats.ID, stats)
+    } else if ordered {
+        (ChannelType::Reliable, 0)
+    } else {
+        (ChannelType::ReliableUnordered, 0)
     }
+}
 
-    */
+/// reliability returns the ordered, max_packet_lifetime and max_retransmits settings
+/// announced by a DCEP channel type and reliability parameter. The parameter is
+/// clamped to the maximum value the settings support.
+pub(crate) fn reliability(
+    channel_type: ChannelType,
+    reliability_parameter: u32,
+) -> (bool, Option<u16>, Option<u16>) {
+    let val = reliability_parameter.min(u16::MAX as u32) as u16;
+    match channel_type {
+        ChannelType::Reliable => (true, None, None),
+        ChannelType::ReliableUnordered => (false, None, None),
+        ChannelType::PartialReliableRexmit => (true, None, Some(val)),
+        ChannelType::PartialReliableRexmitUnordered => (false, None, Some(val)),
+        ChannelType::PartialReliableTimed => (true, Some(val), None),
+        ChannelType::PartialReliableTimedUnordered => (false, Some(val), None),
+    }
+}
 
-    pub(crate) fn set_ready_state(&self, r: DataChannelState) {
-        self.ready_state.store(r as u8, Ordering::SeqCst);
+/// sctp_reliability_params returns whether the SCTP stream of a DCEP channel type is
+/// unordered, and its PR-SCTP policy: the Timed Reliability Policy of RFC 3758 or
+/// the Limited Retransmission Policy of RFC 7496.
+pub(crate) fn sctp_reliability_params(channel_type: ChannelType) -> (bool, ReliabilityType) {
+    match channel_type {
+        ChannelType::Reliable => (false, ReliabilityType::Reliable),
+        ChannelType::ReliableUnordered => (true, ReliabilityType::Reliable),
+        ChannelType::PartialReliableRexmit => (false, ReliabilityType::Rexmit),
+        ChannelType::PartialReliableRexmitUnordered => (true, ReliabilityType::Rexmit),
+        ChannelType::PartialReliableTimed => (false, ReliabilityType::Timed),
+        ChannelType::PartialReliableTimedUnordered => (true, ReliabilityType::Timed),
     }
 }
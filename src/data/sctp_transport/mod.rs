@@ -7,13 +7,15 @@ pub mod sctp_transport_state;
 use sctp_transport_state::SCTPTransportState;
 
 use crate::api::setting_engine::SettingEngine;
-use crate::data::data_channel::DataChannel;
+use crate::data::data_channel::{reliability, DataChannel};
 use crate::data::sctp_transport::sctp_transport_capabilities::SCTPTransportCapabilities;
 use crate::error::*;
 use crate::media::dtls_transport::dtls_role::DTLSRole;
 use crate::media::dtls_transport::*;
+use crate::stats::{
+    stats_timestamp_now, StatsCollector, StatsReportType, StatsType, TransportStats,
+};
 
-use data::message::message_channel_open::ChannelType;
 use sctp::association::Association;
 
 use crate::data::data_channel::data_channel_parameters::DataChannelParameters;
@@ -28,6 +30,22 @@ use util::Conn;
 
 const SCTP_MAX_CHANNELS: u16 = u16::MAX;
 
+/// DEFAULT_SCTP_MAX_MESSAGE_SIZE is the size of the largest DataChannel message
+/// that can be received, unless set with SettingEngine::set_sctp_max_message_size
+pub const DEFAULT_SCTP_MAX_MESSAGE_SIZE: u32 = 262144;
+
+/// SCTP_MAX_MESSAGE_SIZE_UNSET_VALUE is the max-message-size to assume when the
+/// remote description doesn't set one, as defined in
+/// https://tools.ietf.org/html/rfc8841#section-6.1
+pub(crate) const SCTP_MAX_MESSAGE_SIZE_UNSET_VALUE: u32 = 65536;
+
+/// SCTP_MIN_RECEIVE_BUFFER_SIZE is the default receive window of the association
+const SCTP_MIN_RECEIVE_BUFFER_SIZE: u32 = 1024 * 1024;
+
+/// SCTP_TRANSPORT_STATS_ID is the id of the TransportStats object describing
+/// the SCTP association of a PeerConnection
+pub(crate) const SCTP_TRANSPORT_STATS_ID: &str = "sctp_transport";
+
 pub type OnDataChannelHdlrFn = Box<
     dyn (FnMut(Arc<DataChannel>) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
         + Send
@@ -65,7 +83,7 @@ pub struct SCTPTransport {
 
     // max_message_size represents the maximum size of data that can be passed to
     // DataChannel's send() method.
-    max_message_size: usize,
+    max_message_size: AtomicU32,
 
     // max_channels represents the maximum amount of DataChannel's that can
     // be used simultaneously.
@@ -81,7 +99,7 @@ pub struct SCTPTransport {
     pub(crate) data_channels: Arc<Mutex<Vec<Arc<DataChannel>>>>,
     pub(crate) data_channels_opened: Arc<AtomicU32>,
     pub(crate) data_channels_requested: Arc<AtomicU32>,
-    data_channels_accepted: Arc<AtomicU32>,
+    pub(crate) data_channels_accepted: Arc<AtomicU32>,
 
     setting_engine: Arc<SettingEngine>,
 }
@@ -95,7 +113,7 @@ impl SCTPTransport {
             dtls_transport,
             state: AtomicU8::new(SCTPTransportState::Connecting as u8),
             is_started: AtomicBool::new(false),
-            max_message_size: SCTPTransport::calc_message_size(65536, 65536),
+            max_message_size: AtomicU32::new(SCTP_MAX_MESSAGE_SIZE_UNSET_VALUE),
             max_channels: SCTP_MAX_CHANNELS,
             sctp_association: Mutex::new(None),
             on_error_handler: Arc::new(Mutex::new(None)),
@@ -116,29 +134,45 @@ impl SCTPTransport {
         Arc::clone(&self.dtls_transport)
     }
 
-    /// get_capabilities returns the SCTPCapabilities of the SCTPTransport.
+    /// get_capabilities returns the SCTPCapabilities of the SCTPTransport, the
+    /// max_message_size being the size of the largest message it can receive.
     pub fn get_capabilities(&self) -> SCTPTransportCapabilities {
         SCTPTransportCapabilities {
-            max_message_size: 0,
+            max_message_size: self.setting_engine.get_sctp_max_message_size(),
         }
     }
 
     /// Start the SCTPTransport. Since both local and remote parties must mutually
     /// create an SCTPTransport, SCTP SO (Simultaneous Open) is used to establish
-    /// a connection over SCTP.
-    pub async fn start(&self, _remote_caps: SCTPTransportCapabilities) -> Result<()> {
+    /// a connection over SCTP. The messages sent are limited to the max_message_size
+    /// of the remote capabilities, 0 meaning that the remote accepts any size.
+    pub async fn start(&self, remote_caps: SCTPTransportCapabilities) -> Result<()> {
         if self.is_started.load(Ordering::SeqCst) {
             return Ok(());
         }
         self.is_started.store(true, Ordering::SeqCst);
 
+        let max_message_size = if remote_caps.max_message_size == 0 {
+            u32::MAX
+        } else {
+            remote_caps.max_message_size
+        };
+        self.max_message_size
+            .store(max_message_size, Ordering::SeqCst);
+
         let dtls_transport = self.transport();
         if let Some(net_conn) = &dtls_transport.conn().await {
+            // A message is only delivered once all its fragments are received, they
+            // must therefore fit in the receive window
+            let max_receive_buffer_size = self
+                .setting_engine
+                .get_sctp_max_message_size()
+                .max(SCTP_MIN_RECEIVE_BUFFER_SIZE);
             let sctp_association = Arc::new(
                 sctp::association::Association::client(sctp::association::Config {
                     net_conn: Arc::clone(net_conn) as Arc<dyn Conn + Send + Sync>,
-                    max_receive_buffer_size: 0,
-                    max_message_size: 0,
+                    max_receive_buffer_size,
+                    max_message_size,
                     name: String::new(),
                 })
                 .await?,
@@ -207,35 +241,10 @@ impl SCTPTransport {
                 }
             };
 
-            let mut max_retransmits = 0;
-            let mut max_packet_lifetime = 0;
-            let val = dc.config.reliability_parameter as u16;
-            let ordered;
-
-            match dc.config.channel_type {
-                ChannelType::Reliable => {
-                    ordered = true;
-                }
-                ChannelType::ReliableUnordered => {
-                    ordered = false;
-                }
-                ChannelType::PartialReliableRexmit => {
-                    ordered = true;
-                    max_retransmits = val;
-                }
-                ChannelType::PartialReliableRexmitUnordered => {
-                    ordered = false;
-                    max_retransmits = val;
-                }
-                ChannelType::PartialReliableTimed => {
-                    ordered = true;
-                    max_packet_lifetime = val;
-                }
-                ChannelType::PartialReliableTimedUnordered => {
-                    ordered = false;
-                    max_packet_lifetime = val;
-                }
-            };
+            // The reliability announced by the DCEP open, which the data channel
+            // already applied to the SCTP stream
+            let (ordered, max_packet_lifetime, max_retransmits) =
+                reliability(dc.config.channel_type, dc.config.reliability_parameter);
 
             let id = dc.stream_identifier();
             let rtc_dc = Arc::new(DataChannel::new(
@@ -262,7 +271,9 @@ impl SCTPTransport {
                 }
             }
 
-            rtc_dc.handle_open(Arc::new(dc)).await;
+            rtc_dc
+                .handle_open(Arc::new(dc), param.sctp_association.max_message_size())
+                .await;
 
             {
                 let mut handler = param.on_data_channel_opened_handler.lock().await;
@@ -295,16 +306,10 @@ impl SCTPTransport {
         *handler = Some(f);
     }
 
-    fn calc_message_size(remote_max_message_size: usize, can_send_size: usize) -> usize {
-        if remote_max_message_size == 0 && can_send_size == 0 {
-            usize::MAX
-        } else if remote_max_message_size == 0 {
-            can_send_size
-        } else if can_send_size == 0 || can_send_size > remote_max_message_size {
-            remote_max_message_size
-        } else {
-            can_send_size
-        }
+    /// max_message_size is the size of the largest message that can be passed to
+    /// DataChannel::send, as limited by the remote peer.
+    pub fn max_message_size(&self) -> u32 {
+        self.max_message_size.load(Ordering::SeqCst)
     }
 
     /// max_channels is the maximum number of RTCDataChannels that can be open simultaneously.
@@ -321,23 +326,29 @@ impl SCTPTransport {
         self.state.load(Ordering::SeqCst).into()
     }
 
-    /*TODO: func (r *SCTPTransport) collectStats(collector *statsReportCollector) {
-        collector.Collecting()
-
-        stats := TransportStats{
-            Timestamp: statsTimestampFrom(time.Now()),
-            Type:      StatsTypeTransport,
-            ID:        "sctpTransport",
-        }
-
-        association := r.association()
-        if association != nil {
-            stats.BytesSent = association.BytesSent()
-            stats.BytesReceived = association.BytesReceived()
-        }
-
-        collector.Collect(stats.ID, stats)
-    }*/
+    /// collect_stats reports the TransportStats of the SCTP association
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector) {
+        let (bytes_sent, bytes_received) = if let Some(association) = self.association().await {
+            (association.bytes_sent(), association.bytes_received())
+        } else {
+            (0, 0)
+        };
+
+        collector
+            .collect(StatsReportType::Transport(TransportStats {
+                timestamp: stats_timestamp_now(),
+                stats_type: StatsType::Transport,
+                id: SCTP_TRANSPORT_STATS_ID.to_owned(),
+                bytes_sent,
+                bytes_received,
+                ice_role: self.dtls_transport.ice_transport.role().await,
+                dtls_state: self.dtls_transport.state(),
+                selected_candidate_pair_id: String::new(),
+                local_certificate_id: String::new(),
+                remote_certificate_id: String::new(),
+            }))
+            .await;
+    }
 
     async fn is_channel_with_id(&self, id: u16) -> bool {
         let dcs = self.data_channels.lock().await;
@@ -22,6 +22,11 @@ pub enum Error {
     #[error("x509Cert expired")]
     ErrCertificateExpired,
 
+    /// ErrCertificateKeyMismatch indicates that the public key of a x509
+    /// certificate doesn't belong to the key pair it was given with.
+    #[error("x509Cert public key doesn't match the key pair")]
+    ErrCertificateKeyMismatch,
+
     /// ErrNoTurnCredentials indicates that a TURN server URL was provided
     /// without required credentials.
     #[error("turn server credentials required")]
@@ -32,6 +37,11 @@ pub enum Error {
     #[error("invalid turn server credentials")]
     ErrTurnCredentials,
 
+    /// ErrTurnOAuthNotSupported indicates that OAuth credentials were provided
+    /// for a TURN server, which the TURN client can't authenticate with.
+    #[error("oauth turn server credentials are not supported")]
+    ErrTurnOAuthNotSupported,
+
     /// ErrExistingTrack indicates that a track already exists.
     #[error("track already exists")]
     ErrExistingTrack,
@@ -116,6 +126,11 @@ pub enum Error {
     #[error("protocol is larger then 65535 bytes")]
     ErrProtocolTooLarge,
 
+    /// ErrDataChannelMessageTooLarge indicates that a DataChannel message is larger
+    /// than the max-message-size of the remote peer
+    #[error("message is larger than the max-message-size of the remote peer")]
+    ErrDataChannelMessageTooLarge,
+
     /// ErrSenderNotCreatedByConnection indicates remove_track was called with a RtpSender not created
     /// by this PeerConnection
     #[error("RtpSender not created by this PeerConnection")]
@@ -176,6 +191,10 @@ pub enum Error {
     #[error("new track must be of the same kind as previous")]
     ErrRTPSenderNewTrackHasIncorrectKind,
 
+    /// ErrRTPSenderNewTrackHasIncorrectEnvelope indicates that the new track has a different envelope than the previous/original
+    #[error("new track must have the same envelope as previous")]
+    ErrRTPSenderNewTrackHasIncorrectEnvelope,
+
     /// ErrUnbindFailed indicates that a TrackLocal was not able to be unbind
     #[error("failed to unbind TrackLocal from PeerConnection")]
     ErrUnbindFailed,
@@ -184,6 +203,10 @@ pub enum Error {
     #[error("the requested codec does not have a payloader")]
     ErrNoPayloaderForCodec,
 
+    /// ErrNoDepacketizerForCodec indicates that the requested codec does not have a depacketizer
+    #[error("the requested codec does not have a depacketizer")]
+    ErrNoDepacketizerForCodec,
+
     /// ErrRegisterHeaderExtensionInvalidDirection indicates that a extension was registered with a direction besides `sendonly` or `recvonly`
     #[error("a header extension must be registered as 'recvonly', 'sendonly' or both")]
     ErrRegisterHeaderExtensionInvalidDirection,
@@ -208,14 +231,24 @@ pub enum Error {
     ErrInvalidDTLSStart,
     #[error("peer didn't provide certificate via DTLS")]
     ErrNoRemoteCertificate,
-    #[error("identity provider is not implemented")]
-    ErrIdentityProviderNotImplemented,
+    #[error("invalid identity assertion")]
+    ErrInvalidIdentityAssertion,
+    #[error("no identity provider to validate the remote identity assertion")]
+    ErrNoIdentityProvider,
+    #[error("remote description has no identity assertion")]
+    ErrIdentityAssertionMissing,
+    #[error("identity assertion is not bound to the remote DTLS fingerprints")]
+    ErrIdentityAssertionFingerprintMismatch,
+    #[error("remote identity does not match the peer identity")]
+    ErrPeerIdentityMismatch,
     #[error("remote certificate does not match any fingerprint")]
     ErrNoMatchingCertificateFingerprint,
     #[error("unsupported fingerprint algorithm")]
     ErrUnsupportedFingerprintAlgorithm,
     #[error("ICE connection not started")]
     ErrICEConnectionNotStarted,
+    #[error("ICE connection failed")]
+    ErrICEConnectionFailed,
     #[error("unknown candidate type")]
     ErrICECandidateTypeUnknown,
     #[error("cannot convert ice.CandidateType into webrtc.ICECandidateType, invalid type")]
@@ -266,8 +299,6 @@ pub enum Error {
     ErrPeerConnAddTransceiverFromKindSupport,
     #[error("add_transceiver_from_track currently only supports sendonly and sendrecv")]
     ErrPeerConnAddTransceiverFromTrackSupport,
-    #[error("TODO set_identity_provider")]
-    ErrPeerConnSetIdentityProviderNotImplemented,
     #[error("write_rtcp failed to open write_stream")]
     ErrPeerConnWriteRTCPOpenWriteStream,
     #[error("cannot find transceiver with mid")]
@@ -292,6 +323,18 @@ pub enum Error {
     ErrRTPSenderDTLSTransportNil,
     #[error("Send has already been called")]
     ErrRTPSenderSendAlreadyCalled,
+    #[error("Sender has already been stopped")]
+    ErrRTPSenderStopped,
+    #[error("Sender cannot add encoding as rid is empty")]
+    ErrRTPSenderRidNil,
+    #[error("Sender cannot add encoding as there is no base track")]
+    ErrRTPSenderNoBaseEncoding,
+    #[error("Sender cannot add encoding as provided track does not match base track")]
+    ErrRTPSenderBaseEncodingMismatch,
+    #[error("Sender cannot add encoding due to RID collision")]
+    ErrRTPSenderRIDCollision,
+    #[error("no trackEncodings found for RID")]
+    ErrRTPSenderForRIDTrackEncodingNotFound,
     #[error("errRTPSenderTrackNil")]
     ErrRTPTransceiverCannotChangeMid,
     #[error("invalid state change in RTPTransceiver.setSending")]
@@ -333,6 +376,39 @@ pub enum Error {
     #[error("not long enough to be a RTP Packet")]
     ErrRTPTooShort,
 
+    /// ErrInvalidNackSize indicates that the size of a NACK receive log or
+    /// send buffer is not an allowed power of two.
+    #[error("invalid nack buffer size")]
+    ErrInvalidNackSize,
+
+    /// ErrInvalidBitrateBounds indicates that the initial bitrate of a bandwidth
+    /// estimator doesn't lie between its non zero minimum and maximum bitrates.
+    #[error("invalid bandwidth estimator bitrate bounds")]
+    ErrInvalidBitrateBounds,
+
+    #[error("IVF signature mismatch")]
+    ErrIVFSignatureMismatch,
+    #[error("IVF version unknown, parser may not parse correctly")]
+    ErrIVFUnknownVersion,
+    #[error("IVF codec unsupported")]
+    ErrIVFCodecUnsupported,
+    #[error("bad header signature")]
+    ErrOggBadIDPageSignature,
+    #[error("wrong header, expected beginning of stream")]
+    ErrOggBadIDPageType,
+    #[error("payload for id page must be 19 bytes")]
+    ErrOggBadIDPageLength,
+    #[error("bad payload signature")]
+    ErrOggBadIDPagePayloadSignature,
+    #[error("not enough data for payload header")]
+    ErrOggShortPageHeader,
+    #[error("expected and actual checksum do not match")]
+    ErrOggChecksumMismatch,
+    #[error("data is not a H264 bitstream")]
+    ErrH264DataIsNotAnnexBFormat,
+    #[error("writer is closed")]
+    ErrMediaWriterClosed,
+
     #[allow(non_camel_case_types)]
     #[error("{0}")]
     new(String),
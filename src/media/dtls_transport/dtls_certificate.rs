@@ -1,18 +1,29 @@
 use crate::error::Error;
 use crate::media::dtls_transport::dtls_fingerprint::DTLSFingerprint;
+use crate::stats::{
+    stats_timestamp_now, CertificateStats, StatsCollector, StatsReportType, StatsType,
+};
 use crate::util::math_rand_alpha;
 use anyhow::Result;
 use dtls::crypto::{CryptoPrivateKey, CryptoPrivateKeyKind};
 use rcgen::{CertificateParams, KeyPair, RcgenError};
 use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, RsaKeyPair};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use sha2::{Digest, Sha256};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const PEM_PRIVATE_KEY_TAG: &str = "PRIVATE KEY";
+const PEM_CERTIFICATE_TAG: &str = "CERTIFICATE";
 
 /// Certificate represents a x509Cert used to authenticate WebRTC communications.
+///
+/// The DER of the x509 certificate is kept as it was generated or restored, so
+/// a certificate persisted with serialize_der or pem and restored later keeps
+/// the same fingerprint. Clones can be shared across multiple PeerConnections.
+#[derive(Clone)]
 pub struct Certificate {
     pub(crate) certificate: dtls::crypto::Certificate,
     pub(crate) stats_id: String,
-    pub(crate) x509_cert: rcgen::Certificate,
     pub(crate) expires: SystemTime,
 }
 
@@ -38,6 +49,69 @@ impl Certificate {
             KeyPair::generate(params.alg)?
         };
 
+        let private_key = Certificate::crypto_private_key(&key_pair)?;
+        params.key_pair = Some(key_pair);
+
+        let expires = params.not_after.into();
+        let x509_cert = rcgen::Certificate::from_params(params)?;
+        let certificate = x509_cert.serialize_der()?;
+
+        Ok(Certificate {
+            certificate: dtls::crypto::Certificate {
+                certificate: rustls::Certificate(certificate),
+                private_key,
+            },
+            stats_id: Certificate::new_stats_id()?,
+            expires,
+        })
+    }
+
+    /// from_x509 creates a new WebRTC Certificate from an existing rcgen
+    /// certificate, which holds its own key pair.
+    ///
+    /// rcgen signs the certificate each time it is serialized, the certificate
+    /// is signed once here and its fingerprint doesn't change afterwards.
+    pub fn from_x509(x509_cert: rcgen::Certificate) -> Result<Self> {
+        let certificate = x509_cert.serialize_der()?;
+        let key_pair = KeyPair::from_der(&x509_cert.serialize_private_key_der())?;
+
+        Certificate::from_der(&certificate, key_pair)
+    }
+
+    /// from_der creates a new WebRTC Certificate from a DER encoded x509
+    /// certificate and the key pair it was issued for. The certificate keeps
+    /// the same DER and therefore the same fingerprint. It fails if the public
+    /// key of the certificate isn't the one of the key pair.
+    ///
+    /// This can be used if you want to keep a stable fingerprint across
+    /// restarts, along with serialize_der and serialize_private_key_der.
+    pub fn from_der(certificate: &[u8], key_pair: KeyPair) -> Result<Self> {
+        let (_, x509) = x509_parser::parse_x509_certificate(certificate)
+            .map_err(|e| Error::new(e.to_string()))?;
+        if x509.public_key().raw != key_pair.public_key_der().as_slice() {
+            return Err(Error::ErrCertificateKeyMismatch.into());
+        }
+        let not_after = x509.validity().not_after.timestamp();
+        let expires = UNIX_EPOCH + Duration::from_secs(not_after.max(0) as u64);
+
+        Ok(Certificate {
+            certificate: dtls::crypto::Certificate {
+                certificate: rustls::Certificate(certificate.to_vec()),
+                private_key: Certificate::crypto_private_key(&key_pair)?,
+            },
+            stats_id: Certificate::new_stats_id()?,
+            expires,
+        })
+    }
+
+    fn new_stats_id() -> Result<String> {
+        Ok(format!(
+            "certificate-{}",
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64
+        ))
+    }
+
+    fn crypto_private_key(key_pair: &KeyPair) -> Result<CryptoPrivateKey> {
         let serialized_der = key_pair.serialize_der();
         let private_key = if key_pair.is_compatible(&rcgen::PKCS_ED25519) {
             CryptoPrivateKey {
@@ -69,24 +143,8 @@ impl Certificate {
         } else {
             return Err(Error::new("Unsupported key_pair".to_owned()).into());
         };
-        params.key_pair = Some(key_pair);
-
-        let expires = params.not_after.into();
-        let x509_cert = rcgen::Certificate::from_params(params)?;
-        let certificate = x509_cert.serialize_der()?;
 
-        Ok(Certificate {
-            certificate: dtls::crypto::Certificate {
-                certificate: rustls::Certificate(certificate),
-                private_key,
-            },
-            stats_id: format!(
-                "certificate-{}",
-                SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64
-            ),
-            x509_cert,
-            expires,
-        })
+        Ok(private_key)
     }
 
     /// expires returns the timestamp after which this certificate is no longer valid.
@@ -144,67 +202,99 @@ impl Certificate {
         Certificate::from_params(params)
     }
 
-    /*TODO:
-    // CertificateFromX509 creates a new WebRTC Certificate from a given PrivateKey and Certificate
-    //
-    // This can be used if you want to share a certificate across multiple PeerConnections
-    func CertificateFromX509(privateKey crypto.PrivateKey, certificate *x509.Certificate) Certificate {
-        return Certificate{privateKey, certificate, fmt.Sprintf("certificate-%d", time.Now().UnixNano())}
+    /// serialize_der returns the DER encoded x509 certificate.
+    pub fn serialize_der(&self) -> Vec<u8> {
+        self.certificate.certificate.0.clone()
     }
 
-    func (c Certificate) collectStats(report *statsReportCollector) error {
-        report.Collecting()
+    /// serialize_private_key_der returns the private key in the DER encoded
+    /// PKCS#8 format, which KeyPair::from_der reads back.
+    pub fn serialize_private_key_der(&self) -> Vec<u8> {
+        self.certificate.private_key.serialized_der.clone()
+    }
 
-        fingerPrintAlgo, err := c.get_fingerprints()
-        if err != nil {
-            return err
-        }
+    /// collect_stats reports the CertificateStats of this certificate
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector) -> Result<()> {
+        let fingerprint = self.get_fingerprint()?;
+
+        collector
+            .collect(StatsReportType::Certificate(CertificateStats {
+                timestamp: stats_timestamp_now(),
+                stats_type: StatsType::Certificate,
+                id: self.stats_id.clone(),
+                fingerprint: fingerprint.value,
+                fingerprint_algorithm: fingerprint.algorithm,
+                base64_certificate: base64::encode(&self.certificate.certificate.0),
+                issuer_certificate_id: String::new(),
+            }))
+            .await;
 
-        base64Certificate := base64.RawURLEncoding.EncodeToString(c.x509Cert.Raw)
+        Ok(())
+    }
 
-        stats := CertificateStats{
-            Timestamp:            statsTimestampFrom(time.Now()),
-            Type:                 StatsTypeCertificate,
-            ID:                   c.statsID,
-            Fingerprint:          fingerPrintAlgo[0].Value,
-            FingerprintAlgorithm: fingerPrintAlgo[0].Algorithm,
-            Base64Certificate:    base64Certificate,
-            IssuerCertificateID:  c.x509Cert.Issuer.String(),
-        }
+    /// from_pem creates a certificate based on a string containing the pem
+    /// block of the x509 certificate, issued for the given key pair. Any private
+    /// key block in the string is ignored in favor of key_pair.
+    pub fn from_pem(pem_str: &str, key_pair: KeyPair) -> Result<Self> {
+        let blocks = pem::parse_many(pem_str)?;
+        let certificate = Certificate::find_pem_block(&blocks, PEM_CERTIFICATE_TAG)?;
 
-        report.Collect(stats.ID, stats)
-        return nil
-    }*/
+        Certificate::from_der(&certificate.contents, key_pair)
+    }
 
-    /// from_pem creates a fresh certificate based on a string containing
-    /// pem blocks fort the private key and x509 certificate
-    pub fn from_pem(pem_str: &str, key_pair: KeyPair) -> Result<Self> {
-        let params = CertificateParams::from_ca_cert_pem(pem_str, key_pair)?;
+    /// from_pem_with_key creates a certificate based on a string containing pem
+    /// blocks for the private key and x509 certificate, as returned by pem.
+    pub fn from_pem_with_key(pem_str: &str) -> Result<Self> {
+        let blocks = pem::parse_many(pem_str)?;
+        let private_key = Certificate::find_pem_block(&blocks, PEM_PRIVATE_KEY_TAG)?;
+        let certificate = Certificate::find_pem_block(&blocks, PEM_CERTIFICATE_TAG)?;
 
-        /*log::debug!(
-            "from_pem: alg:{:?}, nb:{:?}, na:{:?}, sn:{:?}, san:{:?}, dn:{:?}, ic:{:?}, ku:{:?}, eku:{:?}, ce:{:?}, uakie:{:?}, kim:{:?}, kp:{:?}",
-            params.alg,
-            params.not_before,
-            params.not_after,
-            params.serial_number,
-            params.subject_alt_names,
-            params.distinguished_name,
-            params.is_ca,
-            params.key_usages,
-            params.extended_key_usages,
-            params.custom_extensions,
-            params.use_authority_key_identifier_extension,
-            params.key_identifier_method,
-            params.key_pair,
-        );*/
+        let key_pair = KeyPair::from_der(&private_key.contents)?;
+        Certificate::from_der(&certificate.contents, key_pair)
+    }
 
-        Certificate::from_params(params)
+    fn find_pem_block<'a>(blocks: &'a [pem::Pem], tag: &str) -> Result<&'a pem::Pem> {
+        blocks
+            .iter()
+            .find(|block| block.tag == tag)
+            .ok_or_else(|| Error::new(format!("missing {} pem block", tag)).into())
     }
 
     /// PEM returns the certificate encoded as two pem block: once for the X509
     /// certificate and the other for the private key
     pub fn pem(&self) -> Result<String> {
-        Ok(self.x509_cert.serialize_pem()?)
+        Ok(pem::encode_many(&[
+            pem::Pem {
+                tag: PEM_PRIVATE_KEY_TAG.to_owned(),
+                contents: self.serialize_private_key_der(),
+            },
+            pem::Pem {
+                tag: PEM_CERTIFICATE_TAG.to_owned(),
+                contents: self.serialize_der(),
+            },
+        ]))
+    }
+}
+
+/// Certificates are serialized as a string holding the pem blocks of their
+/// private key and x509 certificate.
+impl Serialize for Certificate {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let pem_str = self.pem().map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&pem_str)
+    }
+}
+
+impl<'de> Deserialize<'de> for Certificate {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let pem_str = String::deserialize(deserializer)?;
+        Certificate::from_pem_with_key(&pem_str).map_err(serde::de::Error::custom)
     }
 }
 
@@ -234,7 +324,7 @@ mod test {
         assert!(kp_pem.contains("PRIVATE KEY"));
 
         let cert = Certificate::from_key_pair(kp)?;
-        let cert_pem = cert.x509_cert.serialize_pem()?;
+        let cert_pem = cert.pem()?;
         assert!(cert_pem.contains("CERTIFICATE"));
 
         //_, err = tls.X509KeyPair(certPEM, skPEM)
@@ -268,15 +358,15 @@ mod test {
         let cert1_pem = cert1.pem()?;
 
         let kp2 = KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?;
-        let _cert2 = Certificate::from_key_pair(kp2)?;
+        let cert2 = Certificate::from_key_pair(kp2)?;
 
         let kp3 = KeyPair::from_pem(kp1_pem.as_str())?;
         let kp3_pem = kp3.serialize_pem();
-        let _cert3 = Certificate::from_pem(cert1_pem.as_str(), kp3)?;
+        let cert3 = Certificate::from_pem(cert1_pem.as_str(), kp3)?;
 
         assert_eq!(kp1_pem, kp3_pem);
-        //assert!(cert1 != cert2);
-        //TODO: assert!(cert1 == cert3);
+        assert!(cert1 != cert2);
+        assert!(cert1 == cert3);
 
         Ok(())
     }
@@ -289,9 +379,36 @@ mod test {
         let now = SystemTime::now();
         assert!(cert.expires().duration_since(now).is_ok());
 
-        //TODO: x509Cert := CertificateFromX509(sk, &x509.Certificate{})
-        //assert.NotNil(t, x509Cert)
-        //assert.Contains(t, x509Cert.statsID, "certificate")
+        let kp = KeyPair::from_der(&cert.serialize_private_key_der())?;
+        let x509_cert = Certificate::from_der(&cert.serialize_der(), kp)?;
+        assert!(x509_cert.stats_id.contains("certificate"));
+        assert_eq!(
+            cert.expires().duration_since(UNIX_EPOCH)?.as_secs(),
+            x509_cert.expires().duration_since(UNIX_EPOCH)?.as_secs()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_certificate_serde() -> Result<()> {
+        let kp = KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+        let cert = Certificate::from_key_pair(kp)?;
+
+        let json = serde_json::to_string(&cert)?;
+        assert!(json.contains("BEGIN PRIVATE KEY"));
+        assert!(json.contains("BEGIN CERTIFICATE"));
+
+        let cert2: Certificate = serde_json::from_str(&json)?;
+        assert!(cert == cert2);
+        assert_eq!(cert.get_fingerprint()?, cert2.get_fingerprint()?);
+        assert_eq!(
+            cert.expires().duration_since(UNIX_EPOCH)?.as_secs(),
+            cert2.expires().duration_since(UNIX_EPOCH)?.as_secs()
+        );
+        assert_eq!(json, serde_json::to_string(&cert2)?);
+
+        assert!(serde_json::from_str::<Certificate>(r#""not a pem""#).is_err());
 
         Ok(())
     }
@@ -326,7 +443,49 @@ mod test {
         log::info!("{}", pem2);
 
         assert_eq!(kp_pem, kp2_pem);
-        //TODO: assert_eq!(pem, pem2);
+        assert_eq!(pem, pem2);
+
+        let cert3 = Certificate::from_pem_with_key(pem.as_str())?;
+        assert_eq!(pem, cert3.pem()?);
+        assert!(Certificate::from_pem_with_key("not a pem").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_certificate_from_x509() -> Result<()> {
+        let mut params = CertificateParams::new(vec!["webrtc.rs".to_owned()]);
+        params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+        let x509_cert = rcgen::Certificate::from_params(params)?;
+        let key_der = x509_cert.serialize_private_key_der();
+
+        let cert = Certificate::from_x509(x509_cert)?;
+        assert_eq!(key_der, cert.serialize_private_key_der());
+
+        // restored from DER, the fingerprint is the same
+        let restored = Certificate::from_der(
+            &cert.serialize_der(),
+            KeyPair::from_der(&cert.serialize_private_key_der())?,
+        )?;
+        assert!(cert == restored);
+        assert_eq!(cert.get_fingerprint()?, restored.get_fingerprint()?);
+
+        // and so is a shared clone
+        let shared = cert.clone();
+        assert_eq!(cert.get_fingerprint()?, shared.get_fingerprint()?);
+
+        assert!(Certificate::from_der(
+            &[0, 1, 2],
+            KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?
+        )
+        .is_err());
+
+        // the key pair must be the one the certificate was issued for
+        let result = Certificate::from_der(
+            &cert.serialize_der(),
+            KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?,
+        );
+        assert!(Error::ErrCertificateKeyMismatch.equal(&result.err().unwrap()));
 
         Ok(())
     }
@@ -0,0 +1,24 @@
+use crate::media::dtls_transport::dtls_fingerprint::DTLSFingerprint;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// DTLSCertificateVerifier checks the certificates presented by the remote peer
+/// once the DTLS handshake has finished. WebRTC peers usually present self-signed
+/// certificates, so the handshake itself doesn't verify them, and by default the
+/// remote certificate is only authenticated by its fingerprint in the SDP.
+/// A verifier can be set with SettingEngine::set_dtls_certificate_verifier to
+/// additionally pin a CA chain, check the SANs or perform any other identity check.
+#[async_trait]
+pub trait DTLSCertificateVerifier {
+    /// verify is given the DER encoded certificate chain of the remote peer, leaf
+    /// first, and the remote fingerprint it matched, None when fingerprint
+    /// verification is disabled. Returning an error rejects the remote peer: the
+    /// DTLSTransport goes to DTLSTransportState::Failed and the error is returned
+    /// as is by DTLSTransport::start.
+    async fn verify(
+        &self,
+        certificates: &[Vec<u8>],
+        fingerprint: Option<&DTLSFingerprint>,
+    ) -> Result<()>;
+}
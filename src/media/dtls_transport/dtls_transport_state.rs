@@ -1,30 +1,36 @@
+use serde::Serialize;
 use std::fmt;
 
 /// DTLSTransportState indicates the DTLS transport establishment state.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
 pub enum DTLSTransportState {
     Unspecified = 0,
 
     /// DTLSTransportStateNew indicates that DTLS has not started negotiating
     /// yet.
+    #[serde(rename = "new")]
     New = 1,
 
     /// DTLSTransportStateConnecting indicates that DTLS is in the process of
     /// negotiating a secure connection and verifying the remote fingerprint.
+    #[serde(rename = "connecting")]
     Connecting = 2,
 
     /// DTLSTransportStateConnected indicates that DTLS has completed
     /// negotiation of a secure connection and verified the remote fingerprint.
+    #[serde(rename = "connected")]
     Connected = 3,
 
     /// DTLSTransportStateClosed indicates that the transport has been closed
     /// intentionally as the result of receipt of a close_notify alert, or
     /// calling close().
+    #[serde(rename = "closed")]
     Closed = 4,
 
     /// DTLSTransportStateFailed indicates that the transport has failed as
     /// the result of an error (such as receipt of an error alert or failure to
     /// validate the remote fingerprint).
+    #[serde(rename = "failed")]
     Failed = 5,
 }
 
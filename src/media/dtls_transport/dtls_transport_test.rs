@@ -2,14 +2,19 @@ use super::*;
 use crate::api::media_engine::MediaEngine;
 use crate::api::APIBuilder;
 use crate::data::data_channel::DataChannel;
+use crate::media::dtls_transport::dtls_certificate_verifier::DTLSCertificateVerifier;
+use crate::media::dtls_transport::dtls_fingerprint::DTLSFingerprint;
 use crate::peer::configuration::Configuration;
 use crate::peer::ice::ice_candidate::ICECandidate;
 use crate::peer::peer_connection::peer_connection_test::{
     close_pair_now, new_pair, signal_pair, until_connection_state,
 };
+use crate::peer::peer_connection::PeerConnection;
 use crate::peer::peer_connection_state::PeerConnectionState;
+use async_trait::async_trait;
 use ice::mdns::MulticastDnsMode;
 use ice::network_type::NetworkType;
+use rcgen::KeyPair;
 use regex::Regex;
 use tokio::time::Duration;
 use waitgroup::WaitGroup;
@@ -209,3 +214,295 @@ async fn test_peer_connection_dtls_role_setting_engine_client() -> Result<()> {
 
     run_test(DTLSRole::Client).await
 }
+
+#[tokio::test]
+async fn test_validate_fingerprint() -> Result<()> {
+    let api = APIBuilder::new().build();
+    let gatherer = Arc::new(api.new_ice_gatherer(Default::default())?);
+    let ice_transport = Arc::new(api.new_ice_transport(gatherer));
+    let dtls_transport = api.new_dtls_transport(ice_transport, vec![])?;
+
+    let remote_cert = dtls_transport.certificates[0].serialize_der();
+    let fingerprint = |algorithm: &str, digest: &'static ring::digest::Algorithm| {
+        let values: Vec<String> = ring::digest::digest(digest, &remote_cert)
+            .as_ref()
+            .iter()
+            .map(|x| format!("{:02X}", x))
+            .collect();
+        DTLSFingerprint {
+            algorithm: algorithm.to_owned(),
+            value: values.join(":"),
+        }
+    };
+    let unsupported = DTLSFingerprint {
+        algorithm: "md5".to_owned(),
+        value: "AA:AA".to_owned(),
+    };
+    let wrong = DTLSFingerprint {
+        algorithm: "sha-256".to_owned(),
+        value: "AA:AA".to_owned(),
+    };
+
+    let tests = vec![
+        (
+            "any fingerprint matches",
+            vec![
+                unsupported.clone(),
+                wrong.clone(),
+                fingerprint("sha-1", &ring::digest::SHA1_FOR_LEGACY_USE_ONLY),
+            ],
+            None,
+        ),
+        (
+            "sha-512",
+            vec![fingerprint("sha-512", &ring::digest::SHA512)],
+            None,
+        ),
+        (
+            "no fingerprint matches",
+            vec![unsupported.clone(), wrong],
+            Some(Error::ErrNoMatchingCertificateFingerprint),
+        ),
+        (
+            "only unsupported fingerprints",
+            vec![unsupported],
+            Some(Error::ErrUnsupportedFingerprintAlgorithm),
+        ),
+    ];
+
+    for (name, fingerprints, expected_err) in tests {
+        {
+            let mut remote_parameters = dtls_transport.remote_parameters.lock().await;
+            remote_parameters.fingerprints = fingerprints;
+        }
+
+        let result = dtls_transport.validate_fingerprint(&remote_cert).await;
+        if let Some(expected_err) = expected_err {
+            assert!(expected_err.equal(&result.err().unwrap()), "{}", name);
+        } else {
+            assert!(result.is_ok(), "{}", name);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_multiple_certificates() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let new_certificates = || -> Result<Vec<Certificate>> {
+        Ok(vec![
+            Certificate::from_key_pair(KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?,
+            Certificate::from_key_pair(KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?,
+        ])
+    };
+    let certificates = new_certificates()?;
+    let fingerprints: Vec<String> = certificates
+        .iter()
+        .map(|c| Ok(c.get_fingerprint()?.value.to_uppercase()))
+        .collect::<Result<_>>()?;
+
+    let mut pc_offer = api
+        .new_peer_connection(Configuration {
+            certificates,
+            ..Default::default()
+        })
+        .await?;
+    let mut pc_answer = api
+        .new_peer_connection(Configuration {
+            certificates: new_certificates()?,
+            ..Default::default()
+        })
+        .await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut pc_offer, &wg, PeerConnectionState::Connected).await;
+    until_connection_state(&mut pc_answer, &wg, PeerConnectionState::Connected).await;
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+
+    // every fingerprint is advertised
+    let offer = pc_offer.local_description().await.unwrap();
+    for fingerprint in &fingerprints {
+        assert!(offer.serde.sdp.contains(fingerprint.as_str()));
+    }
+
+    wg.wait().await;
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_certificate_causes_failed() -> Result<()> {
+    let mut params = rcgen::CertificateParams::new(vec!["expiring".to_owned()]);
+    params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+    params.not_before = chrono::Utc::now() - chrono::Duration::days(1);
+    params.not_after = chrono::Utc::now() + chrono::Duration::seconds(1);
+    let certificate = Certificate::from_params(params)?;
+
+    let api = APIBuilder::new().build();
+    let gatherer = Arc::new(api.new_ice_gatherer(Default::default())?);
+    let ice_transport = Arc::new(api.new_ice_transport(gatherer));
+    let dtls_transport = api.new_dtls_transport(Arc::clone(&ice_transport), vec![certificate])?;
+
+    // the certificate expires after the DTLSTransport was created
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    ice_transport.set_state(ICETransportState::Connected);
+
+    let result = dtls_transport
+        .prepare_transport(DTLSParameters::default())
+        .await;
+    assert!(Error::ErrCertificateExpired.equal(&result.err().unwrap()));
+    assert_eq!(DTLSTransportState::Failed, dtls_transport.state());
+
+    Ok(())
+}
+
+struct RecordingVerifier {
+    reject: bool,
+    verified: Mutex<Vec<(Vec<Vec<u8>>, Option<DTLSFingerprint>)>>,
+}
+
+#[async_trait]
+impl DTLSCertificateVerifier for RecordingVerifier {
+    async fn verify(
+        &self,
+        certificates: &[Vec<u8>],
+        fingerprint: Option<&DTLSFingerprint>,
+    ) -> Result<()> {
+        let mut verified = self.verified.lock().await;
+        verified.push((certificates.to_vec(), fingerprint.cloned()));
+        if self.reject {
+            Err(Error::new("untrusted remote certificate".to_owned()).into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+async fn new_verified_peer_connection(
+    verifier: Arc<RecordingVerifier>,
+    certificate: Certificate,
+    disable_fingerprint_verification: bool,
+) -> Result<PeerConnection> {
+    let mut s = SettingEngine::default();
+    s.set_ice_multicast_dns_mode(MulticastDnsMode::Disabled);
+    s.set_network_types(vec![NetworkType::Udp4]);
+    s.disable_certificate_fingerprint_verification(disable_fingerprint_verification);
+    s.set_dtls_certificate_verifier(verifier);
+
+    APIBuilder::new()
+        .with_setting_engine(s)
+        .build()
+        .new_peer_connection(Configuration {
+            certificates: vec![certificate],
+            ..Default::default()
+        })
+        .await
+}
+
+#[tokio::test]
+async fn test_dtls_certificate_verifier() -> Result<()> {
+    let offer_certificate =
+        Certificate::from_key_pair(KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let answer_certificate =
+        Certificate::from_key_pair(KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+
+    let offer_verifier = Arc::new(RecordingVerifier {
+        reject: false,
+        verified: Mutex::new(vec![]),
+    });
+    let answer_verifier = Arc::new(RecordingVerifier {
+        reject: false,
+        verified: Mutex::new(vec![]),
+    });
+    let mut pc_offer = new_verified_peer_connection(
+        Arc::clone(&offer_verifier),
+        offer_certificate.clone(),
+        false,
+    )
+    .await?;
+    let mut pc_answer = new_verified_peer_connection(
+        Arc::clone(&answer_verifier),
+        answer_certificate.clone(),
+        true,
+    )
+    .await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut pc_offer, &wg, PeerConnectionState::Connected).await;
+    until_connection_state(&mut pc_answer, &wg, PeerConnectionState::Connected).await;
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+    wg.wait().await;
+
+    // the verifiers get the remote chain, and the matched fingerprint unless
+    // fingerprint verification is disabled
+    {
+        let verified = offer_verifier.verified.lock().await;
+        assert_eq!(1, verified.len());
+        assert_eq!(vec![answer_certificate.serialize_der()], verified[0].0);
+        let fingerprint = verified[0].1.as_ref().unwrap();
+        assert_eq!(
+            answer_certificate.get_fingerprint()?.value,
+            fingerprint.value.to_lowercase()
+        );
+    }
+    {
+        let verified = answer_verifier.verified.lock().await;
+        assert_eq!(1, verified.len());
+        assert_eq!(vec![offer_certificate.serialize_der()], verified[0].0);
+        assert!(verified[0].1.is_none());
+    }
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+// A rejected remote certificate MUST cause DTLSTransportState to go to Failed
+#[tokio::test]
+async fn test_dtls_certificate_verifier_rejects() -> Result<()> {
+    let offer_verifier = Arc::new(RecordingVerifier {
+        reject: true,
+        verified: Mutex::new(vec![]),
+    });
+    let answer_verifier = Arc::new(RecordingVerifier {
+        reject: true,
+        verified: Mutex::new(vec![]),
+    });
+    let mut pc_offer = new_verified_peer_connection(
+        Arc::clone(&offer_verifier),
+        Certificate::from_key_pair(KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?,
+        false,
+    )
+    .await?;
+    let mut pc_answer = new_verified_peer_connection(
+        Arc::clone(&answer_verifier),
+        Certificate::from_key_pair(KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?,
+        false,
+    )
+    .await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut pc_offer, &wg, PeerConnectionState::Failed).await;
+    until_connection_state(&mut pc_answer, &wg, PeerConnectionState::Failed).await;
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+    wg.wait().await;
+
+    for (pc, verifier) in [(&pc_offer, &offer_verifier), (&pc_answer, &answer_verifier)].iter() {
+        let transport = pc.sctp().transport();
+        assert_eq!(transport.state(), DTLSTransportState::Failed);
+        assert!(transport.conn().await.is_none());
+        assert!(!verifier.verified.lock().await.is_empty());
+    }
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
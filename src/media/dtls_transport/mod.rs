@@ -2,6 +2,7 @@
 mod dtls_transport_test;
 
 pub mod dtls_certificate;
+pub mod dtls_certificate_verifier;
 pub mod dtls_fingerprint;
 pub mod dtls_parameters;
 pub mod dtls_role;
@@ -10,11 +11,16 @@ pub mod dtls_transport_state;
 use crate::api::setting_engine::SettingEngine;
 use crate::default_srtp_protection_profiles;
 use crate::error::Error;
+use crate::media::dtls_transport::dtls_fingerprint::DTLSFingerprint;
 use crate::media::dtls_transport::dtls_parameters::DTLSParameters;
 use crate::media::dtls_transport::dtls_transport_state::DTLSTransportState;
 use crate::media::ice_transport::ice_transport_state::ICETransportState;
-use crate::media::ice_transport::ICETransport;
+use crate::media::ice_transport::{ICETransport, ICE_TRANSPORT_STATS_ID};
 use crate::peer::ice::ice_role::ICERole;
+use crate::stats::{
+    stats_timestamp_now, CertificateStats, StatsCollector, StatsReportType, StatsType,
+    TransportStats,
+};
 use crate::util::flatten_errs;
 use crate::util::mux::endpoint::Endpoint;
 use crate::util::mux::mux_func::{match_dtls, match_srtcp, match_srtp, MatchFunc};
@@ -33,9 +39,14 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::sync::{mpsc, Mutex};
 use util::Conn;
 
+/// REMOTE_CERTIFICATE_STATS_ID is the id of the CertificateStats object
+/// describing the certificate presented by the remote peer
+pub(crate) const REMOTE_CERTIFICATE_STATS_ID: &str = "certificate-remote";
+
 pub type OnDTLSTransportStateChangeHdlrFn = Box<
     dyn (FnMut(DTLSTransportState) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
         + Send
@@ -317,17 +328,29 @@ impl DTLSTransport {
             *rp = remote_parameters;
         }
 
-        let certificate = if let Some(cert) = self.certificates.first() {
-            cert.certificate.clone()
-        } else {
+        if self.certificates.is_empty() {
             return Err(Error::ErrNonCertificate.into());
-        };
+        }
+        // Certificates may have expired since the DTLSTransport was created
+        let now = SystemTime::now();
+        if self.certificates.iter().any(|cert| cert.expires() <= now) {
+            self.state_change(DTLSTransportState::Failed).await;
+            return Err(Error::ErrCertificateExpired.into());
+        }
+        // Every certificate is handed to the handshake. webrtc-dtls doesn't select
+        // one by the signature algorithms of the remote yet and presents the first
+        // one, the certificates are therefore ordered by preference.
+        let certificates = self
+            .certificates
+            .iter()
+            .map(|cert| cert.certificate.clone())
+            .collect();
         self.state_change(DTLSTransportState::Connecting).await;
 
         Ok((
             self.role().await,
             dtls::config::Config {
-                certificates: vec![certificate],
+                certificates,
                 srtp_protection_profiles: if !self
                     .setting_engine
                     .srtp_protection_profiles
@@ -402,15 +425,8 @@ impl DTLSTransport {
             };
         }
 
-        if self
-            .setting_engine
-            .disable_certificate_fingerprint_verification
-        {
-            return Ok(());
-        }
-
         // Check the fingerprint if a certificate was exchanged
-        let remote_certs = &dtls_conn.connection_state().await.peer_certificates;
+        let remote_certs = dtls_conn.connection_state().await.peer_certificates;
         if remote_certs.is_empty() {
             self.state_change(DTLSTransportState::Failed).await;
             return Err(Error::ErrNoRemoteCertificate.into());
@@ -421,7 +437,7 @@ impl DTLSTransport {
             *remote_certificate = Bytes::from(remote_certs[0].clone());
         }
 
-        if let Err(err) = self.validate_fingerprint(&remote_certs[0]).await {
+        if let Err(err) = self.verify_remote_certificates(&remote_certs).await {
             if dtls_conn.close().await.is_err() {
                 log::error!("{}", err);
             }
@@ -496,25 +512,58 @@ impl DTLSTransport {
         flatten_errs(close_errs)
     }
 
-    pub(crate) async fn validate_fingerprint(&self, remote_cert: &[u8]) -> Result<()> {
+    /// verify_remote_certificates checks the remote certificate against the remote
+    /// fingerprints, then hands the chain to the DTLSCertificateVerifier if any.
+    async fn verify_remote_certificates(&self, remote_certs: &[Vec<u8>]) -> Result<()> {
+        let fingerprint = if !self
+            .setting_engine
+            .disable_certificate_fingerprint_verification
+        {
+            Some(self.validate_fingerprint(&remote_certs[0]).await?)
+        } else {
+            None
+        };
+
+        if let Some(verifier) = &self.setting_engine.dtls_certificate_verifier {
+            verifier.verify(remote_certs, fingerprint.as_ref()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// validate_fingerprint accepts the remote certificate if it matches any of the
+    /// remote fingerprints whose hash function is supported, and returns that fingerprint.
+    pub(crate) async fn validate_fingerprint(&self, remote_cert: &[u8]) -> Result<DTLSFingerprint> {
         let remote_parameters = self.remote_parameters.lock().await;
+        let mut supported = false;
         for fp in &remote_parameters.fingerprints {
-            if fp.algorithm != "sha-256" {
-                return Err(Error::ErrUnsupportedFingerprintAlgorithm.into());
-            }
+            let algorithm = match fp.algorithm.to_lowercase().as_str() {
+                "sha-1" => &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+                "sha-256" => &ring::digest::SHA256,
+                "sha-384" => &ring::digest::SHA384,
+                "sha-512" => &ring::digest::SHA512,
+                _ => continue,
+            };
+            supported = true;
 
-            let mut h = Sha256::new();
-            h.update(remote_cert);
-            let hashed = h.finalize();
-            let values: Vec<String> = hashed.iter().map(|x| format! {"{:02x}", x}).collect();
-            let remote_value = values.join(":").to_lowercase();
+            let hashed = ring::digest::digest(algorithm, remote_cert);
+            let values: Vec<String> = hashed
+                .as_ref()
+                .iter()
+                .map(|x| format! {"{:02x}", x})
+                .collect();
+            let remote_value = values.join(":");
 
             if remote_value == fp.value.to_lowercase() {
-                return Ok(());
+                return Ok(fp.clone());
             }
         }
 
-        Err(Error::ErrNoMatchingCertificateFingerprint.into())
+        if supported {
+            Err(Error::ErrNoMatchingCertificateFingerprint.into())
+        } else {
+            Err(Error::ErrUnsupportedFingerprintAlgorithm.into())
+        }
     }
 
     pub(crate) fn ensure_ice_conn(&self) -> Result<()> {
@@ -525,8 +574,73 @@ impl DTLSTransport {
         }
     }
 
+    /// collect_stats reports the TransportStats of this DTLSTransport and of the
+    /// ICETransport it runs over, along with the local and remote certificates
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector) {
+        for certificate in &self.certificates {
+            if let Err(err) = certificate.collect_stats(collector).await {
+                log::warn!("failed to collect certificate stats: {}", err);
+            }
+        }
+
+        let remote_certificate = self.get_remote_certificate().await;
+        let remote_certificate_id = if !remote_certificate.is_empty() {
+            let mut h = Sha256::new();
+            h.update(&remote_certificate);
+            let hashed = h.finalize();
+            let values: Vec<String> = hashed.iter().map(|x| format! {"{:02x}", x}).collect();
+
+            collector
+                .collect(StatsReportType::Certificate(CertificateStats {
+                    timestamp: stats_timestamp_now(),
+                    stats_type: StatsType::Certificate,
+                    id: REMOTE_CERTIFICATE_STATS_ID.to_owned(),
+                    fingerprint: values.join(":"),
+                    fingerprint_algorithm: "sha-256".to_owned(),
+                    base64_certificate: base64::encode(&remote_certificate),
+                    issuer_certificate_id: String::new(),
+                }))
+                .await;
+
+            REMOTE_CERTIFICATE_STATS_ID.to_owned()
+        } else {
+            String::new()
+        };
+
+        let selected_candidate_pair_id = self
+            .ice_transport
+            .get_selected_candidate_pair()
+            .await
+            .map_or_else(String::new, |pair| pair.get_stats_id().to_owned());
+
+        collector
+            .collect(StatsReportType::Transport(TransportStats {
+                timestamp: stats_timestamp_now(),
+                stats_type: StatsType::Transport,
+                id: ICE_TRANSPORT_STATS_ID.to_owned(),
+                bytes_sent: self.ice_transport.bytes_sent().await,
+                bytes_received: self.ice_transport.bytes_received().await,
+                ice_role: self.ice_transport.role().await,
+                dtls_state: self.state(),
+                selected_candidate_pair_id,
+                local_certificate_id: self
+                    .certificates
+                    .first()
+                    .map_or_else(String::new, |c| c.stats_id.clone()),
+                remote_certificate_id,
+            }))
+            .await;
+    }
+
     pub(crate) async fn store_simulcast_stream(&self, stream: Arc<Stream>) {
         let mut simulcast_streams = self.simulcast_streams.lock().await;
         simulcast_streams.push(stream)
     }
+
+    /// remove_simulcast_stream drops a stream handed over to a RTPReceiver, which
+    /// is then responsible for closing it
+    pub(crate) async fn remove_simulcast_stream(&self, stream: &Arc<Stream>) {
+        let mut simulcast_streams = self.simulcast_streams.lock().await;
+        simulcast_streams.retain(|s| !Arc::ptr_eq(s, stream));
+    }
 }
@@ -0,0 +1,104 @@
+use crate::error::Error;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::watch;
+use util::Conn;
+
+type AgentConn = Option<Arc<dyn Conn + Send + Sync>>;
+
+/// ICEConn is the conn the Mux of an ICETransport is built on. It forwards to the
+/// conn of the ICE agent currently in use, which is replaced once the agent created
+/// by an ICE restart connects. Without a conn, reads wait for one and writes are
+/// dropped, like the agent does when it has no candidate pair.
+pub(crate) struct ICEConn {
+    conn_tx: watch::Sender<AgentConn>,
+    conn_rx: watch::Receiver<AgentConn>,
+}
+
+impl ICEConn {
+    pub(crate) fn new(conn: Arc<dyn Conn + Send + Sync>) -> Self {
+        let (conn_tx, conn_rx) = watch::channel(Some(conn));
+        ICEConn { conn_tx, conn_rx }
+    }
+
+    /// replace sets the conn of the ICE agent in use, and returns the previous one
+    pub(crate) fn replace(&self, conn: AgentConn) -> AgentConn {
+        let previous = self.current();
+        let _ = self.conn_tx.send(conn);
+        previous
+    }
+
+    fn current(&self) -> AgentConn {
+        self.conn_rx.borrow().clone()
+    }
+}
+
+#[async_trait]
+impl Conn for ICEConn {
+    async fn connect(&self, addr: SocketAddr) -> Result<()> {
+        match self.current() {
+            Some(conn) => conn.connect(addr).await,
+            None => Err(Error::ErrICEConnectionNotStarted.into()),
+        }
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        let mut conn_rx = self.conn_rx.clone();
+        loop {
+            let conn = conn_rx.borrow().clone();
+            if let Some(conn) = conn {
+                tokio::select! {
+                    result = conn.recv(buf) => return result,
+                    result = conn_rx.changed() => result?,
+                }
+            } else {
+                conn_rx.changed().await?;
+            }
+        }
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        match self.current() {
+            Some(conn) => conn.recv_from(buf).await,
+            None => Err(Error::ErrICEConnectionNotStarted.into()),
+        }
+    }
+
+    async fn send(&self, buf: &[u8]) -> Result<usize> {
+        match self.current() {
+            Some(conn) => conn.send(buf).await,
+            None => Ok(0),
+        }
+    }
+
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> Result<usize> {
+        match self.current() {
+            Some(conn) => conn.send_to(buf, target).await,
+            None => Ok(0),
+        }
+    }
+
+    async fn local_addr(&self) -> Result<SocketAddr> {
+        match self.current() {
+            Some(conn) => conn.local_addr().await,
+            None => Err(Error::ErrICEConnectionNotStarted.into()),
+        }
+    }
+
+    async fn remote_addr(&self) -> Option<SocketAddr> {
+        match self.current() {
+            Some(conn) => conn.remote_addr().await,
+            None => None,
+        }
+    }
+
+    async fn close(&self) -> Result<()> {
+        match self.replace(None) {
+            Some(conn) => conn.close().await,
+            None => Ok(()),
+        }
+    }
+}
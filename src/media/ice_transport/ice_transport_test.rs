@@ -1,15 +1,31 @@
 use super::*;
 use crate::api::media_engine::MediaEngine;
 use crate::api::APIBuilder;
+use crate::data::data_channel::data_channel_message::DataChannelMessage;
+use crate::data::data_channel::data_channel_state::DataChannelState;
+use crate::data::data_channel::DataChannel;
+use crate::peer::ice::ice_candidate::ICECandidateInit;
 use crate::peer::ice::ice_connection_state::ICEConnectionState;
+use crate::peer::offer_answer_options::OfferOptions;
 use crate::peer::peer_connection::peer_connection_test::{
     close_pair_now, new_pair, signal_pair, until_connection_state,
 };
 use crate::peer::peer_connection_state::PeerConnectionState;
+use crate::peer::sdp::sdp_type::SDPType;
+use crate::peer::sdp::session_description::{SessionDescription, SessionDescriptionSerde};
+use bytes::Bytes;
 use std::sync::atomic::AtomicU32;
 use tokio::time::Duration;
 use waitgroup::WaitGroup;
 
+/// without_candidates removes the candidates of a session description
+fn without_candidates(sdp: &str) -> String {
+    sdp.lines()
+        .filter(|l| !l.starts_with("a=candidate") && !l.starts_with("a=end-of-candidates"))
+        .map(|l| format!("{}\r\n", l))
+        .collect()
+}
+
 #[tokio::test]
 async fn test_ice_transport_on_selected_candidate_pair_change() -> Result<()> {
     let mut m = MediaEngine::default();
@@ -121,3 +137,261 @@ async fn test_ice_transport_get_selected_candidate_pair() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_ice_transport_end_of_candidates() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (pc_offer, pc_answer) = new_pair(&api).await?;
+
+    let (ice_failed_tx, mut ice_failed_rx) = mpsc::channel::<()>(1);
+    pc_answer
+        .on_ice_connection_state_change(Box::new(move |ice_state: ICEConnectionState| {
+            let ice_failed_tx2 = ice_failed_tx.clone();
+            Box::pin(async move {
+                if ice_state == ICEConnectionState::Failed {
+                    let _ = ice_failed_tx2.try_send(());
+                }
+            })
+        }))
+        .await;
+
+    pc_offer.create_data_channel("data", None).await?;
+    let offer = pc_offer.create_offer(None).await?;
+
+    // Trickle the only remote candidate, which is unreachable
+    pc_answer
+        .set_remote_description(SessionDescription {
+            serde: SessionDescriptionSerde {
+                sdp_type: SDPType::Offer,
+                sdp: without_candidates(&offer.serde.sdp),
+            },
+            parsed: None,
+        })
+        .await?;
+
+    let answer = pc_answer.create_answer(None).await?;
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+
+    pc_answer
+        .add_ice_candidate(ICECandidateInit {
+            candidate: "candidate:1 1 udp 2130706431 192.0.2.1 9 typ host".to_owned(),
+            ..Default::default()
+        })
+        .await?;
+    pc_answer
+        .add_ice_candidate(ICECandidateInit::default())
+        .await?;
+
+    // Well before the failed timeout of the ICE agent
+    tokio::time::timeout(Duration::from_secs(10), ice_failed_rx.recv())
+        .await
+        .expect("ICE did not fail after the end of the remote candidates");
+    assert_eq!(
+        pc_answer.sctp().transport().ice_transport().state(),
+        ICETransportState::Failed
+    );
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ice_transport_restart_keeps_media_flowing() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let (message_tx, mut message_rx) = mpsc::channel(16);
+    pc_answer
+        .on_data_channel(Box::new(move |d: Arc<DataChannel>| {
+            let message_tx = message_tx.clone();
+            Box::pin(async move {
+                d.on_message(Box::new(move |msg: DataChannelMessage| {
+                    let message_tx = message_tx.clone();
+                    Box::pin(async move {
+                        let _ = message_tx.send(msg.data).await;
+                    })
+                }))
+                .await;
+            })
+        }))
+        .await;
+    let dc = pc_offer.create_data_channel("data", None).await?;
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+    while dc.ready_state() != DataChannelState::Open {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let offer = pc_offer
+        .create_offer(Some(OfferOptions {
+            ice_restart: true,
+            ..Default::default()
+        }))
+        .await?;
+    let mut offer_gathering_complete = pc_offer.gathering_complete_promise().await;
+    pc_offer.set_local_description(offer).await?;
+    let _ = offer_gathering_complete.recv().await;
+
+    // No pair of the new generation can be selected before the answer is applied
+    dc.send_text("offerer restarted".to_owned()).await?;
+    let received = tokio::time::timeout(Duration::from_secs(5), message_rx.recv())
+        .await
+        .expect("no message received while the offerer restarts");
+    assert_eq!(Some(Bytes::from_static(b"offerer restarted")), received);
+    assert!(pc_offer
+        .sctp()
+        .transport()
+        .ice_transport()
+        .get_selected_candidate_pair()
+        .await
+        .is_some());
+
+    pc_answer
+        .set_remote_description(pc_offer.local_description().await.unwrap())
+        .await?;
+    dc.send_text("answerer restarted".to_owned()).await?;
+    let received = tokio::time::timeout(Duration::from_secs(5), message_rx.recv())
+        .await
+        .expect("no message received while the answerer restarts");
+    assert_eq!(Some(Bytes::from_static(b"answerer restarted")), received);
+
+    let answer = pc_answer.create_answer(None).await?;
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+    pc_offer
+        .set_remote_description(pc_answer.local_description().await.unwrap())
+        .await?;
+
+    dc.send_text("after restart".to_owned()).await?;
+    let received = tokio::time::timeout(Duration::from_secs(10), message_rx.recv())
+        .await
+        .expect("no message received after the ICE restart");
+    assert_eq!(Some(Bytes::from_static(b"after restart")), received);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ice_transport_restart_failed() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let (message_tx, mut message_rx) = mpsc::channel(16);
+    pc_answer
+        .on_data_channel(Box::new(move |d: Arc<DataChannel>| {
+            let message_tx = message_tx.clone();
+            Box::pin(async move {
+                d.on_message(Box::new(move |msg: DataChannelMessage| {
+                    let message_tx = message_tx.clone();
+                    Box::pin(async move {
+                        let _ = message_tx.send(msg.data).await;
+                    })
+                }))
+                .await;
+            })
+        }))
+        .await;
+    let dc = pc_offer.create_data_channel("data", None).await?;
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+    while dc.ready_state() != DataChannelState::Open {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let (ice_failed_tx, mut ice_failed_rx) = mpsc::channel::<()>(1);
+    pc_answer
+        .on_ice_connection_state_change(Box::new(move |ice_state: ICEConnectionState| {
+            let ice_failed_tx2 = ice_failed_tx.clone();
+            Box::pin(async move {
+                if ice_state == ICEConnectionState::Failed {
+                    let _ = ice_failed_tx2.try_send(());
+                }
+            })
+        }))
+        .await;
+
+    let offer = pc_offer
+        .create_offer(Some(OfferOptions {
+            ice_restart: true,
+            ..Default::default()
+        }))
+        .await?;
+    let mut offer_gathering_complete = pc_offer.gathering_complete_promise().await;
+    pc_offer.set_local_description(offer).await?;
+    let _ = offer_gathering_complete.recv().await;
+
+    // None of the candidates of the restarted agents are exchanged
+    let offer = pc_offer.local_description().await.unwrap();
+    pc_answer
+        .set_remote_description(SessionDescription {
+            serde: SessionDescriptionSerde {
+                sdp_type: SDPType::Offer,
+                sdp: without_candidates(&offer.serde.sdp),
+            },
+            parsed: None,
+        })
+        .await?;
+    let answer = pc_answer.create_answer(None).await?;
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+    let answer = pc_answer.local_description().await.unwrap();
+    pc_offer
+        .set_remote_description(SessionDescription {
+            serde: SessionDescriptionSerde {
+                sdp_type: SDPType::Answer,
+                sdp: without_candidates(&answer.serde.sdp),
+            },
+            parsed: None,
+        })
+        .await?;
+
+    pc_answer
+        .add_ice_candidate(ICECandidateInit {
+            candidate: "candidate:1 1 udp 2130706431 192.0.2.1 9 typ host".to_owned(),
+            ..Default::default()
+        })
+        .await?;
+    pc_answer
+        .add_ice_candidate(ICECandidateInit::default())
+        .await?;
+
+    tokio::time::timeout(Duration::from_secs(10), ice_failed_rx.recv())
+        .await
+        .expect("the restarted ICE agent did not fail");
+    let dtls_transport = pc_answer.sctp().transport();
+    let ice_transport = dtls_transport.ice_transport();
+    assert_eq!(ice_transport.state(), ICETransportState::Failed);
+    assert!(ice_transport.get_selected_candidate_pair().await.is_some());
+
+    // The restarted agent is closed once it failed
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let agent = ice_transport.gatherer.get_agent().await.unwrap();
+    assert!(ice::error::Error::ErrClosed.equal(&agent.close().await.err().unwrap()));
+
+    // The packets keep going over the previous agent
+    dc.send_text("restart failed".to_owned()).await?;
+    let received = tokio::time::timeout(Duration::from_secs(5), message_rx.recv())
+        .await
+        .expect("no message received after the ICE restart failed");
+    assert_eq!(Some(Bytes::from_static(b"restart failed")), received);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
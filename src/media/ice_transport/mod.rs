@@ -1,32 +1,46 @@
 #[cfg(test)]
 mod ice_transport_test;
 
+mod ice_conn;
 pub mod ice_transport_state;
 
+use crate::media::ice_transport::ice_conn::ICEConn;
 use crate::media::ice_transport::ice_transport_state::ICETransportState;
 use crate::peer::ice::ice_candidate::ice_candidate_pair::ICECandidatePair;
 use crate::peer::ice::ice_gather::ice_gatherer::ICEGatherer;
+use crate::peer::ice::ice_gather::ice_gatherer_state::ICEGathererState;
 use crate::peer::ice::ice_role::ICERole;
 use crate::util::mux::{Config, Mux};
 
 use crate::error::Error;
 use crate::peer::ice::ice_candidate::ICECandidate;
 use crate::peer::ice::ICEParameters;
+use crate::stats::StatsCollector;
 use crate::util::mux::endpoint::Endpoint;
 use crate::util::mux::mux_func::MatchFunc;
 use crate::RECEIVE_MTU;
 
-use ice::candidate::Candidate;
+use ice::agent::Agent;
+use ice::candidate::{Candidate, CandidatePairState};
 use ice::state::ConnectionState;
 
 use anyhow::Result;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, Mutex, Notify};
+use tokio::time::Duration;
 use util::Conn;
 
+/// ICE_TRANSPORT_STATS_ID is the id of the TransportStats object describing
+/// the ICE and DTLS transport of a PeerConnection
+pub(crate) const ICE_TRANSPORT_STATS_ID: &str = "ice_transport";
+
+/// CANDIDATE_PAIRS_CHECK_INTERVAL is how often the candidate pairs are checked for
+/// failure, once the remote peer signaled the end of its candidates
+const CANDIDATE_PAIRS_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
 pub type OnConnectionStateChangeHdlrFn = Box<
     dyn (FnMut(ICETransportState) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>)
         + Send
@@ -42,9 +56,12 @@ pub type OnSelectedCandidatePairChangeHdlrFn = Box<
 #[derive(Default)]
 pub struct ICETransportInternal {
     role: ICERole,
-    conn: Option<Arc<dyn Conn + Send + Sync>>, //AgentConn
+    conn: Option<Arc<ICEConn>>,
     mux: Option<Mux>,
     cancel_tx: Option<mpsc::Sender<()>>,
+    /// pending_connect is set when a restart replaced the ICE agent of the started
+    /// transport, which connects once the remote credentials are known
+    pending_connect: bool,
 }
 
 /// ICETransport allows an application access to information about the ICE
@@ -57,6 +74,18 @@ pub struct ICETransport {
         Arc<Mutex<Option<OnSelectedCandidatePairChangeHdlrFn>>>,
     state: Arc<AtomicU8>, // ICETransportState
     internal: Mutex<ICETransportInternal>,
+    /// remote_end_of_candidates is set once the remote peer signaled the end of its
+    /// candidates, until the next restart
+    remote_end_of_candidates: AtomicBool,
+    /// generation is bumped by every restart, which ends the failure checks of the
+    /// candidate pairs of the previous generation
+    generation: Arc<AtomicU32>,
+    /// previous_agent is the ICE agent still carrying the packets after a restart of the
+    /// started transport, until the agent which replaced it connects
+    previous_agent: Arc<Mutex<Option<Arc<Agent>>>>,
+    /// failed is notified when the transport fails, which gives up the connection of
+    /// the agent which replaced the previous one
+    failed: Arc<Notify>,
 }
 
 impl ICETransport {
@@ -72,7 +101,13 @@ impl ICETransport {
     /// get_selected_candidate_pair returns the selected candidate pair on which packets are sent
     /// if there is no selected pair nil is returned
     pub async fn get_selected_candidate_pair(&self) -> Option<ICECandidatePair> {
-        if let Some(agent) = self.gatherer.get_agent().await {
+        // During a restart, the packets are sent on the pair of the previous agent
+        let previous_agent = self.previous_agent.lock().await.clone();
+        let agent = match previous_agent {
+            Some(agent) => Some(agent),
+            None => self.gatherer.get_agent().await,
+        };
+        if let Some(agent) = agent {
             if let Some(ice_pair) = agent.get_selected_candidate_pair().await {
                 let local = ICECandidate::from(&ice_pair.local);
                 let remote = ICECandidate::from(&ice_pair.remote);
@@ -91,45 +126,8 @@ impl ICETransport {
         self.ensure_gatherer().await?;
 
         if let Some(agent) = self.gatherer.get_agent().await {
-            let state = Arc::clone(&self.state);
-
-            let on_connection_state_change_handler =
-                Arc::clone(&self.on_connection_state_change_handler);
-            agent
-                .on_connection_state_change(Box::new(move |ice_state: ConnectionState| {
-                    let s = ICETransportState::from(ice_state);
-                    let on_connection_state_change_handler_clone =
-                        Arc::clone(&on_connection_state_change_handler);
-                    state.store(s as u8, Ordering::SeqCst);
-                    Box::pin(async move {
-                        let mut handler = on_connection_state_change_handler_clone.lock().await;
-                        if let Some(f) = &mut *handler {
-                            f(s).await;
-                        }
-                    })
-                }))
-                .await;
-
-            let on_selected_candidate_pair_change_handler =
-                Arc::clone(&self.on_selected_candidate_pair_change_handler);
-            agent
-                .on_selected_candidate_pair_change(Box::new(
-                    move |local: &Arc<dyn Candidate + Send + Sync>,
-                          remote: &Arc<dyn Candidate + Send + Sync>| {
-                        let on_selected_candidate_pair_change_handler_clone =
-                            Arc::clone(&on_selected_candidate_pair_change_handler);
-                        let local = ICECandidate::from(local);
-                        let remote = ICECandidate::from(remote);
-                        Box::pin(async move {
-                            let mut handler =
-                                on_selected_candidate_pair_change_handler_clone.lock().await;
-                            if let Some(f) = &mut *handler {
-                                f(ICECandidatePair::new(local, remote)).await;
-                            }
-                        })
-                    },
-                ))
-                .await;
+            let generation = self.generation.load(Ordering::SeqCst);
+            self.bind_agent(&agent, generation).await;
 
             let role = if let Some(role) = role {
                 role
@@ -139,32 +137,19 @@ impl ICETransport {
 
             let (cancel_tx, cancel_rx) = mpsc::channel(1);
 
-            let conn: Arc<dyn Conn + Send + Sync> = match role {
-                ICERole::Controlling => {
-                    agent
-                        .dial(
-                            cancel_rx,
-                            params.username_fragment.clone(),
-                            params.password.clone(),
-                        )
-                        .await?
-                }
-
-                ICERole::Controlled => {
-                    agent
-                        .accept(
-                            cancel_rx,
-                            params.username_fragment.clone(),
-                            params.password.clone(),
-                        )
-                        .await?
-                }
-
-                _ => return Err(Error::ErrICERoleUnknown.into()),
-            };
+            let conn = Arc::new(ICEConn::new(
+                ICETransport::connect(
+                    &agent,
+                    role,
+                    cancel_rx,
+                    params.username_fragment.clone(),
+                    params.password.clone(),
+                )
+                .await?,
+            ));
 
             let config = Config {
-                conn: Arc::clone(&conn),
+                conn: Arc::clone(&conn) as Arc<dyn Conn + Send + Sync>,
                 buffer_size: RECEIVE_MTU,
             };
 
@@ -182,23 +167,138 @@ impl ICETransport {
         }
     }
 
+    /// bind_agent forwards the connection state and selected candidate pair changes of
+    /// the ICE agent, until a restart moves the transport to the next generation
+    async fn bind_agent(&self, agent: &Agent, agent_generation: u32) {
+        let state = Arc::clone(&self.state);
+        let generation = Arc::clone(&self.generation);
+        let failed = Arc::clone(&self.failed);
+        let on_connection_state_change_handler =
+            Arc::clone(&self.on_connection_state_change_handler);
+        agent
+            .on_connection_state_change(Box::new(move |ice_state: ConnectionState| {
+                let s = ICETransportState::from(ice_state);
+                let on_connection_state_change_handler_clone =
+                    Arc::clone(&on_connection_state_change_handler);
+                let current = generation.load(Ordering::SeqCst) == agent_generation;
+                if current && s == ICETransportState::Failed {
+                    failed.notify_waiters();
+                }
+                // The transport may already have failed, once the remote candidates ended
+                let changed = current && state.swap(s as u8, Ordering::SeqCst) != s as u8;
+                Box::pin(async move {
+                    if !changed {
+                        return;
+                    }
+                    let mut handler = on_connection_state_change_handler_clone.lock().await;
+                    if let Some(f) = &mut *handler {
+                        f(s).await;
+                    }
+                })
+            }))
+            .await;
+
+        let generation = Arc::clone(&self.generation);
+        let on_selected_candidate_pair_change_handler =
+            Arc::clone(&self.on_selected_candidate_pair_change_handler);
+        agent
+            .on_selected_candidate_pair_change(Box::new(
+                move |local: &Arc<dyn Candidate + Send + Sync>,
+                      remote: &Arc<dyn Candidate + Send + Sync>| {
+                    let on_selected_candidate_pair_change_handler_clone =
+                        Arc::clone(&on_selected_candidate_pair_change_handler);
+                    let current = generation.load(Ordering::SeqCst) == agent_generation;
+                    let local = ICECandidate::from(local);
+                    let remote = ICECandidate::from(remote);
+                    Box::pin(async move {
+                        if !current {
+                            return;
+                        }
+                        let mut handler =
+                            on_selected_candidate_pair_change_handler_clone.lock().await;
+                        if let Some(f) = &mut *handler {
+                            f(ICECandidatePair::new(local, remote)).await;
+                        }
+                    })
+                },
+            ))
+            .await;
+    }
+
+    /// connect checks the connectivity with the remote agent and returns the conn of
+    /// the ICE agent, once a candidate pair was selected
+    async fn connect(
+        agent: &Agent,
+        role: ICERole,
+        cancel_rx: mpsc::Receiver<()>,
+        remote_ufrag: String,
+        remote_pwd: String,
+    ) -> Result<Arc<dyn Conn + Send + Sync>> {
+        let conn: Arc<dyn Conn + Send + Sync> = match role {
+            ICERole::Controlling => agent.dial(cancel_rx, remote_ufrag, remote_pwd).await?,
+            ICERole::Controlled => agent.accept(cancel_rx, remote_ufrag, remote_pwd).await?,
+            _ => return Err(Error::ErrICERoleUnknown.into()),
+        };
+        Ok(conn)
+    }
+
+    /// close_agent closes an ICE agent, which is already closed if it failed to connect
+    /// after a restart
+    async fn close_agent(agent: &Agent) -> Result<()> {
+        match agent.close().await {
+            Err(err) if ice::error::Error::ErrClosed.equal(&err) => Ok(()),
+            result => result,
+        }
+    }
+
     /// restart is not exposed currently because ORTC has users create a whole new ICETransport
     /// so for now lets keep it private so we don't cause ORTC users to depend on non-standard APIs
+    ///
+    /// The local credentials are always regenerated, as a restart requires new ones
+    /// (RFC 8445 section 9). Once the transport started, a new ICE agent replaces the one in
+    /// use, which keeps carrying the packets on its selected candidate pair until the new agent
+    /// connects (RFC 8445 section 9). Should the new agent fail instead, the transport fails
+    /// and the previous agent is kept. Before the transport started, the agent is restarted
+    /// in place, unless the ICE servers or the transport policy were changed since it was
+    /// created.
     pub(crate) async fn restart(&self) -> Result<()> {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let started = self.internal.lock().await.conn.is_some();
+        if started || self.gatherer.gather_options_changed() {
+            let replaced_agent = self.gatherer.replace_agent().await?;
+            let closed_agent = {
+                let mut internal = self.internal.lock().await;
+                if internal.conn.is_some() {
+                    internal.cancel_tx.take();
+                    internal.pending_connect = true;
+
+                    // An agent replaced before it connected never carried any packet
+                    let mut previous_agent = self.previous_agent.lock().await;
+                    if previous_agent.is_none() {
+                        *previous_agent = replaced_agent;
+                        None
+                    } else {
+                        replaced_agent
+                    }
+                } else {
+                    replaced_agent
+                }
+            };
+            if let Some(agent) = closed_agent {
+                ICETransport::close_agent(&agent).await?;
+            }
+        } else if let Some(agent) = self.gatherer.get_agent().await {
+            agent.restart(String::new(), String::new()).await?;
+        }
+
         if let Some(agent) = self.gatherer.get_agent().await {
-            agent
-                .restart(
-                    self.gatherer
-                        .setting_engine
-                        .candidates
-                        .username_fragment
-                        .clone(),
-                    self.gatherer.setting_engine.candidates.password.clone(),
-                )
-                .await?;
+            self.bind_agent(&agent, generation).await;
         } else {
             return Err(Error::ErrICEAgentNotExist.into());
         }
+        self.remote_end_of_candidates.store(false, Ordering::SeqCst);
+
         self.gatherer.gather().await
     }
 
@@ -214,6 +314,10 @@ impl ICETransport {
             }
         }
 
+        let previous_agent = self.previous_agent.lock().await.take();
+        if let Some(agent) = previous_agent {
+            agent.close().await?;
+        }
         self.gatherer.close().await?;
 
         Ok(())
@@ -256,7 +360,9 @@ impl ICETransport {
         }
     }
 
-    /// adds a candidate associated with the remote ICETransport.
+    /// adds a candidate associated with the remote ICETransport. None indicates the end
+    /// of the remote candidates, after which the transport fails as soon as all of its
+    /// candidate pairs have failed, rather than after the failed timeout.
     pub async fn add_remote_candidate(&self, remote_candidate: Option<ICECandidate>) -> Result<()> {
         self.ensure_gatherer().await?;
 
@@ -264,6 +370,8 @@ impl ICETransport {
             if let Some(r) = remote_candidate {
                 let c: Arc<dyn Candidate + Send + Sync> = Arc::new(r.to_ice().await?);
                 agent.add_remote_candidate(&c).await?;
+            } else if !self.remote_end_of_candidates.swap(true, Ordering::SeqCst) {
+                self.check_candidate_pairs();
             }
 
             Ok(())
@@ -272,6 +380,56 @@ impl ICETransport {
         }
     }
 
+    /// check_candidate_pairs fails the transport once every candidate pair has failed,
+    /// while checking with all the local and remote candidates known
+    fn check_candidate_pairs(&self) {
+        let gatherer = Arc::clone(&self.gatherer);
+        let state = Arc::clone(&self.state);
+        let on_connection_state_change_handler =
+            Arc::clone(&self.on_connection_state_change_handler);
+        let generation = Arc::clone(&self.generation);
+        let current_generation = generation.load(Ordering::SeqCst);
+        let failed = Arc::clone(&self.failed);
+
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(CANDIDATE_PAIRS_CHECK_INTERVAL).await;
+
+                if generation.load(Ordering::SeqCst) != current_generation {
+                    return;
+                }
+                match ICETransportState::from(state.load(Ordering::SeqCst)) {
+                    // Not started yet
+                    ICETransportState::New => continue,
+                    ICETransportState::Checking => {}
+                    _ => return,
+                }
+                if gatherer.state() != ICEGathererState::Complete {
+                    continue;
+                }
+
+                let agent = match gatherer.get_agent().await {
+                    Some(agent) => agent,
+                    None => return,
+                };
+                let pairs = agent.get_candidate_pairs_stats().await;
+                if !pairs.is_empty() && pairs.iter().all(|p| p.state == CandidatePairState::Failed)
+                {
+                    failed.notify_waiters();
+                    if state.swap(ICETransportState::Failed as u8, Ordering::SeqCst)
+                        != ICETransportState::Failed as u8
+                    {
+                        let mut handler = on_connection_state_change_handler.lock().await;
+                        if let Some(f) = &mut *handler {
+                            f(ICETransportState::Failed).await;
+                        }
+                    }
+                    return;
+                }
+            }
+        });
+    }
+
     /// State returns the current ice transport state.
     pub fn state(&self) -> ICETransportState {
         ICETransportState::from(self.state.load(Ordering::SeqCst))
@@ -298,27 +456,24 @@ impl ICETransport {
         }
     }
 
-    /*TODO: func (t *ICETransport) collectStats(collector *statsReportCollector) {
-        t.lock.Lock()
-        conn := t.conn
-        t.lock.Unlock()
-
-        collector.Collecting()
-
-        stats := TransportStats{
-            Timestamp: statsTimestampFrom(time.Now()),
-            Type:      StatsTypeTransport,
-            ID:        "ice_transport",
-        }
+    /// collect_stats reports the candidates and candidate pairs of this transport
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector) {
+        self.gatherer
+            .collect_stats(collector, ICE_TRANSPORT_STATS_ID)
+            .await;
+    }
 
-        if conn != nil {
-            stats.BytesSent = conn.BytesSent()
-            stats.BytesReceived = conn.BytesReceived()
-        }
+    /// bytes_sent returns the number of bytes sent over the selected candidate pair
+    pub(crate) async fn bytes_sent(&self) -> usize {
+        let internal = self.internal.lock().await;
+        internal.mux.as_ref().map_or(0, |mux| mux.bytes_sent())
+    }
 
-        collector.Collect(stats.ID, stats)
+    /// bytes_received returns the number of bytes received over the selected candidate pair
+    pub(crate) async fn bytes_received(&self) -> usize {
+        let internal = self.internal.lock().await;
+        internal.mux.as_ref().map_or(0, |mux| mux.bytes_received())
     }
-    */
 
     pub(crate) async fn have_remote_credentials_change(
         &self,
@@ -338,10 +493,82 @@ impl ICETransport {
         new_ufrag: String,
         new_pwd: String,
     ) -> Result<()> {
-        if let Some(agent) = self.gatherer.get_agent().await {
-            Ok(agent.set_remote_credentials(new_ufrag, new_pwd).await?)
-        } else {
-            Err(Error::ErrICEAgentNotExist.into())
+        let agent = match self.gatherer.get_agent().await {
+            Some(agent) => agent,
+            None => return Err(Error::ErrICEAgentNotExist.into()),
+        };
+
+        let mut internal = self.internal.lock().await;
+        if !internal.pending_connect {
+            return agent.set_remote_credentials(new_ufrag, new_pwd).await;
         }
+
+        // The agent which replaced the previous one checks the connectivity, its conn is
+        // then used by the transport and the previous agent is closed. If it fails instead,
+        // the transport fails and the packets keep going over the previous agent.
+        internal.pending_connect = false;
+        let (cancel_tx, cancel_rx) = mpsc::channel(1);
+        internal.cancel_tx = Some(cancel_tx);
+        let role = internal.role;
+        let conn = internal.conn.clone();
+        let state = Arc::clone(&self.state);
+        let on_connection_state_change_handler =
+            Arc::clone(&self.on_connection_state_change_handler);
+        let generation = Arc::clone(&self.generation);
+        let agent_generation = generation.load(Ordering::SeqCst);
+        let previous_agent = Arc::clone(&self.previous_agent);
+        let failed = Arc::clone(&self.failed);
+        tokio::spawn(async move {
+            let result = tokio::select! {
+                result = ICETransport::connect(&agent, role, cancel_rx, new_ufrag, new_pwd) => {
+                    result
+                }
+                _ = failed.notified() => Err(Error::ErrICEConnectionFailed.into()),
+            };
+
+            let (closed_agent, changed) = {
+                let mut previous_agent = previous_agent.lock().await;
+                // A later restart already replaced this agent too, or the transport stopped
+                if generation.load(Ordering::SeqCst) != agent_generation
+                    || state.load(Ordering::SeqCst) == ICETransportState::Closed as u8
+                {
+                    return;
+                }
+                match result {
+                    Ok(agent_conn) => {
+                        if let Some(conn) = conn {
+                            conn.replace(Some(agent_conn));
+                        }
+                        (previous_agent.take(), false)
+                    }
+                    Err(err) => {
+                        log::warn!("Failed to connect the restarted ICE agent: {}", err);
+                        // Its closing isn't a change of the transport state
+                        agent
+                            .on_connection_state_change(Box::new(|_: ConnectionState| {
+                                Box::pin(async {})
+                            }))
+                            .await;
+                        let changed = state.swap(ICETransportState::Failed as u8, Ordering::SeqCst)
+                            != ICETransportState::Failed as u8;
+                        (Some(agent), changed)
+                    }
+                }
+            };
+
+            if changed {
+                let mut handler = on_connection_state_change_handler.lock().await;
+                if let Some(f) = &mut *handler {
+                    f(ICETransportState::Failed).await;
+                }
+            }
+            if let Some(agent) = closed_agent {
+                if let Err(err) = ICETransport::close_agent(&agent).await {
+                    log::warn!("Failed to close the ICE agent: {}", err);
+                }
+            }
+        });
+
+        Ok(())
     }
 }
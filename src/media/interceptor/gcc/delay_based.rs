@@ -0,0 +1,334 @@
+use super::PacketResult;
+
+use std::collections::VecDeque;
+
+/// packets sent within 5ms of the first packet of a group belong to the same group
+const BURST_INTERVAL_US: i64 = 5_000;
+/// smoothing coefficient of the accumulated delay
+const SMOOTHING_COEF: f64 = 0.9;
+/// number of smoothed delay samples the trend is computed over
+const TRENDLINE_WINDOW_SIZE: usize = 20;
+/// gain applied to the trend before comparing it against the threshold
+const THRESHOLD_GAIN: f64 = 4.0;
+/// the trend is scaled by the number of deltas, up to this value
+const MAX_NUM_DELTAS: usize = 60;
+
+const INITIAL_THRESHOLD: f64 = 12.5;
+const MIN_THRESHOLD: f64 = 6.0;
+const MAX_THRESHOLD: f64 = 600.0;
+/// adaptation rates of the threshold when the trend is above or below it
+const K_UP: f64 = 0.0087;
+const K_DOWN: f64 = 0.039;
+/// trends further than this from the threshold don't adapt it
+const MAX_ADAPT_OFFSET_MS: f64 = 15.0;
+/// the trend must stay above the threshold this long before signaling overuse
+const OVERUSE_TIME_THRESHOLD_MS: f64 = 10.0;
+
+/// on overuse the rate drops to this fraction of the received rate
+const DECREASE_FACTOR: f64 = 0.85;
+/// minimum time between two decreases of the rate
+const MIN_DECREASE_INTERVAL_US: i64 = 200_000;
+/// growth of the rate per second of normal usage
+const INCREASE_FACTOR_PER_SECOND: f64 = 1.08;
+/// the received rate is measured over the last second of arrivals
+const RECEIVED_RATE_WINDOW_US: i64 = 1_000_000;
+/// the received rate isn't trusted until arrivals span at least 100ms
+const MIN_RECEIVED_RATE_WINDOW_US: i64 = 100_000;
+
+/// BandwidthUsage is the state of the network path inferred from the delay variation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum BandwidthUsage {
+    Normal,
+    Overusing,
+    Underusing,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct PacketGroup {
+    first_send_time_us: i64,
+    last_send_time_us: i64,
+    last_arrival_time_us: i64,
+}
+
+/// TrendlineEstimator estimates the trend of the one way delay variation, with a
+/// linear regression over a window of smoothed accumulated delays.
+struct TrendlineEstimator {
+    num_deltas: usize,
+    first_arrival_time_ms: Option<f64>,
+    accumulated_delay_ms: f64,
+    smoothed_delay_ms: f64,
+    samples: VecDeque<(f64, f64)>,
+    trend: f64,
+}
+
+impl TrendlineEstimator {
+    fn new() -> Self {
+        TrendlineEstimator {
+            num_deltas: 0,
+            first_arrival_time_ms: None,
+            accumulated_delay_ms: 0.0,
+            smoothed_delay_ms: 0.0,
+            samples: VecDeque::with_capacity(TRENDLINE_WINDOW_SIZE + 1),
+            trend: 0.0,
+        }
+    }
+
+    /// update adds the delay variation between two packet groups and returns the
+    /// modified trend, to be compared against the overuse threshold.
+    fn update(&mut self, delay_variation_ms: f64, arrival_time_ms: f64) -> f64 {
+        self.num_deltas = (self.num_deltas + 1).min(MAX_NUM_DELTAS);
+        let first_arrival_time_ms = *self.first_arrival_time_ms.get_or_insert(arrival_time_ms);
+
+        self.accumulated_delay_ms += delay_variation_ms;
+        self.smoothed_delay_ms = SMOOTHING_COEF * self.smoothed_delay_ms
+            + (1.0 - SMOOTHING_COEF) * self.accumulated_delay_ms;
+
+        self.samples.push_back((
+            arrival_time_ms - first_arrival_time_ms,
+            self.smoothed_delay_ms,
+        ));
+        if self.samples.len() > TRENDLINE_WINDOW_SIZE {
+            self.samples.pop_front();
+        }
+
+        if self.samples.len() == TRENDLINE_WINDOW_SIZE {
+            if let Some(slope) = linear_fit_slope(&self.samples) {
+                self.trend = slope;
+            }
+        }
+
+        self.trend * self.num_deltas as f64 * THRESHOLD_GAIN
+    }
+}
+
+fn linear_fit_slope(samples: &VecDeque<(f64, f64)>) -> Option<f64> {
+    let n = samples.len() as f64;
+    let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let mut numerator = 0.0;
+    let mut denominator = 0.0;
+    for (x, y) in samples {
+        numerator += (x - mean_x) * (y - mean_y);
+        denominator += (x - mean_x) * (x - mean_x);
+    }
+
+    if denominator == 0.0 {
+        None
+    } else {
+        Some(numerator / denominator)
+    }
+}
+
+/// OveruseDetector compares the delay trend against an adaptive threshold.
+struct OveruseDetector {
+    threshold: f64,
+    last_update_ms: Option<f64>,
+    time_over_using_ms: f64,
+    overuse_counter: usize,
+    prev_trend: f64,
+    state: BandwidthUsage,
+}
+
+impl OveruseDetector {
+    fn new() -> Self {
+        OveruseDetector {
+            threshold: INITIAL_THRESHOLD,
+            last_update_ms: None,
+            time_over_using_ms: -1.0,
+            overuse_counter: 0,
+            prev_trend: 0.0,
+            state: BandwidthUsage::Normal,
+        }
+    }
+
+    fn detect(&mut self, trend: f64, send_delta_ms: f64, now_ms: f64) {
+        if trend > self.threshold {
+            if self.time_over_using_ms < 0.0 {
+                // assume the overuse started halfway between the two groups
+                self.time_over_using_ms = send_delta_ms / 2.0;
+            } else {
+                self.time_over_using_ms += send_delta_ms;
+            }
+            self.overuse_counter += 1;
+
+            if self.time_over_using_ms > OVERUSE_TIME_THRESHOLD_MS
+                && self.overuse_counter > 1
+                && trend >= self.prev_trend
+            {
+                self.time_over_using_ms = 0.0;
+                self.overuse_counter = 0;
+                self.state = BandwidthUsage::Overusing;
+            }
+        } else if trend < -self.threshold {
+            self.time_over_using_ms = -1.0;
+            self.overuse_counter = 0;
+            self.state = BandwidthUsage::Underusing;
+        } else {
+            self.time_over_using_ms = -1.0;
+            self.overuse_counter = 0;
+            self.state = BandwidthUsage::Normal;
+        }
+        self.prev_trend = trend;
+
+        self.update_threshold(trend, now_ms);
+    }
+
+    fn update_threshold(&mut self, trend: f64, now_ms: f64) {
+        let last_update_ms = *self.last_update_ms.get_or_insert(now_ms);
+        self.last_update_ms = Some(now_ms);
+
+        // sudden spikes, e.g. caused by a route change, shouldn't move the threshold
+        if trend.abs() > self.threshold + MAX_ADAPT_OFFSET_MS {
+            return;
+        }
+
+        let k = if trend.abs() < self.threshold {
+            K_DOWN
+        } else {
+            K_UP
+        };
+        let elapsed_ms = (now_ms - last_update_ms).clamp(0.0, 100.0);
+        self.threshold += k * (trend.abs() - self.threshold) * elapsed_ms;
+        self.threshold = self.threshold.clamp(MIN_THRESHOLD, MAX_THRESHOLD);
+    }
+}
+
+/// DelayBasedEstimator estimates the available bandwidth from the variation of the
+/// one way delay between groups of packets, decreasing the rate as soon as queues
+/// build up along the path.
+pub(crate) struct DelayBasedEstimator {
+    min_bitrate: u64,
+    max_bitrate: u64,
+    rate: u64,
+
+    current_group: Option<PacketGroup>,
+    previous_group: Option<PacketGroup>,
+    trendline: TrendlineEstimator,
+    detector: OveruseDetector,
+
+    /// arrival time and size of the packets received over the last second
+    received: VecDeque<(i64, usize)>,
+    last_update_us: Option<i64>,
+    last_decrease_us: Option<i64>,
+}
+
+impl DelayBasedEstimator {
+    pub(crate) fn new(initial_bitrate: u64, min_bitrate: u64, max_bitrate: u64) -> Self {
+        DelayBasedEstimator {
+            min_bitrate,
+            max_bitrate,
+            rate: initial_bitrate,
+
+            current_group: None,
+            previous_group: None,
+            trendline: TrendlineEstimator::new(),
+            detector: OveruseDetector::new(),
+
+            received: VecDeque::new(),
+            last_update_us: None,
+            last_decrease_us: None,
+        }
+    }
+
+    /// usage returns the latest state of the network path
+    pub(crate) fn usage(&self) -> BandwidthUsage {
+        self.detector.state
+    }
+
+    /// received_rate returns the rate in bits per second the remote received the
+    /// packets at over the last second, if enough packets were received.
+    pub(crate) fn received_rate(&self) -> Option<u64> {
+        let (first, _) = self.received.front()?;
+        let (last, _) = self.received.back()?;
+        let window = last - first;
+        if window < MIN_RECEIVED_RATE_WINDOW_US {
+            return None;
+        }
+
+        let bytes: usize = self.received.iter().map(|(_, size)| size).sum();
+        Some(bytes as u64 * 8 * 1_000_000 / window as u64)
+    }
+
+    /// update processes the packets reported by a feedback, in sending order, and
+    /// returns the new delay based estimate.
+    pub(crate) fn update(&mut self, packets: &[PacketResult], now_us: i64) -> u64 {
+        for p in packets {
+            if let Some(arrival_time_us) = p.arrival_time_us {
+                self.on_packet_received(p.send_time_us, arrival_time_us, p.size);
+            }
+        }
+
+        let received_rate = self.received_rate();
+        match self.detector.state {
+            BandwidthUsage::Overusing => {
+                if !matches!(self.last_decrease_us, Some(t) if now_us - t < MIN_DECREASE_INTERVAL_US)
+                {
+                    let base = received_rate.unwrap_or(self.rate) as f64;
+                    self.rate = self.rate.min((base * DECREASE_FACTOR) as u64);
+                    self.last_decrease_us = Some(now_us);
+                }
+            }
+            BandwidthUsage::Normal => {
+                if let Some(last_update_us) = self.last_update_us {
+                    let elapsed = (now_us - last_update_us).clamp(0, 1_000_000) as f64 / 1e6;
+                    let mut rate =
+                        (self.rate as f64 * INCREASE_FACTOR_PER_SECOND.powf(elapsed)) as u64;
+                    // don't grow much beyond what the path is known to deliver
+                    if let Some(received_rate) = received_rate {
+                        rate = rate.min((received_rate * 3 / 2 + 10_000).max(self.rate));
+                    }
+                    self.rate = rate;
+                }
+            }
+            BandwidthUsage::Underusing => {
+                // hold the rate while the queues drain
+            }
+        }
+        self.last_update_us = Some(now_us);
+
+        self.rate = self.rate.clamp(self.min_bitrate, self.max_bitrate);
+        self.rate
+    }
+
+    fn on_packet_received(&mut self, send_time_us: i64, arrival_time_us: i64, size: usize) {
+        self.received.push_back((arrival_time_us, size));
+        while let Some(&(t, _)) = self.received.front() {
+            if arrival_time_us - t > RECEIVED_RATE_WINDOW_US {
+                self.received.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if let Some(group) = &mut self.current_group {
+            if send_time_us - group.first_send_time_us <= BURST_INTERVAL_US {
+                group.last_send_time_us = group.last_send_time_us.max(send_time_us);
+                group.last_arrival_time_us = group.last_arrival_time_us.max(arrival_time_us);
+                return;
+            }
+        }
+
+        let completed = self.current_group.replace(PacketGroup {
+            first_send_time_us: send_time_us,
+            last_send_time_us: send_time_us,
+            last_arrival_time_us: arrival_time_us,
+        });
+        if let Some(completed) = completed {
+            if let Some(previous) = self.previous_group {
+                let send_delta_ms =
+                    (completed.last_send_time_us - previous.last_send_time_us) as f64 / 1000.0;
+                let arrival_delta_ms = (completed.last_arrival_time_us
+                    - previous.last_arrival_time_us) as f64
+                    / 1000.0;
+                let arrival_time_ms = completed.last_arrival_time_us as f64 / 1000.0;
+
+                let trend = self
+                    .trendline
+                    .update(arrival_delta_ms - send_delta_ms, arrival_time_ms);
+                self.detector.detect(trend, send_delta_ms, arrival_time_ms);
+            }
+            self.previous_group = Some(completed);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use super::delay_based::{BandwidthUsage, DelayBasedEstimator};
+use super::loss_based::LossBasedEstimator;
+use super::send_side_bwe::SendSideBWE;
+use super::*;
+use crate::error::Error;
+use crate::media::interceptor::twcc::recorder::Recorder;
+use crate::media::interceptor::twcc::TRANSPORT_CC_URI;
+use crate::media::interceptor::ATTRIBUTE_TARGET_BITRATE;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use interceptor::stream_info::{RTPHeaderExtension, StreamInfo};
+use interceptor::{Attributes, Interceptor, RTCPReader, RTPWriter};
+use rtp::extension::transport_cc_extension::TransportCcExtension;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use util::Marshal;
+
+/// packet_results returns count packets of 1200 bytes sent every 10ms from
+/// first, arriving 50ms later plus the given extra delay per packet
+fn packet_results(first: i64, count: i64, extra_delay_us: i64) -> Vec<PacketResult> {
+    (first..first + count)
+        .map(|i| PacketResult {
+            send_time_us: i * 10_000,
+            arrival_time_us: Some(50_000 + i * (10_000 + extra_delay_us)),
+            size: 1200,
+        })
+        .collect()
+}
+
+#[test]
+fn test_loss_based_estimator() {
+    let mut estimator = LossBasedEstimator::new(30_000, 1_000_000);
+
+    // nothing reported
+    assert_eq!(300_000, estimator.update(300_000, 0, 0));
+    // low loss
+    assert_eq!(315_000, estimator.update(300_000, 1, 100));
+    // moderate loss
+    assert_eq!(300_000, estimator.update(300_000, 5, 100));
+    // high loss
+    assert_eq!(270_000, estimator.update(300_000, 20, 100));
+    assert!((estimator.fraction_lost() - 0.2).abs() < f64::EPSILON);
+
+    // estimates are kept within bounds
+    assert_eq!(30_000, estimator.update(30_000, 50, 100));
+    assert_eq!(1_000_000, estimator.update(1_000_000, 0, 100));
+}
+
+#[test]
+fn test_delay_based_estimator_normal() {
+    let mut estimator = DelayBasedEstimator::new(300_000, 30_000, 10_000_000);
+
+    // a constant delay leaves room to increase the rate
+    let mut rate = 0;
+    for i in 0..30 {
+        rate = estimator.update(&packet_results(i * 10, 10, 0), (i + 1) * 100_000);
+    }
+
+    assert_eq!(BandwidthUsage::Normal, estimator.usage());
+    assert!(rate > 300_000, "rate {} should have increased", rate);
+    assert!(rate < 300_000 * 3 / 2, "rate {} increased too fast", rate);
+
+    let received_rate = estimator.received_rate().unwrap();
+    assert!(
+        (900_000..1_000_000).contains(&received_rate),
+        "received rate {} should be about 960kbps",
+        received_rate
+    );
+}
+
+#[test]
+fn test_delay_based_estimator_overuse() {
+    let mut estimator = DelayBasedEstimator::new(1_000_000, 30_000, 10_000_000);
+
+    // every packet is queued 5ms longer than the previous one
+    let mut rate = 0;
+    for i in 0..10 {
+        rate = estimator.update(&packet_results(i * 10, 10, 5_000), (i + 1) * 100_000);
+    }
+
+    assert_eq!(BandwidthUsage::Overusing, estimator.usage());
+    // the rate drops below what the path delivers, about 640kbps
+    assert!(rate < 640_000, "rate {} should have decreased", rate);
+    assert!(rate >= 30_000);
+}
+
+#[test]
+fn test_send_side_bwe_with_bitrates() {
+    assert_eq!(300_000, SendSideBWE::new().target_bitrate());
+
+    let bwe = SendSideBWE::with_bitrates(100_000, 50_000, 200_000).unwrap();
+    assert_eq!(100_000, bwe.target_bitrate());
+
+    for (initial, min, max) in &[
+        (100_000, 0, 200_000),
+        (100_000, 150_000, 200_000),
+        (300_000, 50_000, 200_000),
+    ] {
+        let err = SendSideBWE::with_bitrates(*initial, *min, *max)
+            .err()
+            .unwrap();
+        assert!(Error::ErrInvalidBitrateBounds.equal(&err));
+    }
+}
+
+struct MockRTPWriter;
+
+#[async_trait]
+impl RTPWriter for MockRTPWriter {
+    async fn write(&self, pkt: &rtp::packet::Packet, _a: &Attributes) -> Result<usize> {
+        Ok(pkt.payload.len())
+    }
+}
+
+struct MockRTCPReader {
+    rx: Mutex<mpsc::Receiver<Bytes>>,
+}
+
+#[async_trait]
+impl RTCPReader for MockRTCPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let mut rx = self.rx.lock().await;
+        let b = rx.recv().await.unwrap_or_default();
+        buf[..b.len()].copy_from_slice(&b);
+        Ok((b.len(), a.clone()))
+    }
+}
+
+#[tokio::test]
+async fn test_send_side_bwe() -> Result<()> {
+    let bwe = SendSideBWE::new();
+
+    let info = StreamInfo {
+        ssrc: 123456,
+        rtp_header_extensions: vec![RTPHeaderExtension {
+            uri: TRANSPORT_CC_URI.to_owned(),
+            id: 5,
+        }],
+        ..Default::default()
+    };
+    let writer = bwe.bind_local_stream(&info, Arc::new(MockRTPWriter)).await;
+
+    let (tx, rx) = mpsc::channel(1);
+    let reader = bwe
+        .bind_rtcp_reader(Arc::new(MockRTCPReader { rx: Mutex::new(rx) }))
+        .await;
+
+    // every odd packet is lost
+    let mut recorder = Recorder::new(1);
+    for transport_sequence in 0..10u16 {
+        let mut pkt = rtp::packet::Packet {
+            header: rtp::header::Header {
+                version: 2,
+                ssrc: 123456,
+                ..Default::default()
+            },
+            payload: Bytes::from_static(&[0u8; 1000]),
+        };
+        pkt.header
+            .set_extension(5, TransportCcExtension { transport_sequence }.marshal()?)?;
+        writer.write(&pkt, &Attributes::new()).await?;
+
+        if transport_sequence % 2 == 0 {
+            recorder.record(
+                123456,
+                transport_sequence,
+                transport_sequence as i64 * 1_000,
+            );
+        }
+    }
+
+    tx.send(recorder.build_feedback().unwrap().marshal()?)
+        .await?;
+    let mut buf = vec![0u8; 1500];
+    let (_, attributes) = reader.read(&mut buf, &Attributes::new()).await?;
+
+    let target_bitrate = bwe.target_bitrate();
+    assert!(
+        target_bitrate < 300_000,
+        "target bitrate {} should have decreased",
+        target_bitrate
+    );
+    assert_eq!(
+        Some(&(target_bitrate as usize)),
+        attributes.get(&ATTRIBUTE_TARGET_BITRATE)
+    );
+
+    Ok(())
+}
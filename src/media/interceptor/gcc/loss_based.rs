@@ -0,0 +1,47 @@
+/// below this fraction of lost packets the rate is increased
+const LOW_LOSS_THRESHOLD: f64 = 0.02;
+/// above this fraction of lost packets the rate is decreased
+const HIGH_LOSS_THRESHOLD: f64 = 0.1;
+const INCREASE_FACTOR: f64 = 1.05;
+
+/// LossBasedEstimator adapts the target bitrate to the fraction of packets lost,
+/// as reported by each feedback.
+pub(crate) struct LossBasedEstimator {
+    min_bitrate: u64,
+    max_bitrate: u64,
+    fraction_lost: f64,
+}
+
+impl LossBasedEstimator {
+    pub(crate) fn new(min_bitrate: u64, max_bitrate: u64) -> Self {
+        LossBasedEstimator {
+            min_bitrate,
+            max_bitrate,
+            fraction_lost: 0.0,
+        }
+    }
+
+    /// fraction_lost returns the fraction of packets lost reported by the latest feedback
+    pub(crate) fn fraction_lost(&self) -> f64 {
+        self.fraction_lost
+    }
+
+    /// update returns the loss based estimate derived from the current target
+    /// bitrate, given that lost out of total packets were reported lost.
+    pub(crate) fn update(&mut self, target_bitrate: u64, lost: usize, total: usize) -> u64 {
+        if total == 0 {
+            return target_bitrate;
+        }
+        self.fraction_lost = lost as f64 / total as f64;
+
+        let rate = if self.fraction_lost < LOW_LOSS_THRESHOLD {
+            target_bitrate as f64 * INCREASE_FACTOR
+        } else if self.fraction_lost > HIGH_LOSS_THRESHOLD {
+            target_bitrate as f64 * (1.0 - 0.5 * self.fraction_lost)
+        } else {
+            target_bitrate as f64
+        };
+
+        (rate as u64).clamp(self.min_bitrate, self.max_bitrate)
+    }
+}
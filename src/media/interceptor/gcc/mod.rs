@@ -0,0 +1,19 @@
+#[cfg(test)]
+mod gcc_test;
+
+pub mod send_side_bwe;
+
+mod delay_based;
+mod loss_based;
+
+/// PacketResult is the outcome of a sent packet, as reported by a transport-wide
+/// congestion control feedback.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct PacketResult {
+    /// send time in microseconds, on the local clock
+    pub(crate) send_time_us: i64,
+    /// arrival time in microseconds, on the remote clock, None if the packet was lost
+    pub(crate) arrival_time_us: Option<i64>,
+    /// size of the packet in bytes
+    pub(crate) size: usize,
+}
@@ -0,0 +1,277 @@
+use super::delay_based::DelayBasedEstimator;
+use super::loss_based::LossBasedEstimator;
+use super::PacketResult;
+use crate::error::Error;
+use crate::media::interceptor::twcc::{
+    packet_arrivals, transport_cc_extension_id, transport_sequence_number,
+};
+use crate::media::interceptor::{unmarshal_rtcp, ATTRIBUTE_TARGET_BITRATE};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use interceptor::stream_info::StreamInfo;
+use interceptor::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use rtcp::transport_feedbacks::transport_layer_cc::TransportLayerCc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use util::MarshalSize;
+
+const DEFAULT_INITIAL_BITRATE: u64 = 300_000;
+const DEFAULT_MIN_BITRATE: u64 = 30_000;
+const DEFAULT_MAX_BITRATE: u64 = 10_000_000;
+
+#[derive(Debug, Copy, Clone)]
+struct SentPacket {
+    send_time_us: i64,
+    size: usize,
+}
+
+struct Estimators {
+    delay_based: DelayBasedEstimator,
+    loss_based: LossBasedEstimator,
+}
+
+struct SendSideBWEInternal {
+    min_bitrate: u64,
+    max_bitrate: u64,
+    start_time: Instant,
+    /// sent packets which weren't reported yet, keyed by transport-wide sequence number
+    sent_packets: Mutex<HashMap<u16, SentPacket>>,
+    estimators: Mutex<Estimators>,
+    target_bitrate: AtomicU64,
+}
+
+impl SendSideBWEInternal {
+    async fn on_feedback(&self, feedback: &TransportLayerCc) {
+        let packets: Vec<PacketResult> = {
+            let mut sent_packets = self.sent_packets.lock().await;
+            packet_arrivals(feedback)
+                .into_iter()
+                .filter_map(|(sequence_number, arrival_time_us)| {
+                    sent_packets
+                        .remove(&sequence_number)
+                        .map(|sent| PacketResult {
+                            send_time_us: sent.send_time_us,
+                            arrival_time_us,
+                            size: sent.size,
+                        })
+                })
+                .collect()
+        };
+        if packets.is_empty() {
+            return;
+        }
+
+        let lost = packets
+            .iter()
+            .filter(|p| p.arrival_time_us.is_none())
+            .count();
+        let now_us = self.start_time.elapsed().as_micros() as i64;
+
+        let mut estimators = self.estimators.lock().await;
+        let target_bitrate = self.target_bitrate.load(Ordering::SeqCst);
+        let delay_based = estimators.delay_based.update(&packets, now_us);
+        let loss_based = estimators
+            .loss_based
+            .update(target_bitrate, lost, packets.len());
+
+        let target_bitrate = delay_based
+            .min(loss_based)
+            .clamp(self.min_bitrate, self.max_bitrate);
+        log::trace!(
+            "bandwidth estimate {} bps (delay based {} bps: {:?}, loss based {} bps: {} lost)",
+            target_bitrate,
+            delay_based,
+            estimators.delay_based.usage(),
+            loss_based,
+            estimators.loss_based.fraction_lost(),
+        );
+        self.target_bitrate.store(target_bitrate, Ordering::SeqCst);
+    }
+}
+
+/// SendSideBWE interceptor estimates the bandwidth available to the outgoing
+/// streams, combining a delay based and a loss based estimate computed from the
+/// transport-wide congestion control feedback sent by the remote.
+///
+/// Outgoing packets must carry a transport-wide sequence number, which is added
+/// by the twcc HeaderExtensionInterceptor when it is registered after this one.
+pub struct SendSideBWE {
+    internal: Arc<SendSideBWEInternal>,
+}
+
+impl Default for SendSideBWE {
+    fn default() -> Self {
+        SendSideBWE::new()
+    }
+}
+
+impl SendSideBWE {
+    /// new returns a new SendSideBWE starting at 300kbps, estimating between 30kbps and 10Mbps.
+    pub fn new() -> Self {
+        SendSideBWE::build(
+            DEFAULT_INITIAL_BITRATE,
+            DEFAULT_MIN_BITRATE,
+            DEFAULT_MAX_BITRATE,
+        )
+    }
+
+    /// with_bitrates returns a new SendSideBWE starting at initial_bitrate, estimating
+    /// between min_bitrate and max_bitrate, all of them in bits per second.
+    pub fn with_bitrates(initial_bitrate: u64, min_bitrate: u64, max_bitrate: u64) -> Result<Self> {
+        if min_bitrate == 0 || min_bitrate > initial_bitrate || initial_bitrate > max_bitrate {
+            return Err(Error::ErrInvalidBitrateBounds.into());
+        }
+
+        Ok(SendSideBWE::build(
+            initial_bitrate,
+            min_bitrate,
+            max_bitrate,
+        ))
+    }
+
+    fn build(initial_bitrate: u64, min_bitrate: u64, max_bitrate: u64) -> Self {
+        SendSideBWE {
+            internal: Arc::new(SendSideBWEInternal {
+                min_bitrate,
+                max_bitrate,
+                start_time: Instant::now(),
+                sent_packets: Mutex::new(HashMap::new()),
+                estimators: Mutex::new(Estimators {
+                    delay_based: DelayBasedEstimator::new(
+                        initial_bitrate,
+                        min_bitrate,
+                        max_bitrate,
+                    ),
+                    loss_based: LossBasedEstimator::new(min_bitrate, max_bitrate),
+                }),
+                target_bitrate: AtomicU64::new(initial_bitrate),
+            }),
+        }
+    }
+
+    /// target_bitrate returns the current estimate of the available bandwidth, in bits per second.
+    pub fn target_bitrate(&self) -> u64 {
+        self.internal.target_bitrate.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Interceptor for SendSideBWE {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        Arc::new(SendSideBWERTCPReader {
+            internal: Arc::clone(&self.internal),
+            parent_rtcp_reader: reader,
+        })
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        let id = match transport_cc_extension_id(info) {
+            Some(id) => id,
+            None => return writer,
+        };
+
+        Arc::new(SendSideBWERTPWriter {
+            id,
+            internal: Arc::clone(&self.internal),
+            parent_rtp_writer: writer,
+        })
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        reader
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct SendSideBWERTPWriter {
+    id: u8,
+    internal: Arc<SendSideBWEInternal>,
+    parent_rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPWriter for SendSideBWERTPWriter {
+    async fn write(&self, pkt: &rtp::packet::Packet, a: &Attributes) -> Result<usize> {
+        let n = self.parent_rtp_writer.write(pkt, a).await?;
+
+        if let Some(sequence_number) = transport_sequence_number(&pkt.header, self.id) {
+            let sent = SentPacket {
+                send_time_us: self.internal.start_time.elapsed().as_micros() as i64,
+                size: pkt.marshal_size(),
+            };
+            let mut sent_packets = self.internal.sent_packets.lock().await;
+            sent_packets.insert(sequence_number, sent);
+        }
+
+        Ok(n)
+    }
+}
+
+struct SendSideBWERTCPReader {
+    internal: Arc<SendSideBWEInternal>,
+    parent_rtcp_reader: Arc<dyn RTCPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTCPReader for SendSideBWERTCPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, mut attr) = self.parent_rtcp_reader.read(buf, a).await?;
+
+        let feedbacks: Vec<TransportLayerCc> = match unmarshal_rtcp(&buf[..n]) {
+            Ok(pkts) => pkts
+                .iter()
+                .filter_map(|p| p.as_any().downcast_ref::<TransportLayerCc>().cloned())
+                .collect(),
+            Err(_) => vec![],
+        };
+        for feedback in &feedbacks {
+            self.internal.on_feedback(feedback).await;
+        }
+
+        attr.insert(
+            ATTRIBUTE_TARGET_BITRATE,
+            self.internal.target_bitrate.load(Ordering::SeqCst) as usize,
+        );
+        Ok((n, attr))
+    }
+}
@@ -1,3 +1,8 @@
+pub mod gcc;
+pub mod nack;
+pub mod report;
+pub mod twcc;
+
 use crate::media::rtp::rtp_codec::{RTPCodecCapability, RTPHeaderExtensionParameter};
 use crate::media::rtp::{PayloadType, SSRC};
 use crate::media::track::track_local::TrackLocalWriter;
@@ -6,19 +11,66 @@ use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
 use interceptor::stream_info::{RTCPFeedback, RTPHeaderExtension, StreamInfo};
-use interceptor::{Attributes, RTPWriter};
+use interceptor::{Attributes, Interceptor, RTPWriter};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use util::Unmarshal;
 
+/// ATTRIBUTE_RTX_SSRC is the StreamInfo attribute holding the SSRC of the RTX (RFC 4588)
+/// repair stream of a local stream, when RTX was negotiated for it
+pub(crate) const ATTRIBUTE_RTX_SSRC: usize = 1;
+/// ATTRIBUTE_RTX_PAYLOAD_TYPE is the StreamInfo attribute holding the payload type of the
+/// RTX (RFC 4588) repair stream of a local stream, when RTX was negotiated for it
+pub(crate) const ATTRIBUTE_RTX_PAYLOAD_TYPE: usize = 2;
+/// ATTRIBUTE_TARGET_BITRATE is the RTCP read attribute holding the target bitrate in bits
+/// per second estimated by the gcc SendSideBWE interceptor
+pub(crate) const ATTRIBUTE_TARGET_BITRATE: usize = 3;
+
+/// InterceptorBuilder creates an interceptor for each PeerConnection. Interceptors are bound
+/// to the RTCP writer and the streams of a single PeerConnection, so the PeerConnections
+/// built from an API can't share them.
+pub trait InterceptorBuilder {
+    fn build(&self) -> Arc<dyn Interceptor + Send + Sync>;
+}
+
+impl<F, I> InterceptorBuilder for F
+where
+    F: Fn() -> Arc<I>,
+    I: Interceptor + Send + Sync + 'static,
+{
+    fn build(&self) -> Arc<dyn Interceptor + Send + Sync> {
+        self()
+    }
+}
+
 pub(crate) struct InterceptorToTrackLocalWriter {
     pub(crate) interceptor_rtp_writer: Mutex<Option<Arc<dyn RTPWriter + Send + Sync>>>,
+    /// header_extensions are set on every packet written, e.g. the MID and RID of a simulcast layer
+    header_extensions: Vec<(u8, Bytes)>,
+    /// paused drops the packets written, while the negotiated direction doesn't include sending
+    paused: Arc<AtomicBool>,
 }
 
 impl InterceptorToTrackLocalWriter {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(paused: Arc<AtomicBool>) -> Self {
         InterceptorToTrackLocalWriter {
             interceptor_rtp_writer: Mutex::new(None),
+            header_extensions: vec![],
+            paused,
+        }
+    }
+
+    /// with_header_extensions returns a writer that sets the given header extensions,
+    /// keyed by their negotiated id, on every outgoing packet.
+    pub(crate) fn with_header_extensions(
+        header_extensions: Vec<(u8, Bytes)>,
+        paused: Arc<AtomicBool>,
+    ) -> Self {
+        InterceptorToTrackLocalWriter {
+            interceptor_rtp_writer: Mutex::new(None),
+            header_extensions,
+            paused,
         }
     }
 }
@@ -31,19 +83,29 @@ impl std::fmt::Debug for InterceptorToTrackLocalWriter {
 
 impl Default for InterceptorToTrackLocalWriter {
     fn default() -> Self {
-        InterceptorToTrackLocalWriter {
-            interceptor_rtp_writer: Mutex::new(None),
-        }
+        InterceptorToTrackLocalWriter::new(Arc::new(AtomicBool::new(false)))
     }
 }
 
 #[async_trait]
 impl TrackLocalWriter for InterceptorToTrackLocalWriter {
     async fn write_rtp(&self, pkt: &rtp::packet::Packet) -> Result<usize> {
+        if self.paused.load(Ordering::SeqCst) {
+            return Ok(0);
+        }
+
         let interceptor_rtp_writer = self.interceptor_rtp_writer.lock().await;
         if let Some(writer) = &*interceptor_rtp_writer {
             let a = Attributes::new();
-            writer.write(pkt, &a).await
+            if self.header_extensions.is_empty() {
+                writer.write(pkt, &a).await
+            } else {
+                let mut pkt = pkt.clone();
+                for (id, payload) in &self.header_extensions {
+                    pkt.header.set_extension(*id, payload.clone())?;
+                }
+                writer.write(&pkt, &a).await
+            }
         } else {
             Ok(0)
         }
@@ -92,3 +154,19 @@ pub(crate) fn create_stream_info(
         rtcp_feedback: feedbacks,
     }
 }
+
+/// unmarshal_rtcp parses a batch of RTCP packets, flattening compound packets
+/// so that every packet of the batch can be inspected on its own.
+pub(crate) fn unmarshal_rtcp(b: &[u8]) -> Result<Vec<Box<dyn rtcp::packet::Packet>>> {
+    let mut buf = b;
+    let pkt = rtcp::packet::unmarshal(&mut buf)?;
+
+    if let Some(cp) = pkt
+        .as_any()
+        .downcast_ref::<rtcp::compound_packet::CompoundPacket>()
+    {
+        Ok(cp.0.iter().map(|p| p.cloned()).collect())
+    } else {
+        Ok(vec![pkt])
+    }
+}
@@ -0,0 +1,222 @@
+use super::receive_log::ReceiveLog;
+use super::{nack_pairs_from_sequence_numbers, stream_supports_nack};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use interceptor::stream_info::StreamInfo;
+use interceptor::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use rtcp::transport_feedbacks::transport_layer_nack::TransportLayerNack;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Duration, Instant};
+use util::Unmarshal;
+
+struct GeneratorInterceptorInternal {
+    skip_last_n: u16,
+    interval: Duration,
+    sender_ssrc: u32,
+    receive_logs: Mutex<HashMap<u32, Arc<Mutex<ReceiveLog>>>>,
+    rtcp_writer: Mutex<Option<Arc<dyn RTCPWriter + Send + Sync>>>,
+    close_rx: Mutex<Option<mpsc::Receiver<()>>>,
+}
+
+/// GeneratorInterceptor interceptor generates nack feedback messages.
+pub struct GeneratorInterceptor {
+    size: u16,
+    internal: Arc<GeneratorInterceptorInternal>,
+    close_tx: Mutex<Option<mpsc::Sender<()>>>,
+}
+
+impl Default for GeneratorInterceptor {
+    fn default() -> Self {
+        GeneratorInterceptor::new()
+    }
+}
+
+impl GeneratorInterceptor {
+    /// new returns a new GeneratorInterceptor which keeps the state of the last
+    /// 512 packets of every stream and sends nacks every 100ms.
+    pub fn new() -> Self {
+        GeneratorInterceptor::build(512, 0, Duration::from_millis(100))
+    }
+
+    /// with_options returns a new GeneratorInterceptor.
+    /// * size sets the size of the interceptor, it must be a power of two between 64 and 32768.
+    /// * skip_last_n sets the number of packets (n-1 packets before the last received packets) to ignore when generating nack requests.
+    /// * interval sets the nack send interval for the interceptor.
+    pub fn with_options(size: u16, skip_last_n: u16, interval: Duration) -> Result<Self> {
+        // validate the size once, so binding a stream can not fail later on
+        let _ = ReceiveLog::new(size)?;
+
+        Ok(GeneratorInterceptor::build(size, skip_last_n, interval))
+    }
+
+    fn build(size: u16, skip_last_n: u16, interval: Duration) -> Self {
+        let (close_tx, close_rx) = mpsc::channel(1);
+        GeneratorInterceptor {
+            size,
+            internal: Arc::new(GeneratorInterceptorInternal {
+                skip_last_n,
+                interval,
+                sender_ssrc: rand::random::<u32>(),
+                receive_logs: Mutex::new(HashMap::new()),
+                rtcp_writer: Mutex::new(None),
+                close_rx: Mutex::new(Some(close_rx)),
+            }),
+            close_tx: Mutex::new(Some(close_tx)),
+        }
+    }
+
+    async fn run(internal: Arc<GeneratorInterceptorInternal>, mut close_rx: mpsc::Receiver<()>) {
+        let mut ticker =
+            tokio::time::interval_at(Instant::now() + internal.interval, internal.interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let receive_logs: Vec<(u32, Arc<Mutex<ReceiveLog>>)> = {
+                        let receive_logs = internal.receive_logs.lock().await;
+                        receive_logs.iter().map(|(ssrc, log)| (*ssrc, Arc::clone(log))).collect()
+                    };
+                    let rtcp_writer = {
+                        let rtcp_writer = internal.rtcp_writer.lock().await;
+                        rtcp_writer.clone()
+                    };
+
+                    if let Some(rtcp_writer) = rtcp_writer {
+                        for (ssrc, receive_log) in receive_logs {
+                            let missing = {
+                                let receive_log = receive_log.lock().await;
+                                receive_log.missing_seq_numbers(internal.skip_last_n)
+                            };
+                            if missing.is_empty() {
+                                continue;
+                            }
+
+                            let nack = TransportLayerNack {
+                                sender_ssrc: internal.sender_ssrc,
+                                media_ssrc: ssrc,
+                                nacks: nack_pairs_from_sequence_numbers(&missing),
+                            };
+
+                            if let Err(err) = rtcp_writer.write(&nack, &Attributes::new()).await {
+                                log::warn!("failed sending nack: {}", err);
+                            }
+                        }
+                    }
+                }
+                _ = close_rx.recv() => return,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for GeneratorInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        {
+            let mut rtcp_writer = self.internal.rtcp_writer.lock().await;
+            *rtcp_writer = Some(Arc::clone(&writer));
+        }
+
+        let close_rx = {
+            let mut close_rx = self.internal.close_rx.lock().await;
+            close_rx.take()
+        };
+        if let Some(close_rx) = close_rx {
+            let internal = Arc::clone(&self.internal);
+            tokio::spawn(async move {
+                GeneratorInterceptor::run(internal, close_rx).await;
+            });
+        }
+
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        writer
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        if !stream_supports_nack(info) {
+            return reader;
+        }
+
+        // the size was validated when the interceptor was created
+        let receive_log = match ReceiveLog::new(self.size) {
+            Ok(receive_log) => Arc::new(Mutex::new(receive_log)),
+            Err(_) => return reader,
+        };
+        {
+            let mut receive_logs = self.internal.receive_logs.lock().await;
+            receive_logs.insert(info.ssrc, Arc::clone(&receive_log));
+        }
+
+        Arc::new(GeneratorRTPReader {
+            receive_log,
+            parent_rtp_reader: reader,
+        })
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, info: &StreamInfo) {
+        let mut receive_logs = self.internal.receive_logs.lock().await;
+        receive_logs.remove(&info.ssrc);
+    }
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        let mut close_tx = self.close_tx.lock().await;
+        close_tx.take();
+        Ok(())
+    }
+}
+
+struct GeneratorRTPReader {
+    receive_log: Arc<Mutex<ReceiveLog>>,
+    parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPReader for GeneratorRTPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, attr) = self.parent_rtp_reader.read(buf, a).await?;
+
+        let mut b = &buf[..n];
+        if let Ok(header) = rtp::header::Header::unmarshal(&mut b) {
+            let mut receive_log = self.receive_log.lock().await;
+            receive_log.add(header.sequence_number);
+        }
+
+        Ok((n, attr))
+    }
+}
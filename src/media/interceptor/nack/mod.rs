@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod nack_test;
+
+pub mod generator_interceptor;
+pub mod responder_interceptor;
+
+mod receive_log;
+mod send_buffer;
+
+use interceptor::stream_info::StreamInfo;
+use rtcp::transport_feedbacks::transport_layer_nack::NackPair;
+
+/// stream_supports_nack checks if a stream has negotiated generic NACK feedback
+fn stream_supports_nack(info: &StreamInfo) -> bool {
+    info.rtcp_feedback
+        .iter()
+        .any(|fb| fb.typ == "nack" && fb.parameter.is_empty())
+}
+
+/// nack_pairs_from_sequence_numbers packs a sorted list of missing sequence
+/// numbers into the NackPairs of a TransportLayerNack.
+fn nack_pairs_from_sequence_numbers(seq_nos: &[u16]) -> Vec<NackPair> {
+    if seq_nos.is_empty() {
+        return vec![];
+    }
+
+    let mut pairs = vec![];
+    let mut nack_pair = NackPair {
+        packet_id: seq_nos[0],
+        lost_packets: 0,
+    };
+
+    for &m in &seq_nos[1..] {
+        let diff = m.wrapping_sub(nack_pair.packet_id);
+        if diff > 16 {
+            pairs.push(nack_pair);
+            nack_pair = NackPair {
+                packet_id: m,
+                lost_packets: 0,
+            };
+            continue;
+        }
+
+        nack_pair.lost_packets |= 1 << (diff - 1);
+    }
+    pairs.push(nack_pair);
+
+    pairs
+}
+
+/// nack_pair_packet_list returns the sequence numbers referenced by a NackPair,
+/// counting for rollovers.
+fn nack_pair_packet_list(pair: &NackPair) -> Vec<u16> {
+    let mut out = vec![pair.packet_id];
+    for i in 0..16u16 {
+        if pair.lost_packets & (1 << i) != 0 {
+            out.push(pair.packet_id.wrapping_add(i + 1));
+        }
+    }
+    out
+}
@@ -0,0 +1,385 @@
+use super::generator_interceptor::GeneratorInterceptor;
+use super::responder_interceptor::ResponderInterceptor;
+use super::*;
+use crate::media::interceptor::{ATTRIBUTE_RTX_PAYLOAD_TYPE, ATTRIBUTE_RTX_SSRC};
+use crate::media::rtp::SDES_REPAIR_RTP_STREAM_ID_URI;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use interceptor::stream_info::{RTCPFeedback, RTPHeaderExtension};
+use interceptor::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use rtcp::transport_feedbacks::transport_layer_nack::TransportLayerNack;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::Duration;
+use util::Marshal;
+
+struct MockReader {
+    rx: Mutex<mpsc::Receiver<Bytes>>,
+}
+
+impl MockReader {
+    fn new() -> (Arc<Self>, mpsc::Sender<Bytes>) {
+        let (tx, rx) = mpsc::channel(16);
+        (Arc::new(MockReader { rx: Mutex::new(rx) }), tx)
+    }
+
+    async fn next(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let mut rx = self.rx.lock().await;
+        let b = rx.recv().await.unwrap_or_default();
+        buf[..b.len()].copy_from_slice(&b);
+        Ok((b.len(), a.clone()))
+    }
+}
+
+#[async_trait]
+impl RTPReader for MockReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        self.next(buf, a).await
+    }
+}
+
+#[async_trait]
+impl RTCPReader for MockReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        self.next(buf, a).await
+    }
+}
+
+struct MockRTCPWriter {
+    tx: mpsc::Sender<TransportLayerNack>,
+}
+
+#[async_trait]
+impl RTCPWriter for MockRTCPWriter {
+    async fn write(
+        &self,
+        pkt: &(dyn rtcp::packet::Packet + Send + Sync),
+        _a: &Attributes,
+    ) -> Result<usize> {
+        if let Some(nack) = pkt.as_any().downcast_ref::<TransportLayerNack>() {
+            let _ = self.tx.send(nack.clone()).await;
+        }
+        Ok(0)
+    }
+}
+
+struct MockRTPWriter {
+    tx: mpsc::Sender<u16>,
+}
+
+#[async_trait]
+impl RTPWriter for MockRTPWriter {
+    async fn write(&self, pkt: &rtp::packet::Packet, _a: &Attributes) -> Result<usize> {
+        let _ = self.tx.send(pkt.header.sequence_number).await;
+        Ok(pkt.payload.len())
+    }
+}
+
+struct MockRTPPacketWriter {
+    tx: mpsc::Sender<rtp::packet::Packet>,
+}
+
+#[async_trait]
+impl RTPWriter for MockRTPPacketWriter {
+    async fn write(&self, pkt: &rtp::packet::Packet, _a: &Attributes) -> Result<usize> {
+        let _ = self.tx.send(pkt.clone()).await;
+        Ok(pkt.payload.len())
+    }
+}
+
+fn nack_stream_info(ssrc: u32) -> StreamInfo {
+    StreamInfo {
+        ssrc,
+        rtcp_feedback: vec![RTCPFeedback {
+            typ: "nack".to_owned(),
+            parameter: "".to_owned(),
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_stream_supports_nack() {
+    assert!(stream_supports_nack(&nack_stream_info(1)));
+
+    let pli_only = StreamInfo {
+        rtcp_feedback: vec![RTCPFeedback {
+            typ: "nack".to_owned(),
+            parameter: "pli".to_owned(),
+        }],
+        ..Default::default()
+    };
+    assert!(!stream_supports_nack(&pli_only));
+    assert!(!stream_supports_nack(&StreamInfo::default()));
+}
+
+#[test]
+fn test_nack_pairs_from_sequence_numbers() {
+    let tests: Vec<(Vec<u16>, Vec<NackPair>)> = vec![
+        (vec![], vec![]),
+        (
+            vec![42],
+            vec![NackPair {
+                packet_id: 42,
+                lost_packets: 0,
+            }],
+        ),
+        (
+            vec![42, 43, 58, 59],
+            vec![
+                NackPair {
+                    packet_id: 42,
+                    lost_packets: 0x8001,
+                },
+                NackPair {
+                    packet_id: 59,
+                    lost_packets: 0,
+                },
+            ],
+        ),
+        (
+            vec![65534, 65535, 0, 1],
+            vec![NackPair {
+                packet_id: 65534,
+                lost_packets: 0b111,
+            }],
+        ),
+    ];
+
+    for (seq_nos, expected) in tests {
+        let pairs = nack_pairs_from_sequence_numbers(&seq_nos);
+        assert_eq!(expected, pairs, "{:?}", seq_nos);
+
+        let packets: Vec<u16> = pairs.iter().flat_map(nack_pair_packet_list).collect();
+        assert_eq!(seq_nos, packets);
+    }
+}
+
+#[tokio::test]
+async fn test_generator_interceptor() -> Result<()> {
+    let icpr = GeneratorInterceptor::with_options(64, 2, Duration::from_millis(10))?;
+
+    let (rtcp_tx, mut rtcp_rx) = mpsc::channel(16);
+    icpr.bind_rtcp_writer(Arc::new(MockRTCPWriter { tx: rtcp_tx }))
+        .await;
+
+    let (reader, packets_tx) = MockReader::new();
+    let rtp_reader = icpr
+        .bind_remote_stream(
+            &nack_stream_info(1),
+            reader as Arc<dyn RTPReader + Send + Sync>,
+        )
+        .await;
+
+    let mut buf = vec![0u8; 1500];
+    for seq in &[10u16, 11, 12, 14, 16, 18] {
+        let header = rtp::header::Header {
+            version: 2,
+            sequence_number: *seq,
+            ssrc: 1,
+            ..Default::default()
+        };
+        packets_tx.send(header.marshal()?).await?;
+        rtp_reader.read(&mut buf, &Attributes::new()).await?;
+    }
+
+    // 17 is not missing yet since the last 2 packets are skipped
+    let expected = vec![NackPair {
+        packet_id: 13,
+        lost_packets: 0b10,
+    }];
+    let result = tokio::time::timeout(Duration::from_secs(1), async {
+        // a nack may have been generated before all packets were read
+        while let Some(nack) = rtcp_rx.recv().await {
+            assert_eq!(1, nack.media_ssrc);
+            if nack.nacks == expected {
+                return true;
+            }
+        }
+        false
+    })
+    .await;
+    assert_eq!(Ok(true), result.map_err(|_| ()), "nack not sent");
+
+    icpr.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_responder_interceptor() -> Result<()> {
+    let icpr = ResponderInterceptor::with_size(8)?;
+
+    let (rtp_tx, mut rtp_rx) = mpsc::channel(16);
+    let rtp_writer = icpr
+        .bind_local_stream(&nack_stream_info(1), Arc::new(MockRTPWriter { tx: rtp_tx }))
+        .await;
+
+    for seq in 10u16..15 {
+        let pkt = rtp::packet::Packet {
+            header: rtp::header::Header {
+                sequence_number: seq,
+                ssrc: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        rtp_writer.write(&pkt, &Attributes::new()).await?;
+        assert_eq!(Some(seq), rtp_rx.recv().await);
+    }
+
+    let (reader, rtcp_tx) = MockReader::new();
+    let rtcp_reader = icpr
+        .bind_rtcp_reader(reader as Arc<dyn RTCPReader + Send + Sync>)
+        .await;
+
+    let nack = TransportLayerNack {
+        sender_ssrc: 2,
+        media_ssrc: 1,
+        // 15 was never sent, it must not be resent
+        nacks: nack_pairs_from_sequence_numbers(&[11, 12, 15]),
+    };
+    rtcp_tx.send(nack.marshal()?).await?;
+    let mut buf = vec![0u8; 1500];
+    rtcp_reader.read(&mut buf, &Attributes::new()).await?;
+
+    for expected in &[11u16, 12] {
+        let seq = tokio::time::timeout(Duration::from_secs(1), rtp_rx.recv()).await?;
+        assert_eq!(Some(*expected), seq);
+    }
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), rtp_rx.recv())
+            .await
+            .is_err(),
+        "unexpected packet resent"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_responder_interceptor_rtx() -> Result<()> {
+    let icpr = ResponderInterceptor::with_size(8)?;
+
+    let mut info = nack_stream_info(1);
+    info.attributes.insert(ATTRIBUTE_RTX_SSRC, 5);
+    info.attributes.insert(ATTRIBUTE_RTX_PAYLOAD_TYPE, 97);
+
+    let (rtp_tx, mut rtp_rx) = mpsc::channel(16);
+    let rtp_writer = icpr
+        .bind_local_stream(&info, Arc::new(MockRTPPacketWriter { tx: rtp_tx }))
+        .await;
+
+    for seq in 10u16..13 {
+        let pkt = rtp::packet::Packet {
+            header: rtp::header::Header {
+                sequence_number: seq,
+                payload_type: 96,
+                ssrc: 1,
+                ..Default::default()
+            },
+            payload: Bytes::from(vec![seq as u8; 4]),
+        };
+        rtp_writer.write(&pkt, &Attributes::new()).await?;
+        assert_eq!(Some(pkt), rtp_rx.recv().await);
+    }
+
+    let (reader, rtcp_tx) = MockReader::new();
+    let rtcp_reader = icpr
+        .bind_rtcp_reader(reader as Arc<dyn RTCPReader + Send + Sync>)
+        .await;
+
+    let nack = TransportLayerNack {
+        sender_ssrc: 2,
+        media_ssrc: 1,
+        nacks: nack_pairs_from_sequence_numbers(&[10, 12]),
+    };
+    rtcp_tx.send(nack.marshal()?).await?;
+    let mut buf = vec![0u8; 1500];
+    rtcp_reader.read(&mut buf, &Attributes::new()).await?;
+
+    let mut rtx_sequence_number = None;
+    for original in &[10u16, 12] {
+        let pkt = tokio::time::timeout(Duration::from_secs(1), rtp_rx.recv())
+            .await?
+            .expect("missing retransmission");
+        assert_eq!(5, pkt.header.ssrc);
+        assert_eq!(97, pkt.header.payload_type);
+        assert_eq!(original.to_be_bytes(), pkt.payload[..2]);
+        assert_eq!(vec![*original as u8; 4], pkt.payload[2..]);
+
+        // the repair stream has its own sequence numbers
+        if let Some(prev) = rtx_sequence_number {
+            assert_eq!(pkt.header.sequence_number, u16::wrapping_add(prev, 1));
+        }
+        rtx_sequence_number = Some(pkt.header.sequence_number);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_responder_interceptor_rtx_simulcast() -> Result<()> {
+    let icpr = ResponderInterceptor::with_size(8)?;
+
+    let mut info = nack_stream_info(1);
+    info.attributes.insert(ATTRIBUTE_RTX_SSRC, 5);
+    info.attributes.insert(ATTRIBUTE_RTX_PAYLOAD_TYPE, 97);
+    info.rtp_header_extensions = vec![
+        RTPHeaderExtension {
+            uri: sdp::extmap::SDES_RTP_STREAM_ID_URI.to_owned(),
+            id: 2,
+        },
+        RTPHeaderExtension {
+            uri: SDES_REPAIR_RTP_STREAM_ID_URI.to_owned(),
+            id: 3,
+        },
+    ];
+
+    let (rtp_tx, mut rtp_rx) = mpsc::channel(16);
+    let rtp_writer = icpr
+        .bind_local_stream(&info, Arc::new(MockRTPPacketWriter { tx: rtp_tx }))
+        .await;
+
+    let mut header = rtp::header::Header {
+        sequence_number: 10,
+        payload_type: 96,
+        ssrc: 1,
+        ..Default::default()
+    };
+    header.set_extension(1, Bytes::from_static(b"0"))?;
+    header.set_extension(2, Bytes::from_static(b"f"))?;
+    let pkt = rtp::packet::Packet {
+        header,
+        payload: Bytes::from_static(&[0xAA; 4]),
+    };
+    rtp_writer.write(&pkt, &Attributes::new()).await?;
+    assert_eq!(Some(pkt), rtp_rx.recv().await);
+
+    let (reader, rtcp_tx) = MockReader::new();
+    let rtcp_reader = icpr
+        .bind_rtcp_reader(reader as Arc<dyn RTCPReader + Send + Sync>)
+        .await;
+
+    let nack = TransportLayerNack {
+        sender_ssrc: 2,
+        media_ssrc: 1,
+        nacks: nack_pairs_from_sequence_numbers(&[10]),
+    };
+    rtcp_tx.send(nack.marshal()?).await?;
+    let mut buf = vec![0u8; 1500];
+    rtcp_reader.read(&mut buf, &Attributes::new()).await?;
+
+    // the retransmission keeps the MID and carries the RID as the repaired RID
+    let pkt = tokio::time::timeout(Duration::from_secs(1), rtp_rx.recv())
+        .await?
+        .expect("missing retransmission");
+    assert_eq!(5, pkt.header.ssrc);
+    assert_eq!(Some(Bytes::from_static(b"0")), pkt.header.get_extension(1));
+    assert_eq!(None, pkt.header.get_extension(2));
+    assert_eq!(Some(Bytes::from_static(b"f")), pkt.header.get_extension(3));
+
+    Ok(())
+}
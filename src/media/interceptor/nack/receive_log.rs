@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod receive_log_test;
+
+use crate::error::Error;
+
+use anyhow::Result;
+
+const UINT16_SIZE_HALF: u16 = 1 << 15;
+
+/// ReceiveLog remembers which of the last `size` sequence numbers of a remote
+/// stream were received, so the missing ones can be NACKed.
+pub(crate) struct ReceiveLog {
+    packets: Vec<u64>,
+    size: u16,
+    end: u16,
+    started: bool,
+    last_consecutive: u16,
+}
+
+impl ReceiveLog {
+    pub(crate) fn new(size: u16) -> Result<Self> {
+        let allowed = (6..=15).any(|i| size == 1 << i);
+        if !allowed {
+            return Err(Error::ErrInvalidNackSize.into());
+        }
+
+        Ok(ReceiveLog {
+            packets: vec![0u64; (size / 64) as usize],
+            size,
+            end: 0,
+            started: false,
+            last_consecutive: 0,
+        })
+    }
+
+    pub(crate) fn add(&mut self, seq: u16) {
+        if !self.started {
+            self.set_received(seq);
+            self.end = seq;
+            self.started = true;
+            self.last_consecutive = seq;
+            return;
+        }
+
+        let diff = seq.wrapping_sub(self.end);
+        if diff == 0 {
+            return;
+        } else if diff < UINT16_SIZE_HALF {
+            // this means a positive diff, in other words seq > end (with counting for rollovers)
+            let mut i = self.end.wrapping_add(1);
+            while i != seq {
+                // clear packets between end and seq (these may contain packets from a "size" ago)
+                self.del_received(i);
+                i = i.wrapping_add(1);
+            }
+            self.end = seq;
+
+            if self.last_consecutive.wrapping_add(1) == seq {
+                self.last_consecutive = seq;
+            } else if seq.wrapping_sub(self.last_consecutive) > self.size {
+                self.last_consecutive = seq.wrapping_sub(self.size);
+                self.fix_last_consecutive(); // there might be valid packets at the beginning of the buffer now
+            }
+        } else if self.last_consecutive.wrapping_add(1) == seq {
+            // negative diff, seq < end (with counting for rollovers)
+            self.last_consecutive = seq;
+            self.fix_last_consecutive(); // there might be other valid packets after seq
+        }
+
+        self.set_received(seq);
+    }
+
+    pub(crate) fn get(&self, seq: u16) -> bool {
+        let diff = self.end.wrapping_sub(seq);
+        if diff >= UINT16_SIZE_HALF {
+            return false;
+        }
+
+        if diff >= self.size {
+            return false;
+        }
+
+        self.get_received(seq)
+    }
+
+    pub(crate) fn missing_seq_numbers(&self, skip_last_n: u16) -> Vec<u16> {
+        let until = self.end.wrapping_sub(skip_last_n);
+        if until.wrapping_sub(self.last_consecutive) >= UINT16_SIZE_HALF {
+            // until < s.last_consecutive (counting for rollover)
+            return vec![];
+        }
+
+        let mut missing_packet_seq_nums = vec![];
+        let mut i = self.last_consecutive.wrapping_add(1);
+        while i != until.wrapping_add(1) {
+            if !self.get_received(i) {
+                missing_packet_seq_nums.push(i);
+            }
+            i = i.wrapping_add(1);
+        }
+
+        missing_packet_seq_nums
+    }
+
+    fn set_received(&mut self, seq: u16) {
+        let pos = seq % self.size;
+        self.packets[(pos / 64) as usize] |= 1u64 << (pos % 64);
+    }
+
+    fn del_received(&mut self, seq: u16) {
+        let pos = seq % self.size;
+        self.packets[(pos / 64) as usize] &= !(1u64 << (pos % 64));
+    }
+
+    fn get_received(&self, seq: u16) -> bool {
+        let pos = seq % self.size;
+        (self.packets[(pos / 64) as usize] & (1u64 << (pos % 64))) != 0
+    }
+
+    fn fix_last_consecutive(&mut self) {
+        let mut i = self.last_consecutive.wrapping_add(1);
+        while i != self.end.wrapping_add(1) && self.get_received(i) {
+            // find all consecutive packets
+            i = i.wrapping_add(1);
+        }
+        self.last_consecutive = i.wrapping_sub(1);
+    }
+}
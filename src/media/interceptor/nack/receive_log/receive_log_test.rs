@@ -0,0 +1,118 @@
+use super::*;
+
+#[test]
+fn test_receive_log_invalid_size() {
+    for size in &[0u16, 1, 32, 100, 513] {
+        assert!(
+            ReceiveLog::new(*size).is_err(),
+            "size {} should be invalid",
+            size
+        );
+    }
+}
+
+#[test]
+fn test_receive_log() -> Result<()> {
+    for start in &[
+        0u16, 1, 127, 128, 129, 511, 512, 513, 32767, 32768, 32769, 65407, 65408, 65409, 65534,
+        65535,
+    ] {
+        let start = *start;
+
+        let mut rl = ReceiveLog::new(128)?;
+
+        let all = |min: u16, max: u16| -> Vec<u16> { (min..=max).collect() };
+        let join = |parts: &[&[u16]]| -> Vec<u16> {
+            parts.iter().flat_map(|p| p.iter().cloned()).collect()
+        };
+
+        let add = |rl: &mut ReceiveLog, nums: &[u16]| {
+            for n in nums {
+                rl.add(start.wrapping_add(*n));
+            }
+        };
+
+        let assert_get = |rl: &ReceiveLog, nums: &[u16]| {
+            for n in nums {
+                let seq = start.wrapping_add(*n);
+                assert!(rl.get(seq), "not found: {}", seq);
+            }
+        };
+
+        let assert_not_get = |rl: &ReceiveLog, nums: &[u16]| {
+            for n in nums {
+                let seq = start.wrapping_add(*n);
+                assert!(!rl.get(seq), "packet found for {}", seq);
+            }
+        };
+
+        let assert_missing = |rl: &ReceiveLog, skip_last_n: u16, nums: &[u16]| {
+            let missing = rl.missing_seq_numbers(skip_last_n);
+            let want: Vec<u16> = nums.iter().map(|n| start.wrapping_add(*n)).collect();
+            assert_eq!(want, missing, "missing with skip_last_n {}", skip_last_n);
+        };
+
+        let assert_last_consecutive = |rl: &ReceiveLog, last_consecutive: u16| {
+            let want = last_consecutive.wrapping_add(start);
+            assert_eq!(want, rl.last_consecutive, "invalid last_consecutive");
+        };
+
+        add(&mut rl, &[0]);
+        assert_get(&rl, &[0]);
+        assert_missing(&rl, 0, &[]);
+        assert_last_consecutive(&rl, 0); // first element added
+
+        add(&mut rl, &all(1, 127));
+        assert_get(&rl, &all(1, 127));
+        assert_missing(&rl, 0, &[]);
+        assert_last_consecutive(&rl, 127);
+
+        add(&mut rl, &[128]);
+        assert_get(&rl, &[128]);
+        assert_not_get(&rl, &[0]);
+        assert_missing(&rl, 0, &[]);
+        assert_last_consecutive(&rl, 128);
+
+        add(&mut rl, &[130]);
+        assert_get(&rl, &[130]);
+        assert_not_get(&rl, &[1, 2, 129]);
+        assert_missing(&rl, 0, &[129]);
+        assert_last_consecutive(&rl, 128);
+
+        add(&mut rl, &[333]);
+        assert_get(&rl, &[333]);
+        assert_not_get(&rl, &all(0, 332));
+        assert_missing(&rl, 0, &all(206, 332)); // all 127 elements missing before 333
+        assert_missing(&rl, 10, &all(206, 323)); // skip last 10 packets (324-333) from check
+        assert_last_consecutive(&rl, 205); // last_consecutive is still out of the buffer
+
+        add(&mut rl, &[329]);
+        assert_get(&rl, &[329]);
+        assert_missing(&rl, 0, &join(&[&all(206, 328), &all(330, 332)]));
+        assert_missing(&rl, 5, &all(206, 328)); // skip last 5 packets (329-333) from check
+        assert_last_consecutive(&rl, 205);
+
+        add(&mut rl, &all(207, 320));
+        assert_get(&rl, &all(207, 320));
+        assert_missing(&rl, 0, &join(&[&[206], &all(321, 328), &all(330, 332)]));
+        assert_last_consecutive(&rl, 205);
+
+        add(&mut rl, &[334]);
+        assert_get(&rl, &[334]);
+        assert_not_get(&rl, &[206]);
+        assert_missing(&rl, 0, &join(&[&all(321, 328), &all(330, 332)]));
+        assert_last_consecutive(&rl, 320); // head of buffer is full of consecutive packages
+
+        add(&mut rl, &all(322, 328));
+        assert_get(&rl, &all(322, 328));
+        assert_missing(&rl, 0, &join(&[&[321], &all(330, 332)]));
+        assert_last_consecutive(&rl, 320);
+
+        add(&mut rl, &[321]);
+        assert_get(&rl, &[321]);
+        assert_missing(&rl, 0, &all(330, 332));
+        assert_last_consecutive(&rl, 329); // after adding a single missing packet, last_consecutive should jump forward
+    }
+
+    Ok(())
+}
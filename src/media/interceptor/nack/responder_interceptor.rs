@@ -0,0 +1,277 @@
+use super::send_buffer::SendBuffer;
+use super::{nack_pair_packet_list, stream_supports_nack};
+use crate::media::interceptor::{unmarshal_rtcp, ATTRIBUTE_RTX_PAYLOAD_TYPE, ATTRIBUTE_RTX_SSRC};
+use crate::media::rtp::SDES_REPAIR_RTP_STREAM_ID_URI;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::{BufMut, BytesMut};
+use interceptor::stream_info::StreamInfo;
+use interceptor::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use rtcp::transport_feedbacks::transport_layer_nack::TransportLayerNack;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// RtxStream is the RTX (RFC 4588) repair stream nacked packets are resent on
+struct RtxStream {
+    ssrc: u32,
+    payload_type: u8,
+    sequence_number: AtomicU16,
+    /// rid_extension_ids are the ids of the RID header extension of the stream, and of
+    /// the repaired RID extension which replaces it on the retransmissions of a simulcast layer
+    rid_extension_ids: Option<(u8, u8)>,
+}
+
+impl RtxStream {
+    /// from_stream_info returns the repair stream announced by the attributes of
+    /// a local stream, if any
+    fn from_stream_info(info: &StreamInfo) -> Option<Self> {
+        let ssrc = *info.attributes.get(&ATTRIBUTE_RTX_SSRC)?;
+        let payload_type = *info.attributes.get(&ATTRIBUTE_RTX_PAYLOAD_TYPE)?;
+
+        let extension_id = |uri: &str| {
+            info.rtp_header_extensions
+                .iter()
+                .find(|e| e.uri == uri)
+                .map(|e| e.id as u8)
+        };
+        let rid_extension_ids = match (
+            extension_id(sdp::extmap::SDES_RTP_STREAM_ID_URI),
+            extension_id(SDES_REPAIR_RTP_STREAM_ID_URI),
+        ) {
+            (Some(rid_id), Some(repair_rid_id)) => Some((rid_id, repair_rid_id)),
+            _ => None,
+        };
+
+        Some(RtxStream {
+            ssrc: ssrc as u32,
+            payload_type: payload_type as u8,
+            sequence_number: AtomicU16::new(rand::random::<u16>()),
+            rid_extension_ids,
+        })
+    }
+
+    /// wrap turns a packet of the original stream into a retransmission packet,
+    /// carrying the original sequence number in the first two bytes of the payload.
+    /// The RID of a simulcast layer is moved to the repaired RID extension, since
+    /// the retransmission belongs to the repair stream of the layer.
+    fn wrap(&self, pkt: &rtp::packet::Packet) -> rtp::packet::Packet {
+        let mut payload = BytesMut::with_capacity(2 + pkt.payload.len());
+        payload.put_u16(pkt.header.sequence_number);
+        payload.extend_from_slice(&pkt.payload);
+
+        let mut header = pkt.header.clone();
+        header.ssrc = self.ssrc;
+        header.payload_type = self.payload_type;
+        header.sequence_number = self.sequence_number.fetch_add(1, Ordering::SeqCst);
+        if let Some((rid_id, repair_rid_id)) = self.rid_extension_ids {
+            if let Some(rid) = header.get_extension(rid_id) {
+                if header.del_extension(rid_id).is_ok() {
+                    let _ = header.set_extension(repair_rid_id, rid);
+                }
+            }
+        }
+
+        rtp::packet::Packet {
+            header,
+            payload: payload.freeze(),
+        }
+    }
+}
+
+struct ResponderStream {
+    send_buffer: Mutex<SendBuffer>,
+    next_rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
+    rtx: Option<RtxStream>,
+}
+
+/// ResponderInterceptor responds to nack feedback messages, resending the packets
+/// on the RTX repair stream of the local stream when one was negotiated
+pub struct ResponderInterceptor {
+    size: u16,
+    streams: Arc<Mutex<HashMap<u32, Arc<ResponderStream>>>>,
+}
+
+impl Default for ResponderInterceptor {
+    fn default() -> Self {
+        ResponderInterceptor::new()
+    }
+}
+
+impl ResponderInterceptor {
+    /// new returns a new ResponderInterceptor which keeps the last 1024 packets
+    /// of every stream.
+    pub fn new() -> Self {
+        ResponderInterceptor {
+            size: 1024,
+            streams: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// with_size returns a new ResponderInterceptor keeping the last size packets
+    /// of every stream, it must be a power of two between 1 and 32768.
+    pub fn with_size(size: u16) -> Result<Self> {
+        // validate the size once, so binding a stream can not fail later on
+        let _ = SendBuffer::new(size)?;
+
+        Ok(ResponderInterceptor {
+            size,
+            streams: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    async fn resend_packets(
+        streams: Arc<Mutex<HashMap<u32, Arc<ResponderStream>>>>,
+        nack: TransportLayerNack,
+    ) {
+        let stream = {
+            let streams = streams.lock().await;
+            match streams.get(&nack.media_ssrc) {
+                Some(stream) => Arc::clone(stream),
+                None => return,
+            }
+        };
+
+        for n in &nack.nacks {
+            for seq in nack_pair_packet_list(n) {
+                let pkt = {
+                    let send_buffer = stream.send_buffer.lock().await;
+                    send_buffer.get(seq).cloned()
+                };
+
+                if let Some(mut pkt) = pkt {
+                    if let Some(rtx) = &stream.rtx {
+                        pkt = rtx.wrap(&pkt);
+                    }
+                    if let Err(err) = stream.next_rtp_writer.write(&pkt, &Attributes::new()).await {
+                        log::warn!("failed resending nacked packet: {}", err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for ResponderInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        Arc::new(ResponderRTCPReader {
+            streams: Arc::clone(&self.streams),
+            parent_rtcp_reader: reader,
+        })
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        if !stream_supports_nack(info) {
+            return writer;
+        }
+
+        // the size was validated when the interceptor was created
+        let send_buffer = match SendBuffer::new(self.size) {
+            Ok(send_buffer) => send_buffer,
+            Err(_) => return writer,
+        };
+        let stream = Arc::new(ResponderStream {
+            send_buffer: Mutex::new(send_buffer),
+            next_rtp_writer: writer,
+            rtx: RtxStream::from_stream_info(info),
+        });
+        {
+            let mut streams = self.streams.lock().await;
+            streams.insert(info.ssrc, Arc::clone(&stream));
+        }
+
+        Arc::new(ResponderRTPWriter { stream })
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, info: &StreamInfo) {
+        let mut streams = self.streams.lock().await;
+        streams.remove(&info.ssrc);
+    }
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        reader
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct ResponderRTPWriter {
+    stream: Arc<ResponderStream>,
+}
+
+#[async_trait]
+impl RTPWriter for ResponderRTPWriter {
+    async fn write(&self, pkt: &rtp::packet::Packet, a: &Attributes) -> Result<usize> {
+        {
+            let mut send_buffer = self.stream.send_buffer.lock().await;
+            send_buffer.add(pkt);
+        }
+
+        self.stream.next_rtp_writer.write(pkt, a).await
+    }
+}
+
+struct ResponderRTCPReader {
+    streams: Arc<Mutex<HashMap<u32, Arc<ResponderStream>>>>,
+    parent_rtcp_reader: Arc<dyn RTCPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTCPReader for ResponderRTCPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, attr) = self.parent_rtcp_reader.read(buf, a).await?;
+
+        let nacks: Vec<TransportLayerNack> = match unmarshal_rtcp(&buf[..n]) {
+            Ok(pkts) => pkts
+                .iter()
+                .filter_map(|p| p.as_any().downcast_ref::<TransportLayerNack>().cloned())
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        for nack in nacks {
+            let streams = Arc::clone(&self.streams);
+            tokio::spawn(async move {
+                ResponderInterceptor::resend_packets(streams, nack).await;
+            });
+        }
+
+        Ok((n, attr))
+    }
+}
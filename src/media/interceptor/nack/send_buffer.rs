@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod send_buffer_test;
+
+use crate::error::Error;
+
+use anyhow::Result;
+
+const UINT16_SIZE_HALF: u16 = 1 << 15;
+
+/// SendBuffer keeps the last `size` packets sent on a local stream, so they
+/// can be retransmitted when the remote peer NACKs them.
+pub(crate) struct SendBuffer {
+    packets: Vec<Option<rtp::packet::Packet>>,
+    size: u16,
+    last_added: u16,
+    started: bool,
+}
+
+impl SendBuffer {
+    pub(crate) fn new(size: u16) -> Result<Self> {
+        let allowed = (0..=15).any(|i| size == 1 << i);
+        if !allowed {
+            return Err(Error::ErrInvalidNackSize.into());
+        }
+
+        Ok(SendBuffer {
+            packets: vec![None; size as usize],
+            size,
+            last_added: 0,
+            started: false,
+        })
+    }
+
+    pub(crate) fn add(&mut self, packet: &rtp::packet::Packet) {
+        let seq = packet.header.sequence_number;
+        if !self.started {
+            self.packets[(seq % self.size) as usize] = Some(packet.clone());
+            self.last_added = seq;
+            self.started = true;
+            return;
+        }
+
+        let diff = seq.wrapping_sub(self.last_added);
+        if diff == 0 {
+            return;
+        } else if diff < UINT16_SIZE_HALF {
+            let mut i = self.last_added.wrapping_add(1);
+            while i != seq {
+                self.packets[(i % self.size) as usize] = None;
+                i = i.wrapping_add(1);
+            }
+        }
+
+        self.packets[(seq % self.size) as usize] = Some(packet.clone());
+        self.last_added = seq;
+    }
+
+    pub(crate) fn get(&self, seq: u16) -> Option<&rtp::packet::Packet> {
+        let diff = self.last_added.wrapping_sub(seq);
+        if diff >= UINT16_SIZE_HALF {
+            return None;
+        }
+
+        if diff >= self.size {
+            return None;
+        }
+
+        match &self.packets[(seq % self.size) as usize] {
+            Some(pkt) if pkt.header.sequence_number == seq => Some(pkt),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use super::*;
+
+#[test]
+fn test_send_buffer_invalid_size() {
+    for size in &[0u16, 3, 100, 1000] {
+        assert!(
+            SendBuffer::new(*size).is_err(),
+            "size {} should be invalid",
+            size
+        );
+    }
+}
+
+#[test]
+fn test_send_buffer() -> Result<()> {
+    for start in &[
+        0u16, 1, 127, 128, 129, 511, 512, 513, 32767, 32768, 32769, 65407, 65408, 65409, 65534,
+        65535,
+    ] {
+        let start = *start;
+
+        let mut sb = SendBuffer::new(8)?;
+
+        let add = |sb: &mut SendBuffer, nums: &[u16]| {
+            for n in nums {
+                let seq = start.wrapping_add(*n);
+                sb.add(&rtp::packet::Packet {
+                    header: rtp::header::Header {
+                        sequence_number: seq,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+            }
+        };
+
+        let assert_get = |sb: &SendBuffer, nums: &[u16]| {
+            for n in nums {
+                let seq = start.wrapping_add(*n);
+                if let Some(packet) = sb.get(seq) {
+                    assert_eq!(
+                        seq, packet.header.sequence_number,
+                        "packet for {} returned with incorrect sequence number",
+                        seq
+                    );
+                } else {
+                    panic!("packet not found: {}", seq);
+                }
+            }
+        };
+
+        let assert_not_get = |sb: &SendBuffer, nums: &[u16]| {
+            for n in nums {
+                let seq = start.wrapping_add(*n);
+                assert!(sb.get(seq).is_none(), "packet found for {}", seq);
+            }
+        };
+
+        add(&mut sb, &[0]);
+        assert_get(&sb, &[0]);
+
+        add(&mut sb, &[1, 2, 3, 4, 5, 6, 7]);
+        assert_get(&sb, &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        add(&mut sb, &[8]);
+        assert_get(&sb, &[8]);
+        assert_not_get(&sb, &[0]);
+
+        add(&mut sb, &[10]);
+        assert_get(&sb, &[10]);
+        assert_not_get(&sb, &[1, 2, 9]);
+
+        add(&mut sb, &[22]);
+        assert_get(&sb, &[22]);
+        assert_not_get(&sb, &(0..=21).collect::<Vec<u16>>());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,8 @@
+#[cfg(test)]
+mod report_test;
+
+pub mod receiver_interceptor;
+pub mod sender_interceptor;
+
+mod receiver_stream;
+mod sender_stream;
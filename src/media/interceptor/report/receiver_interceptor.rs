@@ -0,0 +1,230 @@
+use super::receiver_stream::ReceiverStream;
+use crate::media::interceptor::unmarshal_rtcp;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use interceptor::stream_info::StreamInfo;
+use interceptor::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Duration, Instant};
+use util::Unmarshal;
+
+struct ReceiverInterceptorInternal {
+    interval: Duration,
+    streams: Mutex<HashMap<u32, Arc<Mutex<ReceiverStream>>>>,
+    rtcp_writer: Mutex<Option<Arc<dyn RTCPWriter + Send + Sync>>>,
+    close_rx: Mutex<Option<mpsc::Receiver<()>>>,
+}
+
+/// ReceiverInterceptor interceptor generates receiver reports.
+pub struct ReceiverInterceptor {
+    internal: Arc<ReceiverInterceptorInternal>,
+    close_tx: Mutex<Option<mpsc::Sender<()>>>,
+}
+
+impl Default for ReceiverInterceptor {
+    fn default() -> Self {
+        ReceiverInterceptor::new()
+    }
+}
+
+impl ReceiverInterceptor {
+    /// new returns a new ReceiverInterceptor sending a report every second.
+    pub fn new() -> Self {
+        ReceiverInterceptor::with_interval(Duration::from_secs(1))
+    }
+
+    /// with_interval returns a new ReceiverInterceptor sending a report every interval.
+    pub fn with_interval(interval: Duration) -> Self {
+        let (close_tx, close_rx) = mpsc::channel(1);
+        ReceiverInterceptor {
+            internal: Arc::new(ReceiverInterceptorInternal {
+                interval,
+                streams: Mutex::new(HashMap::new()),
+                rtcp_writer: Mutex::new(None),
+                close_rx: Mutex::new(Some(close_rx)),
+            }),
+            close_tx: Mutex::new(Some(close_tx)),
+        }
+    }
+
+    async fn run(internal: Arc<ReceiverInterceptorInternal>, mut close_rx: mpsc::Receiver<()>) {
+        let mut ticker =
+            tokio::time::interval_at(Instant::now() + internal.interval, internal.interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let now = SystemTime::now();
+                    let streams: Vec<Arc<Mutex<ReceiverStream>>> = {
+                        let streams = internal.streams.lock().await;
+                        streams.values().cloned().collect()
+                    };
+                    let rtcp_writer = {
+                        let rtcp_writer = internal.rtcp_writer.lock().await;
+                        rtcp_writer.clone()
+                    };
+
+                    if let Some(rtcp_writer) = rtcp_writer {
+                        for stream in streams {
+                            let pkt = {
+                                let mut stream = stream.lock().await;
+                                stream.generate_report(now)
+                            };
+
+                            if let Err(err) = rtcp_writer.write(&pkt, &Attributes::new()).await {
+                                log::warn!("failed sending receiver report: {}", err);
+                            }
+                        }
+                    }
+                }
+                _ = close_rx.recv() => return,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for ReceiverInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        Arc::new(ReceiverReportRTCPReader {
+            internal: Arc::clone(&self.internal),
+            parent_rtcp_reader: reader,
+        })
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        {
+            let mut rtcp_writer = self.internal.rtcp_writer.lock().await;
+            *rtcp_writer = Some(Arc::clone(&writer));
+        }
+
+        let close_rx = {
+            let mut close_rx = self.internal.close_rx.lock().await;
+            close_rx.take()
+        };
+        if let Some(close_rx) = close_rx {
+            let internal = Arc::clone(&self.internal);
+            tokio::spawn(async move {
+                ReceiverInterceptor::run(internal, close_rx).await;
+            });
+        }
+
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        writer
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        let stream = Arc::new(Mutex::new(ReceiverStream::new(info.ssrc, info.clock_rate)));
+        {
+            let mut streams = self.internal.streams.lock().await;
+            streams.insert(info.ssrc, Arc::clone(&stream));
+        }
+
+        Arc::new(ReceiverReportRTPReader {
+            stream,
+            parent_rtp_reader: reader,
+        })
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, info: &StreamInfo) {
+        let mut streams = self.internal.streams.lock().await;
+        streams.remove(&info.ssrc);
+    }
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        let mut close_tx = self.close_tx.lock().await;
+        close_tx.take();
+        Ok(())
+    }
+}
+
+struct ReceiverReportRTPReader {
+    stream: Arc<Mutex<ReceiverStream>>,
+    parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPReader for ReceiverReportRTPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, attr) = self.parent_rtp_reader.read(buf, a).await?;
+
+        let mut b = &buf[..n];
+        if let Ok(header) = rtp::header::Header::unmarshal(&mut b) {
+            let mut stream = self.stream.lock().await;
+            stream.process_rtp(SystemTime::now(), &header);
+        }
+
+        Ok((n, attr))
+    }
+}
+
+struct ReceiverReportRTCPReader {
+    internal: Arc<ReceiverInterceptorInternal>,
+    parent_rtcp_reader: Arc<dyn RTCPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTCPReader for ReceiverReportRTCPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, attr) = self.parent_rtcp_reader.read(buf, a).await?;
+
+        let sender_reports: Vec<rtcp::sender_report::SenderReport> = match unmarshal_rtcp(&buf[..n])
+        {
+            Ok(pkts) => pkts
+                .iter()
+                .filter_map(|p| {
+                    p.as_any()
+                        .downcast_ref::<rtcp::sender_report::SenderReport>()
+                        .cloned()
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        if !sender_reports.is_empty() {
+            let now = SystemTime::now();
+            let streams = self.internal.streams.lock().await;
+            for sr in &sender_reports {
+                if let Some(stream) = streams.get(&sr.ssrc) {
+                    let mut stream = stream.lock().await;
+                    stream.process_sender_report(now, sr);
+                }
+            }
+        }
+
+        Ok((n, attr))
+    }
+}
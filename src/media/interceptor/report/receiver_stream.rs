@@ -0,0 +1,168 @@
+use std::time::SystemTime;
+
+const PACKETS_SIZE: usize = 128;
+const PACKETS_BITS: u16 = (PACKETS_SIZE * 64) as u16;
+
+/// ReceiverStream keeps the state needed to build the reception report of a
+/// single remote stream, see RFC 3550 Section 6.4.2.
+pub(crate) struct ReceiverStream {
+    ssrc: u32,
+    receiver_ssrc: u32,
+    clock_rate: f64,
+
+    packets: Vec<u64>,
+    started: bool,
+    seqnum_cycles: u16,
+    last_seqnum: u16,
+    last_report_seqnum: u16,
+    last_rtp_time_rtp: u32,
+    last_rtp_time_time: SystemTime,
+    jitter: f64,
+    last_sender_report: u32,
+    last_sender_report_time: Option<SystemTime>,
+    total_lost: u32,
+}
+
+impl ReceiverStream {
+    pub(crate) fn new(ssrc: u32, clock_rate: u32) -> Self {
+        ReceiverStream {
+            ssrc,
+            receiver_ssrc: rand::random::<u32>(),
+            clock_rate: clock_rate as f64,
+
+            packets: vec![0u64; PACKETS_SIZE],
+            started: false,
+            seqnum_cycles: 0,
+            last_seqnum: 0,
+            last_report_seqnum: 0,
+            last_rtp_time_rtp: 0,
+            last_rtp_time_time: SystemTime::UNIX_EPOCH,
+            jitter: 0.0,
+            last_sender_report: 0,
+            last_sender_report_time: None,
+            total_lost: 0,
+        }
+    }
+
+    fn set_received(&mut self, seq: u16) {
+        let pos = seq % PACKETS_BITS;
+        self.packets[(pos / 64) as usize] |= 1 << (pos % 64);
+    }
+
+    fn del_received(&mut self, seq: u16) {
+        let pos = seq % PACKETS_BITS;
+        self.packets[(pos / 64) as usize] &= !(1u64 << (pos % 64));
+    }
+
+    fn get_received(&self, seq: u16) -> bool {
+        let pos = seq % PACKETS_BITS;
+        (self.packets[(pos / 64) as usize] & (1 << (pos % 64))) != 0
+    }
+
+    pub(crate) fn process_rtp(&mut self, now: SystemTime, header: &rtp::header::Header) {
+        if !self.started {
+            // first frame
+            self.started = true;
+            self.set_received(header.sequence_number);
+            self.last_seqnum = header.sequence_number;
+            self.last_report_seqnum = header.sequence_number.wrapping_sub(1);
+        } else {
+            // following frames
+            self.set_received(header.sequence_number);
+
+            let diff = header.sequence_number as i32 - self.last_seqnum as i32;
+            if !(-0x0FFF..=0).contains(&diff) {
+                // overflow
+                if diff < -0x0FFF {
+                    self.seqnum_cycles = self.seqnum_cycles.wrapping_add(1);
+                }
+
+                // set missing packets as missing
+                let mut i = self.last_seqnum.wrapping_add(1);
+                while i != header.sequence_number {
+                    self.del_received(i);
+                    i = i.wrapping_add(1);
+                }
+
+                self.last_seqnum = header.sequence_number;
+            }
+
+            // compute jitter
+            // https://tools.ietf.org/html/rfc3550#page-39
+            let elapsed = now
+                .duration_since(self.last_rtp_time_time)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let d = (elapsed * self.clock_rate
+                - (header.timestamp as f64 - self.last_rtp_time_rtp as f64))
+                .abs();
+            self.jitter += (d - self.jitter) / 16.0;
+        }
+
+        self.last_rtp_time_rtp = header.timestamp;
+        self.last_rtp_time_time = now;
+    }
+
+    pub(crate) fn process_sender_report(
+        &mut self,
+        now: SystemTime,
+        sr: &rtcp::sender_report::SenderReport,
+    ) {
+        self.last_sender_report = (sr.ntp_time >> 16) as u32;
+        self.last_sender_report_time = Some(now);
+    }
+
+    pub(crate) fn generate_report(
+        &mut self,
+        now: SystemTime,
+    ) -> rtcp::receiver_report::ReceiverReport {
+        let total_since_report = self.last_seqnum.wrapping_sub(self.last_report_seqnum);
+        let mut total_lost_since_report = {
+            let mut ret = 0u32;
+            let mut i = self.last_report_seqnum.wrapping_add(1);
+            while i != self.last_seqnum {
+                if !self.get_received(i) {
+                    ret += 1;
+                }
+                i = i.wrapping_add(1);
+            }
+            ret
+        };
+
+        self.total_lost = self.total_lost.saturating_add(total_lost_since_report);
+
+        // allow up to 24 bits
+        if total_lost_since_report > 0xFFFFFF {
+            total_lost_since_report = 0xFFFFFF;
+        }
+        if self.total_lost > 0xFFFFFF {
+            self.total_lost = 0xFFFFFF;
+        }
+
+        let r = rtcp::receiver_report::ReceiverReport {
+            ssrc: self.receiver_ssrc,
+            reports: vec![rtcp::reception_report::ReceptionReport {
+                ssrc: self.ssrc,
+                last_sequence_number: (self.seqnum_cycles as u32) << 16 | (self.last_seqnum as u32),
+                last_sender_report: self.last_sender_report,
+                fraction_lost: if total_since_report != 0 {
+                    ((total_lost_since_report * 256) as f64 / total_since_report as f64) as u8
+                } else {
+                    0
+                },
+                total_lost: self.total_lost,
+                delay: if let Some(t) = self.last_sender_report_time {
+                    (now.duration_since(t).unwrap_or_default().as_secs_f64() * 65536.0) as u32
+                } else {
+                    0
+                },
+                jitter: self.jitter as u32,
+            }],
+            ..Default::default()
+        };
+
+        self.last_report_seqnum = self.last_seqnum;
+
+        r
+    }
+}
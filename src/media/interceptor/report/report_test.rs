@@ -0,0 +1,118 @@
+use super::receiver_stream::ReceiverStream;
+use super::sender_stream::SenderStream;
+use crate::stats::rtp_stream_stats::ntp_time;
+
+use bytes::Bytes;
+use std::time::{Duration, SystemTime};
+
+fn rtp_header(sequence_number: u16, timestamp: u32) -> rtp::header::Header {
+    rtp::header::Header {
+        version: 2,
+        sequence_number,
+        timestamp,
+        ssrc: 123456,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_receiver_stream_report() {
+    let mut stream = ReceiverStream::new(123456, 90000);
+    let now = SystemTime::now();
+
+    for seq in &[0x01u16, 0x02, 0x03, 0x05] {
+        stream.process_rtp(now, &rtp_header(*seq, 0));
+    }
+
+    let rr = stream.generate_report(now);
+    assert_eq!(1, rr.reports.len());
+    let report = &rr.reports[0];
+    assert_eq!(123456, report.ssrc);
+    assert_eq!(0x05, report.last_sequence_number);
+    assert_eq!(1, report.total_lost);
+    assert_eq!(((1.0 / 5.0) * 256.0) as u8, report.fraction_lost);
+    assert_eq!(0, report.last_sender_report);
+    assert_eq!(0, report.delay);
+
+    // nothing lost since the previous report
+    stream.process_rtp(now, &rtp_header(0x06, 0));
+    let rr = stream.generate_report(now);
+    assert_eq!(1, rr.reports[0].total_lost);
+    assert_eq!(0, rr.reports[0].fraction_lost);
+}
+
+#[test]
+fn test_receiver_stream_report_overflow() {
+    let mut stream = ReceiverStream::new(123456, 90000);
+    let now = SystemTime::now();
+
+    for seq in &[0xfffdu16, 0xfffe, 0x0000, 0x0001] {
+        stream.process_rtp(now, &rtp_header(*seq, 0));
+    }
+
+    let rr = stream.generate_report(now);
+    let report = &rr.reports[0];
+    assert_eq!(1 << 16 | 0x0001, report.last_sequence_number);
+    assert_eq!(1, report.total_lost);
+}
+
+#[test]
+fn test_receiver_stream_report_last_sender_report() {
+    let mut stream = ReceiverStream::new(123456, 90000);
+    let now = SystemTime::now();
+
+    stream.process_rtp(now, &rtp_header(0x01, 0));
+    stream.process_sender_report(
+        now,
+        &rtcp::sender_report::SenderReport {
+            ssrc: 123456,
+            ntp_time: 0x1122_3344_5566_7788,
+            ..Default::default()
+        },
+    );
+
+    let rr = stream.generate_report(now + Duration::from_secs(1));
+    let report = &rr.reports[0];
+    assert_eq!(0x3344_5566, report.last_sender_report);
+    assert_eq!(65536, report.delay);
+}
+
+#[test]
+fn test_receiver_stream_jitter() {
+    let mut stream = ReceiverStream::new(123456, 90000);
+    let now = SystemTime::now();
+
+    stream.process_rtp(now, &rtp_header(0x01, 42378934));
+    // the packet arrives 1s later than expected from its timestamp
+    stream.process_rtp(
+        now + Duration::from_secs(1),
+        &rtp_header(0x02, 42378934 + 60000),
+    );
+
+    let rr = stream.generate_report(now + Duration::from_secs(1));
+    assert_eq!(30000 / 16, rr.reports[0].jitter);
+}
+
+#[test]
+fn test_sender_stream_report() {
+    let mut stream = SenderStream::new(123456, 90000);
+    let now = SystemTime::now();
+
+    for _ in 0..3 {
+        stream.process_rtp(
+            now,
+            &rtp::packet::Packet {
+                header: rtp_header(0, 10000),
+                payload: Bytes::from_static(&[0u8; 100]),
+            },
+        );
+    }
+
+    let report_time = now + Duration::from_secs(1);
+    let sr = stream.generate_report(report_time);
+    assert_eq!(123456, sr.ssrc);
+    assert_eq!(ntp_time(report_time), sr.ntp_time);
+    assert_eq!(10000 + 90000, sr.rtp_time);
+    assert_eq!(3, sr.packet_count);
+    assert_eq!(300, sr.octet_count);
+}
@@ -0,0 +1,184 @@
+use super::sender_stream::SenderStream;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use interceptor::stream_info::StreamInfo;
+use interceptor::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Duration, Instant};
+
+struct SenderInterceptorInternal {
+    interval: Duration,
+    streams: Mutex<HashMap<u32, Arc<Mutex<SenderStream>>>>,
+    rtcp_writer: Mutex<Option<Arc<dyn RTCPWriter + Send + Sync>>>,
+    close_rx: Mutex<Option<mpsc::Receiver<()>>>,
+}
+
+/// SenderInterceptor interceptor generates sender reports.
+pub struct SenderInterceptor {
+    internal: Arc<SenderInterceptorInternal>,
+    close_tx: Mutex<Option<mpsc::Sender<()>>>,
+}
+
+impl Default for SenderInterceptor {
+    fn default() -> Self {
+        SenderInterceptor::new()
+    }
+}
+
+impl SenderInterceptor {
+    /// new returns a new SenderInterceptor sending a report every second.
+    pub fn new() -> Self {
+        SenderInterceptor::with_interval(Duration::from_secs(1))
+    }
+
+    /// with_interval returns a new SenderInterceptor sending a report every interval.
+    pub fn with_interval(interval: Duration) -> Self {
+        let (close_tx, close_rx) = mpsc::channel(1);
+        SenderInterceptor {
+            internal: Arc::new(SenderInterceptorInternal {
+                interval,
+                streams: Mutex::new(HashMap::new()),
+                rtcp_writer: Mutex::new(None),
+                close_rx: Mutex::new(Some(close_rx)),
+            }),
+            close_tx: Mutex::new(Some(close_tx)),
+        }
+    }
+
+    async fn run(internal: Arc<SenderInterceptorInternal>, mut close_rx: mpsc::Receiver<()>) {
+        let mut ticker =
+            tokio::time::interval_at(Instant::now() + internal.interval, internal.interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let now = SystemTime::now();
+                    let streams: Vec<Arc<Mutex<SenderStream>>> = {
+                        let streams = internal.streams.lock().await;
+                        streams.values().cloned().collect()
+                    };
+                    let rtcp_writer = {
+                        let rtcp_writer = internal.rtcp_writer.lock().await;
+                        rtcp_writer.clone()
+                    };
+
+                    if let Some(rtcp_writer) = rtcp_writer {
+                        for stream in streams {
+                            let pkt = {
+                                let stream = stream.lock().await;
+                                stream.generate_report(now)
+                            };
+
+                            if let Err(err) = rtcp_writer.write(&pkt, &Attributes::new()).await {
+                                log::warn!("failed sending sender report: {}", err);
+                            }
+                        }
+                    }
+                }
+                _ = close_rx.recv() => return,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for SenderInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        {
+            let mut rtcp_writer = self.internal.rtcp_writer.lock().await;
+            *rtcp_writer = Some(Arc::clone(&writer));
+        }
+
+        let close_rx = {
+            let mut close_rx = self.internal.close_rx.lock().await;
+            close_rx.take()
+        };
+        if let Some(close_rx) = close_rx {
+            let internal = Arc::clone(&self.internal);
+            tokio::spawn(async move {
+                SenderInterceptor::run(internal, close_rx).await;
+            });
+        }
+
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        let stream = Arc::new(Mutex::new(SenderStream::new(info.ssrc, info.clock_rate)));
+        {
+            let mut streams = self.internal.streams.lock().await;
+            streams.insert(info.ssrc, Arc::clone(&stream));
+        }
+
+        Arc::new(SenderReportRTPWriter {
+            stream,
+            parent_rtp_writer: writer,
+        })
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, info: &StreamInfo) {
+        let mut streams = self.internal.streams.lock().await;
+        streams.remove(&info.ssrc);
+    }
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        reader
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        let mut close_tx = self.close_tx.lock().await;
+        close_tx.take();
+        Ok(())
+    }
+}
+
+struct SenderReportRTPWriter {
+    stream: Arc<Mutex<SenderStream>>,
+    parent_rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPWriter for SenderReportRTPWriter {
+    async fn write(&self, pkt: &rtp::packet::Packet, a: &Attributes) -> Result<usize> {
+        {
+            let mut stream = self.stream.lock().await;
+            stream.process_rtp(SystemTime::now(), pkt);
+        }
+
+        self.parent_rtp_writer.write(pkt, a).await
+    }
+}
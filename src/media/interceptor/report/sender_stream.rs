@@ -0,0 +1,56 @@
+use crate::stats::rtp_stream_stats::ntp_time;
+
+use std::time::SystemTime;
+
+/// SenderStream keeps the state needed to build the sender report of a
+/// single local stream, see RFC 3550 Section 6.4.1.
+pub(crate) struct SenderStream {
+    ssrc: u32,
+    clock_rate: f64,
+
+    // data from rtp packets
+    last_rtp_time_rtp: u32,
+    last_rtp_time_time: SystemTime,
+    packet_count: u32,
+    octet_count: u32,
+}
+
+impl SenderStream {
+    pub(crate) fn new(ssrc: u32, clock_rate: u32) -> Self {
+        SenderStream {
+            ssrc,
+            clock_rate: clock_rate as f64,
+            last_rtp_time_rtp: 0,
+            last_rtp_time_time: SystemTime::UNIX_EPOCH,
+            packet_count: 0,
+            octet_count: 0,
+        }
+    }
+
+    pub(crate) fn process_rtp(&mut self, now: SystemTime, pkt: &rtp::packet::Packet) {
+        // always update time to minimize errors
+        self.last_rtp_time_rtp = pkt.header.timestamp;
+        self.last_rtp_time_time = now;
+
+        self.packet_count = self.packet_count.wrapping_add(1);
+        self.octet_count = self.octet_count.wrapping_add(pkt.payload.len() as u32);
+    }
+
+    pub(crate) fn generate_report(&self, now: SystemTime) -> rtcp::sender_report::SenderReport {
+        let elapsed = now
+            .duration_since(self.last_rtp_time_time)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        rtcp::sender_report::SenderReport {
+            ssrc: self.ssrc,
+            ntp_time: ntp_time(now),
+            rtp_time: self
+                .last_rtp_time_rtp
+                .wrapping_add((elapsed * self.clock_rate) as u32),
+            packet_count: self.packet_count,
+            octet_count: self.octet_count,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::recorder::Recorder;
+use super::{transport_cc_extension_id, transport_sequence_number};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use interceptor::stream_info::StreamInfo;
+use interceptor::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Duration, Instant};
+use util::Unmarshal;
+
+struct GeneratorInterceptorInternal {
+    interval: Duration,
+    start_time: Instant,
+    recorder: Mutex<Recorder>,
+    rtcp_writer: Mutex<Option<Arc<dyn RTCPWriter + Send + Sync>>>,
+    close_rx: Mutex<Option<mpsc::Receiver<()>>>,
+}
+
+/// GeneratorInterceptor interceptor generates transport-wide congestion control
+/// feedback about the packets received with a transport-wide sequence number.
+pub struct GeneratorInterceptor {
+    internal: Arc<GeneratorInterceptorInternal>,
+    close_tx: Mutex<Option<mpsc::Sender<()>>>,
+}
+
+impl Default for GeneratorInterceptor {
+    fn default() -> Self {
+        GeneratorInterceptor::new()
+    }
+}
+
+impl GeneratorInterceptor {
+    /// new returns a new GeneratorInterceptor sending feedback every 100ms.
+    pub fn new() -> Self {
+        GeneratorInterceptor::with_interval(Duration::from_millis(100))
+    }
+
+    /// with_interval returns a new GeneratorInterceptor sending feedback every interval.
+    pub fn with_interval(interval: Duration) -> Self {
+        let (close_tx, close_rx) = mpsc::channel(1);
+        GeneratorInterceptor {
+            internal: Arc::new(GeneratorInterceptorInternal {
+                interval,
+                start_time: Instant::now(),
+                recorder: Mutex::new(Recorder::new(rand::random::<u32>())),
+                rtcp_writer: Mutex::new(None),
+                close_rx: Mutex::new(Some(close_rx)),
+            }),
+            close_tx: Mutex::new(Some(close_tx)),
+        }
+    }
+
+    async fn run(internal: Arc<GeneratorInterceptorInternal>, mut close_rx: mpsc::Receiver<()>) {
+        let mut ticker =
+            tokio::time::interval_at(Instant::now() + internal.interval, internal.interval);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let feedback = {
+                        let mut recorder = internal.recorder.lock().await;
+                        recorder.build_feedback()
+                    };
+                    let rtcp_writer = {
+                        let rtcp_writer = internal.rtcp_writer.lock().await;
+                        rtcp_writer.clone()
+                    };
+
+                    if let (Some(feedback), Some(rtcp_writer)) = (feedback, rtcp_writer) {
+                        if let Err(err) = rtcp_writer.write(&feedback, &Attributes::new()).await {
+                            log::warn!("failed sending transport-wide cc feedback: {}", err);
+                        }
+                    }
+                }
+                _ = close_rx.recv() => return,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for GeneratorInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        {
+            let mut rtcp_writer = self.internal.rtcp_writer.lock().await;
+            *rtcp_writer = Some(Arc::clone(&writer));
+        }
+
+        let close_rx = {
+            let mut close_rx = self.internal.close_rx.lock().await;
+            close_rx.take()
+        };
+        if let Some(close_rx) = close_rx {
+            let internal = Arc::clone(&self.internal);
+            tokio::spawn(async move {
+                GeneratorInterceptor::run(internal, close_rx).await;
+            });
+        }
+
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        writer
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        let id = match transport_cc_extension_id(info) {
+            Some(id) => id,
+            None => return reader,
+        };
+
+        Arc::new(GeneratorRTPReader {
+            id,
+            internal: Arc::clone(&self.internal),
+            parent_rtp_reader: reader,
+        })
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        let mut close_tx = self.close_tx.lock().await;
+        close_tx.take();
+        Ok(())
+    }
+}
+
+struct GeneratorRTPReader {
+    id: u8,
+    internal: Arc<GeneratorInterceptorInternal>,
+    parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPReader for GeneratorRTPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let (n, attr) = self.parent_rtp_reader.read(buf, a).await?;
+        let arrival_time = self.internal.start_time.elapsed();
+
+        let mut b = &buf[..n];
+        if let Ok(header) = rtp::header::Header::unmarshal(&mut b) {
+            if let Some(sequence_number) = transport_sequence_number(&header, self.id) {
+                let mut recorder = self.internal.recorder.lock().await;
+                recorder.record(
+                    header.ssrc,
+                    sequence_number,
+                    arrival_time.as_micros() as i64,
+                );
+            }
+        }
+
+        Ok((n, attr))
+    }
+}
@@ -0,0 +1,112 @@
+use super::transport_cc_extension_id;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use interceptor::stream_info::StreamInfo;
+use interceptor::{Attributes, Interceptor, RTCPReader, RTCPWriter, RTPReader, RTPWriter};
+use rtp::extension::transport_cc_extension::TransportCcExtension;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use util::Marshal;
+
+/// HeaderExtensionInterceptor adds the transport-wide sequence number header
+/// extension to every outgoing packet of the streams that negotiated it.
+pub struct HeaderExtensionInterceptor {
+    next_sequence_number: Arc<AtomicU32>,
+}
+
+impl Default for HeaderExtensionInterceptor {
+    fn default() -> Self {
+        HeaderExtensionInterceptor::new()
+    }
+}
+
+impl HeaderExtensionInterceptor {
+    /// new returns a new HeaderExtensionInterceptor
+    pub fn new() -> Self {
+        HeaderExtensionInterceptor {
+            next_sequence_number: Arc::new(AtomicU32::new(0)),
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for HeaderExtensionInterceptor {
+    /// bind_rtcp_reader lets you modify any incoming RTCP packets. It is called once per sender/receiver, however this might
+    /// change in the future. The returned method will be called once per packet batch.
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    /// bind_rtcp_writer lets you modify any outgoing RTCP packets. It is called once per PeerConnection. The returned method
+    /// will be called once per packet batch.
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    /// bind_local_stream lets you modify any outgoing RTP packets. It is called once for per LocalStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_local_stream(
+        &self,
+        info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        let id = match transport_cc_extension_id(info) {
+            Some(id) => id,
+            None => return writer,
+        };
+
+        Arc::new(HeaderExtensionRTPWriter {
+            id,
+            next_sequence_number: Arc::clone(&self.next_sequence_number),
+            parent_rtp_writer: writer,
+        })
+    }
+
+    /// unbind_local_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    /// bind_remote_stream lets you modify any incoming RTP packets. It is called once for per RemoteStream. The returned method
+    /// will be called once per rtp packet.
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        reader
+    }
+
+    /// unbind_remote_stream is called when the Stream is removed. It can be used to clean up any data related to that track.
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    /// close closes the Interceptor, cleaning up any data if necessary.
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct HeaderExtensionRTPWriter {
+    id: u8,
+    next_sequence_number: Arc<AtomicU32>,
+    parent_rtp_writer: Arc<dyn RTPWriter + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPWriter for HeaderExtensionRTPWriter {
+    async fn write(&self, pkt: &rtp::packet::Packet, a: &Attributes) -> Result<usize> {
+        // the sequence numbers are shared by all the streams of the transport
+        let transport_sequence = self.next_sequence_number.fetch_add(1, Ordering::SeqCst) as u16;
+        let ext = TransportCcExtension { transport_sequence }.marshal()?;
+
+        let mut pkt = pkt.clone();
+        pkt.header.set_extension(self.id, ext)?;
+
+        self.parent_rtp_writer.write(&pkt, a).await
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod twcc_test;
+
+pub mod generator_interceptor;
+pub mod header_extension_interceptor;
+
+pub(crate) mod recorder;
+
+use interceptor::stream_info::StreamInfo;
+use rtcp::transport_feedbacks::transport_layer_cc::{
+    PacketStatusChunk, SymbolTypeTcc, TransportLayerCc,
+};
+use rtp::extension::transport_cc_extension::TransportCcExtension;
+use util::Unmarshal;
+
+/// TRANSPORT_CC_URI is the URI of the transport-wide sequence number header extension
+/// https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01
+pub const TRANSPORT_CC_URI: &str =
+    "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01";
+
+/// transport_cc_extension_id returns the negotiated id of the transport-wide
+/// sequence number header extension of a stream, if any
+pub(crate) fn transport_cc_extension_id(info: &StreamInfo) -> Option<u8> {
+    info.rtp_header_extensions
+        .iter()
+        .find(|e| e.uri == TRANSPORT_CC_URI)
+        .map(|e| e.id as u8)
+}
+
+/// transport_sequence_number returns the transport-wide sequence number of a
+/// packet, if it carries the header extension with the given id
+pub(crate) fn transport_sequence_number(header: &rtp::header::Header, id: u8) -> Option<u16> {
+    let mut payload = header.get_extension(id)?;
+    TransportCcExtension::unmarshal(&mut payload)
+        .ok()
+        .map(|e| e.transport_sequence)
+}
+
+/// packet_arrivals decodes a feedback into the transport-wide sequence number of
+/// every packet it reports, along with its arrival time in microseconds on the
+/// remote clock, or None when the packet wasn't received.
+pub(crate) fn packet_arrivals(feedback: &TransportLayerCc) -> Vec<(u16, Option<i64>)> {
+    let mut symbols = vec![];
+    for chunk in &feedback.packet_chunks {
+        match chunk {
+            PacketStatusChunk::RunLengthChunk(c) => symbols.resize(
+                symbols.len() + c.run_length as usize,
+                c.packet_status_symbol,
+            ),
+            PacketStatusChunk::StatusVectorChunk(c) => symbols.extend(c.symbol_list.iter()),
+        }
+    }
+    symbols.truncate(feedback.packet_status_count as usize);
+
+    let mut arrival = feedback.reference_time as i64 * recorder::REFERENCE_TIME_RESOLUTION_US;
+    let mut recv_deltas = feedback.recv_deltas.iter();
+    symbols
+        .iter()
+        .enumerate()
+        .map(|(i, symbol)| {
+            let sequence_number = feedback.base_sequence_number.wrapping_add(i as u16);
+            match symbol {
+                SymbolTypeTcc::PacketReceivedSmallDelta
+                | SymbolTypeTcc::PacketReceivedLargeDelta => {
+                    let delta = recv_deltas.next().map_or(0, |d| d.delta);
+                    arrival += delta;
+                    (sequence_number, Some(arrival))
+                }
+                _ => (sequence_number, None),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,162 @@
+use rtcp::transport_feedbacks::transport_layer_cc::{
+    PacketStatusChunk, RecvDelta, RunLengthChunk, StatusChunkTypeTcc, StatusVectorChunk,
+    SymbolSizeTypeTcc, SymbolTypeTcc, TransportLayerCc,
+};
+use std::collections::BTreeMap;
+
+/// the reference time of a feedback is expressed in multiples of 64ms
+pub(crate) const REFERENCE_TIME_RESOLUTION_US: i64 = 64_000;
+/// the receive deltas of a feedback are expressed in multiples of 250us
+const DELTA_RESOLUTION_US: i64 = 250;
+/// the longest run a run length chunk can hold
+const MAX_RUN_LENGTH: usize = (1 << 13) - 1;
+/// the number of symbols of a two bit status vector chunk
+const STATUS_VECTOR_SYMBOLS: usize = 7;
+
+/// Recorder records the arrival times of the packets received on a transport and
+/// builds the transport-wide congestion control feedback about them.
+pub(crate) struct Recorder {
+    sender_ssrc: u32,
+    media_ssrc: u32,
+    fb_pkt_count: u8,
+
+    /// arrival times in microseconds, keyed by unwrapped transport sequence number
+    arrivals: BTreeMap<i64, i64>,
+    last_sequence_number: Option<(u16, i64)>,
+    /// the first sequence number which hasn't been reported yet
+    next_sequence_number: Option<i64>,
+}
+
+impl Recorder {
+    pub(crate) fn new(sender_ssrc: u32) -> Self {
+        Recorder {
+            sender_ssrc,
+            media_ssrc: 0,
+            fb_pkt_count: 0,
+            arrivals: BTreeMap::new(),
+            last_sequence_number: None,
+            next_sequence_number: None,
+        }
+    }
+
+    /// record records the arrival of the packet with the given transport-wide sequence
+    /// number, sent on the stream media_ssrc.
+    pub(crate) fn record(&mut self, media_ssrc: u32, sequence_number: u16, arrival_time_us: i64) {
+        let unwrapped = match self.last_sequence_number {
+            Some((last, last_unwrapped)) => {
+                last_unwrapped + sequence_number.wrapping_sub(last) as i16 as i64
+            }
+            None => sequence_number as i64,
+        };
+        if !matches!(self.last_sequence_number, Some((_, last_unwrapped)) if unwrapped <= last_unwrapped)
+        {
+            self.last_sequence_number = Some((sequence_number, unwrapped));
+        }
+
+        // packets arriving after they were reported as lost can't be reported anymore
+        if matches!(self.next_sequence_number, Some(next) if unwrapped < next) {
+            return;
+        }
+
+        self.media_ssrc = media_ssrc;
+        self.arrivals.insert(unwrapped, arrival_time_us);
+    }
+
+    /// build_feedback returns the feedback about every packet received since the
+    /// previous feedback, or None when nothing was received since then.
+    pub(crate) fn build_feedback(&mut self) -> Option<TransportLayerCc> {
+        let (&first, _) = self.arrivals.iter().next()?;
+        let (&last, _) = self.arrivals.iter().next_back()?;
+
+        let mut base = self.next_sequence_number.unwrap_or(first);
+        // the packet status count is a 16 bits value
+        if last - base >= u16::MAX as i64 {
+            base = last - u16::MAX as i64 + 1;
+        }
+
+        let first_arrival = *self.arrivals.range(base..).next()?.1;
+        let reference_time = first_arrival.div_euclid(REFERENCE_TIME_RESOLUTION_US);
+        let mut last_arrival = reference_time * REFERENCE_TIME_RESOLUTION_US;
+
+        let mut symbols = vec![];
+        let mut recv_deltas = vec![];
+        for sequence_number in base..=last {
+            let arrival = match self.arrivals.get(&sequence_number) {
+                Some(arrival) => *arrival,
+                None => {
+                    symbols.push(SymbolTypeTcc::PacketNotReceived);
+                    continue;
+                }
+            };
+
+            let delta = ((arrival - last_arrival) / DELTA_RESOLUTION_US)
+                .clamp(i16::MIN as i64, i16::MAX as i64);
+            let symbol = if (0..=u8::MAX as i64).contains(&delta) {
+                SymbolTypeTcc::PacketReceivedSmallDelta
+            } else {
+                SymbolTypeTcc::PacketReceivedLargeDelta
+            };
+            symbols.push(symbol);
+            recv_deltas.push(RecvDelta {
+                type_tcc_packet: symbol,
+                delta: delta * DELTA_RESOLUTION_US,
+            });
+            last_arrival += delta * DELTA_RESOLUTION_US;
+        }
+
+        let feedback = TransportLayerCc {
+            sender_ssrc: self.sender_ssrc,
+            media_ssrc: self.media_ssrc,
+            base_sequence_number: base as u16,
+            packet_status_count: symbols.len() as u16,
+            reference_time: (reference_time & 0xFF_FFFF) as u32,
+            fb_pkt_count: self.fb_pkt_count,
+            packet_chunks: packet_chunks(&symbols),
+            recv_deltas,
+        };
+
+        self.fb_pkt_count = self.fb_pkt_count.wrapping_add(1);
+        self.next_sequence_number = Some(last + 1);
+        self.arrivals.clear();
+
+        Some(feedback)
+    }
+}
+
+/// packet_chunks encodes the status of every packet of a feedback, using run length
+/// chunks for long runs of the same status and two bit status vectors otherwise.
+fn packet_chunks(symbols: &[SymbolTypeTcc]) -> Vec<PacketStatusChunk> {
+    let mut chunks = vec![];
+
+    let mut i = 0;
+    while i < symbols.len() {
+        let run = symbols[i..]
+            .iter()
+            .take(MAX_RUN_LENGTH)
+            .take_while(|s| **s == symbols[i])
+            .count();
+
+        if run >= STATUS_VECTOR_SYMBOLS {
+            chunks.push(PacketStatusChunk::RunLengthChunk(RunLengthChunk {
+                type_tcc: StatusChunkTypeTcc::RunLengthChunk,
+                packet_status_symbol: symbols[i],
+                run_length: run as u16,
+            }));
+            i += run;
+        } else {
+            let end = (i + STATUS_VECTOR_SYMBOLS).min(symbols.len());
+            let mut symbol_list = symbols[i..end].to_vec();
+            // the unused symbols of the last chunk are reported as not received
+            symbol_list.resize(STATUS_VECTOR_SYMBOLS, SymbolTypeTcc::PacketNotReceived);
+
+            chunks.push(PacketStatusChunk::StatusVectorChunk(StatusVectorChunk {
+                type_tcc: StatusChunkTypeTcc::StatusVectorChunk,
+                symbol_size: SymbolSizeTypeTcc::TwoBit,
+                symbol_list,
+            }));
+            i = end;
+        }
+    }
+
+    chunks
+}
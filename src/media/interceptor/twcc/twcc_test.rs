@@ -0,0 +1,292 @@
+use super::generator_interceptor::GeneratorInterceptor;
+use super::header_extension_interceptor::HeaderExtensionInterceptor;
+use super::recorder::Recorder;
+use super::*;
+use crate::api::interceptor_registry::{configure_congestion_control, Registry};
+use crate::api::media_engine::MediaEngine;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use interceptor::stream_info::RTPHeaderExtension;
+use interceptor::{Attributes, Interceptor, RTCPWriter, RTPReader, RTPWriter};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::Duration;
+use util::Marshal;
+
+fn twcc_stream_info(ssrc: u32) -> StreamInfo {
+    StreamInfo {
+        ssrc,
+        rtp_header_extensions: vec![RTPHeaderExtension {
+            uri: TRANSPORT_CC_URI.to_owned(),
+            id: 5,
+        }],
+        ..Default::default()
+    }
+}
+
+/// round_trip marshals and unmarshals a feedback, as it would be sent on the wire
+fn round_trip(feedback: &TransportLayerCc) -> TransportLayerCc {
+    let mut raw = feedback.marshal().unwrap();
+    TransportLayerCc::unmarshal(&mut raw).unwrap()
+}
+
+#[test]
+fn test_transport_cc_extension_id() {
+    assert_eq!(Some(5), transport_cc_extension_id(&twcc_stream_info(1)));
+    assert_eq!(None, transport_cc_extension_id(&StreamInfo::default()));
+}
+
+#[test]
+fn test_recorder_feedback() {
+    let mut recorder = Recorder::new(1);
+    assert!(recorder.build_feedback().is_none());
+
+    recorder.record(2, 10, 1_000_000);
+    recorder.record(2, 11, 1_001_000);
+    // 12 is lost
+    recorder.record(2, 13, 1_000_500);
+    recorder.record(2, 14, 1_200_000);
+
+    let feedback = round_trip(&recorder.build_feedback().unwrap());
+    assert_eq!(1, feedback.sender_ssrc);
+    assert_eq!(2, feedback.media_ssrc);
+    assert_eq!(10, feedback.base_sequence_number);
+    assert_eq!(5, feedback.packet_status_count);
+    assert_eq!(0, feedback.fb_pkt_count);
+
+    assert_eq!(
+        vec![
+            (10, Some(1_000_000)),
+            (11, Some(1_001_000)),
+            (12, None),
+            (13, Some(1_000_500)),
+            (14, Some(1_200_000)),
+        ],
+        packet_arrivals(&feedback)
+    );
+
+    // everything was reported
+    assert!(recorder.build_feedback().is_none());
+
+    // packets arriving after being reported lost are ignored
+    recorder.record(2, 12, 1_300_000);
+    recorder.record(2, 16, 1_300_000);
+    let feedback = round_trip(&recorder.build_feedback().unwrap());
+    assert_eq!(15, feedback.base_sequence_number);
+    assert_eq!(1, feedback.fb_pkt_count);
+    assert_eq!(
+        vec![(15, None), (16, Some(1_300_000))],
+        packet_arrivals(&feedback)
+    );
+}
+
+#[test]
+fn test_recorder_feedback_run_length() {
+    let mut recorder = Recorder::new(1);
+
+    for i in 0..20u16 {
+        recorder.record(2, i, 64_000 + i as i64 * 1_000);
+    }
+    // 20..40 are lost
+    recorder.record(2, 40, 200_000);
+
+    let feedback = recorder.build_feedback().unwrap();
+    assert_eq!(3, feedback.packet_chunks.len());
+    assert!(feedback.packet_chunks[..2]
+        .iter()
+        .all(|c| matches!(c, PacketStatusChunk::RunLengthChunk(_))));
+
+    let arrivals = packet_arrivals(&round_trip(&feedback));
+    assert_eq!(41, arrivals.len());
+    for (i, (sequence_number, arrival)) in arrivals.iter().enumerate().take(20) {
+        assert_eq!(i as u16, *sequence_number);
+        assert_eq!(Some(64_000 + i as i64 * 1_000), *arrival);
+    }
+    assert!(arrivals[20..40]
+        .iter()
+        .all(|(_, arrival)| arrival.is_none()));
+    assert_eq!((40, Some(200_000)), arrivals[40]);
+}
+
+#[test]
+fn test_recorder_feedback_overflow() {
+    let mut recorder = Recorder::new(1);
+
+    recorder.record(2, 0xfffe, 0);
+    recorder.record(2, 0xffff, 1_000);
+    recorder.record(2, 0x0000, 2_000);
+    recorder.record(2, 0x0001, 3_000);
+
+    let feedback = round_trip(&recorder.build_feedback().unwrap());
+    assert_eq!(0xfffe, feedback.base_sequence_number);
+    assert_eq!(
+        vec![
+            (0xfffe, Some(0)),
+            (0xffff, Some(1_000)),
+            (0x0000, Some(2_000)),
+            (0x0001, Some(3_000)),
+        ],
+        packet_arrivals(&feedback)
+    );
+}
+
+struct MockRTPWriter {
+    tx: mpsc::Sender<rtp::packet::Packet>,
+}
+
+#[async_trait]
+impl RTPWriter for MockRTPWriter {
+    async fn write(&self, pkt: &rtp::packet::Packet, _a: &Attributes) -> Result<usize> {
+        let _ = self.tx.send(pkt.clone()).await;
+        Ok(pkt.payload.len())
+    }
+}
+
+#[tokio::test]
+async fn test_header_extension_interceptor() -> Result<()> {
+    let icpr = HeaderExtensionInterceptor::new();
+
+    let (tx, mut rx) = mpsc::channel(16);
+    let writer = Arc::new(MockRTPWriter { tx });
+    let first = icpr
+        .bind_local_stream(&twcc_stream_info(1), Arc::clone(&writer) as _)
+        .await;
+    let second = icpr
+        .bind_local_stream(&twcc_stream_info(2), Arc::clone(&writer) as _)
+        .await;
+    let without_extension = icpr
+        .bind_local_stream(&StreamInfo::default(), writer as _)
+        .await;
+
+    // the sequence numbers are shared by every stream
+    let a = Attributes::new();
+    for (i, stream) in [&first, &second, &first].iter().enumerate() {
+        stream.write(&rtp::packet::Packet::default(), &a).await?;
+        let pkt = rx.recv().await.unwrap();
+        assert_eq!(Some(i as u16), transport_sequence_number(&pkt.header, 5));
+    }
+
+    without_extension
+        .write(&rtp::packet::Packet::default(), &a)
+        .await?;
+    let pkt = rx.recv().await.unwrap();
+    assert_eq!(None, transport_sequence_number(&pkt.header, 5));
+
+    Ok(())
+}
+
+struct MockRTPReader {
+    rx: Mutex<mpsc::Receiver<rtp::packet::Packet>>,
+}
+
+#[async_trait]
+impl RTPReader for MockRTPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let mut rx = self.rx.lock().await;
+        let b = match rx.recv().await {
+            Some(pkt) => pkt.marshal()?,
+            None => Bytes::new(),
+        };
+        buf[..b.len()].copy_from_slice(&b);
+        Ok((b.len(), a.clone()))
+    }
+}
+
+struct MockRTCPWriter {
+    tx: mpsc::Sender<TransportLayerCc>,
+}
+
+#[async_trait]
+impl RTCPWriter for MockRTCPWriter {
+    async fn write(
+        &self,
+        pkt: &(dyn rtcp::packet::Packet + Send + Sync),
+        _a: &Attributes,
+    ) -> Result<usize> {
+        if let Some(feedback) = pkt.as_any().downcast_ref::<TransportLayerCc>() {
+            let _ = self.tx.send(feedback.clone()).await;
+        }
+        Ok(0)
+    }
+}
+
+#[tokio::test]
+async fn test_generator_interceptor() -> Result<()> {
+    let icpr = GeneratorInterceptor::with_interval(Duration::from_millis(50));
+
+    let (feedback_tx, mut feedback_rx) = mpsc::channel(16);
+    icpr.bind_rtcp_writer(Arc::new(MockRTCPWriter { tx: feedback_tx }))
+        .await;
+
+    let (pkt_tx, pkt_rx) = mpsc::channel(16);
+    let reader = icpr
+        .bind_remote_stream(
+            &twcc_stream_info(123456),
+            Arc::new(MockRTPReader {
+                rx: Mutex::new(pkt_rx),
+            }),
+        )
+        .await;
+
+    let mut buf = vec![0u8; 1500];
+    for transport_sequence in &[0u16, 1, 3] {
+        let mut pkt = rtp::packet::Packet {
+            header: rtp::header::Header {
+                version: 2,
+                ssrc: 123456,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        pkt.header.set_extension(
+            5,
+            TransportCcExtension {
+                transport_sequence: *transport_sequence,
+            }
+            .marshal()?,
+        )?;
+        pkt_tx.send(pkt).await?;
+        reader.read(&mut buf, &Attributes::new()).await?;
+    }
+
+    let feedback = tokio::time::timeout(Duration::from_secs(1), feedback_rx.recv())
+        .await?
+        .unwrap();
+    assert_eq!(123456, feedback.media_ssrc);
+    let arrivals = packet_arrivals(&feedback);
+    let received: Vec<(u16, bool)> = arrivals
+        .iter()
+        .map(|(sequence_number, arrival)| (*sequence_number, arrival.is_some()))
+        .collect();
+    assert_eq!(vec![(0, true), (1, true), (2, false), (3, true)], received);
+
+    icpr.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_header_extension_interceptor_per_peer_connection() -> Result<()> {
+    let mut media_engine = MediaEngine::default();
+    let registry = configure_congestion_control(Registry::new(), &mut media_engine).await?;
+
+    // every PeerConnection numbers the packets it sends from 0
+    let (tx, mut rx) = mpsc::channel(16);
+    let writer = Arc::new(MockRTPWriter { tx });
+    let a = Attributes::new();
+    for _ in 0..2 {
+        let chain = registry.build();
+        let stream = chain
+            .bind_local_stream(&twcc_stream_info(1), Arc::clone(&writer) as _)
+            .await;
+        for i in 0..2 {
+            stream.write(&rtp::packet::Packet::default(), &a).await?;
+            let pkt = rx.recv().await.unwrap();
+            assert_eq!(Some(i), transport_sequence_number(&pkt.header, 5));
+        }
+        chain.close().await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use super::*;
+use std::io::Cursor;
+
+const STREAM: &[u8] = &[
+    0x00, 0x00, 0x00, 0x01, 0x09, 0xf0, // AUD
+    0x00, 0x00, 0x00, 0x01, 0x67, 0x42, 0x00, 0x1f, // SPS
+    0x00, 0x00, 0x01, 0x68, 0xce, 0x3c, 0x80, // PPS
+    0x00, 0x00, 0x01, 0x65, 0x88, 0x84, // IDR, first slice
+    0x00, 0x00, 0x01, 0x65, 0x00, 0x11, // IDR, second slice
+    0x00, 0x00, 0x00, 0x01, 0x41, 0x9a, 0x02, // non-IDR, first slice
+    0x00, 0x00, 0x01, 0x41, 0x9b, 0x03, 0x00, // non-IDR, first slice, trailing zero
+];
+
+/// OneByteReader returns the stream one byte at a time
+struct OneByteReader(Cursor<&'static [u8]>);
+
+impl Read for OneByteReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(1);
+        self.0.read(&mut buf[..n])
+    }
+}
+
+fn assert_nals<R: Read>(reader: &mut H264Reader<R>) -> Result<()> {
+    let expected = [
+        (NalUnitType::AUD, &[0x09u8, 0xf0][..]),
+        (NalUnitType::SPS, &[0x67, 0x42, 0x00, 0x1f]),
+        (NalUnitType::PPS, &[0x68, 0xce, 0x3c, 0x80]),
+        (NalUnitType::CodedSliceIdr, &[0x65, 0x88, 0x84]),
+        (NalUnitType::CodedSliceIdr, &[0x65, 0x00, 0x11]),
+        (NalUnitType::CodedSliceNonIdr, &[0x41, 0x9a, 0x02]),
+        (NalUnitType::CodedSliceNonIdr, &[0x41, 0x9b, 0x03]),
+    ];
+
+    for (unit_type, data) in expected.iter() {
+        let nal = reader.next_nal()?;
+        assert_eq!(nal.unit_type, *unit_type);
+        assert_eq!(&nal.data[..], *data);
+        assert!(!nal.forbidden_zero_bit);
+    }
+    assert!(is_eof(&reader.next_nal().err().unwrap()));
+
+    Ok(())
+}
+
+#[test]
+fn test_h264_reader_next_nal() -> Result<()> {
+    let mut reader = H264Reader::new(Cursor::new(STREAM), Duration::from_millis(33));
+    assert_nals(&mut reader)?;
+
+    // Start codes spanning reads
+    let mut reader = H264Reader::new(
+        OneByteReader(Cursor::new(STREAM)),
+        Duration::from_millis(33),
+    );
+    assert_nals(&mut reader)
+}
+
+#[test]
+fn test_h264_reader_invalid_data() {
+    let mut reader = H264Reader::new(
+        Cursor::new(&[0xff, 0x00, 0x00, 0x01, 0x09, 0xf0][..]),
+        Duration::from_millis(33),
+    );
+    let result = reader.next_nal();
+    assert!(Error::ErrH264DataIsNotAnnexBFormat.equal(&result.err().unwrap()));
+
+    let mut reader = H264Reader::new(Cursor::new(&[][..]), Duration::from_millis(33));
+    assert!(is_eof(&reader.next_nal().err().unwrap()));
+}
+
+#[test]
+fn test_h264_reader_read_sample() -> Result<()> {
+    let mut reader = H264Reader::new(Cursor::new(STREAM), Duration::from_millis(33));
+
+    let expected: [&[u8]; 3] = [
+        &[
+            0x00, 0x00, 0x00, 0x01, 0x09, 0xf0, 0x00, 0x00, 0x00, 0x01, 0x67, 0x42, 0x00, 0x1f,
+            0x00, 0x00, 0x00, 0x01, 0x68, 0xce, 0x3c, 0x80, 0x00, 0x00, 0x00, 0x01, 0x65, 0x88,
+            0x84, 0x00, 0x00, 0x00, 0x01, 0x65, 0x00, 0x11,
+        ],
+        &[0x00, 0x00, 0x00, 0x01, 0x41, 0x9a, 0x02],
+        &[0x00, 0x00, 0x00, 0x01, 0x41, 0x9b, 0x03],
+    ];
+    for data in expected.iter() {
+        let sample = reader.read_sample()?;
+        assert_eq!(&sample.data[..], *data);
+        assert_eq!(sample.duration, Duration::from_millis(33));
+    }
+    assert!(is_eof(&reader.read_sample().err().unwrap()));
+
+    Ok(())
+}
@@ -0,0 +1,301 @@
+#[cfg(test)]
+mod h264_reader_test;
+
+use crate::error::Error;
+use crate::media::io::{is_eof, Reader};
+use crate::media::Sample;
+
+use anyhow::Result;
+use bytes::{Buf, BufMut, BytesMut};
+use std::fmt;
+use std::io::Read;
+use tokio::time::Duration;
+
+/// the size of the chunks read from the stream
+const READ_BUFFER_SIZE: usize = 4096;
+
+/// the start code written before each NAL of the samples
+const ANNEXB_NALUSTART_CODE: &[u8] = &[0x00, 0x00, 0x00, 0x01];
+
+/// NalUnitType is the type of a NAL
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NalUnitType {
+    /// Unspecified
+    Unspecified,
+    /// Coded slice of a non-IDR picture
+    CodedSliceNonIdr,
+    /// Coded slice data partition A
+    CodedSliceDataPartitionA,
+    /// Coded slice data partition B
+    CodedSliceDataPartitionB,
+    /// Coded slice data partition C
+    CodedSliceDataPartitionC,
+    /// Coded slice of an IDR picture
+    CodedSliceIdr,
+    /// Supplemental enhancement information (SEI)
+    SEI,
+    /// Sequence parameter set
+    SPS,
+    /// Picture parameter set
+    PPS,
+    /// Access unit delimiter
+    AUD,
+    /// End of sequence
+    EndOfSequence,
+    /// End of stream
+    EndOfStream,
+    /// Filler data
+    Filler,
+    /// Sequence parameter set extension
+    SpsExt,
+    /// Coded slice of an auxiliary coded picture without partitioning
+    CodedSliceAux,
+    /// Reserved
+    Reserved,
+}
+
+impl fmt::Display for NalUnitType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match *self {
+            NalUnitType::Unspecified => "Unspecified",
+            NalUnitType::CodedSliceNonIdr => "CodedSliceNonIdr",
+            NalUnitType::CodedSliceDataPartitionA => "CodedSliceDataPartitionA",
+            NalUnitType::CodedSliceDataPartitionB => "CodedSliceDataPartitionB",
+            NalUnitType::CodedSliceDataPartitionC => "CodedSliceDataPartitionC",
+            NalUnitType::CodedSliceIdr => "CodedSliceIdr",
+            NalUnitType::SEI => "SEI",
+            NalUnitType::SPS => "SPS",
+            NalUnitType::PPS => "PPS",
+            NalUnitType::AUD => "AUD",
+            NalUnitType::EndOfSequence => "EndOfSequence",
+            NalUnitType::EndOfStream => "EndOfStream",
+            NalUnitType::Filler => "Filler",
+            NalUnitType::SpsExt => "SpsExt",
+            NalUnitType::CodedSliceAux => "CodedSliceAux",
+            NalUnitType::Reserved => "Reserved",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl From<u8> for NalUnitType {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => NalUnitType::Unspecified,
+            1 => NalUnitType::CodedSliceNonIdr,
+            2 => NalUnitType::CodedSliceDataPartitionA,
+            3 => NalUnitType::CodedSliceDataPartitionB,
+            4 => NalUnitType::CodedSliceDataPartitionC,
+            5 => NalUnitType::CodedSliceIdr,
+            6 => NalUnitType::SEI,
+            7 => NalUnitType::SPS,
+            8 => NalUnitType::PPS,
+            9 => NalUnitType::AUD,
+            10 => NalUnitType::EndOfSequence,
+            11 => NalUnitType::EndOfStream,
+            12 => NalUnitType::Filler,
+            13 => NalUnitType::SpsExt,
+            19 => NalUnitType::CodedSliceAux,
+            _ => NalUnitType::Reserved,
+        }
+    }
+}
+
+/// NAL H.264 Network Abstraction Layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct NAL {
+    /// NAL header
+    pub forbidden_zero_bit: bool,
+    pub ref_idc: u8,
+    pub unit_type: NalUnitType,
+
+    /// header byte + rbsp
+    pub data: BytesMut,
+}
+
+impl NAL {
+    fn new(data: BytesMut) -> Self {
+        let first_byte = data[0];
+        NAL {
+            forbidden_zero_bit: first_byte & 0x80 != 0,
+            ref_idc: (first_byte & 0x60) >> 5,
+            unit_type: NalUnitType::from(first_byte & 0x1F),
+            data,
+        }
+    }
+
+    /// is_vcl reports whether the NAL holds a slice of the primary coded picture
+    fn is_vcl(&self) -> bool {
+        matches!(
+            self.unit_type,
+            NalUnitType::CodedSliceNonIdr
+                | NalUnitType::CodedSliceDataPartitionA
+                | NalUnitType::CodedSliceIdr
+        )
+    }
+
+    /// starts_access_unit reports whether the NAL begins a new access unit,
+    /// when it follows a slice of the previous picture
+    /// https://www.itu.int/rec/T-REC-H.264 section 7.4.1.2.3
+    fn starts_access_unit(&self) -> bool {
+        match self.unit_type {
+            NalUnitType::SEI | NalUnitType::SPS | NalUnitType::PPS | NalUnitType::AUD => true,
+            // first_mb_in_slice, the first field of the slice header, is 0 when its
+            // Exp-Golomb code is the single bit 1
+            _ if self.is_vcl() => self.data.len() > 1 && self.data[1] & 0x80 != 0,
+            _ => false,
+        }
+    }
+}
+
+/// H264Reader reads data from stream and constructs h264 nal units
+pub struct H264Reader<R: Read> {
+    reader: R,
+    frame_duration: Duration,
+
+    buffer: BytesMut,
+    /// the position up to which buffer holds no start code
+    scanned: usize,
+    started: bool,
+    eof: bool,
+
+    next_nal: Option<NAL>,
+}
+
+impl<R: Read> H264Reader<R> {
+    /// new creates new H264Reader. Annex-B streams carry no timing, so the
+    /// samples all last frame_duration.
+    pub fn new(reader: R, frame_duration: Duration) -> Self {
+        H264Reader {
+            reader,
+            frame_duration,
+
+            buffer: BytesMut::new(),
+            scanned: 0,
+            started: false,
+            eof: false,
+
+            next_nal: None,
+        }
+    }
+
+    fn fill_buffer(&mut self) -> Result<()> {
+        let mut chunk = [0u8; READ_BUFFER_SIZE];
+        let n = self.reader.read(&mut chunk)?;
+        if n == 0 {
+            self.eof = true;
+        }
+        self.buffer.put_slice(&chunk[..n]);
+        Ok(())
+    }
+
+    /// next_nal reads from stream and returns the next NAL, without its start code.
+    /// When no more NALs are available, it returns an error for which is_eof holds.
+    pub fn next_nal(&mut self) -> Result<NAL> {
+        if let Some(nal) = self.next_nal.take() {
+            return Ok(nal);
+        }
+
+        loop {
+            if !self.started {
+                match find_start_code(&self.buffer, 0) {
+                    Some((start, end)) => {
+                        if self.buffer[..start].iter().any(|b| *b != 0) {
+                            return Err(Error::ErrH264DataIsNotAnnexBFormat.into());
+                        }
+                        self.buffer.advance(end);
+                        self.started = true;
+                    }
+                    None => {
+                        if self.buffer.iter().any(|b| *b != 0) {
+                            return Err(Error::ErrH264DataIsNotAnnexBFormat.into());
+                        }
+                        if self.eof {
+                            return Err(
+                                std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()
+                            );
+                        }
+                        self.fill_buffer()?;
+                    }
+                }
+                continue;
+            }
+
+            let data = match find_start_code(&self.buffer, self.scanned) {
+                Some((start, end)) => {
+                    let data = self.buffer.split_to(start);
+                    self.buffer.advance(end - start);
+                    self.scanned = 0;
+                    data
+                }
+                None if self.eof => {
+                    self.scanned = 0;
+                    self.buffer.split()
+                }
+                None => {
+                    // A start code may span the end of the buffer
+                    self.scanned = self.buffer.len().saturating_sub(2);
+                    self.fill_buffer()?;
+                    continue;
+                }
+            };
+
+            let data = trim_trailing_zeros(data);
+            if !data.is_empty() {
+                return Ok(NAL::new(data));
+            }
+            if self.eof && self.buffer.is_empty() {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+        }
+    }
+}
+
+impl<R: Read> Reader for H264Reader<R> {
+    /// read_sample returns the next access unit, made of the NALs of a
+    /// picture written in Annex-B format.
+    fn read_sample(&mut self) -> Result<Sample> {
+        let mut data = BytesMut::new();
+        let mut has_vcl = false;
+
+        loop {
+            let nal = match self.next_nal() {
+                Ok(nal) => nal,
+                Err(err) if is_eof(&err) && !data.is_empty() => break,
+                Err(err) => return Err(err),
+            };
+
+            if has_vcl && nal.starts_access_unit() {
+                self.next_nal = Some(nal);
+                break;
+            }
+            has_vcl = has_vcl || nal.is_vcl();
+
+            data.put_slice(ANNEXB_NALUSTART_CODE);
+            data.put(nal.data);
+        }
+
+        Ok(Sample {
+            data: data.freeze(),
+            duration: self.frame_duration,
+            ..Default::default()
+        })
+    }
+}
+
+/// find_start_code returns the bounds of the first 3 bytes start code
+/// found from the given position
+fn find_start_code(data: &[u8], from: usize) -> Option<(usize, usize)> {
+    data.windows(3)
+        .skip(from)
+        .position(|w| w == [0x00, 0x00, 0x01])
+        .map(|i| (from + i, from + i + 3))
+}
+
+/// trim_trailing_zeros removes the zero bytes following a NAL, which
+/// includes the first byte of a 4 bytes start code
+fn trim_trailing_zeros(mut data: BytesMut) -> BytesMut {
+    let len = data.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    data.truncate(len);
+    data
+}
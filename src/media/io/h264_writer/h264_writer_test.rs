@@ -0,0 +1,82 @@
+use super::*;
+use crate::media::io::h264_reader::{H264Reader, NalUnitType};
+use crate::media::io::is_eof;
+use bytes::Bytes;
+use std::io::Cursor;
+use tokio::time::Duration;
+
+fn packet(payload: &[u8]) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            payload_type: 102,
+            ..Default::default()
+        },
+        payload: Bytes::copy_from_slice(payload),
+    }
+}
+
+#[test]
+fn test_h264_writer_is_key_frame() {
+    let tests = vec![
+        // Single SPS
+        (vec![0x67, 0x42, 0x00, 0x1f], true),
+        // STAP-A starting with a SPS
+        (vec![0x78, 0x00, 0x04, 0x67, 0x42, 0x00, 0x1f], true),
+        // STAP-A starting with a PPS
+        (vec![0x78, 0x00, 0x04, 0x68, 0xce, 0x3c, 0x80], false),
+        // Single non-IDR
+        (vec![0x41, 0x9a, 0x02, 0x03], false),
+        (vec![0x67], false),
+    ];
+
+    for (payload, expected) in tests {
+        assert_eq!(is_key_frame(&payload), expected, "{:?}", payload);
+    }
+}
+
+#[test]
+fn test_h264_writer_write_rtp() -> Result<()> {
+    let mut buffer = Cursor::new(vec![]);
+    {
+        let mut writer = H264Writer::new(&mut buffer);
+
+        // Packets before the first key frame are dropped
+        writer.write_rtp(&packet(&[0x41, 0x9a, 0x01, 0x02]))?;
+        // SPS and PPS aggregated in a STAP-A
+        writer.write_rtp(&packet(&[
+            0x78, 0x00, 0x04, 0x67, 0x42, 0x00, 0x1f, 0x00, 0x04, 0x68, 0xce, 0x3c, 0x80,
+        ]))?;
+        // IDR fragmented in FU-A
+        writer.write_rtp(&packet(&[0x7c, 0x85, 0x88, 0x84]))?;
+        writer.write_rtp(&packet(&[0x7c, 0x45, 0x21, 0x22]))?;
+        // Empty packets are ignored
+        writer.write_rtp(&packet(&[]))?;
+        writer.write_rtp(&packet(&[0x41, 0x9a, 0x03, 0x04]))?;
+
+        writer.close()?;
+        // close is idempotent
+        writer.close()?;
+
+        let result = writer.write_rtp(&packet(&[0x41, 0x9a, 0x05, 0x06]));
+        assert!(Error::ErrMediaWriterClosed.equal(&result.err().unwrap()));
+    }
+
+    buffer.set_position(0);
+    let mut reader = H264Reader::new(buffer, Duration::from_millis(33));
+
+    let expected = [
+        (NalUnitType::SPS, &[0x67u8, 0x42, 0x00, 0x1f][..]),
+        (NalUnitType::PPS, &[0x68, 0xce, 0x3c, 0x80]),
+        (NalUnitType::CodedSliceIdr, &[0x65, 0x88, 0x84, 0x21, 0x22]),
+        (NalUnitType::CodedSliceNonIdr, &[0x41, 0x9a, 0x03, 0x04]),
+    ];
+    for (unit_type, data) in expected.iter() {
+        let nal = reader.next_nal()?;
+        assert_eq!(nal.unit_type, *unit_type);
+        assert_eq!(&nal.data[..], *data);
+    }
+    assert!(is_eof(&reader.next_nal().err().unwrap()));
+
+    Ok(())
+}
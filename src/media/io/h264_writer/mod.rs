@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod h264_writer_test;
+
+use crate::error::Error;
+use crate::media::io::Writer;
+
+use anyhow::Result;
+use rtp::codecs::h264::H264Packet;
+use rtp::packetizer::Depacketizer;
+use std::io::Write;
+
+const NALU_TYPE_BITMASK: u8 = 0x1F;
+const STAPA_NALU_TYPE: u8 = 24;
+const SPS_NALU_TYPE: u8 = 7;
+
+/// H264Writer is used to take RTP packets, parse them and
+/// write the data to an io::Write in Annex-B format
+pub struct H264Writer<W: Write> {
+    writer: Option<W>,
+    has_key_frame: bool,
+    cached_packet: H264Packet,
+}
+
+impl<W: Write> H264Writer<W> {
+    /// new initializes a new H264 writer with an io::Write output
+    pub fn new(writer: W) -> Self {
+        H264Writer {
+            writer: Some(writer),
+            has_key_frame: false,
+            cached_packet: H264Packet::default(),
+        }
+    }
+}
+
+impl<W: Write> Writer for H264Writer<W> {
+    /// write_rtp adds a new packet and writes the appropriate headers for it.
+    /// Packets before the first key frame, starting with a SPS, are dropped.
+    fn write_rtp(&mut self, pkt: &rtp::packet::Packet) -> Result<()> {
+        if pkt.payload.is_empty() {
+            return Ok(());
+        }
+
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => return Err(Error::ErrMediaWriterClosed.into()),
+        };
+
+        if !self.has_key_frame {
+            self.has_key_frame = is_key_frame(&pkt.payload);
+            if !self.has_key_frame {
+                // key frame not defined yet. discarding packet
+                return Ok(());
+            }
+        }
+
+        self.cached_packet.depacketize(&pkt.payload)?;
+        writer.write_all(&self.cached_packet.payload)?;
+
+        Ok(())
+    }
+
+    /// close closes the underlying writer
+    fn close(&mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// is_key_frame reports whether the payload starts with a SPS, alone or
+/// as the first NAL of a STAP-A
+fn is_key_frame(payload: &[u8]) -> bool {
+    if payload.len() < 4 {
+        return false;
+    }
+
+    let nalu_type = payload[0] & NALU_TYPE_BITMASK;
+    if nalu_type == STAPA_NALU_TYPE {
+        // STAP-A header, followed by the 2 bytes size of the first NAL
+        payload[3] & NALU_TYPE_BITMASK == SPS_NALU_TYPE
+    } else {
+        nalu_type == SPS_NALU_TYPE
+    }
+}
@@ -0,0 +1,154 @@
+use super::*;
+use bytes::BufMut;
+use std::io::Cursor;
+
+fn build_ivf_container() -> BytesMut {
+    let mut ivf = BytesMut::new();
+    ivf.put_slice(b"DKIF"); // signature
+    ivf.put_u16_le(0); // version
+    ivf.put_u16_le(32); // header size
+    ivf.put_slice(b"VP80"); // FourCC
+    ivf.put_u16_le(640); // width
+    ivf.put_u16_le(480); // height
+    ivf.put_u32_le(30); // timebase denominator
+    ivf.put_u32_le(1); // timebase numerator
+    ivf.put_u32_le(3); // frame count
+    ivf.put_u32_le(0); // unused
+
+    for (timestamp, frame) in [
+        (0u64, &[0x01u8, 0x02, 0x03][..]),
+        (2, &[0x04, 0x05]),
+        (3, &[0x06]),
+    ]
+    .iter()
+    {
+        ivf.put_u32_le(frame.len() as u32);
+        ivf.put_u64_le(*timestamp);
+        ivf.put_slice(frame);
+    }
+
+    ivf
+}
+
+#[test]
+fn test_ivf_reader_parse_valid_file_header() -> Result<()> {
+    let ivf = build_ivf_container();
+    let (_, header) = IVFReader::new(Cursor::new(ivf))?;
+
+    assert_eq!(
+        header,
+        IVFFileHeader {
+            signature: *b"DKIF",
+            version: 0,
+            header_size: 32,
+            four_cc: *b"VP80",
+            width: 640,
+            height: 480,
+            timebase_denominator: 30,
+            timebase_numerator: 1,
+            num_frames: 3,
+            unused: 0,
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_ivf_reader_parse_invalid_file_header() {
+    let mut ivf = build_ivf_container();
+    ivf[0] = b'X';
+    let result = IVFReader::new(Cursor::new(ivf));
+    assert!(Error::ErrIVFSignatureMismatch.equal(&result.err().unwrap()));
+
+    let mut ivf = build_ivf_container();
+    ivf[4] = 1;
+    let result = IVFReader::new(Cursor::new(ivf));
+    assert!(Error::ErrIVFUnknownVersion.equal(&result.err().unwrap()));
+}
+
+#[test]
+fn test_ivf_reader_parse_next_frame() -> Result<()> {
+    let ivf = build_ivf_container();
+    let (mut reader, _) = IVFReader::new(Cursor::new(ivf))?;
+
+    let (payload, header) = reader.parse_next_frame()?;
+    assert_eq!(&payload[..], &[0x01, 0x02, 0x03]);
+    assert_eq!(
+        header,
+        IVFFrameHeader {
+            frame_size: 3,
+            timestamp: 0,
+        }
+    );
+
+    reader.parse_next_frame()?;
+    reader.parse_next_frame()?;
+    assert!(is_eof(&reader.parse_next_frame().err().unwrap()));
+
+    Ok(())
+}
+
+#[test]
+fn test_ivf_reader_parse_truncated_frame() -> Result<()> {
+    for frame_size in [4u32, u32::MAX].iter() {
+        let mut ivf = build_ivf_container();
+        ivf.truncate(IVF_FILE_HEADER_SIZE);
+        ivf.put_u32_le(*frame_size);
+        ivf.put_u64_le(0);
+        ivf.put_slice(&[0x01, 0x02, 0x03]);
+
+        let (mut reader, _) = IVFReader::new(Cursor::new(ivf))?;
+        let err = reader.parse_next_frame().err().unwrap();
+        assert!(is_eof(&err), "frame size {}: got {}", frame_size, err);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_ivf_reader_read_sample() -> Result<()> {
+    let ivf = build_ivf_container();
+    let (mut reader, _) = IVFReader::new(Cursor::new(ivf))?;
+
+    // Each frame lasts until the next one, the last one as long as the previous
+    let expected = [
+        (&[0x01u8, 0x02, 0x03][..], 2),
+        (&[0x04, 0x05][..], 1),
+        (&[0x06][..], 1),
+    ];
+    for (data, frames) in expected.iter() {
+        let sample = reader.read_sample()?;
+        assert_eq!(&sample.data[..], *data);
+        assert_eq!(
+            sample.duration,
+            Duration::from_nanos(frames * 1_000_000_000 / 30)
+        );
+    }
+    assert!(is_eof(&reader.read_sample().err().unwrap()));
+
+    Ok(())
+}
+
+#[test]
+fn test_ivf_reader_reset_reader() -> Result<()> {
+    let ivf = build_ivf_container();
+    let (mut reader, _) = IVFReader::new(Cursor::new(
+        ivf[..IVF_FILE_HEADER_SIZE + IVF_FRAME_HEADER_SIZE + 3].to_vec(),
+    ))?;
+
+    // The second frame is not written yet
+    let (payload, _) = reader.parse_next_frame()?;
+    assert_eq!(&payload[..], &[0x01, 0x02, 0x03]);
+    assert!(is_eof(&reader.parse_next_frame().err().unwrap()));
+
+    reader.reset_reader(|bytes_read| {
+        let mut cursor = Cursor::new(ivf.to_vec());
+        cursor.set_position(bytes_read as u64);
+        cursor
+    });
+    let (payload, _) = reader.parse_next_frame()?;
+    assert_eq!(&payload[..], &[0x04, 0x05]);
+
+    Ok(())
+}
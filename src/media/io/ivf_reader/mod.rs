@@ -0,0 +1,182 @@
+#[cfg(test)]
+mod ivf_reader_test;
+
+use crate::error::Error;
+use crate::media::io::{is_eof, Reader};
+use crate::media::Sample;
+
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::io::{self, Read};
+use tokio::time::Duration;
+
+pub(crate) const IVF_FILE_HEADER_SIGNATURE: &[u8; 4] = b"DKIF";
+pub(crate) const IVF_FILE_HEADER_SIZE: usize = 32;
+pub(crate) const IVF_FRAME_HEADER_SIZE: usize = 12;
+
+/// IVFFileHeader 32-byte header for IVF files
+/// https://wiki.multimedia.cx/index.php/IVF
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct IVFFileHeader {
+    pub signature: [u8; 4],        // 0-3
+    pub version: u16,              // 4-5
+    pub header_size: u16,          // 6-7
+    pub four_cc: [u8; 4],          // 8-11
+    pub width: u16,                // 12-13
+    pub height: u16,               // 14-15
+    pub timebase_denominator: u32, // 16-19
+    pub timebase_numerator: u32,   // 20-23
+    pub num_frames: u32,           // 24-27
+    pub unused: u32,               // 28-31
+}
+
+impl IVFFileHeader {
+    /// duration converts a number of timebase units into a duration
+    pub fn duration(&self, ticks: u64) -> Duration {
+        if self.timebase_denominator == 0 {
+            return Duration::from_secs(0);
+        }
+        let nanos = ticks as u128 * self.timebase_numerator as u128 * 1_000_000_000
+            / self.timebase_denominator as u128;
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+/// IVFFrameHeader 12-byte header for IVF frames
+/// https://wiki.multimedia.cx/index.php/IVF
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct IVFFrameHeader {
+    pub frame_size: u32, // 0-3
+    pub timestamp: u64,  // 4-11
+}
+
+/// IVFReader is used to read IVF files and return frame payloads,
+/// whatever the codec (VP8, VP9 or AV1)
+pub struct IVFReader<R: Read> {
+    reader: R,
+    header: IVFFileHeader,
+    bytes_read: usize,
+
+    next_frame: Option<(Bytes, IVFFrameHeader)>,
+    last_duration: Duration,
+}
+
+impl<R: Read> IVFReader<R> {
+    /// new returns a new IVF reader and IVF file header
+    /// with an io::Read input
+    pub fn new(mut reader: R) -> Result<(IVFReader<R>, IVFFileHeader)> {
+        let header = IVFReader::parse_file_header(&mut reader)?;
+
+        Ok((
+            IVFReader {
+                reader,
+                header,
+                bytes_read: IVF_FILE_HEADER_SIZE.max(header.header_size as usize),
+                next_frame: None,
+                last_duration: header.duration(1),
+            },
+            header,
+        ))
+    }
+
+    /// reset_reader resets the internal stream of IVFReader. This is useful
+    /// for live streams, where the end of the file might be read without the
+    /// data being finished.
+    pub fn reset_reader(&mut self, reset: impl FnOnce(usize) -> R) {
+        self.reader = reset(self.bytes_read);
+    }
+
+    /// parse_next_frame reads from stream and returns IVF frame payload and header.
+    /// When no more frames are available, it returns an error for which is_eof holds.
+    pub fn parse_next_frame(&mut self) -> Result<(BytesMut, IVFFrameHeader)> {
+        let mut buf = [0u8; IVF_FRAME_HEADER_SIZE];
+        self.reader.read_exact(&mut buf)?;
+
+        let mut b = &buf[..];
+        let header = IVFFrameHeader {
+            frame_size: b.get_u32_le(),
+            timestamp: b.get_u64_le(),
+        };
+
+        // The frame size isn't trusted to allocate the payload, which grows as it is read
+        let mut payload = BytesMut::new().writer();
+        let n = io::copy(
+            &mut (&mut self.reader).take(header.frame_size as u64),
+            &mut payload,
+        )?;
+        if n != header.frame_size as u64 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let payload = payload.into_inner();
+
+        self.bytes_read += IVF_FRAME_HEADER_SIZE + header.frame_size as usize;
+
+        Ok((payload, header))
+    }
+
+    /// parse_file_header parses 32 bytes of the IVF file header
+    fn parse_file_header(reader: &mut R) -> Result<IVFFileHeader> {
+        let mut buf = [0u8; IVF_FILE_HEADER_SIZE];
+        reader.read_exact(&mut buf)?;
+
+        let mut b = &buf[..];
+        let mut header = IVFFileHeader::default();
+        b.copy_to_slice(&mut header.signature);
+        header.version = b.get_u16_le();
+        header.header_size = b.get_u16_le();
+        b.copy_to_slice(&mut header.four_cc);
+        header.width = b.get_u16_le();
+        header.height = b.get_u16_le();
+        header.timebase_denominator = b.get_u32_le();
+        header.timebase_numerator = b.get_u32_le();
+        header.num_frames = b.get_u32_le();
+        header.unused = b.get_u32_le();
+
+        if &header.signature != IVF_FILE_HEADER_SIGNATURE {
+            return Err(Error::ErrIVFSignatureMismatch.into());
+        }
+        if header.version != 0 {
+            return Err(Error::ErrIVFUnknownVersion.into());
+        }
+
+        // Skip the remainder of a larger header
+        if header.header_size as usize > IVF_FILE_HEADER_SIZE {
+            let mut rest = vec![0u8; header.header_size as usize - IVF_FILE_HEADER_SIZE];
+            reader.read_exact(&mut rest)?;
+        }
+
+        Ok(header)
+    }
+}
+
+impl<R: Read> Reader for IVFReader<R> {
+    /// read_sample returns the next frame, lasting until the timestamp of the
+    /// frame after it. The last frame lasts as long as the one before it.
+    fn read_sample(&mut self) -> Result<Sample> {
+        let (data, header) = match self.next_frame.take() {
+            Some(frame) => frame,
+            None => {
+                let (data, header) = self.parse_next_frame()?;
+                (data.freeze(), header)
+            }
+        };
+
+        match self.parse_next_frame() {
+            Ok((next_data, next_header)) => {
+                self.last_duration = self
+                    .header
+                    .duration(next_header.timestamp.saturating_sub(header.timestamp));
+                self.next_frame = Some((next_data.freeze(), next_header));
+            }
+            Err(err) if is_eof(&err) => {}
+            Err(err) => return Err(err),
+        }
+
+        Ok(Sample {
+            data,
+            duration: self.last_duration,
+            packet_timestamp: header.timestamp as u32,
+            ..Default::default()
+        })
+    }
+}
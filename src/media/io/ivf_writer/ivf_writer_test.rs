@@ -0,0 +1,129 @@
+use super::*;
+use crate::media::io::ivf_reader::IVFReader;
+use crate::media::io::{is_eof, Reader};
+use std::io::Cursor;
+use tokio::time::Duration;
+
+fn vp8_header() -> IVFFileHeader {
+    IVFFileHeader {
+        four_cc: *b"VP80",
+        width: 640,
+        height: 480,
+        timebase_denominator: 30,
+        timebase_numerator: 1,
+        ..Default::default()
+    }
+}
+
+fn packet(payload: &[u8], timestamp: u32, marker: bool) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            marker,
+            payload_type: 96,
+            timestamp,
+            ..Default::default()
+        },
+        payload: Bytes::copy_from_slice(payload),
+    }
+}
+
+#[test]
+fn test_ivf_writer_unsupported_codec() {
+    let mut buffer = Cursor::new(vec![]);
+    let result = IVFWriter::new(
+        &mut buffer,
+        &IVFFileHeader {
+            four_cc: *b"AV01",
+            ..vp8_header()
+        },
+    );
+    assert!(Error::ErrIVFCodecUnsupported.equal(&result.err().unwrap()));
+}
+
+#[test]
+fn test_ivf_writer_vp8() -> Result<()> {
+    let mut buffer = Cursor::new(vec![]);
+    {
+        let mut writer = IVFWriter::new(&mut buffer, &vp8_header())?;
+
+        // An inter frame before any key frame is dropped
+        writer.write_rtp(&packet(&[0x10, 0x01, 0xaa, 0xbb], 0, true))?;
+        // A key frame split over two packets
+        writer.write_rtp(&packet(&[0x10, 0x00, 0x01, 0x02], 3000, false))?;
+        writer.write_rtp(&packet(&[0x00, 0x03, 0x04, 0x05], 3000, true))?;
+        // An inter frame whose last packet is lost is dropped
+        writer.write_rtp(&packet(&[0x10, 0x01, 0x06, 0x07], 6000, false))?;
+        // Empty packets are ignored
+        writer.write_rtp(&packet(&[], 9000, true))?;
+        writer.write_rtp(&packet(&[0x10, 0x01, 0x08, 0x09], 12000, true))?;
+        writer.write_rtp(&packet(&[0x10, 0x01, 0x0a, 0x0b], 15000, true))?;
+
+        writer.close()?;
+        // close is idempotent
+        writer.close()?;
+
+        let result = writer.write_rtp(&packet(&[0x10, 0x01, 0x0c, 0x0d], 18000, true));
+        assert!(Error::ErrMediaWriterClosed.equal(&result.err().unwrap()));
+    }
+
+    buffer.set_position(0);
+    let (mut reader, header) = IVFReader::new(buffer)?;
+    assert_eq!(header.num_frames, 3);
+    assert_eq!(&header.four_cc, b"VP80");
+
+    let expected = [
+        (&[0x00u8, 0x01, 0x02, 0x03, 0x04, 0x05][..], 0, 3),
+        (&[0x01, 0x08, 0x09][..], 3, 1),
+        (&[0x01, 0x0a, 0x0b][..], 4, 1),
+    ];
+    for (data, timestamp, frames) in expected.iter() {
+        let sample = reader.read_sample()?;
+        assert_eq!(&sample.data[..], *data);
+        assert_eq!(sample.packet_timestamp, *timestamp);
+        assert_eq!(
+            sample.duration,
+            Duration::from_nanos(frames * 1_000_000_000 / 30)
+        );
+    }
+    assert!(is_eof(&reader.read_sample().err().unwrap()));
+
+    Ok(())
+}
+
+#[test]
+fn test_ivf_writer_vp9() -> Result<()> {
+    let mut buffer = Cursor::new(vec![]);
+    {
+        let mut writer = IVFWriter::new(
+            &mut buffer,
+            &IVFFileHeader {
+                four_cc: *b"VP90",
+                timebase_denominator: 90000,
+                ..vp8_header()
+            },
+        )?;
+
+        // Start of an inter-predicted frame, before any key frame
+        writer.write_rtp(&packet(&[0x48, 0xaa], 0, true))?;
+        // A key frame split over two packets: B, then E
+        writer.write_rtp(&packet(&[0x08, 0x01, 0x02], 3000, false))?;
+        writer.write_rtp(&packet(&[0x04, 0x03], 3000, true))?;
+        writer.write_rtp(&packet(&[0x4c, 0x04], 4500, true))?;
+
+        writer.close()?;
+    }
+
+    buffer.set_position(0);
+    let (mut reader, header) = IVFReader::new(buffer)?;
+    assert_eq!(header.num_frames, 2);
+
+    let (frame, frame_header) = reader.parse_next_frame()?;
+    assert_eq!(&frame[..], &[0x01, 0x02, 0x03]);
+    assert_eq!(frame_header.timestamp, 0);
+    let (frame, frame_header) = reader.parse_next_frame()?;
+    assert_eq!(&frame[..], &[0x04]);
+    assert_eq!(frame_header.timestamp, 1500);
+
+    Ok(())
+}
@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod ivf_writer_test;
+
+use crate::error::Error;
+use crate::media::io::ivf_reader::{
+    IVFFileHeader, IVF_FILE_HEADER_SIGNATURE, IVF_FILE_HEADER_SIZE,
+};
+use crate::media::io::Writer;
+
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use rtp::codecs::vp8::Vp8Packet;
+use rtp::codecs::vp9::Vp9Packet;
+use rtp::packetizer::Depacketizer;
+use std::io::{Seek, SeekFrom, Write};
+
+/// the clock rate of the RTP timestamps of VP8 and VP9
+const VIDEO_CLOCK_RATE: u64 = 90000;
+
+/// offset of the frame count in the IVF file header
+const IVF_NUM_FRAMES_OFFSET: u64 = 24;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum IVFCodec {
+    Vp8,
+    Vp9,
+}
+
+/// IVFWriter is used to take RTP packets and write them to an IVF on disk.
+/// VP8 and VP9 are supported, according to the FourCC of the file header.
+pub struct IVFWriter<W: Write + Seek> {
+    writer: Option<W>,
+    header: IVFFileHeader,
+    codec: IVFCodec,
+    count: u32,
+
+    seen_key_frame: bool,
+    current_frame: Option<BytesMut>,
+    last_timestamp: Option<u32>,
+    pts: u64,
+}
+
+impl<W: Write + Seek> IVFWriter<W> {
+    /// new initialize a new IVF writer with an io::Write output. The
+    /// signature, version, header size and frame count of the header are
+    /// filled in by the writer.
+    pub fn new(writer: W, header: &IVFFileHeader) -> Result<Self> {
+        let codec = match &header.four_cc {
+            b"VP80" => IVFCodec::Vp8,
+            b"VP90" => IVFCodec::Vp9,
+            _ => return Err(Error::ErrIVFCodecUnsupported.into()),
+        };
+
+        let mut w = IVFWriter {
+            writer: Some(writer),
+            header: IVFFileHeader {
+                signature: *IVF_FILE_HEADER_SIGNATURE,
+                version: 0,
+                header_size: IVF_FILE_HEADER_SIZE as u16,
+                num_frames: 0,
+                ..*header
+            },
+            codec,
+            count: 0,
+
+            seen_key_frame: false,
+            current_frame: None,
+            last_timestamp: None,
+            pts: 0,
+        };
+        w.write_header()?;
+
+        Ok(w)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let h = &self.header;
+        let mut header = BytesMut::with_capacity(IVF_FILE_HEADER_SIZE);
+        header.put_slice(&h.signature);
+        header.put_u16_le(h.version);
+        header.put_u16_le(h.header_size);
+        header.put_slice(&h.four_cc);
+        header.put_u16_le(h.width);
+        header.put_u16_le(h.height);
+        header.put_u32_le(h.timebase_denominator);
+        header.put_u32_le(h.timebase_numerator);
+        header.put_u32_le(h.num_frames);
+        header.put_u32_le(h.unused);
+
+        if let Some(w) = &mut self.writer {
+            w.write_all(&header)?;
+        }
+
+        Ok(())
+    }
+
+    /// depacketize returns the frame data of the packet, whether it starts
+    /// a frame, and whether that frame is a key frame
+    fn depacketize(&self, payload: &Bytes) -> Result<(Bytes, bool, bool)> {
+        match self.codec {
+            IVFCodec::Vp8 => {
+                let mut vp8 = Vp8Packet::default();
+                vp8.depacketize(payload)?;
+                let start = vp8.s == 1 && vp8.pid == 0;
+                // The P bit of the VP8 frame tag is 0 for key frames
+                let key_frame = start && !vp8.payload.is_empty() && vp8.payload[0] & 0x01 == 0;
+                Ok((vp8.payload, start, key_frame))
+            }
+            IVFCodec::Vp9 => {
+                let mut vp9 = Vp9Packet::default();
+                vp9.depacketize(payload)?;
+                let start = vp9.b == 1;
+                let key_frame = start && vp9.p == 0;
+                Ok((vp9.payload, start, key_frame))
+            }
+        }
+    }
+
+    fn write_frame(&mut self, frame: &[u8], timestamp: u32) -> Result<()> {
+        if let Some(last_timestamp) = self.last_timestamp {
+            self.pts += timestamp.wrapping_sub(last_timestamp) as u64;
+        }
+        self.last_timestamp = Some(timestamp);
+
+        let numerator = self.header.timebase_numerator.max(1) as u64;
+        let ticks =
+            self.pts * self.header.timebase_denominator as u64 / (VIDEO_CLOCK_RATE * numerator);
+
+        let mut frame_header = BytesMut::with_capacity(12);
+        frame_header.put_u32_le(frame.len() as u32);
+        frame_header.put_u64_le(ticks);
+
+        if let Some(w) = &mut self.writer {
+            w.write_all(&frame_header)?;
+            w.write_all(frame)?;
+        }
+        self.count += 1;
+
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Writer for IVFWriter<W> {
+    /// write_rtp adds a new packet and writes the appropriate headers for it.
+    /// Frames are written once their last packet, carrying the marker bit, is received.
+    fn write_rtp(&mut self, pkt: &rtp::packet::Packet) -> Result<()> {
+        if self.writer.is_none() {
+            return Err(Error::ErrMediaWriterClosed.into());
+        }
+        if pkt.payload.is_empty() {
+            return Ok(());
+        }
+
+        let (data, start, key_frame) = self.depacketize(&pkt.payload)?;
+
+        if start {
+            // The file starts with a key frame
+            if !self.seen_key_frame && !key_frame {
+                return Ok(());
+            }
+            self.seen_key_frame = true;
+            // Any frame whose last packet was lost is dropped
+            self.current_frame = Some(BytesMut::new());
+        }
+        let mut frame = match self.current_frame.take() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        frame.put(data);
+
+        if !pkt.header.marker {
+            self.current_frame = Some(frame);
+            return Ok(());
+        }
+        if frame.is_empty() {
+            return Ok(());
+        }
+
+        self.write_frame(&frame, pkt.header.timestamp)
+    }
+
+    /// close stops the recording, writing the frame count to the file header
+    fn close(&mut self) -> Result<()> {
+        if let Some(mut w) = self.writer.take() {
+            w.seek(SeekFrom::Start(IVF_NUM_FRAMES_OFFSET))?;
+            w.write_all(&self.count.to_le_bytes())?;
+            w.seek(SeekFrom::End(0))?;
+            w.flush()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+pub mod h264_reader;
+pub mod h264_writer;
+pub mod ivf_reader;
+pub mod ivf_writer;
+pub mod ogg_reader;
+pub mod ogg_writer;
+
+use crate::media::Sample;
+
+use anyhow::Result;
+
+/// Reader defines an interface to read the samples of media files
+pub trait Reader {
+    /// read_sample returns the next sample of the media, with its duration.
+    /// At the end of the media, it returns an error for which is_eof holds.
+    fn read_sample(&mut self) -> Result<Sample>;
+}
+
+/// Writer defines an interface to handle
+/// the creation of media files
+pub trait Writer {
+    /// Add the content of an RTP packet to the media
+    fn write_rtp(&mut self, pkt: &rtp::packet::Packet) -> Result<()>;
+
+    /// close the media
+    /// Note: close implementation must be idempotent
+    fn close(&mut self) -> Result<()>;
+}
+
+/// is_eof reports whether the error returned by a Reader is the end of the media
+pub fn is_eof(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<std::io::Error>(),
+        Some(e) if e.kind() == std::io::ErrorKind::UnexpectedEof
+    )
+}
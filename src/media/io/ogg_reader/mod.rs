@@ -0,0 +1,281 @@
+#[cfg(test)]
+mod ogg_reader_test;
+
+use crate::error::Error;
+use crate::media::io::Reader;
+use crate::media::Sample;
+
+use anyhow::Result;
+use bytes::{Buf, Bytes, BytesMut};
+use std::collections::VecDeque;
+use std::io::Read;
+use tokio::time::Duration;
+
+pub(crate) const PAGE_HEADER_TYPE_CONTINUATION_OF_STREAM: u8 = 0x00;
+pub(crate) const PAGE_HEADER_TYPE_BEGINNING_OF_STREAM: u8 = 0x02;
+pub(crate) const PAGE_HEADER_TYPE_END_OF_STREAM: u8 = 0x04;
+pub(crate) const DEFAULT_PRE_SKIP: u16 = 3840; // 3840 recommended in the RFC
+pub(crate) const ID_PAGE_SIGNATURE: &[u8] = b"OpusHead";
+pub(crate) const COMMENT_PAGE_SIGNATURE: &[u8] = b"OpusTags";
+pub(crate) const PAGE_HEADER_SIGNATURE: &[u8] = b"OggS";
+pub(crate) const PAGE_HEADER_SIZE: usize = 27;
+pub(crate) const ID_PAGE_PAYLOAD_SIZE: usize = 19;
+
+/// the offset of the checksum in the page header
+pub(crate) const PAGE_HEADER_CHECKSUM_OFFSET: usize = 22;
+
+/// the sample rate of Opus timestamps, whatever the input sample rate
+pub(crate) const OPUS_CLOCK_RATE: u32 = 48000;
+
+/// OggHeader is the metadata from the first two pages
+/// in the file (ID and Comment)
+/// https://tools.ietf.org/html/rfc7845.html#section-3
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct OggHeader {
+    pub channel_map: u8,
+    pub channels: u8,
+    pub output_gain: u16,
+    pub pre_skip: u16,
+    pub sample_rate: u32,
+    pub version: u8,
+}
+
+/// OggPageHeader is the metadata for a Page
+/// Pages are the fundamental unit of multiplexing in an Ogg stream
+/// https://tools.ietf.org/html/rfc7845.html#section-1
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct OggPageHeader {
+    pub granule_position: u64,
+
+    pub sig: [u8; 4],
+    pub version: u8,
+    pub header_type: u8,
+    pub serial: u32,
+    pub index: u32,
+    pub segments_count: u8,
+}
+
+/// OggReader is used to read Ogg files and return page payloads
+pub struct OggReader<R: Read> {
+    reader: R,
+    bytes_read: usize,
+    do_checksum: bool,
+
+    packets: VecDeque<Bytes>,
+    partial_packet: BytesMut,
+}
+
+impl<R: Read> OggReader<R> {
+    /// new returns a new Ogg reader and Ogg header
+    /// with an io::Read input
+    pub fn new(reader: R) -> Result<(OggReader<R>, OggHeader)> {
+        OggReader::new_with(reader, true)
+    }
+
+    /// new_with returns a new Ogg reader and Ogg header, and allows
+    /// skipping the checksum verification of the pages
+    pub fn new_with(reader: R, do_checksum: bool) -> Result<(OggReader<R>, OggHeader)> {
+        let mut r = OggReader {
+            reader,
+            bytes_read: 0,
+            do_checksum,
+
+            packets: VecDeque::new(),
+            partial_packet: BytesMut::new(),
+        };
+
+        let header = r.read_headers()?;
+
+        Ok((r, header))
+    }
+
+    fn read_headers(&mut self) -> Result<OggHeader> {
+        let (payload, page_header) = self.parse_next_page()?;
+
+        if page_header.sig != PAGE_HEADER_SIGNATURE {
+            return Err(Error::ErrOggBadIDPageSignature.into());
+        }
+
+        if page_header.header_type != PAGE_HEADER_TYPE_BEGINNING_OF_STREAM {
+            return Err(Error::ErrOggBadIDPageType.into());
+        }
+
+        if payload.len() != ID_PAGE_PAYLOAD_SIZE {
+            return Err(Error::ErrOggBadIDPageLength.into());
+        }
+
+        if &payload[..8] != ID_PAGE_SIGNATURE {
+            return Err(Error::ErrOggBadIDPagePayloadSignature.into());
+        }
+
+        let mut b = &payload[8..];
+        Ok(OggHeader {
+            version: b.get_u8(),
+            channels: b.get_u8(),
+            pre_skip: b.get_u16_le(),
+            sample_rate: b.get_u32_le(),
+            output_gain: b.get_u16_le(),
+            channel_map: b.get_u8(),
+        })
+    }
+
+    /// parse_next_page reads from stream and returns Ogg page payload, header,
+    /// and an error if there is incomplete page data.
+    /// When no more pages are available, it returns an error for which is_eof holds.
+    pub fn parse_next_page(&mut self) -> Result<(BytesMut, OggPageHeader)> {
+        let (payload, _, page_header) = self.read_page()?;
+        Ok((payload, page_header))
+    }
+
+    /// read_page returns the payload, segment table and header of the next page
+    fn read_page(&mut self) -> Result<(BytesMut, Vec<u8>, OggPageHeader)> {
+        let mut h = [0u8; PAGE_HEADER_SIZE];
+        self.reader.read_exact(&mut h)?;
+
+        let mut b = &h[..];
+        let mut page_header = OggPageHeader::default();
+        b.copy_to_slice(&mut page_header.sig);
+        page_header.version = b.get_u8();
+        page_header.header_type = b.get_u8();
+        page_header.granule_position = b.get_u64_le();
+        page_header.serial = b.get_u32_le();
+        page_header.index = b.get_u32_le();
+        let expected_checksum = b.get_u32_le();
+        page_header.segments_count = b.get_u8();
+
+        let mut size_buffer = vec![0u8; page_header.segments_count as usize];
+        self.reader.read_exact(&mut size_buffer)?;
+
+        let payload_size: usize = size_buffer.iter().map(|s| *s as usize).sum();
+
+        let mut payload = BytesMut::new();
+        payload.resize(payload_size, 0);
+        self.reader.read_exact(&mut payload)?;
+
+        self.bytes_read += PAGE_HEADER_SIZE + size_buffer.len() + payload_size;
+
+        if self.do_checksum {
+            h[PAGE_HEADER_CHECKSUM_OFFSET..PAGE_HEADER_CHECKSUM_OFFSET + 4].fill(0);
+
+            let mut checksum = 0;
+            for data in [&h[..], &size_buffer[..], &payload[..]].iter() {
+                checksum = update_checksum(checksum, data);
+            }
+
+            if checksum != expected_checksum {
+                return Err(Error::ErrOggChecksumMismatch.into());
+            }
+        }
+
+        Ok((payload, size_buffer, page_header))
+    }
+
+    /// reset_reader resets the internal stream of OggReader. This is useful
+    /// for live streams, where the end of the file might be read without the
+    /// data being finished.
+    pub fn reset_reader(&mut self, reset: impl FnOnce(usize) -> R) {
+        self.reader = reset(self.bytes_read);
+    }
+}
+
+impl<R: Read> Reader for OggReader<R> {
+    /// read_sample returns the next Opus packet, lasting as long as the
+    /// frames it holds. Packets spanning several pages are reassembled.
+    fn read_sample(&mut self) -> Result<Sample> {
+        loop {
+            if let Some(data) = self.packets.pop_front() {
+                // The comment header carries no audio
+                if data.starts_with(COMMENT_PAGE_SIGNATURE) {
+                    continue;
+                }
+
+                let samples = opus_packet_samples(&data);
+                return Ok(Sample {
+                    data,
+                    duration: Duration::from_nanos(
+                        samples as u64 * 1_000_000_000 / OPUS_CLOCK_RATE as u64,
+                    ),
+                    ..Default::default()
+                });
+            }
+
+            let (mut payload, segments, _) = self.read_page()?;
+            for size in segments {
+                let segment = payload.split_to(size as usize);
+                self.partial_packet.unsplit(segment);
+                // A segment shorter than 255 bytes ends the packet
+                if size < 255 {
+                    let packet = self.partial_packet.split().freeze();
+                    self.packets.push_back(packet);
+                }
+            }
+        }
+    }
+}
+
+/// opus_packet_samples returns the number of samples, at 48 kHz, of the frames
+/// of an Opus packet as described by its TOC byte
+/// https://tools.ietf.org/html/rfc6716#section-3.1
+pub(crate) fn opus_packet_samples(packet: &[u8]) -> u32 {
+    if packet.is_empty() {
+        return 0;
+    }
+
+    let toc = packet[0];
+    let config = toc >> 3;
+    let frame_samples = match config {
+        // SILK-only: 10, 20, 40 or 60 ms
+        0..=11 => [480, 960, 1920, 2880][(config % 4) as usize],
+        // Hybrid: 10 or 20 ms
+        12..=15 => [480, 960][(config % 2) as usize],
+        // CELT-only: 2.5, 5, 10 or 20 ms
+        _ => [120, 240, 480, 960][(config % 4) as usize],
+    };
+
+    let frames = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => {
+            if packet.len() < 2 {
+                return 0;
+            }
+            (packet[1] & 0x3f) as u32
+        }
+    };
+
+    frame_samples * frames
+}
+
+const fn generate_checksum_table() -> [u32; 256] {
+    const POLY: u32 = 0x04c11db7;
+
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut r = (i as u32) << 24;
+        let mut j = 0;
+        while j < 8 {
+            if r & 0x80000000 != 0 {
+                r = (r << 1) ^ POLY;
+            } else {
+                r <<= 1;
+            }
+            j += 1;
+        }
+        table[i] = r;
+        i += 1;
+    }
+
+    table
+}
+
+const CHECKSUM_TABLE: [u32; 256] = generate_checksum_table();
+
+/// update_checksum computes the CRC of the Ogg pages, which is not reflected
+/// and has neither initial value nor final xor
+pub(crate) fn update_checksum(mut checksum: u32, data: &[u8]) -> u32 {
+    for v in data {
+        checksum = (checksum << 8) ^ CHECKSUM_TABLE[(((checksum >> 24) as u8) ^ v) as usize];
+    }
+    checksum
+}
@@ -0,0 +1,153 @@
+use super::*;
+use crate::media::io::is_eof;
+use bytes::BufMut;
+use std::io::Cursor;
+
+fn build_page(header_type: u8, index: u32, segments: &[u8], payload: &[u8]) -> BytesMut {
+    let mut page = BytesMut::new();
+    page.put_slice(PAGE_HEADER_SIGNATURE);
+    page.put_u8(0); // version
+    page.put_u8(header_type);
+    page.put_u64_le(0); // granule position
+    page.put_u32_le(0x1234); // serial
+    page.put_u32_le(index);
+    page.put_u32_le(0); // checksum
+    page.put_u8(segments.len() as u8);
+    page.put_slice(segments);
+    page.put_slice(payload);
+
+    let checksum = update_checksum(0, &page);
+    page[PAGE_HEADER_CHECKSUM_OFFSET..PAGE_HEADER_CHECKSUM_OFFSET + 4]
+        .copy_from_slice(&checksum.to_le_bytes());
+    page
+}
+
+fn build_id_page() -> BytesMut {
+    let mut id = BytesMut::new();
+    id.put_slice(ID_PAGE_SIGNATURE);
+    id.put_u8(1); // version
+    id.put_u8(2); // channels
+    id.put_u16_le(DEFAULT_PRE_SKIP);
+    id.put_u32_le(48000); // sample rate
+    id.put_u16_le(0); // output gain
+    id.put_u8(0); // channel map
+
+    build_page(
+        PAGE_HEADER_TYPE_BEGINNING_OF_STREAM,
+        0,
+        &[ID_PAGE_PAYLOAD_SIZE as u8],
+        &id,
+    )
+}
+
+#[test]
+fn test_ogg_reader_parse_valid_header() -> Result<()> {
+    let (_, header) = OggReader::new(Cursor::new(build_id_page()))?;
+
+    assert_eq!(
+        header,
+        OggHeader {
+            channel_map: 0,
+            channels: 2,
+            output_gain: 0,
+            pre_skip: DEFAULT_PRE_SKIP,
+            sample_rate: 48000,
+            version: 1,
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_ogg_reader_parse_invalid_header() {
+    let mut ogg = build_id_page();
+    ogg[PAGE_HEADER_SIZE + 1] = 0;
+    let result = OggReader::new(Cursor::new(ogg.clone()));
+    assert!(Error::ErrOggChecksumMismatch.equal(&result.err().unwrap()));
+
+    // Without checksum, the payload signature is wrong
+    let result = OggReader::new_with(Cursor::new(ogg), false);
+    assert!(Error::ErrOggBadIDPagePayloadSignature.equal(&result.err().unwrap()));
+
+    let mut ogg = build_id_page();
+    ogg[0] = 0;
+    let result = OggReader::new_with(Cursor::new(ogg), false);
+    assert!(Error::ErrOggBadIDPageSignature.equal(&result.err().unwrap()));
+
+    let mut ogg = build_id_page();
+    ogg[5] = PAGE_HEADER_TYPE_CONTINUATION_OF_STREAM;
+    let result = OggReader::new_with(Cursor::new(ogg), false);
+    assert!(Error::ErrOggBadIDPageType.equal(&result.err().unwrap()));
+}
+
+#[test]
+fn test_ogg_reader_read_sample() -> Result<()> {
+    let mut ogg = build_id_page();
+    ogg.put(build_page(
+        PAGE_HEADER_TYPE_CONTINUATION_OF_STREAM,
+        1,
+        &[16],
+        b"OpusTags\x00\x00\x00\x00\x00\x00\x00\x00",
+    ));
+
+    // A 20ms packet, then a packet continued on the next page
+    let long_packet: Vec<u8> = std::iter::once(0xf9)
+        .chain(std::iter::repeat(0xaa).take(299))
+        .collect();
+    let mut payload = vec![0x08, 0x01, 0x02];
+    payload.extend_from_slice(&long_packet[..255]);
+    ogg.put(build_page(
+        PAGE_HEADER_TYPE_CONTINUATION_OF_STREAM,
+        2,
+        &[3, 255],
+        &payload,
+    ));
+    ogg.put(build_page(0x01, 3, &[45], &long_packet[255..]));
+
+    let (mut reader, _) = OggReader::new(Cursor::new(ogg))?;
+
+    let sample = reader.read_sample()?;
+    assert_eq!(&sample.data[..], &[0x08, 0x01, 0x02]);
+    assert_eq!(sample.duration, Duration::from_millis(20));
+
+    // 2 CELT frames of 20ms
+    let sample = reader.read_sample()?;
+    assert_eq!(&sample.data[..], &long_packet[..]);
+    assert_eq!(sample.duration, Duration::from_millis(40));
+
+    assert!(is_eof(&reader.read_sample().err().unwrap()));
+
+    Ok(())
+}
+
+#[test]
+fn test_opus_packet_samples() {
+    let tests = vec![
+        (vec![], 0),
+        // SILK 10ms, 20ms, 40ms and 60ms
+        (vec![0x00], 480),
+        (vec![0x08], 960),
+        (vec![0x10], 1920),
+        (vec![0x18], 2880),
+        // Hybrid 10ms and 20ms
+        (vec![0x60], 480),
+        (vec![0x68], 960),
+        // CELT 2.5ms, two frames
+        (vec![0x81], 240),
+        (vec![0x82], 240),
+        // CELT 20ms, arbitrary number of frames
+        (vec![0xfb, 0x03], 2880),
+        (vec![0xfb], 0),
+    ];
+
+    for (packet, expected) in tests {
+        assert_eq!(
+            opus_packet_samples(&packet),
+            expected,
+            "{:?} should have {} samples",
+            packet,
+            expected
+        );
+    }
+}
@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod ogg_writer_test;
+
+use crate::error::Error;
+use crate::media::io::ogg_reader::*;
+use crate::media::io::Writer;
+
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use rtp::codecs::opus::OpusPacket;
+use rtp::packetizer::Depacketizer;
+use std::io::Write;
+
+/// the vendor string of the comment header
+const OGG_VENDOR: &[u8] = b"webrtc-rs";
+
+/// OggWriter is used to take RTP packets and write them to an OGG on disk
+pub struct OggWriter<W: Write> {
+    writer: Option<W>,
+    sample_rate: u32,
+    channel_count: u8,
+    serial: u32,
+    page_index: u32,
+
+    last_timestamp: Option<u32>,
+    elapsed_samples: u64,
+    /// the last page, written once it is known whether it ends the stream
+    pending_page: Option<(Bytes, u64)>,
+}
+
+impl<W: Write> OggWriter<W> {
+    /// new initialize a new OGG Opus writer with an io::Write output
+    pub fn new(writer: W, sample_rate: u32, channel_count: u8) -> Result<Self> {
+        let mut w = OggWriter {
+            writer: Some(writer),
+            sample_rate,
+            channel_count,
+            serial: rand::random::<u32>(),
+            page_index: 0,
+
+            last_timestamp: None,
+            elapsed_samples: 0,
+            pending_page: None,
+        };
+
+        w.write_headers()?;
+
+        Ok(w)
+    }
+
+    /// write_headers writes the ID and the comment headers
+    /// https://tools.ietf.org/html/rfc7845.html#section-5
+    fn write_headers(&mut self) -> Result<()> {
+        let mut id_header = BytesMut::with_capacity(ID_PAGE_PAYLOAD_SIZE);
+        id_header.put_slice(ID_PAGE_SIGNATURE); // Magic Signature 'OpusHead'
+        id_header.put_u8(1); // Version
+        id_header.put_u8(self.channel_count); // Channel count
+        id_header.put_u16_le(DEFAULT_PRE_SKIP); // pre-skip
+        id_header.put_u32_le(self.sample_rate); // original sample rate, any valid sample e.g 48000
+        id_header.put_u16_le(0); // output gain
+        id_header.put_u8(0); // channel map 0 = one stream: mono or stereo
+        self.write_page(&id_header, PAGE_HEADER_TYPE_BEGINNING_OF_STREAM, 0)?;
+
+        let mut comment_header = BytesMut::new();
+        comment_header.put_slice(COMMENT_PAGE_SIGNATURE); // Magic Signature 'OpusTags'
+        comment_header.put_u32_le(OGG_VENDOR.len() as u32); // Vendor Length
+        comment_header.put_slice(OGG_VENDOR); // Vendor name
+        comment_header.put_u32_le(0); // User Comment List Length
+        self.write_page(&comment_header, PAGE_HEADER_TYPE_CONTINUATION_OF_STREAM, 0)
+    }
+
+    /// write_page writes a page holding a single packet
+    fn write_page(&mut self, payload: &[u8], header_type: u8, granule_pos: u64) -> Result<()> {
+        // Lacing values: as many 255 as needed, then a last segment shorter than 255
+        let mut segments = vec![255u8; payload.len() / 255];
+        segments.push((payload.len() % 255) as u8);
+
+        let mut page = BytesMut::with_capacity(PAGE_HEADER_SIZE + segments.len() + payload.len());
+        page.put_slice(PAGE_HEADER_SIGNATURE); // page headers starts with 'OggS'
+        page.put_u8(0); // Version
+        page.put_u8(header_type); // 2 = beginning of stream, 4 = end of stream
+        page.put_u64_le(granule_pos); // granule position
+        page.put_u32_le(self.serial); // Bitstream serial number
+        page.put_u32_le(self.page_index); // Page sequence number
+        page.put_u32_le(0); // Checksum, computed below
+        page.put_u8(segments.len() as u8); // Number of segments in page
+        page.put_slice(&segments);
+        page.put_slice(payload);
+
+        let checksum = update_checksum(0, &page);
+        page[PAGE_HEADER_CHECKSUM_OFFSET..PAGE_HEADER_CHECKSUM_OFFSET + 4]
+            .copy_from_slice(&checksum.to_le_bytes());
+
+        self.page_index += 1;
+
+        if let Some(w) = &mut self.writer {
+            w.write_all(&page)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_pending_page(&mut self, header_type: u8) -> Result<()> {
+        if let Some((payload, granule_pos)) = self.pending_page.take() {
+            self.write_page(&payload, header_type, granule_pos)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Writer for OggWriter<W> {
+    /// write_rtp adds a new packet and writes the appropriate headers for it.
+    /// The granule position of each page is the end of its packet, following
+    /// the RTP timestamps so that gaps in the stream are preserved.
+    fn write_rtp(&mut self, pkt: &rtp::packet::Packet) -> Result<()> {
+        if self.writer.is_none() {
+            return Err(Error::ErrMediaWriterClosed.into());
+        }
+        if pkt.payload.is_empty() {
+            return Ok(());
+        }
+
+        let mut opus_packet = OpusPacket::default();
+        opus_packet.depacketize(&pkt.payload)?;
+
+        if let Some(last_timestamp) = self.last_timestamp {
+            self.elapsed_samples += pkt.header.timestamp.wrapping_sub(last_timestamp) as u64;
+        }
+        self.last_timestamp = Some(pkt.header.timestamp);
+
+        let granule_pos = self.elapsed_samples + opus_packet_samples(&opus_packet.payload) as u64;
+
+        self.write_pending_page(PAGE_HEADER_TYPE_CONTINUATION_OF_STREAM)?;
+        self.pending_page = Some((opus_packet.payload, granule_pos));
+
+        Ok(())
+    }
+
+    /// close stops the recording, marking the last page as the end of the stream
+    fn close(&mut self) -> Result<()> {
+        if self.writer.is_none() {
+            return Ok(());
+        }
+
+        self.write_pending_page(PAGE_HEADER_TYPE_END_OF_STREAM)?;
+
+        if let Some(mut w) = self.writer.take() {
+            w.flush()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+use crate::media::io::{is_eof, Reader};
+use std::io::Cursor;
+use tokio::time::Duration;
+
+fn packet(payload: &[u8], timestamp: u32) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            payload_type: 111,
+            timestamp,
+            ..Default::default()
+        },
+        payload: Bytes::copy_from_slice(payload),
+    }
+}
+
+#[test]
+fn test_ogg_writer_write_rtp() -> Result<()> {
+    let mut buffer = Cursor::new(vec![]);
+    {
+        let mut writer = OggWriter::new(&mut buffer, 48000, 2)?;
+
+        writer.write_rtp(&packet(&[0x08, 0x01, 0x02], 1000))?;
+        // Empty packets are ignored
+        writer.write_rtp(&packet(&[], 1960))?;
+        writer.write_rtp(&packet(&[0x08, 0x03], 1960))?;
+        // A packet is missing
+        writer.write_rtp(&packet(&[0x08, 0x04], 3880))?;
+
+        writer.close()?;
+        // close is idempotent
+        writer.close()?;
+
+        let result = writer.write_rtp(&packet(&[0x08, 0x05], 4840));
+        assert!(Error::ErrMediaWriterClosed.equal(&result.err().unwrap()));
+    }
+
+    buffer.set_position(0);
+    let (mut reader, header) = OggReader::new(buffer.clone())?;
+    assert_eq!(header.channels, 2);
+    assert_eq!(header.sample_rate, 48000);
+    assert_eq!(header.pre_skip, DEFAULT_PRE_SKIP);
+
+    for data in [&[0x08u8, 0x01, 0x02][..], &[0x08, 0x03], &[0x08, 0x04]].iter() {
+        let sample = reader.read_sample()?;
+        assert_eq!(&sample.data[..], *data);
+        assert_eq!(sample.duration, Duration::from_millis(20));
+    }
+    assert!(is_eof(&reader.read_sample().err().unwrap()));
+
+    // The granule position of the pages is the end of their packet
+    buffer.set_position(0);
+    let (mut reader, _) = OggReader::new(buffer)?;
+    let (payload, page_header) = reader.parse_next_page()?;
+    assert!(payload.starts_with(COMMENT_PAGE_SIGNATURE));
+    assert_eq!(page_header.index, 1);
+
+    for (granule_position, header_type) in [
+        (960, PAGE_HEADER_TYPE_CONTINUATION_OF_STREAM),
+        (1920, PAGE_HEADER_TYPE_CONTINUATION_OF_STREAM),
+        (3840, PAGE_HEADER_TYPE_END_OF_STREAM),
+    ]
+    .iter()
+    {
+        let (_, page_header) = reader.parse_next_page()?;
+        assert_eq!(page_header.granule_position, *granule_position);
+        assert_eq!(page_header.header_type, *header_type);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_ogg_writer_large_packet() -> Result<()> {
+    let payload: Vec<u8> = std::iter::once(0x08)
+        .chain(std::iter::repeat(0xaa).take(509))
+        .collect();
+
+    let mut buffer = Cursor::new(vec![]);
+    {
+        let mut writer = OggWriter::new(&mut buffer, 48000, 1)?;
+        writer.write_rtp(&packet(&payload, 0))?;
+        writer.close()?;
+    }
+
+    buffer.set_position(0);
+    let (mut reader, _) = OggReader::new(buffer)?;
+    let sample = reader.read_sample()?;
+    assert_eq!(&sample.data[..], &payload[..]);
+
+    Ok(())
+}
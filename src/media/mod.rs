@@ -1,7 +1,9 @@
 pub mod dtls_transport;
 pub mod ice_transport;
 pub mod interceptor;
+pub mod io;
 pub mod rtp;
+pub mod sample_builder;
 pub mod track;
 
 use bytes::Bytes;
@@ -27,15 +29,3 @@ impl Default for Sample {
         }
     }
 }
-
-/*
-// Writer defines an interface to handle
-// the creation of media files
-type Writer interface {
-    // Add the content of an RTP packet to the media
-    write_rtp(packet *rtp.Packet) error
-    // Close the media
-    // Note: Close implementation must be idempotent
-    Close() error
-}
-*/
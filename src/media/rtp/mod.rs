@@ -25,6 +25,12 @@ pub type SSRC = u32;
 /// https://tools.ietf.org/html/rfc3550#section-3
 pub type PayloadType = u8;
 
+/// SDES_REPAIR_RTP_STREAM_ID_URI is the URI of the header extension carrying the RID of
+/// the simulcast layer repaired by a RTX retransmission
+/// https://tools.ietf.org/html/rfc8852#section-3.2
+pub const SDES_REPAIR_RTP_STREAM_ID_URI: &str =
+    "urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id";
+
 /// TYPE_RTCP_FBT_RANSPORT_CC ..
 pub const TYPE_RTCP_FB_TRANSPORT_CC: &str = "transport-cc";
 
@@ -62,6 +68,13 @@ pub struct RTPCapabilities {
     pub header_extensions: Vec<RTPHeaderExtensionCapability>,
 }
 
+/// RTPRtxParameters dictionary contains information relating to retransmission (RTX) settings.
+/// https://draft.ortc.org/#dom-rtcrtprtxparameters
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct RTPRtxParameters {
+    pub ssrc: SSRC,
+}
+
 /// RTPCodingParameters provides information relating to both encoding and decoding.
 /// This is a subset of the RFC since Pion WebRTC doesn't implement encoding/decoding itself
 /// http://draft.ortc.org/#dom-rtcrtpcodingparameters
@@ -70,6 +83,7 @@ pub struct RTPCodingParameters {
     pub rid: String,
     pub ssrc: SSRC,
     pub payload_type: PayloadType,
+    pub rtx: RTPRtxParameters,
 }
 
 /// RTPDecodingParameters provides information relating to both encoding and decoding.
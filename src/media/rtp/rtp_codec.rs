@@ -2,19 +2,23 @@ use super::*;
 use crate::api::media_engine::*;
 use crate::error::Error;
 use crate::media::rtp::fmtp::*;
+use crate::media::sample_builder::PartitionDepacketizer;
 
 use anyhow::Result;
+use serde::Serialize;
 use std::fmt;
 
 /// RTPCodecType determines the type of a codec
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
 pub enum RTPCodecType {
     Unspecified = 0,
 
     /// RTPCodecTypeAudio indicates this is an audio codec
+    #[serde(rename = "audio")]
     Audio = 1,
 
     /// RTPCodecTypeVideo indicates this is a video codec
+    #[serde(rename = "video")]
     Video = 2,
 }
 
@@ -88,6 +92,23 @@ impl RTPCodecCapability {
             Err(Error::ErrNoPayloaderForCodec.into())
         }
     }
+
+    pub(crate) fn depacketizer_for_codec(
+        &self,
+    ) -> Result<Box<dyn PartitionDepacketizer + Send + Sync>> {
+        let mime_type = self.mime_type.to_lowercase();
+        if mime_type == MIME_TYPE_H264.to_lowercase() {
+            Ok(Box::new(rtp::codecs::h264::H264Packet::default()))
+        } else if mime_type == MIME_TYPE_VP8.to_lowercase() {
+            Ok(Box::new(rtp::codecs::vp8::Vp8Packet::default()))
+        } else if mime_type == MIME_TYPE_VP9.to_lowercase() {
+            Ok(Box::new(rtp::codecs::vp9::Vp9Packet::default()))
+        } else if mime_type == MIME_TYPE_OPUS.to_lowercase() {
+            Ok(Box::new(rtp::codecs::opus::OpusPacket::default()))
+        } else {
+            Err(Error::ErrNoDepacketizerForCodec.into())
+        }
+    }
 }
 
 /// RTPHeaderExtensionCapability is used to define a RFC5285 RTP header extension supported by the codec.
@@ -166,3 +187,38 @@ pub(crate) fn codec_parameters_fuzzy_search(
 
     (RTPCodecParameters::default(), CodecMatch::None)
 }
+
+/// find_rtx_payload_type returns the payload type of the RTX codec in haystack
+/// that retransmits the codec with the payload type needle, if any.
+pub(crate) fn find_rtx_payload_type(
+    needle: PayloadType,
+    haystack: &[RTPCodecParameters],
+) -> Option<PayloadType> {
+    let apt = needle.to_string();
+    haystack
+        .iter()
+        .find(|c| {
+            c.capability.mime_type.eq_ignore_ascii_case(MIME_TYPE_RTX)
+                && parse_fmtp(&c.capability.sdp_fmtp_line).get("apt") == Some(&apt)
+        })
+        .map(|c| c.payload_type)
+}
+
+/// find_apt_payload_type returns the payload type of the codec in haystack that
+/// the RTX codec with the payload type needle retransmits, if any.
+pub(crate) fn find_apt_payload_type(
+    needle: PayloadType,
+    haystack: &[RTPCodecParameters],
+) -> Option<PayloadType> {
+    haystack
+        .iter()
+        .find(|c| {
+            c.payload_type == needle && c.capability.mime_type.eq_ignore_ascii_case(MIME_TYPE_RTX)
+        })
+        .and_then(|c| {
+            parse_fmtp(&c.capability.sdp_fmtp_line)
+                .get("apt")?
+                .parse()
+                .ok()
+        })
+}
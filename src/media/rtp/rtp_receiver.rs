@@ -4,24 +4,31 @@ mod rtp_receiver_test;
 use crate::api::media_engine::MediaEngine;
 use crate::error::Error;
 use crate::media::dtls_transport::DTLSTransport;
+use crate::media::ice_transport::ICE_TRANSPORT_STATS_ID;
 use crate::media::interceptor::*;
 use crate::media::rtp::rtp_codec::{
-    codec_parameters_fuzzy_search, CodecMatch, RTPCodecCapability, RTPCodecParameters,
-    RTPCodecType, RTPParameters,
+    codec_parameters_fuzzy_search, find_apt_payload_type, CodecMatch, RTPCodecCapability,
+    RTPCodecParameters, RTPCodecType, RTPParameters,
 };
 use crate::media::rtp::rtp_transceiver_direction::RTPTransceiverDirection;
-use crate::media::rtp::{RTPCodingParameters, RTPReceiveParameters, SSRC};
+use crate::media::rtp::{RTPCodingParameters, RTPReceiveParameters, RTPRtxParameters, SSRC};
 use crate::media::track::track_remote::TrackRemote;
 use crate::media::track::TrackStreams;
+use crate::stats::{
+    stats_timestamp_from, stats_timestamp_now, InboundRTPStreamStats, StatsCollector,
+    StatsReportType, StatsType,
+};
 use crate::util::flatten_errs;
 use crate::RECEIVE_MTU;
 
 use crate::peer::sdp::TrackDetails;
 use anyhow::Result;
+use async_trait::async_trait;
 use interceptor::stream_info::{RTPHeaderExtension, StreamInfo};
 use interceptor::{Attributes, Interceptor, RTCPReader, RTPReader};
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
+use util::{Marshal, Unmarshal};
 
 pub(crate) struct RTPReceiverInternal {
     pub(crate) kind: RTPCodecType,
@@ -114,7 +121,14 @@ impl RTPReceiverInternal {
         Ok((pkts, attributes))
     }
 
-    pub(crate) async fn read_rtp(&self, b: &mut [u8], tid: &str) -> Result<(usize, Attributes)> {
+    /// read_rtp reads the RTP of the track tid, the simulcast layers of a track
+    /// sharing its id being told apart by their rid
+    pub(crate) async fn read_rtp(
+        &self,
+        b: &mut [u8],
+        tid: &str,
+        rid: &str,
+    ) -> Result<(usize, Attributes)> {
         {
             let mut received_rx = self.received_rx.lock().await;
             let _ = received_rx.recv().await;
@@ -125,7 +139,7 @@ impl RTPReceiverInternal {
         {
             let tracks = self.tracks.lock().await;
             for t in &*tracks {
-                if t.track.id().await == tid {
+                if t.track.id().await == tid && t.track.rid() == rid {
                     rtp_interceptor = t.rtp_interceptor.clone();
                     break;
                 }
@@ -317,10 +331,17 @@ impl RTPReceiver {
                     codec,
                     &global_params.header_extensions,
                 );
+                let repair_read_stream = if encoding.rtx.ssrc != 0 {
+                    RTPReceiver::repair_stream_for_ssrc(&self.transport, encoding.rtx.ssrc).await
+                } else {
+                    None
+                };
                 let (rtp_read_stream, rtp_interceptor, rtcp_read_stream, rtcp_interceptor) =
                     RTPReceiver::streams_for_ssrc(
                         &self.transport,
                         encoding.ssrc,
+                        repair_read_stream.clone(),
+                        &media_engine.get_codecs_by_kind(self.kind).await,
                         &stream_info,
                         &interceptor,
                     )
@@ -340,6 +361,8 @@ impl RTPReceiver {
                     rtp_interceptor,
                     rtcp_read_stream,
                     rtcp_interceptor,
+                    repair_read_stream,
+                    repair_tx: None,
                 };
 
                 tracks.push(t);
@@ -360,6 +383,8 @@ impl RTPReceiver {
                     rtp_interceptor: None,
                     rtcp_read_stream: None,
                     rtcp_interceptor: None,
+                    repair_read_stream: None,
+                    repair_tx: None,
                 };
 
                 tracks.push(t);
@@ -398,6 +423,47 @@ impl RTPReceiver {
         self.internal.read_simulcast_rtcp(rid).await
     }
 
+    /// collect_stats reports the InboundRTPStreamStats of every track that
+    /// has received at least one packet
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector, mid: &str) {
+        for track in &self.tracks().await {
+            let counters = {
+                let counters = track.inbound_counters.lock().await;
+                counters.clone()
+            };
+            if counters.packets_received == 0 {
+                continue;
+            }
+
+            let ssrc = track.ssrc();
+            collector
+                .collect(StatsReportType::InboundRTP(InboundRTPStreamStats {
+                    timestamp: stats_timestamp_now(),
+                    stats_type: StatsType::InboundRTP,
+                    id: format!("InboundRTP-{}", ssrc),
+                    ssrc,
+                    kind: track.kind(),
+                    transport_id: ICE_TRANSPORT_STATS_ID.to_owned(),
+                    codec_id: self
+                        .internal
+                        .media_engine
+                        .get_codec_stats_id(track.payload_type()),
+                    track_identifier: track.id().await,
+                    mid: mid.to_owned(),
+                    rid: track.rid().to_owned(),
+                    packets_received: counters.packets_received,
+                    packets_lost: counters.packets_lost(),
+                    jitter: counters.jitter(),
+                    bytes_received: counters.bytes_received,
+                    header_bytes_received: counters.header_bytes_received,
+                    last_packet_received_timestamp: counters
+                        .last_packet_received_timestamp
+                        .map_or(0.0, stats_timestamp_from),
+                }))
+                .await;
+        }
+    }
+
     pub(crate) async fn have_received(&self) -> bool {
         let received_tx = self.received_tx.lock().await;
         received_tx.is_none()
@@ -408,6 +474,9 @@ impl RTPReceiver {
         if incoming.ssrc != 0 {
             encodings.push(RTPCodingParameters {
                 ssrc: incoming.ssrc,
+                rtx: RTPRtxParameters {
+                    ssrc: incoming.repair_ssrc,
+                },
                 ..Default::default()
             });
         }
@@ -465,6 +534,12 @@ impl RTPReceiver {
                     }
                 }
 
+                if let Some(repair_read_stream) = &t.repair_read_stream {
+                    if let Err(err) = repair_read_stream.close().await {
+                        errs.push(err);
+                    }
+                }
+
                 self.internal
                     .interceptor
                     .unbind_remote_stream(&t.stream_info)
@@ -476,19 +551,31 @@ impl RTPReceiver {
     }
 
     /// read_rtp should only be called by a track, this only exists so we can keep state in one place
-    pub(crate) async fn read_rtp(&self, b: &mut [u8], tid: &str) -> Result<(usize, Attributes)> {
-        self.internal.read_rtp(b, tid).await
+    pub(crate) async fn read_rtp(
+        &self,
+        b: &mut [u8],
+        tid: &str,
+        rid: &str,
+    ) -> Result<(usize, Attributes)> {
+        self.internal.read_rtp(b, tid, rid).await
     }
 
     /// receive_for_rid is the sibling of Receive expect for RIDs instead of SSRCs
-    /// It populates all the internal state for the given RID
+    /// It populates all the internal state for the given RID, reading from the
+    /// rtp_read_stream which has already been accepted for the undeclared SSRC
     pub(crate) async fn receive_for_rid(
         &self,
         rid: &str,
         params: &RTPParameters,
-        ssrc: SSRC,
+        rtp_read_stream: Arc<srtp::stream::Stream>,
     ) -> Result<Arc<TrackRemote>> {
+        let ssrc = rtp_read_stream.get_ssrc();
         let interceptor = Arc::clone(&self.internal.interceptor);
+        let rtx_enabled = self
+            .internal
+            .media_engine
+            .is_rtx_enabled(self.kind, &[RTPTransceiverDirection::Recvonly])
+            .await;
         log::debug!("receive_for_rid enter tracks");
         {
             let mut tracks = self.internal.tracks.lock().await;
@@ -506,17 +593,24 @@ impl RTPReceiver {
                         &params.header_extensions,
                     );
 
-                    let (rtp_read_stream, rtp_interceptor, rtcp_read_stream, rtcp_interceptor) =
-                        RTPReceiver::streams_for_ssrc(
-                            &self.transport,
-                            ssrc,
-                            &t.stream_info,
-                            &interceptor,
-                        )
-                        .await?;
-
-                    t.rtp_read_stream = rtp_read_stream;
-                    t.rtp_interceptor = rtp_interceptor;
+                    // The repair stream of the layer is attached once its first
+                    // retransmission is received, see receive_repair_for_rid
+                    let rtp_stream_reader = if rtx_enabled {
+                        let (rtx_reader, repair_tx) = RTXReader::new(Arc::clone(&rtp_read_stream));
+                        t.repair_tx = Some(repair_tx);
+                        Arc::new(rtx_reader) as Arc<dyn RTPReader + Send + Sync>
+                    } else {
+                        Arc::clone(&rtp_read_stream) as Arc<dyn RTPReader + Send + Sync>
+                    };
+                    let rtp_interceptor = interceptor
+                        .bind_remote_stream(&t.stream_info, rtp_stream_reader)
+                        .await;
+                    let (rtcp_read_stream, rtcp_interceptor) =
+                        RTPReceiver::rtcp_streams_for_ssrc(&self.transport, ssrc, &interceptor)
+                            .await?;
+
+                    t.rtp_read_stream = Some(rtp_read_stream);
+                    t.rtp_interceptor = Some(rtp_interceptor);
                     t.rtcp_read_stream = rtcp_read_stream;
                     t.rtcp_interceptor = rtcp_interceptor;
 
@@ -530,9 +624,50 @@ impl RTPReceiver {
         Err(Error::ErrRTPReceiverForSSRCTrackStreamNotFound.into())
     }
 
+    /// receive_repair_for_rid attaches the RTX repair stream of the simulcast layer rid,
+    /// which has already been accepted for the undeclared SSRC. probe is the packet of the
+    /// repair stream read to identify it, it is unwrapped first.
+    pub(crate) async fn receive_repair_for_rid(
+        &self,
+        rid: &str,
+        repair_read_stream: Arc<srtp::stream::Stream>,
+        probe: &[u8],
+    ) -> Result<()> {
+        let codecs = self
+            .internal
+            .media_engine
+            .get_codecs_by_kind(self.kind)
+            .await;
+
+        let mut tracks = self.internal.tracks.lock().await;
+        for t in &mut *tracks {
+            if t.track.rid() != rid {
+                continue;
+            }
+            if let Some(repair_tx) = t.repair_tx.take() {
+                read_repair_stream(
+                    Arc::clone(&repair_read_stream),
+                    t.track.ssrc(),
+                    codecs,
+                    repair_tx,
+                    probe.to_vec(),
+                );
+                t.repair_read_stream = Some(repair_read_stream);
+                return Ok(());
+            }
+        }
+
+        Err(Error::ErrRTPReceiverForRIDTrackStreamNotFound.into())
+    }
+
+    /// streams_for_ssrc opens the RTP and RTCP streams of ssrc and binds them to the
+    /// interceptor. Retransmissions read from repair_read_stream are unwrapped into
+    /// the RTP stream before reaching the interceptor.
     async fn streams_for_ssrc(
         transport: &Arc<DTLSTransport>,
         ssrc: SSRC,
+        repair_read_stream: Option<Arc<srtp::stream::Stream>>,
+        codecs: &[RTPCodecParameters],
         stream_info: &StreamInfo,
         interceptor: &Arc<dyn Interceptor + Send + Sync>,
     ) -> Result<(
@@ -546,11 +681,56 @@ impl RTPReceiver {
             .await
             .ok_or(Error::ErrDtlsTransportNotStarted)?;
         let rtp_read_stream = Arc::new(srtp_session.listen(ssrc).await?);
-        let rtp_stream_reader = Arc::clone(&rtp_read_stream) as Arc<dyn RTPReader + Send + Sync>;
+        let rtp_stream_reader = if let Some(repair_read_stream) = repair_read_stream {
+            let (rtx_reader, repair_tx) = RTXReader::new(Arc::clone(&rtp_read_stream));
+            read_repair_stream(repair_read_stream, ssrc, codecs.to_vec(), repair_tx, vec![]);
+            Arc::new(rtx_reader) as Arc<dyn RTPReader + Send + Sync>
+        } else {
+            Arc::clone(&rtp_read_stream) as Arc<dyn RTPReader + Send + Sync>
+        };
         let rtp_interceptor = interceptor
             .bind_remote_stream(stream_info, rtp_stream_reader)
             .await;
 
+        let (rtcp_read_stream, rtcp_interceptor) =
+            RTPReceiver::rtcp_streams_for_ssrc(transport, ssrc, interceptor).await?;
+
+        Ok((
+            Some(rtp_read_stream),
+            Some(rtp_interceptor),
+            rtcp_read_stream,
+            rtcp_interceptor,
+        ))
+    }
+
+    /// repair_stream_for_ssrc opens the RTX repair stream with the given ssrc. Failing
+    /// to do so isn't fatal, the track is then received without retransmissions.
+    async fn repair_stream_for_ssrc(
+        transport: &Arc<DTLSTransport>,
+        repair_ssrc: SSRC,
+    ) -> Option<Arc<srtp::stream::Stream>> {
+        let srtp_session = transport.get_srtp_session().await?;
+        match srtp_session.listen(repair_ssrc).await {
+            Ok(repair_read_stream) => Some(Arc::new(repair_read_stream)),
+            Err(err) => {
+                log::warn!(
+                    "failed to open RTX stream for SSRC {}: {}",
+                    repair_ssrc,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    async fn rtcp_streams_for_ssrc(
+        transport: &Arc<DTLSTransport>,
+        ssrc: SSRC,
+        interceptor: &Arc<dyn Interceptor + Send + Sync>,
+    ) -> Result<(
+        Option<Arc<srtp::stream::Stream>>,
+        Option<Arc<dyn RTCPReader + Send + Sync>>,
+    )> {
         let srtcp_session = transport
             .get_srtcp_session()
             .await
@@ -559,11 +739,102 @@ impl RTPReceiver {
         let rtcp_stream_reader = Arc::clone(&rtcp_read_stream) as Arc<dyn RTCPReader + Send + Sync>;
         let rtcp_interceptor = interceptor.bind_rtcp_reader(rtcp_stream_reader).await;
 
-        Ok((
-            Some(rtp_read_stream),
-            Some(rtp_interceptor),
-            Some(rtcp_read_stream),
-            Some(rtcp_interceptor),
-        ))
+        Ok((Some(rtcp_read_stream), Some(rtcp_interceptor)))
+    }
+}
+
+/// RTXReader reads a RTP stream together with the retransmissions of its RTX (RFC 4588)
+/// repair stream, which are unwrapped back into the original stream by read_repair_stream
+struct RTXReader {
+    rtp_read_stream: Arc<srtp::stream::Stream>,
+    repair_rx: Mutex<mpsc::Receiver<rtp::packet::Packet>>,
+}
+
+impl RTXReader {
+    /// new returns a RTXReader of rtp_read_stream, and the sender its retransmissions
+    /// are fed with
+    fn new(
+        rtp_read_stream: Arc<srtp::stream::Stream>,
+    ) -> (Self, mpsc::Sender<rtp::packet::Packet>) {
+        let (repair_tx, repair_rx) = mpsc::channel(RTX_CHANNEL_SIZE);
+        (
+            RTXReader {
+                rtp_read_stream,
+                repair_rx: Mutex::new(repair_rx),
+            },
+            repair_tx,
+        )
+    }
+}
+
+#[async_trait]
+impl RTPReader for RTXReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        let mut repair_rx = self.repair_rx.lock().await;
+        tokio::select! {
+            n = self.rtp_read_stream.read(buf) => Ok((n?, a.clone())),
+            Some(pkt) = repair_rx.recv() => Ok((pkt.marshal_to(buf)?, a.clone())),
+        }
+    }
+}
+
+/// RTX_CHANNEL_SIZE is how many retransmissions are queued until the track is read
+const RTX_CHANNEL_SIZE: usize = 64;
+
+/// read_repair_stream reads the RTX repair stream of ssrc until it is closed, in its
+/// own task, and feeds the unwrapped retransmissions to repair_tx. probe holds a packet
+/// of the repair stream which was already read, if not empty.
+fn read_repair_stream(
+    repair_read_stream: Arc<srtp::stream::Stream>,
+    ssrc: SSRC,
+    codecs: Vec<RTPCodecParameters>,
+    repair_tx: mpsc::Sender<rtp::packet::Packet>,
+    probe: Vec<u8>,
+) {
+    tokio::spawn(async move {
+        let mut b = probe;
+        let mut n = b.len();
+        b.resize(RECEIVE_MTU, 0);
+        loop {
+            if n != 0 {
+                let mut buf = &b[..n];
+                if let Ok(pkt) = rtp::packet::Packet::unmarshal(&mut buf) {
+                    if let Some(pkt) = unwrap_rtx(&pkt, ssrc, &codecs) {
+                        if repair_tx.send(pkt).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+
+            n = match repair_read_stream.read(&mut b).await {
+                Ok(n) => n,
+                Err(_) => return,
+            };
+        }
+    });
+}
+
+/// unwrap_rtx restores the original packet of the stream ssrc carried by a RTX
+/// retransmission, returning None for packets without one such as padding probes
+fn unwrap_rtx(
+    pkt: &rtp::packet::Packet,
+    ssrc: SSRC,
+    codecs: &[RTPCodecParameters],
+) -> Option<rtp::packet::Packet> {
+    if pkt.payload.len() < 2 {
+        return None;
     }
+    let payload_type = find_apt_payload_type(pkt.header.payload_type, codecs)?;
+
+    let mut header = pkt.header.clone();
+    header.ssrc = ssrc;
+    header.payload_type = payload_type;
+    header.sequence_number = u16::from_be_bytes([pkt.payload[0], pkt.payload[1]]);
+    header.padding = false;
+
+    Some(rtp::packet::Packet {
+        header,
+        payload: pkt.payload.slice(2..),
+    })
 }
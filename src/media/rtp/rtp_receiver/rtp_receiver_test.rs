@@ -246,3 +246,50 @@ async fn test_rtp_receiver_set_read_deadline() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_rtp_receiver_unwrap_rtx() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let codecs = m.get_codecs_by_kind(RTPCodecType::Video).await;
+
+    let rtx = rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            marker: true,
+            payload_type: 97,
+            sequence_number: 7,
+            timestamp: 3000,
+            ssrc: 2,
+            ..Default::default()
+        },
+        payload: Bytes::from_static(&[0x12, 0x34, 0xAA, 0xBB]),
+    };
+
+    let pkt = unwrap_rtx(&rtx, 1, &codecs).expect("should unwrap rtx packet");
+    assert_eq!(1, pkt.header.ssrc);
+    assert_eq!(96, pkt.header.payload_type);
+    assert_eq!(0x1234, pkt.header.sequence_number);
+    assert_eq!(3000, pkt.header.timestamp);
+    assert!(pkt.header.marker);
+    assert_eq!(Bytes::from_static(&[0xAA, 0xBB]), pkt.payload);
+
+    // padding only probes carry no original packet
+    let probe = rtp::packet::Packet {
+        payload: Bytes::new(),
+        ..rtx.clone()
+    };
+    assert!(unwrap_rtx(&probe, 1, &codecs).is_none());
+
+    // a payload type that isn't RTX can't be unwrapped
+    let unknown = rtp::packet::Packet {
+        header: rtp::header::Header {
+            payload_type: 96,
+            ..rtx.header.clone()
+        },
+        ..rtx
+    };
+    assert!(unwrap_rtx(&unknown, 1, &codecs).is_none());
+
+    Ok(())
+}
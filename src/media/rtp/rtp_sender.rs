@@ -4,83 +4,98 @@ mod rtp_sender_test;
 use crate::api::media_engine::MediaEngine;
 use crate::error::Error;
 use crate::media::dtls_transport::DTLSTransport;
-use crate::media::interceptor::{create_stream_info, InterceptorToTrackLocalWriter};
-use crate::media::rtp::rtp_codec::{RTPCodecParameters, RTPCodecType};
+use crate::media::ice_transport::ICE_TRANSPORT_STATS_ID;
+use crate::media::interceptor::{
+    create_stream_info, InterceptorToTrackLocalWriter, ATTRIBUTE_RTX_PAYLOAD_TYPE,
+    ATTRIBUTE_RTX_SSRC, ATTRIBUTE_TARGET_BITRATE,
+};
+use crate::media::rtp::rtp_codec::{
+    find_rtx_payload_type, RTPCodecParameters, RTPCodecType, RTPHeaderExtensionCapability,
+};
 use crate::media::rtp::rtp_transceiver::RTPTransceiver;
 use crate::media::rtp::rtp_transceiver_direction::RTPTransceiverDirection;
 use crate::media::rtp::srtp_writer_future::SrtpWriterFuture;
-use crate::media::rtp::{PayloadType, RTPEncodingParameters, RTPSendParameters, SSRC};
+use crate::media::rtp::{
+    PayloadType, RTPEncodingParameters, RTPRtxParameters, RTPSendParameters,
+    SDES_REPAIR_RTP_STREAM_ID_URI, SSRC,
+};
 use crate::media::track::track_local::{TrackLocal, TrackLocalContext, TrackLocalWriter};
+use crate::stats::rtp_stream_stats::{OutboundRTPStreamCounters, RemoteInboundRTPStreamCounters};
+use crate::stats::{
+    stats_timestamp_from, stats_timestamp_now, OutboundRTPStreamStats, RemoteInboundRTPStreamStats,
+    StatsCollector, StatsReportType, StatsType,
+};
+use crate::util::flatten_errs;
 use crate::RECEIVE_MTU;
 
 use anyhow::Result;
+use bytes::Bytes;
 use ice::rand::generate_crypto_random_string;
 use interceptor::stream_info::StreamInfo;
 use interceptor::{Attributes, Interceptor, RTCPReader, RTPWriter};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
 
+pub type OnTargetBitrateChangeHdlrFn =
+    Box<dyn (FnMut(u64) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>) + Send + Sync>;
+
 pub(crate) struct RTPSenderInternal {
     pub(crate) send_called_rx: Mutex<mpsc::Receiver<()>>,
     pub(crate) stop_called_rx: Mutex<mpsc::Receiver<()>>,
     pub(crate) stop_called_signal: Arc<AtomicBool>,
-    pub(crate) rtcp_interceptor: Mutex<Option<Arc<dyn RTCPReader + Send + Sync>>>,
 }
 
 impl RTPSenderInternal {
-    /// read reads incoming RTCP for this RTPReceiver
-    async fn read(&self, b: &mut [u8]) -> Result<(usize, Attributes)> {
+    /// wait_for_send blocks until send has been called, failing if the RTPSender is
+    /// stopped first
+    async fn wait_for_send(&self) -> Result<()> {
         let (mut send_called_rx, mut stop_called_rx) = (
             self.send_called_rx.lock().await,
             self.stop_called_rx.lock().await,
         );
         tokio::select! {
-            _ = send_called_rx.recv() =>{
-                let rtcp_interceptor = self.rtcp_interceptor.lock().await;
-                if let Some(rtcp_interceptor) = &*rtcp_interceptor{
-                    let a = Attributes::new();
-                    rtcp_interceptor.read(b, &a).await
-                }else{
-                    Err(Error::ErrInterceptorNotBind.into())
-                }
-            }
-            _ = stop_called_rx.recv() =>{
-                Err(Error::ErrClosedPipe.into())
-            }
+            _ = send_called_rx.recv() => Ok(()),
+            _ = stop_called_rx.recv() => Err(Error::ErrClosedPipe.into()),
         }
     }
+}
 
-    /// read_rtcp is a convenience method that wraps Read and unmarshals for you.
-    async fn read_rtcp(&self) -> Result<(Box<dyn rtcp::packet::Packet>, Attributes)> {
-        let mut b = vec![0u8; RECEIVE_MTU];
-        let (n, attributes) = self.read(&mut b).await?;
+/// TrackEncoding is a single encoding sent by a RTPSender. A RTPSender has more
+/// than one when it sends simulcast, each with its own RID and SSRC
+pub(crate) struct TrackEncoding {
+    pub(crate) track: Option<Arc<dyn TrackLocal + Send + Sync>>,
+    pub(crate) rid: String,
+    pub(crate) ssrc: SSRC,
+    /// rtx_ssrc is the SSRC of the RTX repair stream of the encoding, only
+    /// signalled when RTX is enabled
+    pub(crate) rtx_ssrc: SSRC,
 
-        let mut buf = &b[..n];
-        let pkts = rtcp::packet::unmarshal(&mut buf)?;
+    pub(crate) srtp_stream: Arc<SrtpWriterFuture>,
+    pub(crate) rtcp_interceptor: Arc<dyn RTCPReader + Send + Sync>,
+    pub(crate) stream_info: StreamInfo,
 
-        Ok((pkts, attributes))
-    }
+    pub(crate) context: TrackLocalContext,
 }
 
 /// RTPSender allows an application to control how a given Track is encoded and transmitted to a remote peer
 pub struct RTPSender {
-    pub(crate) track: Mutex<Option<Arc<dyn TrackLocal + Send + Sync>>>,
-
-    pub(crate) srtp_stream: Arc<SrtpWriterFuture>,
-    pub(crate) stream_info: Mutex<StreamInfo>,
-
-    pub(crate) context: Mutex<TrackLocalContext>,
+    pub(crate) track_encodings: Mutex<Vec<TrackEncoding>>,
 
     pub(crate) transport: Arc<DTLSTransport>,
 
     pub(crate) payload_type: PayloadType,
-    pub(crate) ssrc: SSRC,
 
     /// a transceiver sender since we can just check the
     /// transceiver negotiation status
     pub(crate) negotiated: AtomicBool,
 
+    /// paused stops the packets of the track from being sent, while the
+    /// negotiated direction of the transceiver doesn't include sending
+    paused: Arc<AtomicBool>,
+
     pub(crate) media_engine: Arc<MediaEngine>,
     pub(crate) interceptor: Arc<dyn Interceptor + Send + Sync>,
 
@@ -92,6 +107,9 @@ pub struct RTPSender {
     stop_called_tx: Mutex<Option<mpsc::Sender<()>>>,
     stop_called_signal: Arc<AtomicBool>,
 
+    target_bitrate: AtomicU64,
+    on_target_bitrate_change_handler: Mutex<Option<OnTargetBitrateChangeHdlrFn>>,
+
     internal: Arc<RTPSenderInternal>,
 }
 
@@ -108,45 +126,28 @@ impl RTPSender {
         );
         let (send_called_tx, send_called_rx) = mpsc::channel(1);
         let (stop_called_tx, stop_called_rx) = mpsc::channel(1);
-        let ssrc = rand::random::<u32>();
         let stop_called_signal = Arc::new(AtomicBool::new(false));
 
         let internal = Arc::new(RTPSenderInternal {
             send_called_rx: Mutex::new(send_called_rx),
             stop_called_rx: Mutex::new(stop_called_rx),
             stop_called_signal: Arc::clone(&stop_called_signal),
-            rtcp_interceptor: Mutex::new(None),
         });
 
-        let srtp_stream = Arc::new(SrtpWriterFuture {
-            ssrc,
-            rtp_sender: Arc::clone(&internal),
-            rtp_transport: Arc::clone(&transport),
-            rtcp_read_stream: Mutex::new(None),
-            rtp_write_session: Mutex::new(None),
-        });
-
-        let srtp_rtcp_reader = Arc::clone(&srtp_stream) as Arc<dyn RTCPReader + Send + Sync>;
-        let rtcp_interceptor = interceptor.bind_rtcp_reader(srtp_rtcp_reader).await;
-        {
-            let mut internal_rtcp_interceptor = internal.rtcp_interceptor.lock().await;
-            *internal_rtcp_interceptor = Some(rtcp_interceptor);
-        }
+        let track_encoding =
+            RTPSender::new_track_encoding(track, &transport, &interceptor, &internal).await;
 
         RTPSender {
-            track: Mutex::new(Some(track)),
-
-            srtp_stream,
-            stream_info: Mutex::new(StreamInfo::default()),
+            track_encodings: Mutex::new(vec![track_encoding]),
 
-            context: Mutex::new(TrackLocalContext::default()),
             transport,
 
             payload_type: 0,
-            ssrc,
 
             negotiated: AtomicBool::new(false),
 
+            paused: Arc::new(AtomicBool::new(false)),
+
             media_engine,
             interceptor,
 
@@ -158,10 +159,89 @@ impl RTPSender {
             stop_called_tx: Mutex::new(Some(stop_called_tx)),
             stop_called_signal,
 
+            target_bitrate: AtomicU64::new(0),
+            on_target_bitrate_change_handler: Mutex::new(None),
+
             internal,
         }
     }
 
+    async fn new_track_encoding(
+        track: Arc<dyn TrackLocal + Send + Sync>,
+        transport: &Arc<DTLSTransport>,
+        interceptor: &Arc<dyn Interceptor + Send + Sync>,
+        internal: &Arc<RTPSenderInternal>,
+    ) -> TrackEncoding {
+        let ssrc = rand::random::<u32>();
+
+        let srtp_stream = Arc::new(SrtpWriterFuture {
+            ssrc,
+            rtp_sender: Arc::clone(internal),
+            rtp_transport: Arc::clone(transport),
+            rtcp_read_stream: Mutex::new(None),
+            rtp_write_session: Mutex::new(None),
+            outbound_counters: Mutex::new(OutboundRTPStreamCounters::default()),
+            remote_inbound_counters: Mutex::new(RemoteInboundRTPStreamCounters::default()),
+        });
+
+        let srtp_rtcp_reader = Arc::clone(&srtp_stream) as Arc<dyn RTCPReader + Send + Sync>;
+        let rtcp_interceptor = interceptor.bind_rtcp_reader(srtp_rtcp_reader).await;
+
+        TrackEncoding {
+            rid: track.rid().to_owned(),
+            track: Some(track),
+            ssrc,
+            rtx_ssrc: rand::random::<u32>(),
+
+            srtp_stream,
+            rtcp_interceptor,
+            stream_info: StreamInfo::default(),
+
+            context: TrackLocalContext::default(),
+        }
+    }
+
+    /// add_encoding adds an encoding to RTPSender. Used by simulcast senders.
+    /// The track must have the same id, stream_id and kind as the track the
+    /// RTPSender was created with, and a rid that isn't used by any other encoding.
+    pub async fn add_encoding(&self, track: Arc<dyn TrackLocal + Send + Sync>) -> Result<()> {
+        if track.rid().is_empty() {
+            return Err(Error::ErrRTPSenderRidNil.into());
+        }
+        if self.has_stopped().await {
+            return Err(Error::ErrRTPSenderStopped.into());
+        }
+        if self.has_sent().await {
+            return Err(Error::ErrRTPSenderSendAlreadyCalled.into());
+        }
+
+        let mut track_encodings = self.track_encodings.lock().await;
+        let base_track = match track_encodings.first().and_then(|e| e.track.clone()) {
+            Some(base_track) if !base_track.rid().is_empty() => base_track,
+            _ => return Err(Error::ErrRTPSenderNoBaseEncoding.into()),
+        };
+        if base_track.id() != track.id()
+            || base_track.stream_id() != track.stream_id()
+            || base_track.kind() != track.kind()
+        {
+            return Err(Error::ErrRTPSenderBaseEncodingMismatch.into());
+        }
+        if track_encodings.iter().any(|e| e.rid == track.rid()) {
+            return Err(Error::ErrRTPSenderRIDCollision.into());
+        }
+
+        let track_encoding = RTPSender::new_track_encoding(
+            track,
+            &self.transport,
+            &self.interceptor,
+            &self.internal,
+        )
+        .await;
+        track_encodings.push(track_encoding);
+
+        Ok(())
+    }
+
     pub(crate) fn is_negotiated(&self) -> bool {
         self.negotiated.load(Ordering::SeqCst)
     }
@@ -170,6 +250,14 @@ impl RTPSender {
         self.negotiated.store(true, Ordering::SeqCst);
     }
 
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
     pub(crate) async fn set_rtp_transceiver(&self, t: Option<Arc<RTPTransceiver>>) {
         let mut tr = self.tr.lock().await;
         *tr = t;
@@ -184,26 +272,44 @@ impl RTPSender {
     /// get_parameters describes the current configuration for the encoding and
     /// transmission of media on the sender's track.
     pub async fn get_parameters(&self) -> RTPSendParameters {
-        let mut send_parameters = {
-            let track = self.track.lock().await;
-            RTPSendParameters {
-                rtp_parameters: self
-                    .media_engine
-                    .get_rtp_parameters_by_kind(
-                        if let Some(t) = &*track {
-                            t.kind()
+        let kind = self.kind().await;
+        let rtx_enabled = self
+            .media_engine
+            .is_rtx_enabled(kind, &[RTPTransceiverDirection::Sendonly])
+            .await;
+        // The repair streams of simulcast layers are told apart by the repaired RID
+        // header extension, the layers themselves being signalled by RID only
+        let (_, audio_supported, video_supported) = self
+            .media_engine
+            .get_header_extension_id(RTPHeaderExtensionCapability {
+                uri: SDES_REPAIR_RTP_STREAM_ID_URI.to_owned(),
+            })
+            .await;
+        let repair_rid_supported = audio_supported || video_supported;
+        let encodings = {
+            let track_encodings = self.track_encodings.lock().await;
+            track_encodings
+                .iter()
+                .map(|e| RTPEncodingParameters {
+                    rid: e.rid.clone(),
+                    ssrc: e.ssrc,
+                    payload_type: self.payload_type,
+                    rtx: RTPRtxParameters {
+                        ssrc: if rtx_enabled && (e.rid.is_empty() || repair_rid_supported) {
+                            e.rtx_ssrc
                         } else {
-                            RTPCodecType::default()
+                            0
                         },
-                        &[RTPTransceiverDirection::Sendonly],
-                    )
-                    .await,
-                encodings: vec![RTPEncodingParameters {
-                    rid: String::new(),
-                    ssrc: self.ssrc,
-                    payload_type: self.payload_type,
-                }],
-            }
+                    },
+                })
+                .collect()
+        };
+        let mut send_parameters = RTPSendParameters {
+            rtp_parameters: self
+                .media_engine
+                .get_rtp_parameters_by_kind(kind, &[RTPTransceiverDirection::Sendonly])
+                .await,
+            encodings,
         };
 
         let codecs = {
@@ -221,13 +327,22 @@ impl RTPSender {
 
     /// track returns the RTCRtpTransceiver track, or nil
     pub async fn track(&self) -> Option<Arc<dyn TrackLocal + Send + Sync>> {
-        let track = self.track.lock().await;
-        track.clone()
+        let track_encodings = self.track_encodings.lock().await;
+        track_encodings.first().and_then(|e| e.track.clone())
+    }
+
+    /// kind returns the kind of the track currently sent, if any
+    async fn kind(&self) -> RTPCodecType {
+        if let Some(t) = self.track().await {
+            t.kind()
+        } else {
+            RTPCodecType::default()
+        }
     }
 
     /// replace_track replaces the track currently being used as the sender's source with a new TrackLocal.
     /// The new track must be of the same media kind (audio, video, etc) and switching the track should not
-    /// require negotiation.
+    /// require negotiation. The track of a simulcast sender can only be replaced by None.
     pub async fn replace_track(
         &self,
         track: Option<Arc<dyn TrackLocal + Send + Sync>>,
@@ -241,26 +356,37 @@ impl RTPSender {
             } else {
                 //TODO: what about None tr?
             }
+
+            let track_encodings = self.track_encodings.lock().await;
+            if track_encodings.len() > 1 {
+                return Err(Error::ErrRTPSenderNewTrackHasIncorrectEnvelope.into());
+            }
         }
 
-        if self.has_sent().await {
-            let t = self.track.lock().await;
-            if let Some(track) = &*t {
-                let context = self.context.lock().await;
-                track.unbind(&*context).await?;
+        let has_sent = self.has_sent().await;
+        let mut track_encodings = self.track_encodings.lock().await;
+
+        if has_sent {
+            for encoding in &*track_encodings {
+                if let Some(t) = &encoding.track {
+                    t.unbind(&encoding.context).await?;
+                }
             }
         }
 
-        if !self.has_sent().await || track.is_none() {
-            let mut t = self.track.lock().await;
-            *t = track;
+        if !has_sent || track.is_none() {
+            for encoding in &mut *track_encodings {
+                encoding.track = track.clone();
+            }
             return Ok(());
         }
 
-        let context = {
-            let context = self.context.lock().await;
-            context.clone()
+        // A track can only be replaced on a sender with a single encoding
+        let encoding = match track_encodings.first_mut() {
+            Some(encoding) => encoding,
+            None => return Err(Error::ErrRTPSenderTrackNil.into()),
         };
+        let context = encoding.context.clone();
 
         let result = if let Some(t) = &track {
             let new_context = TrackLocalContext {
@@ -281,8 +407,7 @@ impl RTPSender {
         match result {
             Err(err) => {
                 // Re-bind the original track
-                let track = self.track.lock().await;
-                if let Some(t) = &*track {
+                if let Some(t) = &encoding.track {
                     t.bind(&context).await?;
                 }
 
@@ -291,12 +416,10 @@ impl RTPSender {
             Ok(codec) => {
                 // Codec has changed
                 if self.payload_type != codec.payload_type {
-                    let mut context = self.context.lock().await;
-                    context.params.codecs = vec![codec];
+                    encoding.context.params.codecs = vec![codec];
                 }
 
-                let mut t = self.track.lock().await;
-                *t = track;
+                encoding.track = track;
 
                 Ok(())
             }
@@ -309,64 +432,80 @@ impl RTPSender {
             return Err(Error::ErrRTPSenderSendAlreadyCalled.into());
         }
 
-        let write_stream = Arc::new(InterceptorToTrackLocalWriter::new());
-        let (context, stream_info) = {
-            let track = self.track.lock().await;
-            let mut context = TrackLocalContext {
-                id: self.id.clone(),
-                params: self
-                    .media_engine
-                    .get_rtp_parameters_by_kind(
-                        if let Some(t) = &*track {
-                            t.kind()
-                        } else {
-                            RTPCodecType::default()
-                        },
-                        &[RTPTransceiverDirection::Sendonly],
-                    )
-                    .await,
-                ssrc: parameters.encodings[0].ssrc,
-                write_stream: Some(
-                    Arc::clone(&write_stream) as Arc<dyn TrackLocalWriter + Send + Sync>
-                ),
-            };
-
-            let codec = if let Some(t) = &*track {
-                t.bind(&context).await?
-            } else {
-                RTPCodecParameters::default()
-            };
-            let payload_type = codec.payload_type;
-            let capability = codec.capability.clone();
-            context.params.codecs = vec![codec];
-            let stream_info = create_stream_info(
-                self.id.clone(),
-                parameters.encodings[0].ssrc,
-                payload_type,
-                capability,
-                &parameters.rtp_parameters.header_extensions,
-            );
-
-            (context, stream_info)
-        };
+        let kind = self.kind().await;
 
-        let srtp_rtp_writer = Arc::clone(&self.srtp_stream) as Arc<dyn RTPWriter + Send + Sync>;
-        let rtp_interceptor = self
-            .interceptor
-            .bind_local_stream(&stream_info, srtp_rtp_writer)
-            .await;
         {
-            let mut interceptor_rtp_writer = write_stream.interceptor_rtp_writer.lock().await;
-            *interceptor_rtp_writer = Some(rtp_interceptor);
-        }
+            let mut track_encodings = self.track_encodings.lock().await;
+            for (idx, encoding) in track_encodings.iter_mut().enumerate() {
+                let (ssrc, rtx_ssrc) = parameters
+                    .encodings
+                    .get(idx)
+                    .map_or((encoding.ssrc, 0), |e| (e.ssrc, e.rtx.ssrc));
+
+                let write_stream = if encoding.rid.is_empty() {
+                    Arc::new(InterceptorToTrackLocalWriter::new(Arc::clone(&self.paused)))
+                } else {
+                    Arc::new(InterceptorToTrackLocalWriter::with_header_extensions(
+                        self.simulcast_header_extensions(&encoding.rid).await,
+                        Arc::clone(&self.paused),
+                    ))
+                };
+                let mut context = TrackLocalContext {
+                    id: self.id.clone(),
+                    params: self
+                        .media_engine
+                        .get_rtp_parameters_by_kind(kind, &[RTPTransceiverDirection::Sendonly])
+                        .await,
+                    ssrc,
+                    write_stream: Some(
+                        Arc::clone(&write_stream) as Arc<dyn TrackLocalWriter + Send + Sync>
+                    ),
+                };
+
+                let codec = if let Some(t) = &encoding.track {
+                    t.bind(&context).await?
+                } else {
+                    RTPCodecParameters::default()
+                };
+                let payload_type = codec.payload_type;
+                let capability = codec.capability.clone();
+                let rtx_payload_type = if rtx_ssrc != 0 {
+                    find_rtx_payload_type(payload_type, &context.params.codecs)
+                } else {
+                    None
+                };
+                context.params.codecs = vec![codec];
+                let mut stream_info = create_stream_info(
+                    self.id.clone(),
+                    ssrc,
+                    payload_type,
+                    capability,
+                    &parameters.rtp_parameters.header_extensions,
+                );
+                if let Some(rtx_payload_type) = rtx_payload_type {
+                    stream_info
+                        .attributes
+                        .insert(ATTRIBUTE_RTX_SSRC, rtx_ssrc as usize);
+                    stream_info
+                        .attributes
+                        .insert(ATTRIBUTE_RTX_PAYLOAD_TYPE, rtx_payload_type as usize);
+                }
 
-        {
-            let mut ctx = self.context.lock().await;
-            *ctx = context;
-        }
-        {
-            let mut si = self.stream_info.lock().await;
-            *si = stream_info;
+                let srtp_rtp_writer =
+                    Arc::clone(&encoding.srtp_stream) as Arc<dyn RTPWriter + Send + Sync>;
+                let rtp_interceptor = self
+                    .interceptor
+                    .bind_local_stream(&stream_info, srtp_rtp_writer)
+                    .await;
+                {
+                    let mut interceptor_rtp_writer =
+                        write_stream.interceptor_rtp_writer.lock().await;
+                    *interceptor_rtp_writer = Some(rtp_interceptor);
+                }
+
+                encoding.context = context;
+                encoding.stream_info = stream_info;
+            }
         }
 
         {
@@ -394,22 +533,276 @@ impl RTPSender {
 
         self.replace_track(None).await?;
 
+        let mut errs = vec![];
         {
-            let stream_info = self.stream_info.lock().await;
-            self.interceptor.unbind_local_stream(&*stream_info).await;
+            let track_encodings = self.track_encodings.lock().await;
+            for encoding in &*track_encodings {
+                self.interceptor
+                    .unbind_local_stream(&encoding.stream_info)
+                    .await;
+
+                if let Err(err) = encoding.srtp_stream.close().await {
+                    errs.push(err);
+                }
+            }
         }
 
-        self.srtp_stream.close().await
+        flatten_errs(errs)
     }
 
-    /// read reads incoming RTCP for this RTPReceiver
+    /// read reads incoming RTCP for this RTPSender
     pub async fn read(&self, b: &mut [u8]) -> Result<(usize, Attributes)> {
-        self.internal.read(b).await
+        self.internal.wait_for_send().await?;
+
+        let rtcp_interceptor = {
+            let track_encodings = self.track_encodings.lock().await;
+            track_encodings
+                .first()
+                .map(|e| Arc::clone(&e.rtcp_interceptor))
+        };
+        if let Some(rtcp_interceptor) = rtcp_interceptor {
+            let a = Attributes::new();
+            let (n, a) = rtcp_interceptor.read(b, &a).await?;
+            self.update_target_bitrate(&a).await;
+            Ok((n, a))
+        } else {
+            Err(Error::ErrInterceptorNotBind.into())
+        }
     }
 
     /// read_rtcp is a convenience method that wraps Read and unmarshals for you.
     pub async fn read_rtcp(&self) -> Result<(Box<dyn rtcp::packet::Packet>, Attributes)> {
-        self.internal.read_rtcp().await
+        let mut b = vec![0u8; RECEIVE_MTU];
+        let (n, attributes) = self.read(&mut b).await?;
+
+        let mut buf = &b[..n];
+        let pkts = rtcp::packet::unmarshal(&mut buf)?;
+
+        Ok((pkts, attributes))
+    }
+
+    /// read_simulcast reads incoming RTCP for this RTPSender for given rid
+    pub async fn read_simulcast(&self, b: &mut [u8], rid: &str) -> Result<(usize, Attributes)> {
+        self.internal.wait_for_send().await?;
+
+        let rtcp_interceptor = {
+            let track_encodings = self.track_encodings.lock().await;
+            track_encodings
+                .iter()
+                .find(|e| e.rid == rid)
+                .map(|e| Arc::clone(&e.rtcp_interceptor))
+        };
+        if let Some(rtcp_interceptor) = rtcp_interceptor {
+            let a = Attributes::new();
+            let (n, a) = rtcp_interceptor.read(b, &a).await?;
+            self.update_target_bitrate(&a).await;
+            Ok((n, a))
+        } else {
+            Err(Error::ErrRTPSenderForRIDTrackEncodingNotFound.into())
+        }
+    }
+
+    /// read_simulcast_rtcp is a convenience method that wraps ReadSimulcast and unmarshal for you
+    pub async fn read_simulcast_rtcp(
+        &self,
+        rid: &str,
+    ) -> Result<(Box<dyn rtcp::packet::Packet>, Attributes)> {
+        let mut b = vec![0u8; RECEIVE_MTU];
+        let (n, attributes) = self.read_simulcast(&mut b, rid).await?;
+
+        let mut buf = &b[..n];
+        let pkts = rtcp::packet::unmarshal(&mut buf)?;
+
+        Ok((pkts, attributes))
+    }
+
+    /// on_target_bitrate_change sets an event handler which is invoked when the bandwidth
+    /// estimated by the congestion controller changes, with the new target bitrate in bits
+    /// per second. Estimates are only updated while the application reads the RTCP of this
+    /// RTPSender, see configure_congestion_control.
+    pub async fn on_target_bitrate_change(&self, f: OnTargetBitrateChangeHdlrFn) {
+        let mut handler = self.on_target_bitrate_change_handler.lock().await;
+        *handler = Some(f);
+    }
+
+    /// target_bitrate returns the latest bandwidth estimated by the congestion controller,
+    /// in bits per second, or 0 if no estimate was made yet.
+    pub fn target_bitrate(&self) -> u64 {
+        self.target_bitrate.load(Ordering::SeqCst)
+    }
+
+    /// update_target_bitrate fires on_target_bitrate_change when the estimate carried
+    /// by the attributes of a RTCP read changed.
+    async fn update_target_bitrate(&self, a: &Attributes) {
+        let target_bitrate = match a.get(&ATTRIBUTE_TARGET_BITRATE) {
+            Some(target_bitrate) => *target_bitrate as u64,
+            None => return,
+        };
+        if self.target_bitrate.swap(target_bitrate, Ordering::SeqCst) == target_bitrate {
+            return;
+        }
+
+        let mut handler = self.on_target_bitrate_change_handler.lock().await;
+        if let Some(f) = &mut *handler {
+            f(target_bitrate).await;
+        }
+    }
+
+    /// simulcast_header_extensions returns the MID and RID header extensions, keyed by
+    /// their negotiated id, that identify the simulcast layer rid sent by this RTPSender.
+    /// Extensions which haven't been negotiated are left out.
+    async fn simulcast_header_extensions(&self, rid: &str) -> Vec<(u8, Bytes)> {
+        let mut header_extensions = vec![];
+
+        let mid = {
+            let tr = self.tr.lock().await;
+            if let Some(t) = &*tr {
+                t.mid().await
+            } else {
+                String::new()
+            }
+        };
+
+        for (uri, value) in [
+            (sdp::extmap::SDES_MID_URI, mid),
+            (sdp::extmap::SDES_RTP_STREAM_ID_URI, rid.to_owned()),
+        ]
+        .iter()
+        {
+            let (id, audio_supported, video_supported) = self
+                .media_engine
+                .get_header_extension_id(RTPHeaderExtensionCapability {
+                    uri: uri.to_string(),
+                })
+                .await;
+            if !value.is_empty() && (audio_supported || video_supported) {
+                header_extensions.push((id as u8, Bytes::from(value.clone())));
+            }
+        }
+
+        header_extensions
+    }
+
+    /// collect_stats reports the OutboundRTPStreamStats of every encoding sent by this
+    /// RTPSender, and their RemoteInboundRTPStreamStats once the remote peer has
+    /// sent a reception report about them
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector, mid: &str) {
+        if !self.has_sent().await {
+            return;
+        }
+
+        let kind = self.kind().await;
+        let encodings: Vec<(
+            SSRC,
+            String,
+            Option<RTPCodecParameters>,
+            Arc<SrtpWriterFuture>,
+        )> = {
+            let track_encodings = self.track_encodings.lock().await;
+            track_encodings
+                .iter()
+                .map(|e| {
+                    (
+                        e.ssrc,
+                        e.rid.clone(),
+                        e.context.params.codecs.first().cloned(),
+                        Arc::clone(&e.srtp_stream),
+                    )
+                })
+                .collect()
+        };
+
+        for (ssrc, rid, codec, srtp_stream) in encodings {
+            self.collect_encoding_stats(collector, mid, kind, ssrc, rid, codec, &srtp_stream)
+                .await;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn collect_encoding_stats(
+        &self,
+        collector: &StatsCollector,
+        mid: &str,
+        kind: RTPCodecType,
+        ssrc: SSRC,
+        rid: String,
+        codec: Option<RTPCodecParameters>,
+        srtp_stream: &SrtpWriterFuture,
+    ) {
+        let (codec_id, clock_rate) = if let Some(codec) = &codec {
+            (
+                self.media_engine.get_codec_stats_id(codec.payload_type),
+                codec.capability.clock_rate,
+            )
+        } else {
+            (String::new(), 0)
+        };
+
+        let outbound_id = format!("OutboundRTP-{}", ssrc);
+        let remote_inbound_id = format!("RemoteInboundRTP-{}", ssrc);
+
+        let remote_inbound = {
+            let counters = srtp_stream.remote_inbound_counters.lock().await;
+            counters.clone()
+        };
+
+        let outbound = {
+            let counters = srtp_stream.outbound_counters.lock().await;
+            counters.clone()
+        };
+        collector
+            .collect(StatsReportType::OutboundRTP(OutboundRTPStreamStats {
+                timestamp: stats_timestamp_now(),
+                stats_type: StatsType::OutboundRTP,
+                id: outbound_id.clone(),
+                ssrc,
+                kind,
+                transport_id: ICE_TRANSPORT_STATS_ID.to_owned(),
+                codec_id: codec_id.clone(),
+                mid: mid.to_owned(),
+                rid,
+                remote_id: if remote_inbound.received_report {
+                    remote_inbound_id.clone()
+                } else {
+                    String::new()
+                },
+                packets_sent: outbound.packets_sent,
+                bytes_sent: outbound.bytes_sent,
+                header_bytes_sent: outbound.header_bytes_sent,
+                last_packet_sent_timestamp: outbound
+                    .last_packet_sent_timestamp
+                    .map_or(0.0, stats_timestamp_from),
+            }))
+            .await;
+
+        if remote_inbound.received_report {
+            collector
+                .collect(StatsReportType::RemoteInboundRTP(
+                    RemoteInboundRTPStreamStats {
+                        timestamp: remote_inbound
+                            .last_report_timestamp
+                            .map_or_else(stats_timestamp_now, stats_timestamp_from),
+                        stats_type: StatsType::RemoteInboundRTP,
+                        id: remote_inbound_id,
+                        ssrc,
+                        kind,
+                        transport_id: ICE_TRANSPORT_STATS_ID.to_owned(),
+                        codec_id,
+                        local_id: outbound_id,
+                        packets_lost: remote_inbound.packets_lost,
+                        jitter: if clock_rate != 0 {
+                            remote_inbound.jitter as f64 / clock_rate as f64
+                        } else {
+                            0.0
+                        },
+                        fraction_lost: remote_inbound.fraction_lost,
+                        round_trip_time: remote_inbound.round_trip_time,
+                        total_round_trip_time: remote_inbound.total_round_trip_time,
+                        round_trip_time_measurements: remote_inbound.round_trip_time_measurements,
+                    },
+                ))
+                .await;
+        }
     }
 
     /// has_sent tells if data has been ever sent for this instance
@@ -1,17 +1,25 @@
 use super::*;
+use crate::api::interceptor_registry::{
+    configure_congestion_control, configure_nack, configure_twcc, Registry,
+};
 use crate::api::media_engine::{MIME_TYPE_H264, MIME_TYPE_OPUS, MIME_TYPE_VP8, MIME_TYPE_VP9};
 use crate::api::setting_engine::SettingEngine;
 use crate::api::APIBuilder;
+use crate::media::interceptor::{ATTRIBUTE_RTX_PAYLOAD_TYPE, ATTRIBUTE_RTX_SSRC};
 use crate::media::rtp::rtp_codec::RTPCodecCapability;
 use crate::media::rtp::rtp_receiver::RTPReceiver;
+use crate::media::track::track_local::track_local_static_rtp::TrackLocalStaticRTP;
 use crate::media::track::track_local::track_local_static_sample::TrackLocalStaticSample;
 use crate::media::track::track_remote::TrackRemote;
+use crate::peer::configuration::Configuration;
 use crate::peer::peer_connection::peer_connection_test::{
     close_pair_now, create_vnet_pair, new_pair, send_video_until_done, signal_pair,
     until_connection_state,
 };
 use crate::peer::peer_connection_state::PeerConnectionState;
+use async_trait::async_trait;
 use bytes::Bytes;
+use interceptor::{RTCPWriter, RTPReader};
 use std::sync::atomic::AtomicU64;
 use tokio::time::Duration;
 use waitgroup::WaitGroup;
@@ -132,7 +140,11 @@ async fn test_rtp_sender_get_parameters() -> Result<()> {
         let parameters = sender.get_parameters().await;
         assert_ne!(0, parameters.rtp_parameters.codecs.len());
         assert_eq!(1, parameters.encodings.len());
-        assert_eq!(sender.ssrc, parameters.encodings[0].ssrc);
+        let ssrc = {
+            let track_encodings = sender.track_encodings.lock().await;
+            track_encodings[0].ssrc
+        };
+        assert_eq!(ssrc, parameters.encodings[0].ssrc);
     } else {
         assert!(false);
     }
@@ -322,3 +334,558 @@ async fn test_rtp_sender_replace_track_invalid_codec_change() -> Result<()> {
     close_pair_now(&sender, &receiver).await;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_rtp_sender_add_encoding() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let pc = api.new_peer_connection(Configuration::default()).await?;
+
+    let new_track = |id: &str, rid: &str, stream_id: &str, mime_type: &str| {
+        Arc::new(TrackLocalStaticRTP::new_with_rid(
+            RTPCodecCapability {
+                mime_type: mime_type.to_owned(),
+                ..Default::default()
+            },
+            id.to_owned(),
+            rid.to_owned(),
+            stream_id.to_owned(),
+        )) as Arc<dyn TrackLocal + Send + Sync>
+    };
+
+    let track = new_track("video", "", "webrtc-rs", MIME_TYPE_VP8);
+    let rtp_sender = pc.add_track(track).await?;
+
+    let result = rtp_sender
+        .add_encoding(new_track("video", "", "webrtc-rs", MIME_TYPE_VP8))
+        .await;
+    assert!(Error::ErrRTPSenderRidNil.equal(&result.unwrap_err()));
+
+    let result = rtp_sender
+        .add_encoding(new_track("video", "h", "webrtc-rs", MIME_TYPE_VP8))
+        .await;
+    assert!(Error::ErrRTPSenderNoBaseEncoding.equal(&result.unwrap_err()));
+
+    let track = new_track("video", "q", "webrtc-rs", MIME_TYPE_VP8);
+    let rtp_sender = pc.add_track(track).await?;
+
+    for track in vec![
+        new_track("video-foobar", "h", "webrtc-rs", MIME_TYPE_VP8),
+        new_track("video", "h", "webrtc-rs-foobar", MIME_TYPE_VP8),
+        new_track("video", "h", "webrtc-rs", MIME_TYPE_OPUS),
+    ] {
+        let result = rtp_sender.add_encoding(track).await;
+        assert!(Error::ErrRTPSenderBaseEncodingMismatch.equal(&result.unwrap_err()));
+    }
+
+    let result = rtp_sender
+        .add_encoding(new_track("video", "q", "webrtc-rs", MIME_TYPE_VP8))
+        .await;
+    assert!(Error::ErrRTPSenderRIDCollision.equal(&result.unwrap_err()));
+
+    rtp_sender
+        .add_encoding(new_track("video", "h", "webrtc-rs", MIME_TYPE_VP8))
+        .await?;
+
+    let parameters = rtp_sender.get_parameters().await;
+    assert_eq!(2, parameters.encodings.len());
+    assert_eq!("q", parameters.encodings[0].rid);
+    assert_eq!("h", parameters.encodings[1].rid);
+    assert_ne!(parameters.encodings[0].ssrc, parameters.encodings[1].ssrc);
+
+    rtp_sender.send(&parameters).await?;
+    let result = rtp_sender
+        .add_encoding(new_track("video", "f", "webrtc-rs", MIME_TYPE_VP8))
+        .await;
+    assert!(Error::ErrRTPSenderSendAlreadyCalled.equal(&result.unwrap_err()));
+
+    let result = rtp_sender
+        .replace_track(Some(new_track("video", "", "webrtc-rs", MIME_TYPE_VP8)))
+        .await;
+    assert!(Error::ErrRTPSenderNewTrackHasIncorrectEnvelope.equal(&result.unwrap_err()));
+
+    rtp_sender.stop().await?;
+    let result = rtp_sender
+        .add_encoding(new_track("video", "f", "webrtc-rs", MIME_TYPE_VP8))
+        .await;
+    assert!(Error::ErrRTPSenderStopped.equal(&result.unwrap_err()));
+
+    pc.close().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rtp_sender_simulcast() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    for uri in &[
+        sdp::extmap::SDES_MID_URI,
+        sdp::extmap::SDES_RTP_STREAM_ID_URI,
+    ] {
+        m.register_header_extension(
+            RTPHeaderExtensionCapability {
+                uri: uri.to_string(),
+            },
+            RTPCodecType::Video,
+            vec![],
+        )
+        .await?;
+    }
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut sender, mut receiver) = new_pair(&api).await?;
+
+    let rids = ["q", "h", "f"];
+    let mut tracks = vec![];
+    for rid in &rids {
+        tracks.push(Arc::new(TrackLocalStaticRTP::new_with_rid(
+            RTPCodecCapability {
+                mime_type: MIME_TYPE_VP8.to_owned(),
+                ..Default::default()
+            },
+            "video".to_owned(),
+            rid.to_string(),
+            "webrtc-rs".to_owned(),
+        )));
+    }
+
+    let rtp_sender = sender
+        .add_track(Arc::clone(&tracks[0]) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+    for track in &tracks[1..] {
+        rtp_sender
+            .add_encoding(Arc::clone(track) as Arc<dyn TrackLocal + Send + Sync>)
+            .await?;
+    }
+
+    let (seen_rid_tx, mut seen_rid_rx) = mpsc::channel::<String>(rids.len());
+    let seen_rid_tx = Arc::new(seen_rid_tx);
+    receiver
+        .on_track(Box::new(
+            move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTPReceiver>>| {
+                let seen_rid_tx2 = Arc::clone(&seen_rid_tx);
+                Box::pin(async move {
+                    if let Some(t) = track {
+                        let _ = seen_rid_tx2.send(t.rid().to_owned()).await;
+                    }
+                })
+            },
+        ))
+        .await;
+
+    signal_pair(&mut sender, &mut receiver).await?;
+
+    let offer = sender
+        .local_description()
+        .await
+        .ok_or_else(|| Error::new("non local description".to_owned()))?;
+    for rid in &rids {
+        assert!(offer.serde.sdp.contains(&format!("a=rid:{} send", rid)));
+    }
+    assert!(offer.serde.sdp.contains("a=simulcast:send q;h;f"));
+
+    let (done_tx, mut done_rx) = mpsc::channel::<()>(1);
+    tokio::spawn(async move {
+        let mut sequence_number = 0u16;
+        loop {
+            let timeout = tokio::time::sleep(Duration::from_millis(20));
+            tokio::pin!(timeout);
+
+            tokio::select! {
+                _ = timeout.as_mut() => {
+                    sequence_number = sequence_number.wrapping_add(1);
+                    for track in &tracks {
+                        let _ = track
+                            .write_rtp(&rtp::packet::Packet {
+                                header: rtp::header::Header {
+                                    version: 2,
+                                    sequence_number,
+                                    ..Default::default()
+                                },
+                                payload: Bytes::from_static(&[0x00, 0x01]),
+                            })
+                            .await;
+                    }
+                }
+                _ = done_rx.recv() => break,
+            }
+        }
+    });
+
+    let mut seen_rids = vec![];
+    while seen_rids.len() < rids.len() {
+        if let Some(rid) = seen_rid_rx.recv().await {
+            seen_rids.push(rid);
+        }
+    }
+    seen_rids.sort();
+    assert_eq!(vec!["f", "h", "q"], seen_rids);
+
+    let _ = done_tx.send(()).await;
+    close_pair_now(&sender, &receiver).await;
+    Ok(())
+}
+
+/// DropOnceInterceptor drops the first copy of a packet of every remote stream, so
+/// that it is nacked before the nack generator sees it
+struct DropOnceInterceptor {
+    sequence_number: u16,
+}
+
+struct DropOnceRTPReader {
+    sequence_number: u16,
+    dropped: AtomicBool,
+    parent_rtp_reader: Arc<dyn RTPReader + Send + Sync>,
+}
+
+#[async_trait]
+impl RTPReader for DropOnceRTPReader {
+    async fn read(&self, buf: &mut [u8], a: &Attributes) -> Result<(usize, Attributes)> {
+        loop {
+            let (n, attributes) = self.parent_rtp_reader.read(buf, a).await?;
+            if n >= 4
+                && u16::from_be_bytes([buf[2], buf[3]]) == self.sequence_number
+                && !self.dropped.swap(true, Ordering::SeqCst)
+            {
+                continue;
+            }
+            return Ok((n, attributes));
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for DropOnceInterceptor {
+    async fn bind_rtcp_reader(
+        &self,
+        reader: Arc<dyn RTCPReader + Send + Sync>,
+    ) -> Arc<dyn RTCPReader + Send + Sync> {
+        reader
+    }
+
+    async fn bind_rtcp_writer(
+        &self,
+        writer: Arc<dyn RTCPWriter + Send + Sync>,
+    ) -> Arc<dyn RTCPWriter + Send + Sync> {
+        writer
+    }
+
+    async fn bind_local_stream(
+        &self,
+        _info: &StreamInfo,
+        writer: Arc<dyn RTPWriter + Send + Sync>,
+    ) -> Arc<dyn RTPWriter + Send + Sync> {
+        writer
+    }
+
+    async fn unbind_local_stream(&self, _info: &StreamInfo) {}
+
+    async fn bind_remote_stream(
+        &self,
+        _info: &StreamInfo,
+        reader: Arc<dyn RTPReader + Send + Sync>,
+    ) -> Arc<dyn RTPReader + Send + Sync> {
+        Arc::new(DropOnceRTPReader {
+            sequence_number: self.sequence_number,
+            dropped: AtomicBool::new(false),
+            parent_rtp_reader: reader,
+        })
+    }
+
+    async fn unbind_remote_stream(&self, _info: &StreamInfo) {}
+
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_rtp_sender_simulcast_rtx() -> Result<()> {
+    const DROPPED: u16 = 50;
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    for uri in &[
+        sdp::extmap::SDES_MID_URI,
+        sdp::extmap::SDES_RTP_STREAM_ID_URI,
+        SDES_REPAIR_RTP_STREAM_ID_URI,
+    ] {
+        m.register_header_extension(
+            RTPHeaderExtensionCapability {
+                uri: uri.to_string(),
+            },
+            RTPCodecType::Video,
+            vec![],
+        )
+        .await?;
+    }
+    // the packet is dropped before the nack generator of the receiver reads it
+    let registry = Registry::new().with_interceptor_builder(|| {
+        Arc::new(DropOnceInterceptor {
+            sequence_number: DROPPED,
+        })
+    });
+    let registry = configure_nack(registry, &mut m)?;
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_interceptor_registry(registry)
+        .build();
+
+    let (mut sender, mut receiver) = new_pair(&api).await?;
+
+    let rids = ["q", "h", "f"];
+    let mut tracks = vec![];
+    for rid in &rids {
+        tracks.push(Arc::new(TrackLocalStaticRTP::new_with_rid(
+            RTPCodecCapability {
+                mime_type: MIME_TYPE_VP8.to_owned(),
+                ..Default::default()
+            },
+            "video".to_owned(),
+            rid.to_string(),
+            "webrtc-rs".to_owned(),
+        )));
+    }
+
+    let rtp_sender = sender
+        .add_track(Arc::clone(&tracks[0]) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+    for track in &tracks[1..] {
+        rtp_sender
+            .add_encoding(Arc::clone(track) as Arc<dyn TrackLocal + Send + Sync>)
+            .await?;
+    }
+
+    // the nacks of every layer are answered while its RTCP is read
+    for &rid in &rids {
+        let reading_sender = Arc::clone(&rtp_sender);
+        tokio::spawn(async move { while reading_sender.read_simulcast_rtcp(rid).await.is_ok() {} });
+    }
+
+    let (repaired_tx, mut repaired_rx) = mpsc::channel::<String>(rids.len());
+    let repaired_tx = Arc::new(repaired_tx);
+    receiver
+        .on_track(Box::new(
+            move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTPReceiver>>| {
+                let repaired_tx2 = Arc::clone(&repaired_tx);
+                Box::pin(async move {
+                    if let Some(t) = track {
+                        // the other layers are only announced once the handler returns
+                        tokio::spawn(async move {
+                            while let Ok((pkt, _)) = t.read_rtp().await {
+                                if pkt.header.sequence_number == DROPPED {
+                                    let _ = repaired_tx2.send(t.rid().to_owned()).await;
+                                }
+                            }
+                        });
+                    }
+                })
+            },
+        ))
+        .await;
+
+    signal_pair(&mut sender, &mut receiver).await?;
+
+    let parameters = rtp_sender.get_parameters().await;
+    for encoding in &parameters.encodings {
+        assert_ne!(0, encoding.rtx.ssrc);
+    }
+
+    let (done_tx, mut done_rx) = mpsc::channel::<()>(1);
+    tokio::spawn(async move {
+        let mut sequence_number = 0u16;
+        loop {
+            let timeout = tokio::time::sleep(Duration::from_millis(20));
+            tokio::pin!(timeout);
+
+            tokio::select! {
+                _ = timeout.as_mut() => {
+                    sequence_number = sequence_number.wrapping_add(1);
+                    for track in &tracks {
+                        let _ = track
+                            .write_rtp(&rtp::packet::Packet {
+                                header: rtp::header::Header {
+                                    version: 2,
+                                    sequence_number,
+                                    ..Default::default()
+                                },
+                                payload: Bytes::from_static(&[0x00, 0x01]),
+                            })
+                            .await;
+                    }
+                }
+                _ = done_rx.recv() => break,
+            }
+        }
+    });
+
+    // every layer gets the dropped packet back from its own repair stream
+    let mut repaired_rids = vec![];
+    while repaired_rids.len() < rids.len() {
+        let rid = tokio::time::timeout(Duration::from_secs(10), repaired_rx.recv())
+            .await?
+            .unwrap();
+        repaired_rids.push(rid);
+    }
+    repaired_rids.sort();
+    assert_eq!(vec!["f", "h", "q"], repaired_rids);
+
+    let _ = done_tx.send(()).await;
+    close_pair_now(&sender, &receiver).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rtp_sender_rtx() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut offerer, mut answerer) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTPCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    let rtp_sender = offerer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    signal_pair(&mut offerer, &mut answerer).await?;
+
+    let (ssrc, rtx_ssrc) = {
+        let track_encodings = rtp_sender.track_encodings.lock().await;
+        (track_encodings[0].ssrc, track_encodings[0].rtx_ssrc)
+    };
+    let parameters = rtp_sender.get_parameters().await;
+    assert_eq!(rtx_ssrc, parameters.encodings[0].rtx.ssrc);
+
+    let offer = offerer
+        .local_description()
+        .await
+        .ok_or_else(|| Error::new("non local description".to_owned()))?;
+    assert!(offer
+        .serde
+        .sdp
+        .contains(&format!("a=ssrc-group:FID {} {}", ssrc, rtx_ssrc)));
+
+    // the answerer receives the repair flow as part of the original track
+    let remote = answerer
+        .remote_description()
+        .await
+        .and_then(|rd| rd.parsed)
+        .ok_or_else(|| Error::new("non remote description".to_owned()))?;
+    let tracks = crate::peer::sdp::track_details_from_sdp(&remote);
+    assert_eq!(1, tracks.len());
+    assert_eq!(ssrc, tracks[0].ssrc);
+    assert_eq!(rtx_ssrc, tracks[0].repair_ssrc);
+
+    // once sending, nacked packets are answered on the repair flow
+    let mut attributes = Attributes::new();
+    for _ in 0..500 {
+        attributes = {
+            let track_encodings = rtp_sender.track_encodings.lock().await;
+            track_encodings[0].stream_info.attributes.clone()
+        };
+        if !attributes.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(
+        Some(&(rtx_ssrc as usize)),
+        attributes.get(&ATTRIBUTE_RTX_SSRC)
+    );
+    assert_eq!(Some(&97), attributes.get(&ATTRIBUTE_RTX_PAYLOAD_TYPE));
+
+    close_pair_now(&offerer, &answerer).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rtp_sender_target_bitrate() -> Result<()> {
+    let mut offer_media_engine = MediaEngine::default();
+    offer_media_engine.register_default_codecs()?;
+    let offer_registry =
+        configure_congestion_control(Registry::new(), &mut offer_media_engine).await?;
+    let mut offerer = APIBuilder::new()
+        .with_media_engine(offer_media_engine)
+        .with_interceptor_registry(offer_registry)
+        .build()
+        .new_peer_connection(Configuration::default())
+        .await?;
+
+    let mut answer_media_engine = MediaEngine::default();
+    answer_media_engine.register_default_codecs()?;
+    let answer_registry = configure_twcc(Registry::new(), &mut answer_media_engine).await?;
+    let mut answerer = APIBuilder::new()
+        .with_media_engine(answer_media_engine)
+        .with_interceptor_registry(answer_registry)
+        .build()
+        .new_peer_connection(Configuration::default())
+        .await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTPCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    let rtp_sender = offerer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+    assert_eq!(0, rtp_sender.target_bitrate());
+
+    let (bitrate_tx, mut bitrate_rx) = mpsc::channel::<u64>(1);
+    rtp_sender
+        .on_target_bitrate_change(Box::new(move |bitrate: u64| {
+            let bitrate_tx2 = bitrate_tx.clone();
+            Box::pin(async move {
+                let _ = bitrate_tx2.try_send(bitrate);
+            })
+        }))
+        .await;
+
+    // feedback is generated about the packets read by the answerer
+    answerer
+        .on_track(Box::new(
+            move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTPReceiver>>| {
+                Box::pin(async move {
+                    if let Some(t) = track {
+                        while t.read_rtp().await.is_ok() {}
+                    }
+                })
+            },
+        ))
+        .await;
+
+    // and the estimate is updated while the offerer reads its RTCP, which only
+    // carries the transport-wide congestion control feedback of the answerer
+    let reading_sender = Arc::clone(&rtp_sender);
+    tokio::spawn(async move { while reading_sender.read_rtcp().await.is_ok() {} });
+
+    signal_pair(&mut offerer, &mut answerer).await?;
+
+    let (done_tx, done_rx) = mpsc::channel::<()>(1);
+    tokio::spawn(async move {
+        send_video_until_done(done_rx, vec![track], Bytes::from_static(&[0xAA; 1000])).await;
+    });
+
+    let bitrate = tokio::time::timeout(Duration::from_secs(10), bitrate_rx.recv())
+        .await?
+        .unwrap();
+    assert!(bitrate >= 30_000, "unexpected target bitrate {}", bitrate);
+    assert_ne!(0, rtp_sender.target_bitrate());
+
+    let _ = done_tx.send(()).await;
+    close_pair_now(&offerer, &answerer).await;
+    Ok(())
+}
@@ -11,24 +11,33 @@ use crate::media::rtp::PayloadType;
 use crate::media::track::track_local::TrackLocal;
 
 use anyhow::Result;
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use util::Unmarshal;
 
+pub(crate) type TriggerNegotiationNeededFn =
+    Box<dyn (Fn() -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>) + Send + Sync>;
+
 /// RTPTransceiver represents a combination of an RTPSender and an RTPReceiver that share a common mid.
 pub struct RTPTransceiver {
     mid: Mutex<String>,                        //atomic.Value
     sender: Mutex<Option<Arc<RTPSender>>>,     //atomic.Value
     receiver: Mutex<Option<Arc<RTPReceiver>>>, //atomic.Value
     direction: AtomicU8,                       //RTPTransceiverDirection, //atomic.Value
+    current_direction: AtomicU8,               //RTPTransceiverDirection, //atomic.Value
 
     codecs: Arc<Mutex<Vec<RTPCodecParameters>>>, // User provided codecs via set_codec_preferences
 
-    pub(crate) stopped: bool,
+    stopping: AtomicBool,
+    stopped: AtomicBool,
     pub(crate) kind: RTPCodecType,
 
     media_engine: Arc<MediaEngine>,
+
+    trigger_negotiation_needed: Mutex<Option<TriggerNegotiationNeededFn>>,
 }
 
 impl RTPTransceiver {
@@ -45,10 +54,13 @@ impl RTPTransceiver {
             sender: Mutex::new(None),
             receiver: Mutex::new(None),
             direction: AtomicU8::new(direction as u8),
+            current_direction: AtomicU8::new(RTPTransceiverDirection::Unspecified as u8),
             codecs: Arc::new(Mutex::new(codecs)),
-            stopped: false,
+            stopping: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
             kind,
             media_engine,
+            trigger_negotiation_needed: Mutex::new(None),
         });
 
         t.set_receiver(receiver).await;
@@ -146,6 +158,13 @@ impl RTPTransceiver {
         Ok(())
     }
 
+    /// unset_mid clears the RTPTransceiver's mid, when the media section it was assigned to
+    /// is rolled back before being negotiated.
+    pub(crate) async fn unset_mid(&self) {
+        let mut m = self.mid.lock().await;
+        m.clear();
+    }
+
     /// mid gets the Transceiver's mid value. When not already set, this value will be set in CreateOffer or create_answer.
     pub async fn mid(&self) -> String {
         let mid = self.mid.lock().await;
@@ -157,17 +176,92 @@ impl RTPTransceiver {
         self.kind
     }
 
-    /// direction returns the RTPTransceiver's current direction
+    /// direction returns the RTPTransceiver's preferred direction
     pub fn direction(&self) -> RTPTransceiverDirection {
         self.direction.load(Ordering::SeqCst).into()
     }
 
-    pub(crate) fn set_direction(&self, d: RTPTransceiverDirection) {
+    /// set_direction sets the RTPTransceiver's preferred direction. Changing it fires
+    /// on_negotiation_needed, the new direction applies once negotiated.
+    pub async fn set_direction(&self, d: RTPTransceiverDirection) {
+        let previous = self.direction.swap(d as u8, Ordering::SeqCst);
+        if previous != d as u8 {
+            self.trigger_negotiation_needed().await;
+        }
+    }
+
+    pub(crate) fn set_direction_internal(&self, d: RTPTransceiverDirection) {
         self.direction.store(d as u8, Ordering::SeqCst);
     }
 
-    /// stop irreversibly stops the RTPTransceiver
+    /// current_direction returns the direction negotiated by the last offer/answer exchange,
+    /// Unspecified until the RTPTransceiver has been negotiated
+    pub fn current_direction(&self) -> RTPTransceiverDirection {
+        self.current_direction.load(Ordering::SeqCst).into()
+    }
+
+    pub(crate) fn set_current_direction(&self, d: RTPTransceiverDirection) {
+        self.current_direction.store(d as u8, Ordering::SeqCst);
+    }
+
+    pub(crate) async fn set_trigger_negotiation_needed(
+        &self,
+        f: Option<TriggerNegotiationNeededFn>,
+    ) {
+        let mut trigger_negotiation_needed = self.trigger_negotiation_needed.lock().await;
+        *trigger_negotiation_needed = f;
+    }
+
+    async fn trigger_negotiation_needed(&self) {
+        let trigger_negotiation_needed = self.trigger_negotiation_needed.lock().await;
+        if let Some(f) = &*trigger_negotiation_needed {
+            f().await;
+        }
+    }
+
+    /// stop irreversibly stops the RTPTransceiver. Sending and receiving stop right away,
+    /// the next negotiation rejects its media section, whose m-line can then be reused
+    /// by a new RTPTransceiver.
+    /// https://www.w3.org/TR/webrtc/#dom-rtcrtptransceiver-stop
     pub async fn stop(&self) -> Result<()> {
+        if self.stopping.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        self.stop_sending_and_receiving().await?;
+
+        if self.mid().await.is_empty() {
+            // Never negotiated, there is no media section to reject
+            self.stopped.store(true, Ordering::SeqCst);
+        } else {
+            self.trigger_negotiation_needed().await;
+        }
+
+        Ok(())
+    }
+
+    /// stopping returns whether stop has been called, or the media section of the
+    /// RTPTransceiver has been rejected
+    pub fn stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// stopped returns whether the media section of the RTPTransceiver has been rejected
+    /// by a negotiation, or the PeerConnection closed
+    pub fn stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// set_stopped stops the RTPTransceiver once its media section has been rejected
+    pub(crate) async fn set_stopped(&self) -> Result<()> {
+        self.stopping.store(true, Ordering::SeqCst);
+        self.stopped.store(true, Ordering::SeqCst);
+        self.set_current_direction(RTPTransceiverDirection::Inactive);
+
+        self.stop_sending_and_receiving().await
+    }
+
+    async fn stop_sending_and_receiving(&self) -> Result<()> {
         {
             let s = self.sender.lock().await;
             if let Some(sender) = &*s {
@@ -181,7 +275,7 @@ impl RTPTransceiver {
             }
         }
 
-        self.set_direction(RTPTransceiverDirection::Inactive);
+        self.set_direction_internal(RTPTransceiverDirection::Inactive);
 
         Ok(())
     }
@@ -203,11 +297,11 @@ impl RTPTransceiver {
 
         let direction = self.direction();
         if !track_is_none && direction == RTPTransceiverDirection::Recvonly {
-            self.set_direction(RTPTransceiverDirection::Sendrecv);
+            self.set_direction_internal(RTPTransceiverDirection::Sendrecv);
         } else if !track_is_none && direction == RTPTransceiverDirection::Inactive {
-            self.set_direction(RTPTransceiverDirection::Sendonly);
+            self.set_direction_internal(RTPTransceiverDirection::Sendonly);
         } else if track_is_none && direction == RTPTransceiverDirection::Sendrecv {
-            self.set_direction(RTPTransceiverDirection::Recvonly);
+            self.set_direction_internal(RTPTransceiverDirection::Recvonly);
         } else if !track_is_none
             && (direction == RTPTransceiverDirection::Sendonly
                 || direction == RTPTransceiverDirection::Sendrecv)
@@ -218,7 +312,7 @@ impl RTPTransceiver {
             //} else if !track_is_none && self.direction == RTPTransceiverDirection::Sendrecv {
             // Similar to above, but for sendrecv transceiver.
         } else if track_is_none && direction == RTPTransceiverDirection::Sendonly {
-            self.set_direction(RTPTransceiverDirection::Inactive);
+            self.set_direction_internal(RTPTransceiverDirection::Inactive);
         } else {
             return Err(Error::ErrRTPTransceiverSetSendingInvalidState.into());
         }
@@ -265,6 +359,7 @@ pub(crate) async fn satisfy_type_and_direction(
     for possible_direction in get_preferred_directions() {
         for (i, t) in local_transceivers.iter().enumerate() {
             if t.mid().await.is_empty()
+                && !t.stopping()
                 && t.kind == remote_kind
                 && possible_direction == t.direction()
             {
@@ -282,12 +377,13 @@ pub(crate) fn handle_unknown_rtp_packet(
     buf: &[u8],
     mid_extension_id: u8,
     sid_extension_id: u8,
-) -> Result<(String, String, PayloadType)> {
+    rsid_extension_id: u8,
+) -> Result<(String, String, String, PayloadType)> {
     let mut reader = buf;
     let rp = rtp::packet::Packet::unmarshal(&mut reader)?;
 
     if !rp.header.extension {
-        return Ok((String::new(), String::new(), 0));
+        return Ok((String::new(), String::new(), String::new(), 0));
     }
 
     let payload_type = rp.header.payload_type;
@@ -304,5 +400,11 @@ pub(crate) fn handle_unknown_rtp_packet(
         String::new()
     };
 
-    Ok((mid, rid, payload_type))
+    let rsid = if let Some(payload) = rp.header.get_extension(rsid_extension_id) {
+        String::from_utf8(payload.to_vec())?
+    } else {
+        String::new()
+    };
+
+    Ok((mid, rid, rsid, payload_type))
 }
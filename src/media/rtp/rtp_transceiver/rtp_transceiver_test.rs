@@ -1,8 +1,18 @@
 use super::*;
 use crate::api::media_engine::{MIME_TYPE_OPUS, MIME_TYPE_VP8, MIME_TYPE_VP9};
 use crate::api::APIBuilder;
+use crate::media::track::track_local::track_local_static_sample::TrackLocalStaticSample;
 use crate::peer::configuration::Configuration;
-use crate::peer::peer_connection::peer_connection_test::close_pair_now;
+use crate::peer::peer_connection::peer_connection_test::{
+    close_pair_now, new_pair, signal_pair, until_connection_state,
+};
+use crate::peer::peer_connection::PeerConnection;
+use crate::peer::peer_connection_state::PeerConnectionState;
+use crate::peer::sdp::session_description::SessionDescription;
+
+use std::time::Duration;
+use tokio::sync::mpsc;
+use waitgroup::WaitGroup;
 
 #[tokio::test]
 async fn test_rtp_transceiver_set_codec_preferences() -> Result<()> {
@@ -213,3 +223,227 @@ async fn test_rtp_transceiver_set_codec_preferences_payload_type() -> Result<()>
 
     Ok(())
 }
+
+async fn renegotiate(offer_pc: &PeerConnection, answer_pc: &PeerConnection) -> Result<()> {
+    let offer = offer_pc.create_offer(None).await?;
+    offer_pc.set_local_description(offer.clone()).await?;
+    answer_pc.set_remote_description(offer).await?;
+
+    let answer = answer_pc.create_answer(None).await?;
+    answer_pc.set_local_description(answer.clone()).await?;
+    offer_pc.set_remote_description(answer).await
+}
+
+#[tokio::test]
+async fn test_rtp_transceiver_set_direction_hold() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTPCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    let sender = offer_pc.add_track(track).await?;
+
+    let peer_connection_connected = WaitGroup::new();
+    until_connection_state(
+        &mut offer_pc,
+        &peer_connection_connected,
+        PeerConnectionState::Connected,
+    )
+    .await;
+    until_connection_state(
+        &mut answer_pc,
+        &peer_connection_connected,
+        PeerConnectionState::Connected,
+    )
+    .await;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+    peer_connection_connected.wait().await;
+
+    let offer_transceiver = offer_pc.get_transceivers().await[0].clone();
+    let answer_transceiver = answer_pc.get_transceivers().await[0].clone();
+    assert_eq!(
+        offer_transceiver.current_direction(),
+        RTPTransceiverDirection::Sendonly
+    );
+    assert_eq!(
+        answer_transceiver.current_direction(),
+        RTPTransceiverDirection::Recvonly
+    );
+    assert!(!sender.is_paused());
+
+    let (negotiation_needed_tx, mut negotiation_needed_rx) = mpsc::channel::<()>(1);
+    offer_pc
+        .on_negotiation_needed(Box::new(move || {
+            let negotiation_needed_tx2 = negotiation_needed_tx.clone();
+            Box::pin(async move {
+                let _ = negotiation_needed_tx2.try_send(());
+            })
+        }))
+        .await;
+
+    // Hold
+    offer_transceiver
+        .set_direction(RTPTransceiverDirection::Inactive)
+        .await;
+    tokio::time::timeout(Duration::from_secs(5), negotiation_needed_rx.recv()).await?;
+    assert_eq!(
+        offer_transceiver.current_direction(),
+        RTPTransceiverDirection::Sendonly
+    );
+
+    renegotiate(&offer_pc, &answer_pc).await?;
+    assert_eq!(
+        offer_transceiver.current_direction(),
+        RTPTransceiverDirection::Inactive
+    );
+    assert_eq!(
+        answer_transceiver.current_direction(),
+        RTPTransceiverDirection::Inactive
+    );
+    assert!(sender.is_paused());
+
+    // Unhold
+    offer_transceiver
+        .set_direction(RTPTransceiverDirection::Sendrecv)
+        .await;
+    tokio::time::timeout(Duration::from_secs(5), negotiation_needed_rx.recv()).await?;
+
+    renegotiate(&offer_pc, &answer_pc).await?;
+    assert_eq!(
+        offer_transceiver.current_direction(),
+        RTPTransceiverDirection::Sendonly
+    );
+    assert_eq!(
+        answer_transceiver.current_direction(),
+        RTPTransceiverDirection::Recvonly
+    );
+    assert!(!sender.is_paused());
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
+
+fn media_lines(desc: &SessionDescription) -> Vec<String> {
+    desc.serde
+        .sdp
+        .lines()
+        .filter(|l| l.starts_with("m="))
+        .map(|l| l.to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_rtp_transceiver_stop_recycles_m_line() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+
+    let new_track = |id: &str| {
+        Arc::new(TrackLocalStaticSample::new(
+            RTPCodecCapability {
+                mime_type: MIME_TYPE_VP8.to_owned(),
+                ..Default::default()
+            },
+            id.to_owned(),
+            "webrtc-rs".to_owned(),
+        ))
+    };
+    offer_pc.add_track(new_track("video1")).await?;
+
+    let peer_connection_connected = WaitGroup::new();
+    until_connection_state(
+        &mut offer_pc,
+        &peer_connection_connected,
+        PeerConnectionState::Connected,
+    )
+    .await;
+    until_connection_state(
+        &mut answer_pc,
+        &peer_connection_connected,
+        PeerConnectionState::Connected,
+    )
+    .await;
+
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+    peer_connection_connected.wait().await;
+
+    let stopped_transceiver = offer_pc.get_transceivers().await[0].clone();
+    let stopped_mid = stopped_transceiver.mid().await;
+
+    let (negotiation_needed_tx, mut negotiation_needed_rx) = mpsc::channel::<()>(1);
+    offer_pc
+        .on_negotiation_needed(Box::new(move || {
+            let negotiation_needed_tx2 = negotiation_needed_tx.clone();
+            Box::pin(async move {
+                let _ = negotiation_needed_tx2.try_send(());
+            })
+        }))
+        .await;
+
+    stopped_transceiver.stop().await?;
+    assert!(stopped_transceiver.stopping());
+    assert!(!stopped_transceiver.stopped());
+    tokio::time::timeout(Duration::from_secs(5), negotiation_needed_rx.recv()).await?;
+
+    // The next negotiation rejects the m-line
+    renegotiate(&offer_pc, &answer_pc).await?;
+    assert!(stopped_transceiver.stopped());
+    assert!(answer_pc.get_transceivers().await[0].stopped());
+    let offer = offer_pc
+        .local_description()
+        .await
+        .ok_or_else(|| Error::new("non local description".to_owned()))?;
+    let answer = answer_pc
+        .local_description()
+        .await
+        .ok_or_else(|| Error::new("non local description".to_owned()))?;
+    let offer_media_lines = media_lines(&offer);
+    assert!(offer_media_lines[0].starts_with("m=video 0 "));
+    assert!(media_lines(&answer)[0].starts_with("m=video 0 "));
+    assert!(offer.serde.sdp.contains(&format!("a=mid:{}", stopped_mid)));
+
+    // A new transceiver reuses the rejected m-line
+    offer_pc.add_track(new_track("video2")).await?;
+    renegotiate(&offer_pc, &answer_pc).await?;
+
+    let offer = offer_pc
+        .local_description()
+        .await
+        .ok_or_else(|| Error::new("non local description".to_owned()))?;
+    let recycled_media_lines = media_lines(&offer);
+    assert_eq!(offer_media_lines.len(), recycled_media_lines.len());
+    assert!(!recycled_media_lines[0].starts_with("m=video 0 "));
+    assert!(!offer
+        .serde
+        .sdp
+        .contains(&format!("a=mid:{}\r\n", stopped_mid)));
+
+    let offer_transceivers = offer_pc.get_transceivers().await;
+    assert_eq!(offer_transceivers.len(), 1);
+    assert!(!offer_transceivers[0].stopping());
+    assert_ne!(offer_transceivers[0].mid().await, stopped_mid);
+
+    let answer_transceivers = answer_pc.get_transceivers().await;
+    assert_eq!(answer_transceivers.len(), 1);
+    assert_eq!(
+        answer_transceivers[0].mid().await,
+        offer_transceivers[0].mid().await
+    );
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
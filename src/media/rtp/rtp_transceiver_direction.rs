@@ -80,6 +80,40 @@ impl RTPTransceiverDirection {
             _ => *self,
         }
     }
+
+    /// has_send reports whether the direction includes sending
+    pub fn has_send(&self) -> bool {
+        matches!(
+            *self,
+            RTPTransceiverDirection::Sendrecv | RTPTransceiverDirection::Sendonly
+        )
+    }
+
+    /// has_recv reports whether the direction includes receiving
+    pub fn has_recv(&self) -> bool {
+        matches!(
+            *self,
+            RTPTransceiverDirection::Sendrecv | RTPTransceiverDirection::Recvonly
+        )
+    }
+
+    pub(crate) fn from_send_recv(send: bool, recv: bool) -> RTPTransceiverDirection {
+        match (send, recv) {
+            (true, true) => RTPTransceiverDirection::Sendrecv,
+            (true, false) => RTPTransceiverDirection::Sendonly,
+            (false, true) => RTPTransceiverDirection::Recvonly,
+            (false, false) => RTPTransceiverDirection::Inactive,
+        }
+    }
+
+    /// intersect returns the direction allowed by both directions, as used to
+    /// answer an offer, https://tools.ietf.org/html/rfc8829#section-5.3.1
+    pub(crate) fn intersect(&self, other: RTPTransceiverDirection) -> RTPTransceiverDirection {
+        RTPTransceiverDirection::from_send_recv(
+            self.has_send() && other.has_send(),
+            self.has_recv() && other.has_recv(),
+        )
+    }
 }
 
 pub(crate) fn have_rtp_transceiver_direction_intersection(
@@ -115,6 +149,41 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_rtp_transceiver_direction_intersect() {
+        let tests = vec![
+            (
+                RTPTransceiverDirection::Sendrecv,
+                RTPTransceiverDirection::Recvonly,
+                RTPTransceiverDirection::Recvonly,
+            ),
+            (
+                RTPTransceiverDirection::Sendrecv,
+                RTPTransceiverDirection::Sendrecv,
+                RTPTransceiverDirection::Sendrecv,
+            ),
+            (
+                RTPTransceiverDirection::Sendonly,
+                RTPTransceiverDirection::Recvonly,
+                RTPTransceiverDirection::Inactive,
+            ),
+            (
+                RTPTransceiverDirection::Recvonly,
+                RTPTransceiverDirection::Sendrecv,
+                RTPTransceiverDirection::Recvonly,
+            ),
+            (
+                RTPTransceiverDirection::Inactive,
+                RTPTransceiverDirection::Sendrecv,
+                RTPTransceiverDirection::Inactive,
+            ),
+        ];
+
+        for (a, b, expected) in tests {
+            assert_eq!(expected, a.intersect(b), "{} intersect {}", a, b);
+        }
+    }
+
     #[test]
     fn test_rtp_transceiver_direction_string() {
         let tests = vec![
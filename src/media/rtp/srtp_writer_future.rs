@@ -1,7 +1,9 @@
 use crate::error::Error;
 use crate::media::dtls_transport::DTLSTransport;
+use crate::media::interceptor::unmarshal_rtcp;
 use crate::media::rtp::rtp_sender::RTPSenderInternal;
 use crate::media::rtp::SSRC;
+use crate::stats::rtp_stream_stats::{OutboundRTPStreamCounters, RemoteInboundRTPStreamCounters};
 
 use srtp::session::Session;
 use srtp::stream::Stream;
@@ -10,9 +12,14 @@ use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
 use interceptor::{Attributes, RTCPReader, RTPWriter};
+use rtcp::receiver_report::ReceiverReport;
+use rtcp::reception_report::ReceptionReport;
+use rtcp::sender_report::SenderReport;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::sync::Mutex;
+use util::{MarshalSize, Unmarshal};
 
 /// SrtpWriterFuture blocks Read/Write calls until
 /// the SRTP Session is available
@@ -22,6 +29,8 @@ pub(crate) struct SrtpWriterFuture {
     pub(crate) rtp_transport: Arc<DTLSTransport>,
     pub(crate) rtcp_read_stream: Mutex<Option<Arc<Stream>>>, // atomic.Value // *
     pub(crate) rtp_write_session: Mutex<Option<Arc<Session>>>, // atomic.Value // *
+    pub(crate) outbound_counters: Mutex<OutboundRTPStreamCounters>,
+    pub(crate) remote_inbound_counters: Mutex<RemoteInboundRTPStreamCounters>,
 }
 
 impl SrtpWriterFuture {
@@ -74,26 +83,89 @@ impl SrtpWriterFuture {
     }
 
     pub async fn read(&self, b: &mut [u8]) -> Result<usize> {
-        {
+        let n = self.read_rtcp_stream(b).await?;
+        self.on_rtcp(&b[..n]).await;
+        Ok(n)
+    }
+
+    async fn read_rtcp_stream(&self, b: &mut [u8]) -> Result<usize> {
+        // the stream is not kept locked while reading, so that close can interrupt the read
+        let stream = {
             let stream = self.rtcp_read_stream.lock().await;
-            if let Some(rtcp_read_stream) = &*stream {
-                return rtcp_read_stream.read(b).await;
-            }
+            stream.clone()
+        };
+        if let Some(rtcp_read_stream) = stream {
+            return rtcp_read_stream.read(b).await;
         }
 
         self.init(false).await?;
 
-        {
+        let stream = {
             let stream = self.rtcp_read_stream.lock().await;
-            if let Some(rtcp_read_stream) = &*stream {
-                return rtcp_read_stream.read(b).await;
-            }
+            stream.clone()
+        };
+        if let Some(rtcp_read_stream) = stream {
+            return rtcp_read_stream.read(b).await;
         }
 
         Ok(0)
     }
 
+    /// on_rtcp updates the remote inbound counters with the reception reports
+    /// the remote peer sent about this stream
+    async fn on_rtcp(&self, b: &[u8]) {
+        let reports = self.reception_reports(b);
+        if reports.is_empty() {
+            return;
+        }
+
+        let now = SystemTime::now();
+        let mut counters = self.remote_inbound_counters.lock().await;
+        for report in &reports {
+            counters.on_reception_report(report, now);
+        }
+    }
+
+    fn reception_reports(&self, b: &[u8]) -> Vec<ReceptionReport> {
+        if b.is_empty() {
+            return vec![];
+        }
+
+        let pkts = match unmarshal_rtcp(b) {
+            Ok(pkts) => pkts,
+            Err(_) => return vec![],
+        };
+
+        let mut reports = vec![];
+        for p in &pkts {
+            let rs = if let Some(rr) = p.as_any().downcast_ref::<ReceiverReport>() {
+                &rr.reports
+            } else if let Some(sr) = p.as_any().downcast_ref::<SenderReport>() {
+                &sr.reports
+            } else {
+                continue;
+            };
+
+            reports.extend(rs.iter().filter(|r| r.ssrc == self.ssrc).cloned());
+        }
+        reports
+    }
+
     pub async fn write_rtp(&self, pkt: &rtp::packet::Packet) -> Result<usize> {
+        let n = self.write_rtp_session(pkt).await?;
+        // retransmissions on the RTX repair stream are not part of this stream
+        if n > 0 && pkt.header.ssrc == self.ssrc {
+            let mut counters = self.outbound_counters.lock().await;
+            counters.on_packet(
+                pkt.header.marshal_size(),
+                pkt.payload.len(),
+                SystemTime::now(),
+            );
+        }
+        Ok(n)
+    }
+
+    async fn write_rtp_session(&self, pkt: &rtp::packet::Packet) -> Result<usize> {
         {
             let session = self.rtp_write_session.lock().await;
             if let Some(rtp_write_session) = &*session {
@@ -114,6 +186,23 @@ impl SrtpWriterFuture {
     }
 
     pub async fn write(&self, b: &Bytes) -> Result<usize> {
+        let n = self.write_session(b).await?;
+        if n > 0 {
+            let mut buf = b.clone();
+            if let Ok(header) = rtp::header::Header::unmarshal(&mut buf) {
+                let header_size = header.marshal_size();
+                let mut counters = self.outbound_counters.lock().await;
+                counters.on_packet(
+                    header_size,
+                    b.len().saturating_sub(header_size),
+                    SystemTime::now(),
+                );
+            }
+        }
+        Ok(n)
+    }
+
+    async fn write_session(&self, b: &Bytes) -> Result<usize> {
         {
             let session = self.rtp_write_session.lock().await;
             if let Some(rtp_write_session) = &*session {
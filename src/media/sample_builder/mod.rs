@@ -0,0 +1,340 @@
+#[cfg(test)]
+mod sample_builder_test;
+
+use crate::media::Sample;
+
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use rtp::codecs::h264::H264Packet;
+use rtp::codecs::opus::OpusPacket;
+use rtp::codecs::vp8::Vp8Packet;
+use rtp::codecs::vp9::Vp9Packet;
+use rtp::packetizer::Depacketizer;
+use std::collections::BTreeMap;
+use tokio::time::Duration;
+
+/// PartitionDepacketizer depacketizes the RTP payloads of a codec, and tells
+/// which payloads start and end a frame
+pub trait PartitionDepacketizer {
+    /// depacketize_payload returns the media data carried by a RTP payload
+    fn depacketize_payload(&mut self, payload: &Bytes) -> Result<Bytes>;
+
+    /// is_partition_head reports whether the payload is the first of a frame
+    fn is_partition_head(&self, payload: &Bytes) -> bool;
+
+    /// is_partition_tail reports whether the payload is the last of a frame
+    fn is_partition_tail(&self, marker: bool, payload: &Bytes) -> bool;
+}
+
+impl PartitionDepacketizer for Vp8Packet {
+    fn depacketize_payload(&mut self, payload: &Bytes) -> Result<Bytes> {
+        self.depacketize(payload)?;
+        Ok(self.payload.clone())
+    }
+
+    /// A frame starts with the beginning of its first partition, which has the
+    /// S bit set and a partition index of 0
+    /// https://tools.ietf.org/html/rfc7741#section-4.2
+    fn is_partition_head(&self, payload: &Bytes) -> bool {
+        !payload.is_empty() && payload[0] & 0x10 != 0 && payload[0] & 0x07 == 0
+    }
+
+    fn is_partition_tail(&self, marker: bool, _payload: &Bytes) -> bool {
+        marker
+    }
+}
+
+impl PartitionDepacketizer for Vp9Packet {
+    fn depacketize_payload(&mut self, payload: &Bytes) -> Result<Bytes> {
+        self.depacketize(payload)?;
+        Ok(self.payload.clone())
+    }
+
+    /// A frame starts with the B bit set
+    /// https://tools.ietf.org/html/draft-ietf-payload-vp9-13#section-4.2
+    fn is_partition_head(&self, payload: &Bytes) -> bool {
+        !payload.is_empty() && payload[0] & 0x08 != 0
+    }
+
+    fn is_partition_tail(&self, marker: bool, _payload: &Bytes) -> bool {
+        marker
+    }
+}
+
+impl PartitionDepacketizer for H264Packet {
+    fn depacketize_payload(&mut self, payload: &Bytes) -> Result<Bytes> {
+        self.depacketize(payload)?;
+        Ok(self.payload.clone())
+    }
+
+    /// A frame starts with a whole NAL, a STAP-A or the first fragment of a FU-A
+    /// https://tools.ietf.org/html/rfc6184#section-5.4
+    fn is_partition_head(&self, payload: &Bytes) -> bool {
+        if payload.is_empty() {
+            return false;
+        }
+        match payload[0] & 0x1F {
+            1..=24 => true,
+            28 => payload.len() > 1 && payload[1] & 0x80 != 0,
+            _ => false,
+        }
+    }
+
+    fn is_partition_tail(&self, marker: bool, _payload: &Bytes) -> bool {
+        marker
+    }
+}
+
+impl PartitionDepacketizer for OpusPacket {
+    fn depacketize_payload(&mut self, payload: &Bytes) -> Result<Bytes> {
+        self.depacketize(payload)?;
+        Ok(self.payload.clone())
+    }
+
+    /// Each payload holds a whole Opus packet
+    fn is_partition_head(&self, _payload: &Bytes) -> bool {
+        true
+    }
+
+    fn is_partition_tail(&self, _marker: bool, _payload: &Bytes) -> bool {
+        true
+    }
+}
+
+/// FrameEnd is where the frame at the head of a SampleBuilder ends
+enum FrameEnd {
+    /// all its packets are received, up to this sequence number excluded
+    Complete(u64),
+    /// the packet with this sequence number is missing
+    Missing(u64),
+    /// its last packet is yet to be received
+    Pending,
+}
+
+/// SampleBuilder buffers RTP packets, reorders them by sequence number and
+/// assembles them into Samples once their frames are complete
+pub struct SampleBuilder {
+    /// how many packets to wait for a missing packet before giving up on its frame
+    max_late: u16,
+    depacketizer: Box<dyn PartitionDepacketizer + Send + Sync>,
+    sample_rate: u32,
+
+    /// packets keyed by their sequence number, extended to not wrap around
+    packets: BTreeMap<u64, rtp::packet::Packet>,
+    started: bool,
+    /// whether head moved past a packet, which older packets can no longer precede
+    released: bool,
+    /// the extended sequence number of the oldest packet still expected
+    head: u64,
+    /// one past the extended sequence number of the newest packet
+    tail: u64,
+
+    /// packets lost or discarded since the last sample
+    dropped_packets: u64,
+}
+
+impl SampleBuilder {
+    /// new constructs a new SampleBuilder.
+    /// max_late is how many packets to wait for a missing packet, before
+    /// dropping the frame it belongs to. It bounds the latency added when
+    /// packets are lost, and the reordering which can be recovered from.
+    /// sample_rate is the RTP clock rate, used to compute sample durations.
+    pub fn new(
+        max_late: u16,
+        depacketizer: Box<dyn PartitionDepacketizer + Send + Sync>,
+        sample_rate: u32,
+    ) -> Self {
+        SampleBuilder {
+            max_late,
+            depacketizer,
+            sample_rate,
+
+            packets: BTreeMap::new(),
+            started: false,
+            released: false,
+            head: 0,
+            tail: 0,
+
+            dropped_packets: 0,
+        }
+    }
+
+    /// push adds a RTP packet to the buffer. Duplicated packets, and packets
+    /// older than the frames already built or dropped, are ignored.
+    pub fn push(&mut self, p: rtp::packet::Packet) {
+        if !self.started {
+            self.started = true;
+            // Leave room for the packets sent before the first one received
+            self.head = p.header.sequence_number as u64 + 0x10000;
+            self.tail = self.head;
+        }
+
+        let diff = p.header.sequence_number.wrapping_sub(self.head as u16);
+        if diff >= 0x8000 {
+            let late = 0x10000 - diff as u64;
+            if self.released || late > self.max_late as u64 {
+                return;
+            }
+            self.head -= late;
+            self.packets.insert(self.head, p);
+            return;
+        }
+
+        let seq = self.head + diff as u64;
+        if seq >= self.tail {
+            self.tail = seq + 1;
+        }
+        self.packets.entry(seq).or_insert(p);
+    }
+
+    /// pop returns the oldest complete Sample, if any. A frame is complete once
+    /// all its packets and the first packet after it are received, the latter
+    /// giving the duration of the sample. Frames missing a packet are dropped
+    /// once the newest packet is max_late packets past it.
+    pub fn pop(&mut self) -> Option<Sample> {
+        loop {
+            if self.head == self.tail {
+                return None;
+            }
+            let first = match self.packets.get(&self.head) {
+                Some(first) => first,
+                None => {
+                    if !self.waited_enough(self.head) {
+                        return None;
+                    }
+                    self.dropped_packets += 1;
+                    self.head += 1;
+                    self.released = true;
+                    continue;
+                }
+            };
+
+            // Padding packets carry no media
+            if first.payload.is_empty() {
+                self.packets.remove(&self.head);
+                self.head += 1;
+                self.released = true;
+                continue;
+            }
+
+            // The packets preceding this one in its frame were lost or dropped,
+            // unless they are yet to arrive before the first packet received
+            if !self.depacketizer.is_partition_head(&first.payload) {
+                if !self.released && !self.waited_enough(self.head) {
+                    return None;
+                }
+                self.drop_packets(self.head + 1);
+                continue;
+            }
+
+            let timestamp = first.header.timestamp;
+            let end = match self.find_frame_end(timestamp) {
+                FrameEnd::Complete(end) => end,
+                FrameEnd::Missing(seq) if self.waited_enough(seq) => {
+                    self.drop_packets(seq);
+                    continue;
+                }
+                FrameEnd::Missing(_) | FrameEnd::Pending => return None,
+            };
+
+            // The duration of the sample is only known with the next frame
+            let next_timestamp = self
+                .packets
+                .range(end..)
+                .map(|(_, p)| p)
+                .find(|p| !p.payload.is_empty())?
+                .header
+                .timestamp;
+
+            if let Some(data) = self.depacketize_frame(end) {
+                let prev_dropped_packets = self.dropped_packets.min(u16::MAX as u64) as u16;
+                self.dropped_packets = 0;
+
+                let samples = next_timestamp.wrapping_sub(timestamp) as u64;
+                let duration = if self.sample_rate != 0 {
+                    Duration::from_nanos(samples * 1_000_000_000 / self.sample_rate as u64)
+                } else {
+                    Duration::from_secs(0)
+                };
+
+                return Some(Sample {
+                    data,
+                    duration,
+                    packet_timestamp: timestamp,
+                    prev_dropped_packets,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// waited_enough reports whether the missing packet seq is given up on
+    fn waited_enough(&self, seq: u64) -> bool {
+        self.tail - seq > self.max_late as u64
+    }
+
+    /// find_frame_end looks for the end of the frame starting at head, which
+    /// is either its tail or the start of the next frame
+    fn find_frame_end(&self, timestamp: u32) -> FrameEnd {
+        for seq in self.head..self.tail {
+            let p = match self.packets.get(&seq) {
+                Some(p) => p,
+                None => return FrameEnd::Missing(seq),
+            };
+            if p.payload.is_empty() {
+                continue;
+            }
+            if p.header.timestamp != timestamp {
+                return FrameEnd::Complete(seq);
+            }
+            if self
+                .depacketizer
+                .is_partition_tail(p.header.marker, &p.payload)
+            {
+                return FrameEnd::Complete(seq + 1);
+            }
+        }
+
+        FrameEnd::Pending
+    }
+
+    /// depacketize_frame consumes the packets up to end and returns their media
+    /// data, or None when a packet cannot be depacketized
+    fn depacketize_frame(&mut self, end: u64) -> Option<Bytes> {
+        let mut data = BytesMut::new();
+        let mut valid = true;
+        for seq in self.head..end {
+            if let Some(p) = self.packets.remove(&seq) {
+                if p.payload.is_empty() {
+                    continue;
+                }
+                match self.depacketizer.depacketize_payload(&p.payload) {
+                    Ok(payload) => data.put(payload),
+                    Err(_) => valid = false,
+                }
+            }
+        }
+
+        if !valid {
+            self.dropped_packets += end - self.head;
+        }
+        self.head = end;
+        self.released = true;
+
+        if valid {
+            Some(data.freeze())
+        } else {
+            None
+        }
+    }
+
+    /// drop_packets discards the packets up to end
+    fn drop_packets(&mut self, end: u64) {
+        for seq in self.head..end {
+            self.packets.remove(&seq);
+        }
+        self.dropped_packets += end - self.head;
+        self.head = end;
+        self.released = true;
+    }
+}
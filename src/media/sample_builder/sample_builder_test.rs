@@ -0,0 +1,183 @@
+use super::*;
+
+fn packet(
+    sequence_number: u16,
+    timestamp: u32,
+    marker: bool,
+    payload: &[u8],
+) -> rtp::packet::Packet {
+    rtp::packet::Packet {
+        header: rtp::header::Header {
+            version: 2,
+            marker,
+            payload_type: 96,
+            sequence_number,
+            timestamp,
+            ..Default::default()
+        },
+        payload: Bytes::copy_from_slice(payload),
+    }
+}
+
+fn pop_all(s: &mut SampleBuilder) -> Vec<Sample> {
+    let mut samples = vec![];
+    while let Some(sample) = s.pop() {
+        samples.push(sample);
+    }
+    samples
+}
+
+#[test]
+fn test_sample_builder_opus() {
+    let mut s = SampleBuilder::new(10, Box::new(OpusPacket::default()), 48000);
+
+    s.push(packet(5000, 0, true, &[0x01]));
+    assert!(
+        s.pop().is_none(),
+        "the duration of the sample is not known yet"
+    );
+
+    s.push(packet(5001, 960, true, &[0x02]));
+    s.push(packet(5002, 1920, true, &[0x03]));
+
+    let samples = pop_all(&mut s);
+    assert_eq!(samples.len(), 2);
+    for (i, sample) in samples.iter().enumerate() {
+        assert_eq!(sample.data, Bytes::from(vec![i as u8 + 1]));
+        assert_eq!(sample.packet_timestamp, 960 * i as u32);
+        assert_eq!(sample.duration, Duration::from_millis(20));
+        assert_eq!(sample.prev_dropped_packets, 0);
+    }
+}
+
+#[test]
+fn test_sample_builder_reorder() {
+    let mut s = SampleBuilder::new(10, Box::new(Vp8Packet::default()), 90000);
+
+    s.push(packet(1, 0, true, &[0x00, 0x04, 0x05, 0x06]));
+    s.push(packet(2, 3000, false, &[0x10, 0x07, 0x08, 0x09]));
+    assert!(s.pop().is_none());
+    s.push(packet(0, 0, false, &[0x10, 0x01, 0x02, 0x03]));
+
+    let samples = pop_all(&mut s);
+    assert_eq!(samples.len(), 1);
+    assert_eq!(
+        samples[0].data,
+        Bytes::from_static(&[0x01, 0x02, 0x03, 0x04, 0x05, 0x06])
+    );
+    assert_eq!(samples[0].packet_timestamp, 0);
+    assert_eq!(samples[0].duration, Duration::from_secs(1) / 30);
+    assert_eq!(samples[0].prev_dropped_packets, 0);
+}
+
+#[test]
+fn test_sample_builder_missing_packet() {
+    let mut s = SampleBuilder::new(5, Box::new(Vp8Packet::default()), 90000);
+
+    // The second packet of the first frame is lost
+    s.push(packet(0, 0, false, &[0x10, 0x01, 0x02, 0x03]));
+    s.push(packet(2, 0, true, &[0x00, 0x04, 0x05, 0x06]));
+    for (i, seq) in (3..6).enumerate() {
+        s.push(packet(
+            seq,
+            3000 * (i as u32 + 1),
+            true,
+            &[0x10, seq as u8, 0, 0],
+        ));
+    }
+    assert!(
+        s.pop().is_none(),
+        "the missing packet is waited for max_late packets"
+    );
+
+    s.push(packet(6, 12000, true, &[0x10, 0x06, 0x00, 0x00]));
+
+    let samples = pop_all(&mut s);
+    assert_eq!(samples.len(), 3);
+    assert_eq!(samples[0].data, Bytes::from_static(&[0x03, 0x00, 0x00]));
+    assert_eq!(samples[0].packet_timestamp, 3000);
+    assert_eq!(samples[0].prev_dropped_packets, 3);
+    assert_eq!(samples[1].prev_dropped_packets, 0);
+
+    // The lost packet is ignored once its frame was dropped
+    s.push(packet(1, 0, false, &[0x00, 0x01, 0x02, 0x03]));
+    assert!(s.pop().is_none());
+}
+
+#[test]
+fn test_sample_builder_sequence_number_wrap() {
+    let mut s = SampleBuilder::new(10, Box::new(OpusPacket::default()), 48000);
+
+    for (i, seq) in [65534u16, 65535, 0, 1].iter().enumerate() {
+        s.push(packet(*seq, 960 * i as u32, true, &[i as u8]));
+    }
+
+    let samples = pop_all(&mut s);
+    assert_eq!(samples.len(), 3);
+    for (i, sample) in samples.iter().enumerate() {
+        assert_eq!(sample.data, Bytes::from(vec![i as u8]));
+        assert_eq!(sample.prev_dropped_packets, 0);
+    }
+}
+
+#[test]
+fn test_sample_builder_duplicate_packet() {
+    let mut s = SampleBuilder::new(10, Box::new(OpusPacket::default()), 48000);
+
+    s.push(packet(10, 0, true, &[0x01]));
+    s.push(packet(10, 0, true, &[0x02]));
+    s.push(packet(11, 960, true, &[0x03]));
+
+    let samples = pop_all(&mut s);
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].data, Bytes::from_static(&[0x01]));
+
+    // Packets older than the samples already built are ignored
+    s.push(packet(10, 0, true, &[0x01]));
+    s.push(packet(12, 1920, true, &[0x04]));
+
+    let samples = pop_all(&mut s);
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].data, Bytes::from_static(&[0x03]));
+}
+
+#[test]
+fn test_sample_builder_h264() {
+    let mut s = SampleBuilder::new(10, Box::new(H264Packet::default()), 90000);
+
+    // SPS in a single NAL packet, then an IDR slice fragmented in a FU-A
+    s.push(packet(0, 0, false, &[0x67, 0x01, 0x02]));
+    s.push(packet(1, 0, false, &[0x7C, 0x85, 0x03, 0x04]));
+    s.push(packet(2, 0, true, &[0x7C, 0x45, 0x05, 0x06]));
+    s.push(packet(3, 3000, true, &[0x61, 0x07, 0x08]));
+
+    let samples = pop_all(&mut s);
+    assert_eq!(samples.len(), 1);
+    assert_eq!(
+        samples[0].data,
+        Bytes::from_static(&[
+            0x00, 0x00, 0x00, 0x01, 0x67, 0x01, 0x02, 0x00, 0x00, 0x00, 0x01, 0x65, 0x03, 0x04,
+            0x05, 0x06,
+        ])
+    );
+}
+
+#[test]
+fn test_sample_builder_partition_head() {
+    let vp8 = Vp8Packet::default();
+    assert!(vp8.is_partition_head(&Bytes::from_static(&[0x10, 0x00])));
+    assert!(!vp8.is_partition_head(&Bytes::from_static(&[0x00, 0x00])));
+    assert!(!vp8.is_partition_head(&Bytes::from_static(&[0x11, 0x00])));
+    assert!(!vp8.is_partition_head(&Bytes::new()));
+
+    let vp9 = Vp9Packet::default();
+    assert!(vp9.is_partition_head(&Bytes::from_static(&[0x08, 0x00])));
+    assert!(!vp9.is_partition_head(&Bytes::from_static(&[0x04, 0x00])));
+
+    let h264 = H264Packet::default();
+    assert!(h264.is_partition_head(&Bytes::from_static(&[0x65, 0x00])));
+    assert!(h264.is_partition_head(&Bytes::from_static(&[0x78, 0x00])));
+    assert!(h264.is_partition_head(&Bytes::from_static(&[0x7C, 0x85])));
+    assert!(!h264.is_partition_head(&Bytes::from_static(&[0x7C, 0x05])));
+    assert!(!h264.is_partition_head(&Bytes::from_static(&[0x7C, 0x45])));
+}
@@ -6,6 +6,7 @@ use track_remote::*;
 use interceptor::stream_info::StreamInfo;
 use interceptor::{RTCPReader, RTPReader};
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
 /// TrackStreams maintains a mapping of RTP/RTCP streams to a specific track
 /// a RTPReceiver may contain multiple streams if we are dealing with Multicast
@@ -18,4 +19,11 @@ pub(crate) struct TrackStreams {
     pub(crate) rtp_interceptor: Option<Arc<dyn RTPReader + Send + Sync>>,
     pub(crate) rtcp_read_stream: Option<Arc<srtp::stream::Stream>>, //ReadStreamSRTCP
     pub(crate) rtcp_interceptor: Option<Arc<dyn RTCPReader + Send + Sync>>,
+
+    /// repair_read_stream is the RTX repair stream of the track, if any, whose
+    /// packets are unwrapped into rtp_interceptor
+    pub(crate) repair_read_stream: Option<Arc<srtp::stream::Stream>>, //ReadStreamSRTP
+    /// repair_tx feeds the unwrapped retransmissions of a simulcast track, whose repair
+    /// stream is only known once its first packet is received
+    pub(crate) repair_tx: Option<mpsc::Sender<rtp::packet::Packet>>,
 }
@@ -88,6 +88,12 @@ pub trait TrackLocal {
     /// stream_id is the group this track belongs too. This must be unique
     fn stream_id(&self) -> &str;
 
+    /// rid is the RTP stream identifier of this track when it is a simulcast layer.
+    /// It is empty when the track isn't part of a simulcast envelope
+    fn rid(&self) -> &str {
+        ""
+    }
+
     /// kind controls if this TrackLocal is audio or video
     fn kind(&self) -> RTPCodecType;
 
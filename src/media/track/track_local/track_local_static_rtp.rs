@@ -11,6 +11,7 @@ pub struct TrackLocalStaticRTP {
     pub(crate) bindings: Arc<Mutex<Vec<TrackBinding>>>,
     codec: RTPCodecCapability,
     id: String,
+    rid: String,
     stream_id: String,
 }
 
@@ -21,6 +22,23 @@ impl TrackLocalStaticRTP {
             codec,
             bindings: Arc::new(Mutex::new(vec![])),
             id,
+            rid: String::new(),
+            stream_id,
+        }
+    }
+
+    /// returns a TrackLocalStaticRTP sending the simulcast layer identified by rid.
+    pub fn new_with_rid(
+        codec: RTPCodecCapability,
+        id: String,
+        rid: String,
+        stream_id: String,
+    ) -> Self {
+        TrackLocalStaticRTP {
+            codec,
+            bindings: Arc::new(Mutex::new(vec![])),
+            id,
+            rid,
             stream_id,
         }
     }
@@ -91,6 +109,11 @@ impl TrackLocal for TrackLocalStaticRTP {
         self.stream_id.as_str()
     }
 
+    /// rid is the RTP stream identifier of the simulcast layer sent by this track
+    fn rid(&self) -> &str {
+        self.rid.as_str()
+    }
+
     /// kind controls if this TrackLocal is audio or video
     fn kind(&self) -> RTPCodecType {
         if self.codec.mime_type.starts_with("audio/") {
@@ -27,6 +27,22 @@ impl TrackLocalStaticSample {
     pub fn new(codec: RTPCodecCapability, id: String, stream_id: String) -> Self {
         let rtp_track = TrackLocalStaticRTP::new(codec, id, stream_id);
 
+        TrackLocalStaticSample::with_rtp_track(rtp_track)
+    }
+
+    /// returns a TrackLocalStaticSample sending the simulcast layer identified by rid
+    pub fn new_with_rid(
+        codec: RTPCodecCapability,
+        id: String,
+        rid: String,
+        stream_id: String,
+    ) -> Self {
+        let rtp_track = TrackLocalStaticRTP::new_with_rid(codec, id, rid, stream_id);
+
+        TrackLocalStaticSample::with_rtp_track(rtp_track)
+    }
+
+    fn with_rtp_track(rtp_track: TrackLocalStaticRTP) -> Self {
         TrackLocalStaticSample {
             rtp_track,
             internal: Arc::new(Mutex::new(TrackLocalStaticSampleInternal {
@@ -133,6 +149,11 @@ impl TrackLocal for TrackLocalStaticSample {
         self.rtp_track.stream_id()
     }
 
+    /// rid is the RTP stream identifier of the simulcast layer sent by this track
+    fn rid(&self) -> &str {
+        self.rtp_track.rid()
+    }
+
     /// kind controls if this TrackLocal is audio or video
     fn kind(&self) -> RTPCodecType {
         self.rtp_track.kind()
@@ -2,16 +2,23 @@ use crate::api::media_engine::MediaEngine;
 use crate::error::Error;
 use crate::media::rtp::rtp_codec::{RTPCodecParameters, RTPCodecType, RTPParameters};
 use crate::media::rtp::{PayloadType, SSRC};
+use crate::media::sample_builder::SampleBuilder;
+use crate::media::Sample;
+use crate::stats::rtp_stream_stats::InboundRTPStreamCounters;
 use crate::{RECEIVE_MTU, RTP_PAYLOAD_TYPE_BITMASK};
 
 use crate::media::rtp::rtp_receiver::RTPReceiverInternal;
 use anyhow::Result;
 use bytes::{Bytes, BytesMut};
 use interceptor::{Attributes, Interceptor};
-use std::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU8, Ordering};
 use std::sync::Arc;
+use std::time::SystemTime;
 use tokio::sync::Mutex;
-use util::Unmarshal;
+use util::{MarshalSize, Unmarshal};
+
+/// the default number of packets read_sample waits for a missing packet
+pub const DEFAULT_SAMPLE_BUILDER_MAX_LATE: u16 = 50;
 
 #[derive(Default)]
 struct TrackRemoteInternal {
@@ -36,6 +43,11 @@ pub struct TrackRemote {
 
     receiver: Option<Arc<RTPReceiverInternal>>,
     internal: Mutex<TrackRemoteInternal>,
+    pub(crate) inbound_counters: Mutex<InboundRTPStreamCounters>,
+
+    sample_builder_max_late: AtomicU16,
+    /// the SampleBuilder of read_sample, along with the payload type it depacketizes
+    sample_builder: Mutex<Option<(PayloadType, SampleBuilder)>>,
 }
 
 impl std::fmt::Debug for TrackRemote {
@@ -76,6 +88,10 @@ impl TrackRemote {
             interceptor,
 
             internal: Default::default(),
+            inbound_counters: Default::default(),
+
+            sample_builder_max_late: AtomicU16::new(DEFAULT_SAMPLE_BUILDER_MAX_LATE),
+            sample_builder: Default::default(),
         }
     }
 
@@ -180,16 +196,40 @@ impl TrackRemote {
         } else {
             let (n, attributes) = {
                 if let Some(receiver) = &self.receiver {
-                    receiver.read_rtp(b, self.id().await.as_str()).await?
+                    receiver
+                        .read_rtp(b, self.id().await.as_str(), self.rid())
+                        .await?
                 } else {
                     return Err(Error::ErrRTPReceiverNil.into());
                 }
             };
             self.check_and_update_track(&b[..n]).await?;
+            self.update_inbound_counters(&b[..n]).await;
             Ok((n, attributes))
         }
     }
 
+    /// update_inbound_counters accounts a freshly received packet in the
+    /// InboundRTPStreamStats of this track. Peeked packets are only counted once.
+    async fn update_inbound_counters(&self, b: &[u8]) {
+        let mut buf = b;
+        if let Ok(header) = rtp::header::Header::unmarshal(&mut buf) {
+            let header_size = header.marshal_size();
+            let clock_rate = {
+                let codec = self.codec.lock().await;
+                codec.capability.clock_rate
+            };
+            let mut counters = self.inbound_counters.lock().await;
+            counters.on_packet(
+                &header,
+                header_size,
+                b.len().saturating_sub(header_size),
+                clock_rate,
+                SystemTime::now(),
+            );
+        }
+    }
+
     /// check_and_update_track checks payloadType for every incoming packet
     /// once a different payloadType is detected the track will be updated
     async fn check_and_update_track(&self, b: &[u8]) -> Result<()> {
@@ -235,6 +275,56 @@ impl TrackRemote {
         Ok((r, attributes))
     }
 
+    /// read_sample reads packets from the track until a frame is complete, and
+    /// returns it depacketized. Packets are reordered by sequence number and
+    /// the frames missing packets are dropped, the count of the packets lost
+    /// since the previous sample being reported in prev_dropped_packets.
+    /// It must not be mixed with read and read_rtp, whose packets it would miss.
+    pub async fn read_sample(&self) -> Result<Sample> {
+        let mut sample_builder = self.sample_builder.lock().await;
+        loop {
+            if let Some((_, builder)) = &mut *sample_builder {
+                if let Some(sample) = builder.pop() {
+                    return Ok(sample);
+                }
+            }
+
+            let (pkt, _) = self.read_rtp().await?;
+
+            // The codec of the track changes along with its payload type
+            let payload_type = pkt.header.payload_type;
+            let same_codec = matches!(&*sample_builder, Some((pt, _)) if *pt == payload_type);
+            if !same_codec {
+                let codec = self.codec().await;
+                let depacketizer = codec.capability.depacketizer_for_codec()?;
+                let builder = SampleBuilder::new(
+                    self.sample_builder_max_late(),
+                    depacketizer,
+                    codec.capability.clock_rate,
+                );
+                *sample_builder = Some((payload_type, builder));
+            }
+
+            if let Some((_, builder)) = &mut *sample_builder {
+                builder.push(pkt);
+            }
+        }
+    }
+
+    /// sample_builder_max_late gets how many packets read_sample waits for a
+    /// missing packet before dropping its frame
+    pub fn sample_builder_max_late(&self) -> u16 {
+        self.sample_builder_max_late.load(Ordering::SeqCst)
+    }
+
+    /// set_sample_builder_max_late sets how many packets read_sample waits for
+    /// a missing packet. It trades latency for resilience to reordering, and
+    /// applies from the next codec change, or the first call to read_sample.
+    pub fn set_sample_builder_max_late(&self, max_late: u16) {
+        self.sample_builder_max_late
+            .store(max_late, Ordering::SeqCst);
+    }
+
     /// determine_payload_type blocks and reads a single packet to determine the PayloadType for this Track
     /// this is useful because we can't announce it to the user until we know the payload_type
     pub(crate) async fn determine_payload_type(&self) -> Result<()> {
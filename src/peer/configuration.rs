@@ -4,33 +4,46 @@ use crate::peer::policy::bundle_policy::BundlePolicy;
 use crate::peer::policy::ice_transport_policy::ICETransportPolicy;
 use crate::peer::policy::rtcp_mux_policy::RTCPMuxPolicy;
 use crate::peer::policy::sdp_semantics::SDPSemantics;
+use crate::util::is_default;
+
+use serde::{Deserialize, Serialize};
 
 /// A Configuration defines how peer-to-peer communication via PeerConnection
 /// is established or re-established.
 /// Configurations may be set up once and reused across multiple connections.
 /// Configurations are treated as readonly. As long as they are unmodified,
 /// they are safe for concurrent use.
-#[derive(Default)]
+///
+/// Configurations serialize to the JSON shape of RTCConfiguration, leaving
+/// out the fields which keep their default value. Certificates are written
+/// as pem blocks holding their private key.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Configuration {
     /// iceservers defines a slice describing servers available to be used by
     /// ICE, such as STUN and TURN servers.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub ice_servers: Vec<ICEServer>,
 
     /// icetransport_policy indicates which candidates the ICEAgent is allowed
     /// to use.
+    #[serde(skip_serializing_if = "is_default")]
     pub ice_transport_policy: ICETransportPolicy,
 
     /// bundle_policy indicates which media-bundling policy to use when gathering
     /// ICE candidates.
+    #[serde(skip_serializing_if = "is_default")]
     pub bundle_policy: BundlePolicy,
 
     /// rtcp_mux_policy indicates which rtcp-mux policy to use when gathering ICE
     /// candidates.
+    #[serde(skip_serializing_if = "is_default")]
     pub rtcp_mux_policy: RTCPMuxPolicy,
 
     /// peer_identity sets the target peer identity for the PeerConnection.
     /// The PeerConnection will not establish a connection to a remote peer
     /// unless it can be successfully authenticated with the provided name.
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub peer_identity: String,
 
     /// Certificates describes a set of certificates that the PeerConnection
@@ -44,13 +57,24 @@ pub struct Configuration {
     /// used for a given connection; how certificates are selected is outside
     /// the scope of this specification. If this value is absent, then a default
     /// set of certificates is generated for each PeerConnection instance.
+    ///
+    /// The fingerprints of all certificates are advertised in the SDP and all of
+    /// them are given to the DTLS handshake, which currently presents the first
+    /// one: list the certificates in order of preference.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub certificates: Vec<Certificate>,
 
     /// icecandidate_pool_size describes the size of the prefetched ICE pool.
+    /// When it isn't zero, candidates are gathered as soon as the PeerConnection
+    /// is created, instead of when the first local description is set. They are
+    /// only emitted by on_ice_candidate once the first local description is set,
+    /// the gathering state staying new until then.
+    #[serde(skip_serializing_if = "is_default")]
     pub ice_candidate_pool_size: u8,
 
     /// sdp_semantics controls the type of SDP offers accepted by and
     /// SDP answers generated by the PeerConnection.
+    #[serde(skip_serializing_if = "is_default")]
     pub sdp_semantics: SDPSemantics,
 }
 
@@ -78,6 +102,7 @@ impl Configuration {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::peer::ice::ice_credential_type::ICECredentialType;
 
     #[test]
     fn test_configuration_get_iceservers() {
@@ -112,11 +137,11 @@ mod test {
         }
     }
 
-    /*TODO:#[test] fn test_configuration_json() {
-
-         let j = r#"
+    #[test]
+    fn test_configuration_json() {
+        let j = r#"
             {
-                "iceServers": [{"URLs": ["turn:turn.example.org"],
+                "iceServers": [{"urls": ["turn:turn.example.org"],
                                 "username": "jch",
                                 "credential": "topsecret"
                               }],
@@ -125,28 +150,86 @@ mod test {
                 "rtcpMuxPolicy": "require"
             }"#;
 
-        conf := Configuration{
-            ICEServers: []ICEServer{
-                {
-                    URLs:       []string{"turn:turn.example.org"},
-                    Username:   "jch",
-                    Credential: "topsecret",
-                },
-            },
-            ICETransportPolicy: ICETransportPolicyRelay,
-            BundlePolicy:       BundlePolicyBalanced,
-            RTCPMuxPolicy:      RTCPMuxPolicyRequire,
-        }
+        let conf = Configuration {
+            ice_servers: vec![ICEServer {
+                urls: vec!["turn:turn.example.org".to_owned()],
+                username: "jch".to_owned(),
+                credential: "topsecret".to_owned(),
+                ..Default::default()
+            }],
+            ice_transport_policy: ICETransportPolicy::Relay,
+            bundle_policy: BundlePolicy::Balanced,
+            rtcp_mux_policy: RTCPMuxPolicy::Require,
+            ..Default::default()
+        };
+
+        let conf2: Configuration = serde_json::from_str(j).unwrap();
+        assert!(conf == conf2);
+
+        let j2 = serde_json::to_string(&conf2).unwrap();
+        let conf3: Configuration = serde_json::from_str(&j2).unwrap();
+        assert!(conf2 == conf3);
+    }
 
-        var conf2 Configuration
-        assert.NoError(t, json.Unmarshal([]byte(j), &conf2))
-        assert.Equal(t, conf, conf2)
+    #[test]
+    fn test_configuration_json_browser_shape() {
+        let j = r#"
+            {
+                "iceServers": [
+                    {"urls": "stun:stun.l.google.com:19302"},
+                    {"urls": ["turn:turn.example.org"],
+                     "username": "jch",
+                     "credential": "topsecret",
+                     "credentialType": "password"
+                    }
+                ],
+                "iceCandidatePoolSize": 4,
+                "sdpSemantics": "unified-plan"
+            }"#;
 
-        j2, err := json.Marshal(conf2)
-        assert.NoError(t, err)
+        let conf: Configuration = serde_json::from_str(j).unwrap();
+        assert_eq!(2, conf.ice_servers.len());
+        assert_eq!(
+            vec!["stun:stun.l.google.com:19302".to_owned()],
+            conf.ice_servers[0].urls
+        );
+        assert_eq!("topsecret", conf.ice_servers[1].credential);
+        assert_eq!(
+            ICECredentialType::Password,
+            conf.ice_servers[1].credential_type
+        );
+        assert_eq!(4, conf.ice_candidate_pool_size);
+        assert_eq!(ICETransportPolicy::Unspecified, conf.ice_transport_policy);
+        assert_eq!(RTCPMuxPolicy::Negotiate, conf.rtcp_mux_policy);
+
+        // fields left to their default are not written
+        assert_eq!(
+            r#"{"iceServers":[{"urls":["stun:stun.l.google.com:19302"]},{"urls":["turn:turn.example.org"],"username":"jch","credential":"topsecret"}],"iceCandidatePoolSize":4}"#,
+            serde_json::to_string(&conf).unwrap()
+        );
+        assert_eq!(
+            "{}",
+            serde_json::to_string(&Configuration::default()).unwrap()
+        );
+    }
 
-        var conf3 Configuration
-        assert.NoError(t, json.Unmarshal(j2, &conf3))
-        assert.Equal(t, conf2, conf3)
-    }*/
+    #[test]
+    fn test_configuration_json_certificates() {
+        let certificate = Certificate::from_key_pair(
+            rcgen::KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap(),
+        )
+        .unwrap();
+        let conf = Configuration {
+            certificates: vec![certificate],
+            ..Default::default()
+        };
+
+        let j = serde_json::to_string(&conf).unwrap();
+        let conf2: Configuration = serde_json::from_str(&j).unwrap();
+        assert!(conf == conf2);
+        assert_eq!(
+            conf.certificates[0].get_fingerprint().unwrap(),
+            conf2.certificates[0].get_fingerprint().unwrap()
+        );
+    }
 }
@@ -15,7 +15,7 @@ impl fmt::Display for ICECandidatePair {
 }
 
 impl ICECandidatePair {
-    fn stats_id(local_id: &str, remote_id: &str) -> String {
+    pub(crate) fn stats_id(local_id: &str, remote_id: &str) -> String {
         format!("{}-{}", local_id, remote_id)
     }
 
@@ -29,4 +29,8 @@ impl ICECandidatePair {
             remote,
         }
     }
+
+    pub(crate) fn get_stats_id(&self) -> &str {
+        self.stats_id.as_str()
+    }
 }
@@ -1,18 +1,21 @@
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// ICECredentialType indicates the type of credentials used to connect to
 /// an ICE server.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ICECredentialType {
     Unspecified,
 
     /// ICECredential::Password describes username and password based
     /// credentials as described in https://tools.ietf.org/html/rfc5389.
+    #[serde(rename = "password")]
     Password,
 
     /// ICECredential::Oauth describes token based credential as described
     /// in https://tools.ietf.org/html/rfc7635.
     /// Not supported in WebRTC 1.0 spec
+    #[serde(rename = "oauth")]
     Oauth,
 }
 
@@ -1,10 +1,16 @@
 use crate::api::setting_engine::SettingEngine;
 use crate::error::Error;
+use crate::peer::ice::ice_candidate::ice_candidate_pair::ICECandidatePair;
 use crate::peer::ice::ice_candidate::ice_candidate_type::ICECandidateType;
 use crate::peer::ice::ice_candidate::*;
 use crate::peer::ice::ice_gather::ice_gatherer_state::ICEGathererState;
+use crate::peer::ice::ice_protocol::ICEProtocol;
 use crate::peer::ice::ICEParameters;
 use crate::peer::policy::ice_transport_policy::ICETransportPolicy;
+use crate::stats::{
+    stats_timestamp_from_instant, ICECandidatePairStats, ICECandidateStats, StatsCollector,
+    StatsReportType, StatsType,
+};
 
 use ice::agent::Agent;
 use ice::candidate::{Candidate, CandidateType};
@@ -13,7 +19,7 @@ use ice::url::Url;
 use anyhow::Result;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -38,12 +44,18 @@ pub type OnGatheringCompleteHdlrFn =
 /// exchanged in signaling.
 #[derive(Default)]
 pub struct ICEGatherer {
-    pub(crate) validated_servers: Vec<Url>,
-    pub(crate) gather_policy: ICETransportPolicy,
+    pub(crate) validated_servers: Mutex<Vec<Url>>,
+    pub(crate) gather_policy: Mutex<ICETransportPolicy>,
+    /// gather_options_changed is set when the options differ from the ones the ICE
+    /// agent was created with
+    pub(crate) gather_options_changed: AtomicBool,
     pub(crate) setting_engine: Arc<SettingEngine>,
 
     pub(crate) state: Arc<AtomicU8>, //ICEGathererState,
     pub(crate) agent: Mutex<Option<Arc<ice::agent::Agent>>>,
+    /// pool holds the candidates gathered for the candidate pool, until the first
+    /// gathering emits them
+    pub(crate) pool: Arc<Mutex<Option<Vec<Option<ICECandidate>>>>>,
 
     pub(crate) on_local_candidate_handler: Arc<Mutex<Option<OnLocalCandidateHdlrFn>>>,
     pub(crate) on_state_change_handler: Arc<Mutex<Option<OnICEGathererStateChangeHdlrFn>>>,
@@ -59,8 +71,8 @@ impl ICEGatherer {
         setting_engine: Arc<SettingEngine>,
     ) -> Self {
         ICEGatherer {
-            gather_policy,
-            validated_servers,
+            gather_policy: Mutex::new(gather_policy),
+            validated_servers: Mutex::new(validated_servers),
             setting_engine,
             state: Arc::new(AtomicU8::new(ICEGathererState::New as u8)),
             ..Default::default()
@@ -75,10 +87,19 @@ impl ICEGatherer {
             }
         }
 
+        let validated_servers = {
+            let validated_servers = self.validated_servers.lock().await;
+            validated_servers.clone()
+        };
+        let gather_policy = {
+            let gather_policy = self.gather_policy.lock().await;
+            *gather_policy
+        };
+
         let mut candidate_types = vec![];
         if self.setting_engine.candidates.ice_lite {
             candidate_types.push(ice::candidate::CandidateType::Host);
-        } else if self.gather_policy == ICETransportPolicy::Relay {
+        } else if gather_policy == ICETransportPolicy::Relay {
             candidate_types.push(ice::candidate::CandidateType::Relay);
         }
 
@@ -98,7 +119,7 @@ impl ICEGatherer {
 
         let mut config = ice::agent::agent_config::AgentConfig {
             lite: self.setting_engine.candidates.ice_lite,
-            urls: self.validated_servers.clone(),
+            urls: validated_servers,
             port_min: self.setting_engine.ephemeral_udp.port_min,
             port_max: self.setting_engine.ephemeral_udp.port_max,
             disconnected_timeout: self.setting_engine.timeout.ice_disconnected_timeout,
@@ -140,17 +161,108 @@ impl ICEGatherer {
             let mut agent = self.agent.lock().await;
             *agent = Some(Arc::new(ice::agent::Agent::new(config).await?));
         }
+        self.gather_options_changed.store(false, Ordering::SeqCst);
 
         Ok(())
     }
 
+    /// set_gather_options replaces the ICE servers and the policy candidates are gathered
+    /// with. They are used by the ICE agent created for the next gathering, an agent which
+    /// is already running keeps gathering with the options it was created with until it is
+    /// replaced by an ICE restart.
+    pub(crate) async fn set_gather_options(
+        &self,
+        validated_servers: Vec<Url>,
+        gather_policy: ICETransportPolicy,
+    ) {
+        let mut changed = {
+            let mut servers = self.validated_servers.lock().await;
+            let changed = servers.len() != validated_servers.len()
+                || servers.iter().zip(&validated_servers).any(|(a, b)| {
+                    a.to_string() != b.to_string()
+                        || a.username != b.username
+                        || a.password != b.password
+                });
+            *servers = validated_servers;
+            changed
+        };
+        {
+            let mut policy = self.gather_policy.lock().await;
+            changed |= *policy != gather_policy;
+            *policy = gather_policy;
+        }
+        if changed {
+            self.gather_options_changed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// gather_options_changed returns whether the ICE servers or the policy were changed
+    /// since the ICE agent was created
+    pub(crate) fn gather_options_changed(&self) -> bool {
+        self.gather_options_changed.load(Ordering::SeqCst)
+    }
+
+    /// replace_agent creates a new ICE agent with the current gather options, and returns
+    /// the previous one for the caller to close once it isn't used anymore.
+    pub(crate) async fn replace_agent(&self) -> Result<Option<Arc<Agent>>> {
+        let previous_agent = {
+            let mut agent = self.agent.lock().await;
+            agent.take()
+        };
+        self.state
+            .store(ICEGathererState::New as u8, Ordering::SeqCst);
+        self.create_agent().await?;
+
+        Ok(previous_agent)
+    }
+
+    /// gather_pool starts gathering the candidates of a candidate pool, before the first
+    /// local description is set. They are held back, with the gathering state left to new,
+    /// until gather is called.
+    pub(crate) async fn gather_pool(&self) -> Result<()> {
+        {
+            let mut pool = self.pool.lock().await;
+            if pool.is_some() || self.state() != ICEGathererState::New {
+                return Ok(());
+            }
+            *pool = Some(vec![]);
+        }
+
+        self.create_agent().await?;
+        self.gather_candidates().await
+    }
+
     /// Gather ICE candidates.
     pub async fn gather(&self) -> Result<()> {
+        {
+            // The candidates of the pool are emitted first, the ones gathered meanwhile
+            // wait for them
+            let mut pool = self.pool.lock().await;
+            if let Some(candidates) = pool.take() {
+                self.set_state(ICEGathererState::Gathering).await;
+                for candidate in candidates {
+                    ICEGatherer::emit_candidate(
+                        candidate,
+                        &self.state,
+                        &self.on_local_candidate_handler,
+                        &self.on_state_change_handler,
+                        &self.on_gathering_complete_handler,
+                    )
+                    .await;
+                }
+                return Ok(());
+            }
+        }
+
         self.create_agent().await?;
         self.set_state(ICEGathererState::Gathering).await;
+        self.gather_candidates().await
+    }
 
+    async fn gather_candidates(&self) -> Result<()> {
         if let Some(agent) = self.get_agent().await {
             let state = Arc::clone(&self.state);
+            let pool = Arc::clone(&self.pool);
             let on_local_candidate_handler = Arc::clone(&self.on_local_candidate_handler);
             let on_state_change_handler = Arc::clone(&self.on_state_change_handler);
             let on_gathering_complete_handler = Arc::clone(&self.on_gathering_complete_handler);
@@ -159,6 +271,7 @@ impl ICEGatherer {
                 .on_candidate(Box::new(
                     move |candidate: Option<Arc<dyn Candidate + Send + Sync>>| {
                         let state_clone = Arc::clone(&state);
+                        let pool_clone = Arc::clone(&pool);
                         let on_local_candidate_handler_clone =
                             Arc::clone(&on_local_candidate_handler);
                         let on_state_change_handler_clone = Arc::clone(&on_state_change_handler);
@@ -166,42 +279,23 @@ impl ICEGatherer {
                             Arc::clone(&on_gathering_complete_handler);
 
                         Box::pin(async move {
-                            if let Some(cand) = candidate {
-                                let c = ICECandidate::from(&cand);
-
-                                let mut on_local_candidate_handler =
-                                    on_local_candidate_handler_clone.lock().await;
-                                if let Some(handler) = &mut *on_local_candidate_handler {
-                                    handler(Some(c)).await;
-                                }
-                            } else {
-                                state_clone
-                                    .store(ICEGathererState::Complete as u8, Ordering::SeqCst);
-
-                                {
-                                    let mut on_state_change_handler =
-                                        on_state_change_handler_clone.lock().await;
-                                    if let Some(handler) = &mut *on_state_change_handler {
-                                        handler(ICEGathererState::Complete).await;
-                                    }
-                                }
-
-                                {
-                                    let mut on_gathering_complete_handler =
-                                        on_gathering_complete_handler_clone.lock().await;
-                                    if let Some(handler) = &mut *on_gathering_complete_handler {
-                                        handler().await;
-                                    }
-                                }
-
-                                {
-                                    let mut on_local_candidate_handler =
-                                        on_local_candidate_handler_clone.lock().await;
-                                    if let Some(handler) = &mut *on_local_candidate_handler {
-                                        handler(None).await;
-                                    }
+                            let c = candidate.map(|cand| ICECandidate::from(&cand));
+                            {
+                                let mut pool = pool_clone.lock().await;
+                                if let Some(candidates) = &mut *pool {
+                                    candidates.push(c);
+                                    return;
                                 }
                             }
+
+                            ICEGatherer::emit_candidate(
+                                c,
+                                &state_clone,
+                                &on_local_candidate_handler_clone,
+                                &on_state_change_handler_clone,
+                                &on_gathering_complete_handler_clone,
+                            )
+                            .await;
                         })
                     },
                 ))
@@ -213,15 +307,63 @@ impl ICEGatherer {
         Ok(())
     }
 
+    /// emit_candidate fires the handlers for a gathered candidate, None being the end
+    /// of the candidates
+    async fn emit_candidate(
+        candidate: Option<ICECandidate>,
+        state: &AtomicU8,
+        on_local_candidate_handler: &Mutex<Option<OnLocalCandidateHdlrFn>>,
+        on_state_change_handler: &Mutex<Option<OnICEGathererStateChangeHdlrFn>>,
+        on_gathering_complete_handler: &Mutex<Option<OnGatheringCompleteHdlrFn>>,
+    ) {
+        if let Some(c) = candidate {
+            let mut on_local_candidate_handler = on_local_candidate_handler.lock().await;
+            if let Some(handler) = &mut *on_local_candidate_handler {
+                handler(Some(c)).await;
+            }
+        } else {
+            state.store(ICEGathererState::Complete as u8, Ordering::SeqCst);
+
+            {
+                let mut on_state_change_handler = on_state_change_handler.lock().await;
+                if let Some(handler) = &mut *on_state_change_handler {
+                    handler(ICEGathererState::Complete).await;
+                }
+            }
+
+            {
+                let mut on_gathering_complete_handler = on_gathering_complete_handler.lock().await;
+                if let Some(handler) = &mut *on_gathering_complete_handler {
+                    handler().await;
+                }
+            }
+
+            {
+                let mut on_local_candidate_handler = on_local_candidate_handler.lock().await;
+                if let Some(handler) = &mut *on_local_candidate_handler {
+                    handler(None).await;
+                }
+            }
+        }
+    }
+
     /// Close prunes all local candidates, and closes the ports.
     pub async fn close(&self) -> Result<()> {
+        {
+            let mut pool = self.pool.lock().await;
+            pool.take();
+        }
         let agent = {
             let mut agent_opt = self.agent.lock().await;
             agent_opt.take()
         };
 
         if let Some(agent) = agent {
-            agent.close().await?;
+            match agent.close().await {
+                // The agent failed to connect after an ICE restart
+                Err(err) if ice::error::Error::ErrClosed.equal(&err) => {}
+                result => result?,
+            }
         }
         self.set_state(ICEGathererState::Closed).await;
 
@@ -296,122 +438,99 @@ impl ICEGatherer {
         agent.clone()
     }
 
-    /*TODO:func (g *ICEGatherer) collectStats(collector *statsReportCollector) {
+    /// collect_stats reports the candidate pairs as well as the local and remote
+    /// candidates known to the ICE agent
+    pub(crate) async fn collect_stats(&self, collector: &StatsCollector, transport_id: &str) {
+        let agent = match self.get_agent().await {
+            Some(agent) => agent,
+            None => return,
+        };
 
-        agent := g.getAgent()
-        if agent == nil {
-            return
+        for candidate_pair_stats in agent.get_candidate_pairs_stats().await {
+            let pair_id = ICECandidatePair::stats_id(
+                &candidate_pair_stats.local_candidate_id,
+                &candidate_pair_stats.remote_candidate_id,
+            );
+
+            collector
+                .collect(StatsReportType::CandidatePair(ICECandidatePairStats {
+                    timestamp: stats_timestamp_from_instant(
+                        candidate_pair_stats.timestamp.into_std(),
+                    ),
+                    stats_type: StatsType::CandidatePair,
+                    id: pair_id,
+                    transport_id: transport_id.to_owned(),
+                    local_candidate_id: candidate_pair_stats.local_candidate_id,
+                    remote_candidate_id: candidate_pair_stats.remote_candidate_id,
+                    state: candidate_pair_stats.state.into(),
+                    nominated: candidate_pair_stats.nominated,
+                    packets_sent: candidate_pair_stats.packets_sent,
+                    packets_received: candidate_pair_stats.packets_received,
+                    bytes_sent: candidate_pair_stats.bytes_sent,
+                    bytes_received: candidate_pair_stats.bytes_received,
+                    last_packet_sent_timestamp: stats_timestamp_from_instant(
+                        candidate_pair_stats.last_packet_sent_timestamp.into_std(),
+                    ),
+                    last_packet_received_timestamp: stats_timestamp_from_instant(
+                        candidate_pair_stats
+                            .last_packet_received_timestamp
+                            .into_std(),
+                    ),
+                    total_round_trip_time: candidate_pair_stats.total_round_trip_time,
+                    current_round_trip_time: candidate_pair_stats.current_round_trip_time,
+                    available_outgoing_bitrate: candidate_pair_stats.available_outgoing_bitrate,
+                    available_incoming_bitrate: candidate_pair_stats.available_incoming_bitrate,
+                    requests_received: candidate_pair_stats.requests_received,
+                    requests_sent: candidate_pair_stats.requests_sent,
+                    responses_received: candidate_pair_stats.responses_received,
+                    responses_sent: candidate_pair_stats.responses_sent,
+                    consent_requests_sent: candidate_pair_stats.consent_requests_sent,
+                }))
+                .await;
         }
 
-        collector.Collecting()
-        go func(collector *statsReportCollector, agent *ice.Agent) {
-            for _, candidatePairStats := range agent.GetCandidatePairsStats() {
-                collector.Collecting()
-
-                state, err := toStatsICECandidatePairState(candidatePairStats.State)
-                if err != nil {
-                    g.log.Error(err.Error())
-                }
-
-                pairID := newICECandidatePairStatsID(candidatePairStats.LocalCandidateID,
-                    candidatePairStats.RemoteCandidateID)
-
-                stats := ICECandidatePairStats{
-                    Timestamp: statsTimestampFrom(candidatePairStats.Timestamp),
-                    Type:      StatsTypeCandidatePair,
-                    ID:        pairID,
-                    // TransportID:
-                    LocalCandidateID:            candidatePairStats.LocalCandidateID,
-                    RemoteCandidateID:           candidatePairStats.RemoteCandidateID,
-                    State:                       state,
-                    Nominated:                   candidatePairStats.Nominated,
-                    PacketsSent:                 candidatePairStats.PacketsSent,
-                    PacketsReceived:             candidatePairStats.PacketsReceived,
-                    BytesSent:                   candidatePairStats.BytesSent,
-                    BytesReceived:               candidatePairStats.BytesReceived,
-                    LastPacketSentTimestamp:     statsTimestampFrom(candidatePairStats.LastPacketSentTimestamp),
-                    LastPacketReceivedTimestamp: statsTimestampFrom(candidatePairStats.LastPacketReceivedTimestamp),
-                    FirstRequestTimestamp:       statsTimestampFrom(candidatePairStats.FirstRequestTimestamp),
-                    LastRequestTimestamp:        statsTimestampFrom(candidatePairStats.LastRequestTimestamp),
-                    LastResponseTimestamp:       statsTimestampFrom(candidatePairStats.LastResponseTimestamp),
-                    TotalRoundTripTime:          candidatePairStats.TotalRoundTripTime,
-                    CurrentRoundTripTime:        candidatePairStats.CurrentRoundTripTime,
-                    AvailableOutgoingBitrate:    candidatePairStats.AvailableOutgoingBitrate,
-                    AvailableIncomingBitrate:    candidatePairStats.AvailableIncomingBitrate,
-                    CircuitBreakerTriggerCount:  candidatePairStats.CircuitBreakerTriggerCount,
-                    RequestsReceived:            candidatePairStats.RequestsReceived,
-                    RequestsSent:                candidatePairStats.RequestsSent,
-                    ResponsesReceived:           candidatePairStats.ResponsesReceived,
-                    ResponsesSent:               candidatePairStats.ResponsesSent,
-                    RetransmissionsReceived:     candidatePairStats.RetransmissionsReceived,
-                    RetransmissionsSent:         candidatePairStats.RetransmissionsSent,
-                    ConsentRequestsSent:         candidatePairStats.ConsentRequestsSent,
-                    ConsentExpiredTimestamp:     statsTimestampFrom(candidatePairStats.ConsentExpiredTimestamp),
-                }
-                collector.Collect(stats.ID, stats)
-            }
-
-            for _, candidateStats := range agent.GetLocalCandidatesStats() {
-                collector.Collecting()
-
-                networkType, err := getNetworkType(candidateStats.NetworkType)
-                if err != nil {
-                    g.log.Error(err.Error())
-                }
-
-                candidateType, err := get_candidate_type(candidateStats.CandidateType)
-                if err != nil {
-                    g.log.Error(err.Error())
-                }
-
-                stats := ICECandidateStats{
-                    Timestamp:     statsTimestampFrom(candidateStats.Timestamp),
-                    ID:            candidateStats.ID,
-                    Type:          StatsTypeLocalCandidate,
-                    NetworkType:   networkType,
-                    IP:            candidateStats.IP,
-                    Port:          int32(candidateStats.Port),
-                    Protocol:      networkType.Protocol(),
-                    CandidateType: candidateType,
-                    priority:      int32(candidateStats.priority),
-                    URL:           candidateStats.URL,
-                    RelayProtocol: candidateStats.RelayProtocol,
-                    Deleted:       candidateStats.Deleted,
-                }
-                collector.Collect(stats.ID, stats)
-            }
-
-            for _, candidateStats := range agent.GetRemoteCandidatesStats() {
-                collector.Collecting()
-                networkType, err := getNetworkType(candidateStats.NetworkType)
-                if err != nil {
-                    g.log.Error(err.Error())
-                }
-
-                candidateType, err := get_candidate_type(candidateStats.CandidateType)
-                if err != nil {
-                    g.log.Error(err.Error())
-                }
+        for candidate_stats in agent.get_local_candidates_stats().await {
+            collector
+                .collect(StatsReportType::LocalCandidate(ICECandidateStats {
+                    timestamp: stats_timestamp_from_instant(candidate_stats.timestamp.into_std()),
+                    stats_type: StatsType::LocalCandidate,
+                    id: candidate_stats.id,
+                    transport_id: transport_id.to_owned(),
+                    address: candidate_stats.ip,
+                    port: candidate_stats.port,
+                    protocol: ICEProtocol::from(
+                        candidate_stats.network_type.network_short().as_str(),
+                    ),
+                    candidate_type: candidate_stats.candidate_type.into(),
+                    priority: candidate_stats.priority,
+                    url: candidate_stats.url,
+                    relay_protocol: candidate_stats.relay_protocol,
+                    deleted: candidate_stats.deleted,
+                }))
+                .await;
+        }
 
-                stats := ICECandidateStats{
-                    Timestamp:     statsTimestampFrom(candidateStats.Timestamp),
-                    ID:            candidateStats.ID,
-                    Type:          StatsTypeRemoteCandidate,
-                    NetworkType:   networkType,
-                    IP:            candidateStats.IP,
-                    Port:          int32(candidateStats.Port),
-                    Protocol:      networkType.Protocol(),
-                    CandidateType: candidateType,
-                    priority:      int32(candidateStats.priority),
-                    URL:           candidateStats.URL,
-                    RelayProtocol: candidateStats.RelayProtocol,
-                }
-                collector.Collect(stats.ID, stats)
-            }
-            collector.Done()
-        }(collector, agent)
+        for candidate_stats in agent.get_remote_candidates_stats().await {
+            collector
+                .collect(StatsReportType::RemoteCandidate(ICECandidateStats {
+                    timestamp: stats_timestamp_from_instant(candidate_stats.timestamp.into_std()),
+                    stats_type: StatsType::RemoteCandidate,
+                    id: candidate_stats.id,
+                    transport_id: transport_id.to_owned(),
+                    address: candidate_stats.ip,
+                    port: candidate_stats.port,
+                    protocol: ICEProtocol::from(
+                        candidate_stats.network_type.network_short().as_str(),
+                    ),
+                    candidate_type: candidate_stats.candidate_type.into(),
+                    priority: candidate_stats.priority,
+                    url: candidate_stats.url,
+                    relay_protocol: candidate_stats.relay_protocol,
+                    deleted: candidate_stats.deleted,
+                }))
+                .await;
+        }
     }
-    */
 }
 
 #[cfg(test)]
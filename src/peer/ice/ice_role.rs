@@ -1,8 +1,9 @@
+use serde::Serialize;
 use std::fmt;
 
 /// ICERole describes the role ice.Agent is playing in selecting the
 /// preferred the candidate pair.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
 pub enum ICERole {
     Unspecified,
 
@@ -10,10 +11,12 @@ pub enum ICERole {
     /// for selecting the final choice of candidate pairs and signaling them
     /// through STUN and an updated offer, if needed. In any session, one agent
     /// is always controlling. The other is the controlled agent.
+    #[serde(rename = "controlling")]
     Controlling,
 
     /// ICERoleControlled indicates that an ICE agent that waits for the
     /// controlling agent to select the final choice of candidate pairs.
+    #[serde(rename = "controlled")]
     Controlled,
 }
 
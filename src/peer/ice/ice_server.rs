@@ -1,18 +1,44 @@
 use crate::error::Error;
 use crate::peer::ice::ice_credential_type::ICECredentialType;
+use crate::util::is_default;
 
 use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize};
 
 /// ICEServer describes a single STUN and TURN server that can be used by
 /// the ICEAgent to establish a connection with a peer.
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ICEServer {
+    #[serde(deserialize_with = "deserialize_urls")]
     pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub username: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub credential: String,
+    #[serde(default, skip_serializing_if = "is_default")]
     pub credential_type: ICECredentialType,
 }
 
+/// deserialize_urls accepts both a single url and a list of them, as the
+/// urls of RTCIceServer do.
+fn deserialize_urls<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Urls {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Urls::deserialize(deserializer)? {
+        Urls::One(url) => vec![url],
+        Urls::Many(urls) => urls,
+    })
+}
+
 impl ICEServer {
     pub(crate) fn parse_url(&self, url_str: &str) -> Result<ice::url::Url> {
         ice::url::Url::parse_url(url_str)
@@ -43,11 +69,9 @@ impl ICEServer {
                     }
                     ICECredentialType::Oauth => {
                         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11.3.4)
-                        /*if _, ok: = s.Credential.(OAuthCredential); !ok {
-                                return nil,
-                                &rtcerr.InvalidAccessError{Err: ErrTurnCredentials
-                            }
-                        }*/
+                        // the TURN client doesn't implement the ACCESS-TOKEN attribute
+                        // of RFC 7635, so it can't authenticate with a token
+                        return Err(Error::ErrTurnOAuthNotSupported.into());
                     }
                     _ => return Err(Error::ErrTurnCredentials.into()),
                 };
@@ -139,6 +163,15 @@ mod test {
                 },
                 Error::ErrNoTurnCredentials,
             ),
+            (
+                ICEServer {
+                    urls: vec!["turn:192.158.29.39?transport=udp".to_owned()],
+                    username: "unittest".to_owned(),
+                    credential: "placeholder".to_owned(),
+                    credential_type: ICECredentialType::Oauth,
+                },
+                Error::ErrTurnOAuthNotSupported,
+            ),
         ];
 
         for (ice_server, expected_err) in tests {
@@ -0,0 +1,117 @@
+use crate::error::Error;
+use crate::media::dtls_transport::dtls_fingerprint::DTLSFingerprint;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// IdentityProviderDetails names the identity provider which generated an
+/// identity assertion, and the protocol used to talk to it.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityProviderDetails {
+    pub domain: String,
+    pub protocol: String,
+}
+
+/// IdentityAssertion is the identity assertion generated by an IdentityProvider.
+/// It is carried base64 encoded as JSON in the a=identity attribute of the SDP.
+/// https://www.w3.org/TR/webrtc-identity/#dom-rtcidentityassertionresult
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityAssertion {
+    pub idp: IdentityProviderDetails,
+    /// assertion is opaque to the PeerConnection, only the IdentityProvider
+    /// interprets it.
+    pub assertion: String,
+}
+
+/// IdentityValidationResult is the result of the validation of an identity
+/// assertion by an IdentityProvider.
+/// https://www.w3.org/TR/webrtc-identity/#dom-rtcidentityvalidationresult
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct IdentityValidationResult {
+    /// identity is the validated identity of the remote peer, in the form
+    /// user@domain where domain is the domain of the identity provider.
+    pub identity: String,
+    /// contents are the contents the assertion was generated for.
+    pub contents: String,
+}
+
+/// IdentityProvider generates identity assertions for the local peer and
+/// validates the identity assertions of remote peers, as described in
+/// https://www.w3.org/TR/webrtc-identity/#identity-provider-interaction.
+/// The contents bind an assertion to the DTLS fingerprints of the peer, so the
+/// identity is only accepted for the certificate presented during the handshake.
+#[async_trait]
+pub trait IdentityProvider {
+    /// generate_assertion asserts the identity of the local peer for the contents.
+    async fn generate_assertion(&self, contents: &str) -> Result<IdentityAssertion>;
+
+    /// validate_assertion verifies an assertion generated by the remote peer and
+    /// returns the identity and contents it asserts.
+    async fn validate_assertion(
+        &self,
+        assertion: &IdentityAssertion,
+    ) -> Result<IdentityValidationResult>;
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct AssertedFingerprint {
+    algorithm: String,
+    digest: String,
+}
+
+/// AssertedContents are the contents bound by an identity assertion, as defined
+/// in https://tools.ietf.org/html/rfc8827
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct AssertedContents {
+    fingerprint: Vec<AssertedFingerprint>,
+}
+
+/// identity_contents returns the contents to assert for the DTLS fingerprints.
+pub(crate) fn identity_contents(fingerprints: &[DTLSFingerprint]) -> Result<String> {
+    let contents = AssertedContents {
+        fingerprint: fingerprints
+            .iter()
+            .map(|fp| AssertedFingerprint {
+                algorithm: fp.algorithm.clone(),
+                digest: fp.value.clone(),
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string(&contents)?)
+}
+
+/// contents_match_fingerprints checks that the asserted contents bind exactly the
+/// DTLS fingerprints, ignoring their order and case.
+pub(crate) fn contents_match_fingerprints(
+    contents: &str,
+    fingerprints: &[DTLSFingerprint],
+) -> Result<bool> {
+    let contents: AssertedContents =
+        serde_json::from_str(contents).map_err(|_| Error::ErrInvalidIdentityAssertion)?;
+
+    let mut asserted: Vec<(String, String)> = contents
+        .fingerprint
+        .into_iter()
+        .map(|fp| (fp.algorithm.to_lowercase(), fp.digest.to_lowercase()))
+        .collect();
+    let mut expected: Vec<(String, String)> = fingerprints
+        .iter()
+        .map(|fp| (fp.algorithm.to_lowercase(), fp.value.to_lowercase()))
+        .collect();
+    asserted.sort();
+    expected.sort();
+
+    Ok(asserted == expected)
+}
+
+/// encode_identity returns the value of the a=identity attribute for the assertion.
+pub(crate) fn encode_identity(assertion: &IdentityAssertion) -> Result<String> {
+    Ok(base64::encode(serde_json::to_string(assertion)?))
+}
+
+/// decode_identity parses the value of an a=identity attribute.
+pub(crate) fn decode_identity(value: &str) -> Result<IdentityAssertion> {
+    let json = base64::decode(value).map_err(|_| Error::ErrInvalidIdentityAssertion)?;
+    Ok(serde_json::from_slice(&json).map_err(|_| Error::ErrInvalidIdentityAssertion)?)
+}
@@ -1,5 +1,6 @@
 pub mod configuration;
 pub mod ice;
+pub mod identity_provider;
 pub mod offer_answer_options;
 pub(crate) mod operation;
 pub mod peer_connection;
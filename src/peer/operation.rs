@@ -92,7 +92,10 @@ impl Operations {
                     if let Some(mut f) = result {
                         length.fetch_sub(1, Ordering::SeqCst);
                         if f.0().await {
-                            let _ = ops_tx.send(f);
+                            // Requeue with its own count, or is_empty would never hold again
+                            if ops_tx.send(f).is_ok() {
+                                length.fetch_add(1, Ordering::SeqCst);
+                            }
                         }
                     }
                 }
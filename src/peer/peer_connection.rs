@@ -24,6 +24,9 @@ use crate::peer::ice::ice_gather::ice_gatherer::{
 };
 use crate::peer::ice::ice_gather::ICEGatherOptions;
 use crate::peer::peer_connection_state::{NegotiationNeededState, PeerConnectionState};
+use crate::peer::policy::bundle_policy::BundlePolicy;
+use crate::peer::policy::ice_transport_policy::ICETransportPolicy;
+use crate::peer::policy::rtcp_mux_policy::RTCPMuxPolicy;
 use crate::peer::policy::sdp_semantics::SDPSemantics;
 use crate::peer::sdp::session_description::{SessionDescription, SessionDescriptionSerde};
 use crate::peer::signaling_state::{check_next_signaling_state, SignalingState, StateChangeOp};
@@ -43,7 +46,7 @@ use crate::media::dtls_transport::dtls_role::{
 use crate::media::rtp::rtp_codec::{RTPCodecType, RTPHeaderExtensionCapability};
 use crate::media::rtp::rtp_sender::RTPSender;
 use crate::media::rtp::rtp_transceiver_direction::RTPTransceiverDirection;
-use crate::media::rtp::{RTPTransceiverInit, SSRC};
+use crate::media::rtp::{RTPTransceiverInit, SDES_REPAIR_RTP_STREAM_ID_URI, SSRC};
 use crate::media::track::track_local::track_local_static_sample::TrackLocalStaticSample;
 use crate::media::track::track_local::TrackLocal;
 use crate::peer::ice::ice_candidate::{ICECandidate, ICECandidateInit};
@@ -51,10 +54,18 @@ use crate::peer::ice::ice_gather::ice_gatherer_state::ICEGathererState;
 use crate::peer::ice::ice_gather::ice_gathering_state::ICEGatheringState;
 use crate::peer::ice::ice_role::ICERole;
 use crate::peer::ice::ICEParameters;
+use crate::peer::identity_provider::{
+    contents_match_fingerprints, decode_identity, encode_identity, identity_contents,
+    IdentityProvider,
+};
 use crate::peer::offer_answer_options::{AnswerOptions, OfferOptions};
 use crate::peer::operation::{Operation, Operations};
 use crate::peer::sdp::sdp_type::SDPType;
 use crate::peer::sdp::*;
+use crate::stats::{
+    stats_timestamp_now, PeerConnectionStats, StatsCollector, StatsReport, StatsReportType,
+    StatsType,
+};
 use crate::util::{flatten_errs, math_rand_alpha};
 use crate::{
     MEDIA_SECTION_APPLICATION, RECEIVE_MTU, SIMULCAST_MAX_PROBE_ROUTINES, SIMULCAST_PROBE_COUNT,
@@ -68,9 +79,12 @@ use ice::candidate::Candidate;
 use interceptor::{Attributes, Interceptor, RTCPWriter};
 use peer_connection_internal::*;
 use rcgen::KeyPair;
-use sdp::session_description::{ATTR_KEY_ICELITE, ATTR_KEY_MSID};
+use sdp::session_description::{
+    ATTR_KEY_END_OF_CANDIDATES, ATTR_KEY_ICELITE, ATTR_KEY_IDENTITY, ATTR_KEY_MSID,
+};
 use sdp::util::ConnectionRole;
 use srtp::stream::Stream;
+use std::collections::HashSet;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
@@ -146,9 +160,8 @@ struct NegotiationNeededParams {
 /// browser, or to another endpoint implementing the required protocols.
 pub struct PeerConnection {
     stats_id: String,
-    idp_login_url: Option<String>,
 
-    configuration: Configuration,
+    configuration: Mutex<Configuration>,
 
     interceptor_rtcp_writer: Arc<dyn RTCPWriter + Send + Sync>,
 
@@ -166,8 +179,14 @@ impl PeerConnection {
         PeerConnection::init_configuration(&mut configuration)?;
 
         let internal = Arc::new(PeerConnectionInternal::new(api, &mut configuration).await?);
+        internal
+            .start_candidate_pool(configuration.ice_candidate_pool_size)
+            .await?;
         let internal_rtcp_writer = Arc::clone(&internal) as Arc<dyn RTCPWriter + Send + Sync>;
-        let interceptor_rtcp_writer = api.interceptor.bind_rtcp_writer(internal_rtcp_writer).await;
+        let interceptor_rtcp_writer = internal
+            .interceptor
+            .bind_rtcp_writer(internal_rtcp_writer)
+            .await;
 
         // https://w3c.github.io/webrtc-pc/#constructor (Step #2)
         // Some variables defined explicitly despite their implicit zero values to
@@ -179,8 +198,7 @@ impl PeerConnection {
             ),
             interceptor_rtcp_writer,
             internal,
-            configuration,
-            idp_login_url: None,
+            configuration: Mutex::new(configuration),
         })
     }
 
@@ -301,6 +319,10 @@ impl PeerConnection {
         {
             let handler = params.on_negotiation_needed_handler.lock().await;
             if handler.is_none() {
+                // Let a handler set later be fired by the next change
+                params
+                    .negotiation_needed_state
+                    .store(NegotiationNeededState::Empty as u8, Ordering::SeqCst);
                 return false;
             }
         }
@@ -373,15 +395,15 @@ impl PeerConnection {
             for t in &*transceivers {
                 // https://www.w3.org/TR/webrtc/#dfn-update-the-negotiation-needed-flag
                 // Step 5.1
-                // if t.stopping && !t.stopped {
-                // 	return true
-                // }
+                if t.stopping() && !t.stopped() {
+                    return true;
+                }
                 let m = get_by_mid(t.mid().await.as_str(), local_desc);
                 // Step 5.2
-                if !t.stopped && m.is_none() {
+                if !t.stopped() && m.is_none() {
                     return true;
                 }
-                if !t.stopped {
+                if !t.stopped() {
                     if let Some(m) = m {
                         // Step 5.3.1
                         if t.direction() == RTPTransceiverDirection::Sendrecv
@@ -422,8 +444,19 @@ impl PeerConnection {
                             }
                             SDPType::Answer => {
                                 // Step 5.3.3
-                                if m.attribute(t.direction().to_string().as_str()).is_none() {
-                                    return true;
+                                let current_remote_description =
+                                    params.current_remote_description.lock().await;
+                                if let Some(remote_desc) = &*current_remote_description {
+                                    if let Some(rm) =
+                                        get_by_mid(t.mid().await.as_str(), remote_desc)
+                                    {
+                                        if get_peer_direction(m)
+                                            != t.direction()
+                                                .intersect(get_peer_direction(rm).reverse())
+                                        {
+                                            return true;
+                                        }
+                                    }
                                 }
                             }
                             _ => {}
@@ -431,13 +464,18 @@ impl PeerConnection {
                     }
                 }
                 // Step 5.4
-                if t.stopped && !t.mid().await.is_empty() {
+                if t.stopped() && !t.mid().await.is_empty() {
+                    if let Some(m) = m {
+                        if !is_media_section_rejected(m) {
+                            return true;
+                        }
+                    }
                     let current_remote_description = params.current_remote_description.lock().await;
                     if let Some(remote_desc) = &*current_remote_description {
-                        if get_by_mid(t.mid().await.as_str(), local_desc).is_some()
-                            || get_by_mid(t.mid().await.as_str(), remote_desc).is_some()
-                        {
-                            return true;
+                        if let Some(rm) = get_by_mid(t.mid().await.as_str(), remote_desc) {
+                            if !is_media_section_rejected(rm) {
+                                return true;
+                            }
                         }
                     }
                 }
@@ -537,80 +575,105 @@ impl PeerConnection {
         }
     }
 
-    /*TODO: // set_configuration updates the configuration of this PeerConnection object.
-    pub async fn set_configuration(&mut self, configuration: Configuration) -> Result<()> {
-        //nolint:gocognit
+    /// set_configuration updates the configuration of this PeerConnection object.
+    /// Fields left to their default value are not modified. Certificates, bundle
+    /// policy, rtcp-mux policy and peer identity can't be changed, nor the ICE candidate
+    /// pool size once a local description was set. As negotiate is the default rtcp-mux
+    /// policy, a PeerConnection requiring rtcp-mux must be passed its policy again.
+    ///
+    /// ICE servers and the ICE transport policy are used for the next gathering of
+    /// candidates. Once candidates were gathered, they are applied by the next ICE restart.
+    /// https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-setconfiguration
+    pub async fn set_configuration(&self, configuration: Configuration) -> Result<()> {
         // https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-setconfiguration (step #2)
         if self.internal.is_closed.load(Ordering::SeqCst) {
             return Err(Error::ErrConnectionClosed.into());
         }
 
+        let mut current = self.configuration.lock().await;
+
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #3)
-        if !configuration.peer_identity.is_empty() {
-            if configuration.peer_identity != self.configuration.peer_identity {
-                return Err(Error::ErrModifyingPeerIdentity.into());
-            }
-            self.configuration.peer_identity = configuration.peer_identity;
+        if !configuration.peer_identity.is_empty()
+            && configuration.peer_identity != current.peer_identity
+        {
+            return Err(Error::ErrModifyingPeerIdentity.into());
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #4)
-        if !configuration.certificates.is_empty() {
-            if configuration.certificates.len() != self.configuration.certificates.len() {
-                return Err(Error::ErrModifyingCertificates.into());
-            }
-
-            self.configuration.certificates = configuration.certificates;
+        if !configuration.certificates.is_empty()
+            && configuration.certificates != current.certificates
+        {
+            return Err(Error::ErrModifyingCertificates.into());
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #5)
-        if configuration.bundle_policy != BundlePolicy::Unspecified {
-            if configuration.bundle_policy != self.configuration.bundle_policy {
-                return Err(Error::ErrModifyingBundlePolicy.into());
-            }
-            self.configuration.bundle_policy = configuration.bundle_policy;
+        if configuration.bundle_policy != BundlePolicy::Unspecified
+            && configuration.bundle_policy != current.bundle_policy
+        {
+            return Err(Error::ErrModifyingBundlePolicy.into());
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #6)
-        if configuration.rtcp_mux_policy != RTCPMuxPolicy::Unspecified {
-            if configuration.rtcp_mux_policy != self.configuration.rtcp_mux_policy {
-                return Err(Error::ErrModifyingRTCPMuxPolicy.into());
-            }
-            self.configuration.rtcp_mux_policy = configuration.rtcp_mux_policy;
+        if configuration.rtcp_mux_policy != RTCPMuxPolicy::Unspecified
+            && configuration.rtcp_mux_policy != current.rtcp_mux_policy
+        {
+            return Err(Error::ErrModifyingRTCPMuxPolicy.into());
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #7)
+        if configuration.ice_candidate_pool_size != 0
+            && configuration.ice_candidate_pool_size != current.ice_candidate_pool_size
+            && self.local_description().await.is_some()
+        {
+            return Err(Error::ErrModifyingICECandidatePoolSize.into());
+        }
+
+        // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11)
+        // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11.3)
+        let mut validated_servers = vec![];
+        for server in &configuration.get_ice_servers() {
+            validated_servers.extend(server.urls()?);
+        }
+
+        // nothing is modified until the whole configuration was validated
         if configuration.ice_candidate_pool_size != 0 {
-            if self.configuration.ice_candidate_pool_size != configuration.ice_candidate_pool_size
-                && self.local_description().await.is_some()
-            {
-                return Err(Error::ErrModifyingICECandidatePoolSize.into());
-            }
-            self.configuration.ice_candidate_pool_size = configuration.ice_candidate_pool_size;
+            current.ice_candidate_pool_size = configuration.ice_candidate_pool_size;
         }
 
         // https://www.w3.org/TR/webrtc/#set-the-configuration (step #8)
         if configuration.ice_transport_policy != ICETransportPolicy::Unspecified {
-            self.configuration.ice_transport_policy = configuration.ice_transport_policy
+            current.ice_transport_policy = configuration.ice_transport_policy;
         }
 
-        // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11)
         if !configuration.ice_servers.is_empty() {
-            // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11.3)
-            for server in &configuration.ice_servers {
-                server.validate()?;
+            current.ice_servers = configuration.ice_servers;
+        } else {
+            for server in &current.get_ice_servers() {
+                validated_servers.extend(server.urls()?);
             }
-            self.configuration.ice_servers = configuration.ice_servers
         }
-        Ok(())
-    }*/
+
+        self.internal
+            .ice_gatherer
+            .set_gather_options(validated_servers, current.ice_transport_policy)
+            .await;
+
+        // https://www.w3.org/TR/webrtc/#set-the-configuration (step #11)
+        let ice_candidate_pool_size = current.ice_candidate_pool_size;
+        drop(current);
+        self.internal
+            .start_candidate_pool(ice_candidate_pool_size)
+            .await
+    }
 
     /// get_configuration returns a Configuration object representing the current
     /// configuration of this PeerConnection object. The returned object is a
     /// copy and direct mutation on it will not take affect until set_configuration
     /// has been called with Configuration passed as its only argument.
     /// https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-getconfiguration
-    pub fn get_configuration(&self) -> &Configuration {
-        &self.configuration
+    pub async fn get_configuration(&self) -> Configuration {
+        let configuration = self.configuration.lock().await;
+        configuration.clone()
     }
 
     fn get_stats_id(&self) -> &str {
@@ -620,10 +683,7 @@ impl PeerConnection {
     /// create_offer starts the PeerConnection and generates the localDescription
     /// https://w3c.github.io/webrtc-pc/#dom-rtcpeerconnection-createoffer
     pub async fn create_offer(&self, options: Option<OfferOptions>) -> Result<SessionDescription> {
-        let use_identity = self.idp_login_url.is_some();
-        if use_identity {
-            return Err(Error::ErrIdentityProviderNotImplemented.into());
-        } else if self.internal.is_closed.load(Ordering::SeqCst) {
+        if self.internal.is_closed.load(Ordering::SeqCst) {
             return Err(Error::ErrConnectionClosed.into());
         }
 
@@ -633,6 +693,8 @@ impl PeerConnection {
             }
         }
 
+        let identity = self.internal.generate_identity_assertion().await?;
+
         // This may be necessary to recompute if, for example, createOffer was called when only an
         // audio RTCRtpTransceiver was added to connection, but while performing the in-parallel
         // steps to create an offer, a video RTCRtpTransceiver was added, requiring additional
@@ -657,7 +719,7 @@ impl PeerConnection {
                 if current_remote_description.is_some() {
                     description_is_plan_b(current_remote_description.as_ref())?
                 } else {
-                    self.configuration.sdp_semantics == SDPSemantics::PlanB
+                    self.configuration.lock().await.sdp_semantics == SDPSemantics::PlanB
                 }
             };
 
@@ -691,7 +753,7 @@ impl PeerConnection {
                     }
                 }
                 for t in &current_transceivers {
-                    if !t.mid().await.is_empty() {
+                    if !t.mid().await.is_empty() || t.stopping() {
                         continue;
                     }
                     let greater_mid = self.internal.greater_mid.fetch_add(1, Ordering::SeqCst);
@@ -704,23 +766,20 @@ impl PeerConnection {
                     self.internal.current_remote_description.lock().await;
                 current_remote_description.is_none()
             };
+            let sdp_semantics = self.configuration.lock().await.sdp_semantics;
 
             let mut d = if current_remote_description_is_none {
                 self.internal
-                    .generate_unmatched_sdp(
-                        current_transceivers,
-                        use_identity,
-                        self.configuration.sdp_semantics,
-                    )
+                    .generate_unmatched_sdp(current_transceivers, identity.clone(), sdp_semantics)
                     .await?
             } else {
                 self.internal
                     .generate_matched_sdp(
                         current_transceivers,
-                        use_identity,
+                        identity.clone(),
                         true, /*includeUnmatched */
                         DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
-                        self.configuration.sdp_semantics,
+                        sdp_semantics,
                     )
                     .await?
             };
@@ -810,11 +869,8 @@ impl PeerConnection {
         &self,
         _options: Option<AnswerOptions>,
     ) -> Result<SessionDescription> {
-        let use_identity = self.idp_login_url.is_some();
         if self.remote_description().await.is_none() {
             return Err(Error::ErrNoRemoteDescription.into());
-        } else if use_identity {
-            return Err(Error::ErrIdentityProviderNotImplemented.into());
         } else if self.internal.is_closed.load(Ordering::SeqCst) {
             return Err(Error::ErrConnectionClosed.into());
         } else if self.signaling_state() != SignalingState::HaveRemoteOffer
@@ -832,15 +888,17 @@ impl PeerConnection {
             connection_role = DEFAULT_DTLS_ROLE_ANSWER.to_connection_role();
         }
 
+        let identity = self.internal.generate_identity_assertion().await?;
         let local_transceivers = self.get_transceivers().await;
+        let sdp_semantics = self.configuration.lock().await.sdp_semantics;
         let mut d = self
             .internal
             .generate_matched_sdp(
                 local_transceivers,
-                use_identity,
+                identity,
                 false, /*includeUnmatched */
                 connection_role,
-                self.configuration.sdp_semantics,
+                sdp_semantics,
             )
             .await?;
 
@@ -958,9 +1016,12 @@ impl PeerConnection {
                                 sd.serde.sdp_type,
                             );
                             if next_state.is_ok() {
-                                let mut pending_local_description =
-                                    self.internal.pending_local_description.lock().await;
-                                *pending_local_description = None;
+                                {
+                                    let mut pending_local_description =
+                                        self.internal.pending_local_description.lock().await;
+                                    *pending_local_description = None;
+                                }
+                                self.rollback_transceivers(StateChangeOp::SetLocal).await;
                             }
                             next_state
                         }
@@ -1050,9 +1111,12 @@ impl PeerConnection {
                                 sd.serde.sdp_type,
                             );
                             if next_state.is_ok() {
-                                let mut pending_remote_description =
-                                    self.internal.pending_remote_description.lock().await;
-                                *pending_remote_description = None;
+                                {
+                                    let mut pending_remote_description =
+                                        self.internal.pending_remote_description.lock().await;
+                                    *pending_remote_description = None;
+                                }
+                                self.rollback_transceivers(StateChangeOp::SetRemote).await;
                             }
                             next_state
                         }
@@ -1119,12 +1183,18 @@ impl PeerConnection {
         }
     }
 
-    /// set_local_description sets the SessionDescription of the local peer
+    /// set_local_description sets the SessionDescription of the local peer.
+    /// A description of type rollback discards the pending local offer and returns
+    /// the signaling state to stable.
     pub async fn set_local_description(&self, mut desc: SessionDescription) -> Result<()> {
         if self.internal.is_closed.load(Ordering::SeqCst) {
             return Err(Error::ErrConnectionClosed.into());
         }
 
+        if desc.serde.sdp_type == SDPType::Rollback {
+            return self.set_description(&desc, StateChangeOp::SetLocal).await;
+        }
+
         let have_local_description = {
             let current_local_description = self.internal.current_local_description.lock().await;
             current_local_description.is_some()
@@ -1147,6 +1217,7 @@ impl PeerConnection {
 
         desc.parsed = Some(desc.unmarshal()?);
         self.set_description(&desc, StateChangeOp::SetLocal).await?;
+        self.update_transceivers_from_answer(&desc, true).await;
 
         let we_answer = desc.serde.sdp_type == SDPType::Answer;
         let remote_description = self.remote_description().await;
@@ -1155,7 +1226,7 @@ impl PeerConnection {
                 self.start_rtp_senders().await?;
 
                 let pci = Arc::clone(&self.internal);
-                let sdp_semantics = self.configuration.sdp_semantics;
+                let sdp_semantics = self.configuration.lock().await.sdp_semantics;
                 let remote_desc = Arc::new(remote_desc);
                 self.internal
                     .ops
@@ -1180,6 +1251,60 @@ impl PeerConnection {
         }
     }
 
+    /// set_local_description_implicit creates the description the signaling state calls
+    /// for, an answer when a remote offer is pending and an offer otherwise, and sets it
+    /// as the local description. Together with rollbacks, this lets both peers apply the
+    /// perfect negotiation pattern.
+    /// https://www.w3.org/TR/webrtc/#dom-peerconnection-setlocaldescription
+    pub async fn set_local_description_implicit(&self) -> Result<()> {
+        let desc = match self.signaling_state() {
+            SignalingState::HaveRemoteOffer | SignalingState::HaveLocalPranswer => {
+                self.create_answer(None).await?
+            }
+            _ => self.create_offer(None).await?,
+        };
+        self.set_local_description(desc).await
+    }
+
+    /// rollback_transceivers undoes what a rolled back description did to the transceivers:
+    /// mids which were not negotiated are cleared, and transceivers created by a remote
+    /// offer are removed unless a track was added to them meanwhile.
+    async fn rollback_transceivers(&self, op: StateChangeOp) {
+        let mut removed = vec![];
+        if op == StateChangeOp::SetRemote {
+            let mut remote_offer_transceivers =
+                self.internal.remote_offer_transceivers.lock().await;
+            for t in remote_offer_transceivers.drain(..) {
+                if t.sender().await.is_none() {
+                    removed.push(t);
+                }
+            }
+        }
+
+        let negotiated_mids: HashSet<String> = {
+            let current_local_description = self.internal.current_local_description.lock().await;
+            match current_local_description
+                .as_ref()
+                .and_then(|desc| desc.parsed.as_ref())
+            {
+                Some(parsed) => parsed
+                    .media_descriptions
+                    .iter()
+                    .filter_map(|media| get_mid_value(media).map(|mid| mid.to_owned()))
+                    .collect(),
+                None => HashSet::new(),
+            }
+        };
+
+        let mut rtp_transceivers = self.internal.rtp_transceivers.lock().await;
+        rtp_transceivers.retain(|t| !removed.iter().any(|r| Arc::ptr_eq(r, t)));
+        for t in &*rtp_transceivers {
+            if !negotiated_mids.contains(&t.mid().await) {
+                t.unset_mid().await;
+            }
+        }
+    }
+
     /// local_description returns PendingLocalDescription if it is not null and
     /// otherwise it returns CurrentLocalDescription. This property is used to
     /// determine if set_local_description has already been called.
@@ -1191,20 +1316,42 @@ impl PeerConnection {
         self.current_local_description().await
     }
 
-    /// set_remote_description sets the SessionDescription of the remote peer
+    /// set_remote_description sets the SessionDescription of the remote peer.
+    /// A description of type rollback discards the pending remote offer and returns
+    /// the signaling state to stable.
     pub async fn set_remote_description(&self, mut desc: SessionDescription) -> Result<()> {
         if self.internal.is_closed.load(Ordering::SeqCst) {
             return Err(Error::ErrConnectionClosed.into());
         }
 
+        if desc.serde.sdp_type == SDPType::Rollback {
+            return self.set_description(&desc, StateChangeOp::SetRemote).await;
+        }
+
         let is_renegotation = {
             let current_remote_description = self.internal.current_remote_description.lock().await;
             current_remote_description.is_some()
         };
 
         desc.parsed = Some(desc.unmarshal()?);
+
+        let peer_identity = if let Some(parsed) = &desc.parsed {
+            let expected_identity = self.configuration.lock().await.peer_identity.clone();
+            self.internal
+                .validate_remote_identity(parsed, &expected_identity)
+                .await?
+        } else {
+            None
+        };
+
         self.set_description(&desc, StateChangeOp::SetRemote)
             .await?;
+        self.update_transceivers_from_answer(&desc, false).await;
+
+        if peer_identity.is_some() {
+            let mut validated_peer_identity = self.internal.peer_identity.lock().await;
+            *validated_peer_identity = peer_identity;
+        }
 
         if let Some(parsed) = &desc.parsed {
             self.internal
@@ -1217,6 +1364,12 @@ impl PeerConnection {
             let detected_plan_b = description_is_plan_b(remote_description.as_ref())?;
             let we_offer = desc.serde.sdp_type == SDPType::Answer;
 
+            if !we_offer {
+                let mut remote_offer_transceivers =
+                    self.internal.remote_offer_transceivers.lock().await;
+                remote_offer_transceivers.clear();
+            }
+
             if !we_offer && !detected_plan_b {
                 if let Some(remote_desc) = remote_description {
                     if let Some(parsed) = &remote_desc.parsed {
@@ -1233,17 +1386,46 @@ impl PeerConnection {
                                 }
 
                                 let kind = RTPCodecType::from(media.media_name.media.as_str());
+                                if kind == RTPCodecType::Unspecified {
+                                    continue;
+                                }
+
+                                // A rejected m-line stops its transceiver. One is created for an
+                                // m-line rejected from the start, for the answer to match it.
+                                if is_media_section_rejected(media) {
+                                    if let Some(t) =
+                                        find_by_mid(mid_value, &mut local_transceivers).await
+                                    {
+                                        t.set_stopped().await?;
+                                    } else {
+                                        let t = RTPTransceiver::new(
+                                            None,
+                                            None,
+                                            RTPTransceiverDirection::Inactive,
+                                            kind,
+                                            vec![],
+                                            Arc::clone(&self.internal.media_engine),
+                                        )
+                                        .await;
+                                        t.set_mid(mid_value.to_owned()).await?;
+                                        t.set_stopped().await?;
+
+                                        self.internal.add_rtp_transceiver(Arc::clone(&t)).await;
+                                        let mut remote_offer_transceivers =
+                                            self.internal.remote_offer_transceivers.lock().await;
+                                        remote_offer_transceivers.push(t);
+                                    }
+                                    continue;
+                                }
+
                                 let direction = get_peer_direction(media);
-                                if kind == RTPCodecType::Unspecified
-                                    || direction == RTPTransceiverDirection::Unspecified
-                                {
+                                if direction == RTPTransceiverDirection::Unspecified {
                                     continue;
                                 }
 
                                 let t = if let Some(t) =
                                     find_by_mid(mid_value, &mut local_transceivers).await
                                 {
-                                    t.stop().await?;
                                     Some(t)
                                 } else {
                                     satisfy_type_and_direction(
@@ -1254,17 +1436,9 @@ impl PeerConnection {
                                     .await
                                 };
 
+                                // The direction of the transceiver is left as is, the answer
+                                // intersects it with the offered direction
                                 if let Some(t) = t {
-                                    if direction == RTPTransceiverDirection::Recvonly {
-                                        if t.direction() == RTPTransceiverDirection::Sendrecv {
-                                            t.set_direction(RTPTransceiverDirection::Sendonly);
-                                        }
-                                    } else if direction == RTPTransceiverDirection::Sendrecv
-                                        && t.direction() == RTPTransceiverDirection::Sendonly
-                                    {
-                                        t.set_direction(RTPTransceiverDirection::Sendrecv);
-                                    }
-
                                     if t.mid().await.is_empty() {
                                         t.set_mid(mid_value.to_owned()).await?;
                                     }
@@ -1294,6 +1468,11 @@ impl PeerConnection {
                                     .await;
 
                                     self.internal.add_rtp_transceiver(Arc::clone(&t)).await;
+                                    {
+                                        let mut remote_offer_transceivers =
+                                            self.internal.remote_offer_transceivers.lock().await;
+                                        remote_offer_transceivers.push(Arc::clone(&t));
+                                    }
 
                                     if t.mid().await.is_empty() {
                                         t.set_mid(mid_value.to_owned()).await?;
@@ -1306,6 +1485,7 @@ impl PeerConnection {
             }
 
             let (remote_ufrag, remote_pwd, candidates) = extract_ice_details(parsed).await?;
+            let end_of_candidates = have_end_of_candidates(parsed);
 
             if is_renegotation
                 && self
@@ -1331,13 +1511,19 @@ impl PeerConnection {
                     .add_remote_candidate(Some(candidate))
                     .await?;
             }
+            if end_of_candidates {
+                self.internal
+                    .ice_transport
+                    .add_remote_candidate(None)
+                    .await?;
+            }
 
             if is_renegotation {
                 if we_offer {
                     self.start_rtp_senders().await?;
 
                     let pci = Arc::clone(&self.internal);
-                    let sdp_semantics = self.configuration.sdp_semantics;
+                    let sdp_semantics = self.configuration.lock().await.sdp_semantics;
                     let remote_desc = Arc::new(desc);
                     self.internal
                         .ops
@@ -1362,7 +1548,7 @@ impl PeerConnection {
                 }
             }
 
-            let (fingerprint, fingerprint_hash) = extract_fingerprint(parsed)?;
+            let fingerprints = extract_fingerprints(parsed)?;
 
             // If one of the agents is lite and the other one is not, the lite agent must be the controlling agent.
             // If both or neither agents are lite the offering agent is controlling.
@@ -1385,7 +1571,7 @@ impl PeerConnection {
             //log::trace!("start_transports: parsed={:?}", parsed);
 
             let pci = Arc::clone(&self.internal);
-            let sdp_semantics = self.configuration.sdp_semantics;
+            let sdp_semantics = self.configuration.lock().await.sdp_semantics;
             let dtls_role = DTLSRole::from(parsed);
             let remote_desc = Arc::new(desc);
             self.internal
@@ -1395,16 +1581,14 @@ impl PeerConnection {
                     let rd = Arc::clone(&remote_desc);
                     let ru = remote_ufrag.clone();
                     let rp = remote_pwd.clone();
-                    let fp = fingerprint.clone();
-                    let fp_hash = fingerprint_hash.clone();
+                    let fps = fingerprints.clone();
                     Box::pin(async move {
                         log::trace!(
                             "start_transports: ice_role={}, dtls_role={}",
                             ice_role,
                             dtls_role,
                         );
-                        pc.start_transports(ice_role, dtls_role, ru, rp, fp, fp_hash)
-                            .await;
+                        pc.start_transports(ice_role, dtls_role, ru, rp, fps).await;
 
                         if we_offer {
                             let _ = pc.start_rtp(false, rd, sdp_semantics).await;
@@ -1418,10 +1602,71 @@ impl PeerConnection {
         Ok(())
     }
 
+    /// update_transceivers_from_answer sets the current_direction of the transceivers
+    /// negotiated by an answer, and pauses the senders of the ones that are no longer
+    /// sending. Transceivers whose media section is rejected are stopped, and stopped
+    /// ones the answer no longer has a media section for are removed.
+    /// https://www.w3.org/TR/webrtc/#set-description (step 4.6.9.2)
+    async fn update_transceivers_from_answer(&self, desc: &SessionDescription, local: bool) {
+        if desc.serde.sdp_type != SDPType::Answer && desc.serde.sdp_type != SDPType::Pranswer {
+            return;
+        }
+
+        let mut rtp_transceivers = self.internal.rtp_transceivers.lock().await;
+        let mut removed = vec![];
+        for t in &*rtp_transceivers {
+            let m = match get_by_mid(t.mid().await.as_str(), desc) {
+                Some(m) => m,
+                None => {
+                    if t.stopping() && desc.serde.sdp_type == SDPType::Answer {
+                        if let Err(err) = t.set_stopped().await {
+                            log::warn!("Failed to stop RTPTransceiver: {}", err);
+                        }
+                        removed.push(Arc::clone(t));
+                    }
+                    continue;
+                }
+            };
+            if t.stopped() {
+                continue;
+            }
+            if is_media_section_rejected(m) {
+                if let Err(err) = t.set_stopped().await {
+                    log::warn!("Failed to stop RTPTransceiver: {}", err);
+                }
+                continue;
+            }
+
+            // The answer states the direction of the answerer
+            let direction = if local {
+                get_peer_direction(m)
+            } else {
+                get_peer_direction(m).reverse()
+            };
+            if direction == RTPTransceiverDirection::Unspecified {
+                continue;
+            }
+
+            t.set_current_direction(direction);
+            if let Some(sender) = t.sender().await {
+                sender.set_paused(!direction.has_send());
+            }
+        }
+
+        // The m-line of a removed transceiver has been reused, or was never negotiated
+        for t in &removed {
+            t.set_trigger_negotiation_needed(None).await;
+        }
+        rtp_transceivers.retain(|t| !removed.iter().any(|r| Arc::ptr_eq(r, t)));
+    }
+
     /// start_rtp_senders starts all outbound RTP streams
     pub(crate) async fn start_rtp_senders(&self) -> Result<()> {
         let current_transceivers = self.internal.rtp_transceivers.lock().await;
         for transceiver in &*current_transceivers {
+            if transceiver.stopping() {
+                continue;
+            }
             if let Some(sender) = transceiver.sender().await {
                 if sender.is_negotiated() && !sender.has_sent().await {
                     sender.send(&sender.get_parameters().await).await?;
@@ -1441,7 +1686,8 @@ impl PeerConnection {
     }
 
     /// add_ice_candidate accepts an ICE candidate string and adds it
-    /// to the existing set of candidates.
+    /// to the existing set of candidates. An empty candidate indicates the
+    /// end of the remote candidates.
     pub async fn add_ice_candidate(&self, candidate: ICECandidateInit) -> Result<()> {
         if self.remote_description().await.is_none() {
             return Err(Error::ErrNoRemoteDescription.into());
@@ -1451,8 +1697,14 @@ impl PeerConnection {
             Some(s) => s,
             None => candidate.candidate.as_str(),
         };
+        let candidate_value = candidate_value.trim();
+
+        // An empty candidate indicates the end of the remote candidates, as does
+        // the end-of-candidates attribute (RFC 8838 section 8.2)
+        let end_of_candidates = candidate_value.is_empty()
+            || candidate_value.trim_start_matches("a=") == ATTR_KEY_END_OF_CANDIDATES;
 
-        let ice_candidate = if !candidate_value.is_empty() {
+        let ice_candidate = if !end_of_candidates {
             let candidate: Arc<dyn Candidate + Send + Sync> =
                 Arc::new(unmarshal_candidate(candidate_value).await?);
 
@@ -1518,7 +1770,7 @@ impl PeerConnection {
         {
             let rtp_transceivers = self.internal.rtp_transceivers.lock().await;
             for t in &*rtp_transceivers {
-                if !t.stopped && t.kind == track.kind() && t.sender().await.is_none() {
+                if !t.stopping() && t.kind == track.kind() && t.sender().await.is_none() {
                     let sender = Arc::new(
                         RTPSender::new(
                             Arc::clone(&track),
@@ -1699,14 +1951,10 @@ impl PeerConnection {
             }
 
             // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #7)
-            if let Some(max_packet_life_time) = options.max_packet_life_time {
-                params.max_packet_life_time = max_packet_life_time;
-            }
+            params.max_packet_life_time = options.max_packet_life_time;
 
             // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #8)
-            if let Some(max_retransmits) = options.max_retransmits {
-                params.max_retransmits = max_retransmits;
-            }
+            params.max_retransmits = options.max_retransmits;
 
             // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #10)
             if let Some(protocol) = options.protocol {
@@ -1730,7 +1978,7 @@ impl PeerConnection {
         ));
 
         // https://w3c.github.io/webrtc-pc/#peer-to-peer-data-api (Step #16)
-        if d.max_packet_lifetime != 0 && d.max_retransmits != 0 {
+        if d.max_packet_lifetime.is_some() && d.max_retransmits.is_some() {
             return Err(Error::ErrRetransmitsOrPacketLifeTime.into());
         }
 
@@ -1768,8 +2016,23 @@ impl PeerConnection {
     }
 
     /// set_identity_provider is used to configure an identity provider to generate identity assertions
-    pub fn set_identity_provider(&self, _provider: &str) -> Result<()> {
-        Err(Error::ErrPeerConnSetIdentityProviderNotImplemented.into())
+    /// in the a=identity attribute of the offers and answers, and to validate the ones of the remote peer.
+    /// https://www.w3.org/TR/webrtc-identity/#dom-rtcpeerconnection-setidentityprovider
+    pub async fn set_identity_provider(&self, provider: Arc<dyn IdentityProvider + Send + Sync>) {
+        {
+            let mut identity_provider = self.internal.identity_provider.lock().await;
+            *identity_provider = Some(provider);
+        }
+        let mut identity_assertion = self.internal.identity_assertion.lock().await;
+        *identity_assertion = None;
+    }
+
+    /// peer_identity returns the identity of the remote peer, once it has been validated
+    /// by the identity provider from the identity assertion of a remote description.
+    /// https://www.w3.org/TR/webrtc-identity/#dom-rtcpeerconnection-peeridentity
+    pub async fn peer_identity(&self) -> Option<String> {
+        let peer_identity = self.internal.peer_identity.lock().await;
+        peer_identity.clone()
     }
 
     /// write_rtcp sends a user provided RTCP packet to the connected peer. If no peer is connected the
@@ -1813,8 +2076,10 @@ impl PeerConnection {
         {
             let rtp_transceivers = self.internal.rtp_transceivers.lock().await;
             for t in &*rtp_transceivers {
-                if !t.stopped {
-                    if let Err(err) = t.stop().await {
+                // The trigger holds the transceiver list, release it so both can be dropped
+                t.set_trigger_negotiation_needed(None).await;
+                if !t.stopped() {
+                    if let Err(err) = t.set_stopped().await {
                         close_errs.push(err);
                     }
                 }
@@ -1931,67 +2196,61 @@ impl PeerConnection {
             .into()
     }
 
-    // GetStats return data providing statistics about the overall connection
-    /*TODO: func (pc *PeerConnection) GetStats() StatsReport {
-        var (
-            dataChannelsAccepted  uint32
-            dataChannelsClosed    uint32
-            dataChannelsOpened    uint32
-            dataChannelsRequested uint32
-        )
-        statsCollector := newStatsReportCollector()
-        statsCollector.Collecting()
+    /// get_stats return data providing statistics about the overall connection
+    pub async fn get_stats(&self) -> StatsReport {
+        let collector = StatsCollector::new();
 
-        self.mu.Lock()
-        if self.iceGatherer != nil {
-            self.iceGatherer.collectStats(statsCollector)
-        }
-        if self.iceTransport != nil {
-            self.iceTransport.collectStats(statsCollector)
-        }
+        self.internal.ice_transport.collect_stats(&collector).await;
+        self.internal.dtls_transport.collect_stats(&collector).await;
 
-        self.sctpTransport.lock.Lock()
-        dataChannels := append([]*DataChannel{}, self.sctpTransport.dataChannels...)
-        dataChannelsAccepted = self.sctpTransport.dataChannelsAccepted
-        dataChannelsOpened = self.sctpTransport.dataChannelsOpened
-        dataChannelsRequested = self.sctpTransport.dataChannelsRequested
-        self.sctpTransport.lock.Unlock()
+        let sctp_transport = &self.internal.sctp_transport;
+        let data_channels = {
+            let data_channels = sctp_transport.data_channels.lock().await;
+            data_channels.clone()
+        };
 
-        for _, d := range dataChannels {
-            state := d.ReadyState()
-            if state != DataChannelStateConnecting && state != DataChannelStateOpen {
-                dataChannelsClosed++
+        let mut data_channels_closed = 0;
+        for d in &data_channels {
+            let state = d.ready_state();
+            if state != DataChannelState::Connecting && state != DataChannelState::Open {
+                data_channels_closed += 1;
             }
 
-            d.collectStats(statsCollector)
-        }
-        self.sctpTransport.collectStats(statsCollector)
-
-        stats := PeerConnectionStats{
-            Timestamp:             statsTimestampNow(),
-            Type:                  StatsTypePeerConnection,
-            ID:                    self.stats_id,
-            DataChannelsAccepted:  dataChannelsAccepted,
-            DataChannelsClosed:    dataChannelsClosed,
-            DataChannelsOpened:    dataChannelsOpened,
-            DataChannelsRequested: dataChannelsRequested,
-        }
+            d.collect_stats(&collector).await;
+        }
+        sctp_transport.collect_stats(&collector).await;
+
+        collector
+            .collect(StatsReportType::PeerConnection(PeerConnectionStats {
+                timestamp: stats_timestamp_now(),
+                stats_type: StatsType::PeerConnection,
+                id: self.stats_id.clone(),
+                data_channels_opened: sctp_transport.data_channels_opened.load(Ordering::SeqCst),
+                data_channels_closed,
+                data_channels_requested: sctp_transport
+                    .data_channels_requested
+                    .load(Ordering::SeqCst),
+                data_channels_accepted: sctp_transport
+                    .data_channels_accepted
+                    .load(Ordering::SeqCst),
+            }))
+            .await;
 
-        statsCollector.Collect(stats.ID, stats)
+        self.internal.media_engine.collect_stats(&collector).await;
 
-        certificates := self.configuration.Certificates
-        for _, certificate := range certificates {
-            if err := certificate.collectStats(statsCollector); err != nil {
-                continue
+        let transceivers = self.get_transceivers().await;
+        for t in &transceivers {
+            let mid = t.mid().await;
+            if let Some(sender) = t.sender().await {
+                sender.collect_stats(&collector, &mid).await;
+            }
+            if let Some(receiver) = t.receiver().await {
+                receiver.collect_stats(&collector, &mid).await;
             }
         }
-        self.mu.Unlock()
-
-        self.api.mediaEngine.collectStats(statsCollector)
 
-        return statsCollector.Ready()
+        collector.ready()
     }
-    */
 
     /// sctp returns the SCTPTransport for this PeerConnection
     ///
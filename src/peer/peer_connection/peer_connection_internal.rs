@@ -31,6 +31,8 @@ pub(crate) struct PeerConnectionInternal {
 
     pub(super) sctp_transport: Arc<SCTPTransport>,
     pub(super) rtp_transceivers: Arc<Mutex<Vec<Arc<RTPTransceiver>>>>,
+    /// transceivers created while applying the pending remote offer, removed if it is rolled back
+    pub(super) remote_offer_transceivers: Mutex<Vec<Arc<RTPTransceiver>>>,
 
     pub(super) on_track_handler: Arc<Mutex<Option<OnTrackHdlrFn>>>,
     pub(super) on_signaling_state_change_handler: Arc<Mutex<Option<OnSignalingStateChangeHdlrFn>>>,
@@ -39,6 +41,18 @@ pub(crate) struct PeerConnectionInternal {
     pub(super) on_data_channel_handler: Arc<Mutex<Option<OnDataChannelHdlrFn>>>,
 
     pub(super) ice_gatherer: Arc<ICEGatherer>,
+    /// bundle_policy and rtcp_mux_policy can't be modified once the
+    /// PeerConnection is created, they are read when generating offers.
+    /// All media is always bundled on a single transport multiplexing RTCP.
+    pub(super) bundle_policy: BundlePolicy,
+    pub(super) rtcp_mux_policy: RTCPMuxPolicy,
+
+    /// identity_provider asserts the identity of the local peer, the assertion
+    /// generated for the local DTLS fingerprints is cached in identity_assertion.
+    /// peer_identity is the identity of the remote peer once validated.
+    pub(super) identity_provider: Mutex<Option<Arc<dyn IdentityProvider + Send + Sync>>>,
+    pub(super) identity_assertion: Mutex<Option<String>>,
+    pub(super) peer_identity: Mutex<Option<String>>,
 
     pub(super) current_local_description: Arc<Mutex<Option<SessionDescription>>>,
     pub(super) current_remote_description: Arc<Mutex<Option<SessionDescription>>>,
@@ -70,11 +84,17 @@ impl PeerConnectionInternal {
             ice_connection_state: Arc::new(AtomicU8::new(ICEConnectionState::New as u8)),
             sctp_transport: Arc::new(Default::default()),
             rtp_transceivers: Arc::new(Default::default()),
+            remote_offer_transceivers: Mutex::new(vec![]),
             on_track_handler: Arc::new(Default::default()),
             on_signaling_state_change_handler: Arc::new(Default::default()),
             on_ice_connection_state_change_handler: Arc::new(Default::default()),
             on_data_channel_handler: Arc::new(Default::default()),
             ice_gatherer: Arc::new(Default::default()),
+            bundle_policy: configuration.bundle_policy,
+            rtcp_mux_policy: configuration.rtcp_mux_policy,
+            identity_provider: Mutex::new(None),
+            identity_assertion: Mutex::new(None),
+            peer_identity: Mutex::new(None),
             current_local_description: Arc::new(Default::default()),
             current_remote_description: Arc::new(Default::default()),
             pending_local_description: Arc::new(Default::default()),
@@ -86,7 +106,7 @@ impl PeerConnectionInternal {
             } else {
                 Arc::clone(&api.media_engine)
             },
-            interceptor: Arc::clone(&api.interceptor),
+            interceptor: api.interceptor_registry.build(),
             on_peer_connection_state_change_handler: Arc::new(Default::default()),
             pending_remote_description: Arc::new(Default::default()),
         };
@@ -144,6 +164,9 @@ impl PeerConnectionInternal {
 
         if is_renegotiation {
             for t in &current_transceivers {
+                if t.stopping() {
+                    continue;
+                }
                 if let Some(receiver) = t.receiver().await {
                     if let Some(track) = receiver.track().await {
                         let ssrc = track.ssrc();
@@ -175,7 +198,10 @@ impl PeerConnectionInternal {
 
         if let Some(parsed) = &remote_desc.parsed {
             if have_application_media_section(parsed) {
-                self.start_sctp().await;
+                self.start_sctp(SCTPTransportCapabilities {
+                    max_message_size: get_max_message_size(parsed),
+                })
+                .await;
             }
         }
 
@@ -381,15 +407,9 @@ impl PeerConnectionInternal {
     }
 
     /// Start SCTP subsystem
-    async fn start_sctp(&self) {
+    async fn start_sctp(&self, remote_caps: SCTPTransportCapabilities) {
         // Start sctp
-        if let Err(err) = self
-            .sctp_transport
-            .start(SCTPTransportCapabilities {
-                max_message_size: 0,
-            })
-            .await
-        {
+        if let Err(err) = self.sctp_transport.start(remote_caps).await {
             log::warn!("Failed to start SCTP: {}", err);
             if let Err(err) = self.sctp_transport.stop().await {
                 log::warn!("Failed to stop SCTPTransport: {}", err);
@@ -530,11 +550,7 @@ impl PeerConnectionInternal {
     /// and fires onNegotiationNeeded;
     /// caller of this method should hold `self.mu` lock
     pub(super) async fn add_rtp_transceiver(&self, t: Arc<RTPTransceiver>) {
-        {
-            let mut rtp_transceivers = self.rtp_transceivers.lock().await;
-            rtp_transceivers.push(t);
-        }
-        PeerConnection::do_negotiation_needed(NegotiationNeededParams {
+        let params = NegotiationNeededParams {
             on_negotiation_needed_handler: Arc::clone(&self.on_negotiation_needed_handler),
             is_closed: Arc::clone(&self.is_closed),
             ops: Arc::clone(&self.ops),
@@ -547,8 +563,23 @@ impl PeerConnectionInternal {
                 current_local_description: Arc::clone(&self.current_local_description),
                 current_remote_description: Arc::clone(&self.current_remote_description),
             },
-        })
+        };
+
+        // set_direction on the transceiver fires onNegotiationNeeded as well
+        let params2 = params.clone();
+        t.set_trigger_negotiation_needed(Some(Box::new(move || {
+            let params3 = params2.clone();
+            Box::pin(async move {
+                PeerConnection::do_negotiation_needed(params3).await;
+            })
+        })))
         .await;
+
+        {
+            let mut rtp_transceivers = self.rtp_transceivers.lock().await;
+            rtp_transceivers.push(t);
+        }
+        PeerConnection::do_negotiation_needed(params).await;
     }
 
     pub(super) async fn remote_description(self: &Arc<Self>) -> Option<SessionDescription> {
@@ -572,8 +603,7 @@ impl PeerConnectionInternal {
         dtls_role: DTLSRole,
         remote_ufrag: String,
         remote_pwd: String,
-        fingerprint: String,
-        fingerprint_hash: String,
+        fingerprints: Vec<DTLSFingerprint>,
     ) {
         // Start the ice transport
         if let Err(err) = self
@@ -597,10 +627,7 @@ impl PeerConnectionInternal {
             .dtls_transport
             .start(DTLSParameters {
                 role: dtls_role,
-                fingerprints: vec![DTLSFingerprint {
-                    algorithm: fingerprint_hash,
-                    value: fingerprint,
-                }],
+                fingerprints,
             })
             .await;
         PeerConnection::update_connection_state(
@@ -616,17 +643,114 @@ impl PeerConnectionInternal {
         }
     }
 
+    /// new_jsep_session_description creates the session description of an offer or
+    /// answer, with the identity assertion of the local peer if any.
+    fn new_jsep_session_description(
+        identity: Option<String>,
+    ) -> sdp::session_description::SessionDescription {
+        let d = sdp::session_description::SessionDescription::new_jsep_session_description(false);
+        if let Some(identity) = identity {
+            d.with_value_attribute(ATTR_KEY_IDENTITY.to_owned(), identity)
+        } else {
+            d
+        }
+    }
+
+    /// generate_identity_assertion returns the identity assertion of the local DTLS
+    /// fingerprints, generated by the identity provider the first time it is needed.
+    pub(super) async fn generate_identity_assertion(&self) -> Result<Option<String>> {
+        let provider = {
+            let identity_provider = self.identity_provider.lock().await;
+            match &*identity_provider {
+                Some(provider) => Arc::clone(provider),
+                None => return Ok(None),
+            }
+        };
+
+        let mut identity_assertion = self.identity_assertion.lock().await;
+        if identity_assertion.is_none() {
+            let fingerprints = self.dtls_transport.get_local_parameters()?.fingerprints;
+            let assertion = provider
+                .generate_assertion(&identity_contents(&fingerprints)?)
+                .await?;
+            *identity_assertion = Some(encode_identity(&assertion)?);
+        }
+
+        Ok(identity_assertion.clone())
+    }
+
+    /// validate_remote_identity validates the identity assertion of a remote description
+    /// and returns the identity of the remote peer. An assertion is required when the
+    /// target peer_identity is set, and the identity must then match it.
+    /// https://www.w3.org/TR/webrtc-identity/#verifying-identity-assertions
+    pub(super) async fn validate_remote_identity(
+        &self,
+        parsed: &sdp::session_description::SessionDescription,
+        target_peer_identity: &str,
+    ) -> Result<Option<String>> {
+        let value = match parsed.attribute(ATTR_KEY_IDENTITY) {
+            Some(value) if !value.is_empty() => value,
+            _ => {
+                if !target_peer_identity.is_empty() {
+                    return Err(Error::ErrIdentityAssertionMissing.into());
+                }
+                return Ok(None);
+            }
+        };
+
+        let provider = {
+            let identity_provider = self.identity_provider.lock().await;
+            match &*identity_provider {
+                Some(provider) => Arc::clone(provider),
+                None => {
+                    if !target_peer_identity.is_empty() {
+                        return Err(Error::ErrNoIdentityProvider.into());
+                    }
+                    log::warn!("no identity provider to validate the remote identity assertion");
+                    return Ok(None);
+                }
+            }
+        };
+
+        let assertion = decode_identity(value)?;
+        let result = provider.validate_assertion(&assertion).await?;
+
+        // The assertion must be bound to the certificate the remote peer will present
+        if !contents_match_fingerprints(&result.contents, &extract_fingerprints(parsed)?)? {
+            return Err(Error::ErrIdentityAssertionFingerprintMismatch.into());
+        }
+
+        // The identity must belong to the domain of the identity provider
+        match result.identity.rsplit_once('@') {
+            Some((_, domain)) if domain == assertion.idp.domain => {}
+            _ => return Err(Error::ErrInvalidIdentityAssertion.into()),
+        };
+
+        // The identity must be the target one, and can't change once validated
+        if !target_peer_identity.is_empty() && result.identity != target_peer_identity {
+            return Err(Error::ErrPeerIdentityMismatch.into());
+        }
+        {
+            let peer_identity = self.peer_identity.lock().await;
+            if let Some(peer_identity) = &*peer_identity {
+                if *peer_identity != result.identity {
+                    return Err(Error::ErrPeerIdentityMismatch.into());
+                }
+            }
+        }
+
+        Ok(Some(result.identity))
+    }
+
     /// generate_unmatched_sdp generates an SDP that doesn't take remote state into account
     /// This is used for the initial call for CreateOffer
     pub(super) async fn generate_unmatched_sdp(
         &self,
         local_transceivers: Vec<Arc<RTPTransceiver>>,
-        use_identity: bool,
+        identity: Option<String>,
         sdp_semantics: SDPSemantics,
     ) -> Result<sdp::session_description::SessionDescription> {
-        let d = sdp::session_description::SessionDescription::new_jsep_session_description(
-            use_identity,
-        );
+        let d = PeerConnectionInternal::new_jsep_session_description(identity);
 
         let ice_params = self.ice_gatherer.get_local_parameters().await?;
 
@@ -681,6 +805,9 @@ impl PeerConnectionInternal {
         } else {
             {
                 for t in &local_transceivers {
+                    if t.stopping() {
+                        continue;
+                    }
                     if let Some(sender) = t.sender().await {
                         sender.set_negotiated();
                     }
@@ -706,11 +833,10 @@ impl PeerConnectionInternal {
             }
         }
 
-        let dtls_fingerprints = if let Some(cert) = self.dtls_transport.certificates.first() {
-            vec![cert.get_fingerprint()?]
-        } else {
+        let dtls_fingerprints = self.dtls_transport.get_local_parameters()?.fingerprints;
+        if dtls_fingerprints.is_empty() {
             return Err(Error::ErrNonCertificate.into());
-        };
+        }
 
         let params = PopulateSdpParams {
             is_plan_b,
@@ -718,6 +844,9 @@ impl PeerConnectionInternal {
             is_icelite: self.setting_engine.candidates.ice_lite,
             connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
             ice_gathering_state: self.ice_gathering_state(),
+            rtcp_mux_only: self.rtcp_mux_policy == RTCPMuxPolicy::Require,
+            bundle_only: self.bundle_policy == BundlePolicy::MaxBundle,
+            max_message_size: self.sctp_transport.get_capabilities().max_message_size,
         };
         populate_sdp(
             d,
@@ -736,14 +865,12 @@ impl PeerConnectionInternal {
     pub(super) async fn generate_matched_sdp(
         &self,
         mut local_transceivers: Vec<Arc<RTPTransceiver>>,
-        use_identity: bool,
+        identity: Option<String>,
         include_unmatched: bool,
         connection_role: ConnectionRole,
         sdp_semantics: SDPSemantics,
     ) -> Result<sdp::session_description::SessionDescription> {
-        let d = sdp::session_description::SessionDescription::new_jsep_session_description(
-            use_identity,
-        );
+        let d = PeerConnectionInternal::new_jsep_session_description(identity);
 
         let ice_params = self.ice_gatherer.get_local_parameters().await?;
         let candidates = self.ice_gatherer.get_local_candidates().await?;
@@ -762,7 +889,25 @@ impl PeerConnectionInternal {
         let mut media_sections = vec![];
         let mut already_have_application_media_section = false;
         if let Some(remote_description) = remote_description.as_ref() {
+            // When answering, the directions of the answer are constrained by the offer
+            let remote_is_offer = remote_description.serde.sdp_type == SDPType::Offer;
             if let Some(parsed) = &remote_description.parsed {
+                // When offering, the m-lines of stopped transceivers are reused by the
+                // transceivers that are new to the session
+                let mut recyclable = vec![];
+                if include_unmatched && !remote_is_offer && !detected_plan_b {
+                    let remote_mids: Vec<&String> = parsed
+                        .media_descriptions
+                        .iter()
+                        .filter_map(get_mid_value)
+                        .collect();
+                    for t in &local_transceivers {
+                        if !t.stopping() && !remote_mids.contains(&&t.mid().await) {
+                            recyclable.push(Arc::clone(t));
+                        }
+                    }
+                }
+
                 for media in &parsed.media_descriptions {
                     if let Some(mid_value) = get_mid_value(media) {
                         if mid_value.is_empty() {
@@ -782,10 +927,16 @@ impl PeerConnectionInternal {
                         let kind = RTPCodecType::from(media.media_name.media.as_str());
                         let direction = get_peer_direction(media);
                         if kind == RTPCodecType::Unspecified
-                            || direction == RTPTransceiverDirection::Unspecified
+                            || (direction == RTPTransceiverDirection::Unspecified
+                                && !is_media_section_rejected(media))
                         {
                             continue;
                         }
+                        let offered_direction = if remote_is_offer {
+                            Some(direction)
+                        } else {
+                            None
+                        };
 
                         if sdp_semantics == SDPSemantics::PlanB
                             || (sdp_semantics == SDPSemantics::UnifiedPlanWithFallback
@@ -829,6 +980,7 @@ impl PeerConnectionInternal {
                             media_sections.push(MediaSection {
                                 id: mid_value.to_owned(),
                                 transceivers: media_transceivers,
+                                offered_direction,
                                 ..Default::default()
                             });
                         } else if sdp_semantics == SDPSemantics::UnifiedPlan
@@ -838,6 +990,20 @@ impl PeerConnectionInternal {
                                 return Err(Error::ErrIncorrectSDPSemantics.into());
                             }
                             if let Some(t) = find_by_mid(mid_value, &mut local_transceivers).await {
+                                if t.stopped() && !recyclable.is_empty() {
+                                    let r = recyclable.remove(0);
+                                    local_transceivers.retain(|lt| !Arc::ptr_eq(lt, &r));
+                                    if let Some(sender) = r.sender().await {
+                                        sender.set_negotiated();
+                                    }
+                                    media_sections.push(MediaSection {
+                                        id: r.mid().await,
+                                        transceivers: vec![r],
+                                        ..Default::default()
+                                    });
+                                    continue;
+                                }
+
                                 if let Some(sender) = t.sender().await {
                                     sender.set_negotiated();
                                 }
@@ -846,6 +1012,7 @@ impl PeerConnectionInternal {
                                     id: mid_value.to_owned(),
                                     transceivers: media_transceivers,
                                     rid_map: get_rids(media),
+                                    offered_direction,
                                     ..Default::default()
                                 });
                             } else {
@@ -863,6 +1030,10 @@ impl PeerConnectionInternal {
         if include_unmatched {
             if !detected_plan_b {
                 for t in &local_transceivers {
+                    // Transceivers stopped before being negotiated have no m-line
+                    if t.stopping() {
+                        continue;
+                    }
                     if let Some(sender) = t.sender().await {
                         sender.set_negotiated();
                     }
@@ -901,11 +1072,10 @@ impl PeerConnectionInternal {
             log::info!("Plan-B Offer detected; responding with Plan-B Answer");
         }
 
-        let dtls_fingerprints = if let Some(cert) = self.dtls_transport.certificates.first() {
-            vec![cert.get_fingerprint()?]
-        } else {
+        let dtls_fingerprints = self.dtls_transport.get_local_parameters()?.fingerprints;
+        if dtls_fingerprints.is_empty() {
             return Err(Error::ErrNonCertificate.into());
-        };
+        }
 
         let params = PopulateSdpParams {
             is_plan_b: detected_plan_b,
@@ -913,6 +1083,9 @@ impl PeerConnectionInternal {
             is_icelite: self.setting_engine.candidates.ice_lite,
             connection_role,
             ice_gathering_state: self.ice_gathering_state(),
+            rtcp_mux_only: include_unmatched && self.rtcp_mux_policy == RTCPMuxPolicy::Require,
+            bundle_only: false,
+            max_message_size: self.sctp_transport.get_capabilities().max_message_size,
         };
         populate_sdp(
             d,
@@ -926,6 +1099,20 @@ impl PeerConnectionInternal {
         .await
     }
 
+    /// start_candidate_pool starts gathering before the first local description is set
+    /// when a candidate pool is configured. As all media is bundled on a single ICE
+    /// transport, any non zero pool size pools the candidates and TURN allocations of
+    /// that transport, which the first offer or answer then uses. The pooled candidates
+    /// are emitted once the first local description is set, and released when the
+    /// PeerConnection is closed.
+    pub(super) async fn start_candidate_pool(&self, pool_size: u8) -> Result<()> {
+        if pool_size > 0 {
+            self.ice_gatherer.gather_pool().await?;
+        }
+
+        Ok(())
+    }
+
     pub(super) fn ice_gathering_state(&self) -> ICEGatheringState {
         match self.ice_gatherer.state() {
             ICEGathererState::New => ICEGatheringState::New,
@@ -1004,16 +1191,26 @@ impl PeerConnectionInternal {
                     return Err(Error::ErrPeerConnSimulcastStreamIDRTPExtensionRequired.into());
                 }
 
+                // The repair streams of the layers carry their RID in this extension instead
+                let (rsid_extension_id, _, _) = self
+                    .media_engine
+                    .get_header_extension_id(RTPHeaderExtensionCapability {
+                        uri: SDES_REPAIR_RTP_STREAM_ID_URI.to_owned(),
+                    })
+                    .await;
+
                 let mut b = vec![0u8; RECEIVE_MTU];
-                let (mut mid, mut rid) = (String::new(), String::new());
+                let (mut mid, mut rid, mut rsid) = (String::new(), String::new(), String::new());
                 for _ in 0..=SIMULCAST_PROBE_COUNT {
                     let n = rtp_stream.read(&mut b).await?;
 
-                    let (maybe_mid, maybe_rid, payload_type) = handle_unknown_rtp_packet(
-                        &b[..n],
-                        mid_extension_id as u8,
-                        sid_extension_id as u8,
-                    )?;
+                    let (maybe_mid, maybe_rid, maybe_rsid, payload_type) =
+                        handle_unknown_rtp_packet(
+                            &b[..n],
+                            mid_extension_id as u8,
+                            sid_extension_id as u8,
+                            rsid_extension_id as u8,
+                        )?;
 
                     if !maybe_mid.is_empty() {
                         mid = maybe_mid;
@@ -1021,8 +1218,35 @@ impl PeerConnectionInternal {
                     if !maybe_rid.is_empty() {
                         rid = maybe_rid;
                     }
+                    if !maybe_rsid.is_empty() {
+                        rsid = maybe_rsid;
+                    }
+
+                    if mid.is_empty() || (rid.is_empty() && rsid.is_empty()) {
+                        continue;
+                    }
 
-                    if mid.is_empty() || rid.is_empty() {
+                    if !rsid.is_empty() {
+                        let transceivers = self.rtp_transceivers.lock().await;
+                        for t in &*transceivers {
+                            if t.mid().await != mid || t.receiver().await.is_none() {
+                                continue;
+                            }
+
+                            if let Some(receiver) = t.receiver().await {
+                                receiver
+                                    .receive_repair_for_rid(
+                                        rsid.as_str(),
+                                        Arc::clone(&rtp_stream),
+                                        &b[..n],
+                                    )
+                                    .await?;
+                                self.dtls_transport
+                                    .remove_simulcast_stream(&rtp_stream)
+                                    .await;
+                            }
+                            return Ok(());
+                        }
                         continue;
                     }
 
@@ -1040,8 +1264,11 @@ impl PeerConnectionInternal {
 
                             if let Some(receiver) = t.receiver().await {
                                 let track = receiver
-                                    .receive_for_rid(rid.as_str(), &params, ssrc)
+                                    .receive_for_rid(rid.as_str(), &params, Arc::clone(&rtp_stream))
                                     .await?;
+                                self.dtls_transport
+                                    .remove_simulcast_stream(&rtp_stream)
+                                    .await;
                                 PeerConnection::do_track(
                                     Arc::clone(&self.on_track_handler),
                                     Some(track),
@@ -1172,6 +1399,10 @@ impl PeerConnectionInternal {
     pub(super) async fn has_local_description_changed(&self, desc: &SessionDescription) -> bool {
         let rtp_transceivers = self.rtp_transceivers.lock().await;
         for t in &*rtp_transceivers {
+            // The m-line of a stopping transceiver is rejected or reused
+            if t.stopping() {
+                continue;
+            }
             if let Some(m) = get_by_mid(t.mid().await.as_str(), desc) {
                 if get_peer_direction(m) != t.direction() {
                     return true;
@@ -1,7 +1,17 @@
 use super::*;
 use crate::media::Sample;
 
+use crate::api::interceptor_registry::{configure_rtcp_reports, Registry};
+use crate::api::media_engine::MIME_TYPE_VP8;
 use crate::api::APIBuilder;
+use crate::data::data_channel::data_channel_message::DataChannelMessage;
+use crate::media::interceptor::unmarshal_rtcp;
+use crate::media::rtp::rtp_codec::RTPCodecCapability;
+use crate::peer::ice::ice_credential_type::ICECredentialType;
+use crate::peer::ice::ice_server::ICEServer;
+use crate::peer::identity_provider::{
+    IdentityAssertion, IdentityProviderDetails, IdentityValidationResult,
+};
 use bytes::Bytes;
 use tokio::time::Duration;
 use util::vnet::net::{Net, NetConfig};
@@ -242,3 +252,849 @@ pub(crate) async fn until_connection_state(
     }))
     .await;
 }
+
+fn set_configuration_initial() -> Configuration {
+    Configuration {
+        ice_servers: vec![ICEServer {
+            urls: vec!["stun:stun.l.google.com:19302".to_owned()],
+            ..Default::default()
+        }],
+        ice_transport_policy: ICETransportPolicy::All,
+        bundle_policy: BundlePolicy::MaxCompat,
+        rtcp_mux_policy: RTCPMuxPolicy::Negotiate,
+        ice_candidate_pool_size: 5,
+        ..Default::default()
+    }
+}
+
+fn turn_server(credential: &str) -> ICEServer {
+    ICEServer {
+        urls: vec!["turn:turn.example.org".to_owned()],
+        username: "user".to_owned(),
+        credential: credential.to_owned(),
+        credential_type: ICECredentialType::Password,
+    }
+}
+
+#[tokio::test]
+async fn test_peer_connection_set_configuration() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let tests: Vec<(&str, Configuration, Option<Error>)> = vec![
+        (
+            "update certificates",
+            Configuration {
+                certificates: vec![Certificate::from_key_pair(KeyPair::generate(
+                    &rcgen::PKCS_ECDSA_P256_SHA256,
+                )?)?],
+                ..Default::default()
+            },
+            Some(Error::ErrModifyingCertificates),
+        ),
+        (
+            "update bundle policy",
+            Configuration {
+                bundle_policy: BundlePolicy::MaxBundle,
+                ..Default::default()
+            },
+            Some(Error::ErrModifyingBundlePolicy),
+        ),
+        (
+            "update rtcp mux policy",
+            Configuration {
+                rtcp_mux_policy: RTCPMuxPolicy::Require,
+                ..Default::default()
+            },
+            Some(Error::ErrModifyingRTCPMuxPolicy),
+        ),
+        (
+            "update ice servers without credentials",
+            Configuration {
+                ice_servers: vec![turn_server("")],
+                ice_transport_policy: ICETransportPolicy::Relay,
+                ..Default::default()
+            },
+            Some(Error::ErrNoTurnCredentials),
+        ),
+        (
+            "same policies",
+            Configuration {
+                bundle_policy: BundlePolicy::MaxCompat,
+                rtcp_mux_policy: RTCPMuxPolicy::Negotiate,
+                ..Default::default()
+            },
+            None,
+        ),
+        (
+            "update ice servers, transport policy and pool size",
+            Configuration {
+                ice_servers: vec![turn_server("rotated")],
+                ice_transport_policy: ICETransportPolicy::Relay,
+                ice_candidate_pool_size: 10,
+                ..Default::default()
+            },
+            None,
+        ),
+    ];
+
+    for (name, configuration, expected) in tests {
+        let pc = api.new_peer_connection(set_configuration_initial()).await?;
+        let result = pc.set_configuration(configuration).await;
+        if let Some(err) = expected {
+            let actual = result.err().unwrap();
+            assert!(err.equal(&actual), "{}: got {}", name, actual);
+            // a rejected configuration leaves the current one untouched
+            let current = pc.get_configuration().await;
+            assert_eq!(
+                vec!["stun:stun.l.google.com:19302".to_owned()],
+                current.ice_servers[0].urls
+            );
+            assert_eq!(ICETransportPolicy::All, current.ice_transport_policy);
+        } else {
+            assert!(result.is_ok(), "{}: {:?}", name, result);
+        }
+        pc.close().await?;
+    }
+
+    let pc = api.new_peer_connection(set_configuration_initial()).await?;
+    pc.set_configuration(Configuration {
+        ice_servers: vec![turn_server("rotated")],
+        ice_transport_policy: ICETransportPolicy::Relay,
+        ..Default::default()
+    })
+    .await?;
+    let current = pc.get_configuration().await;
+    assert_eq!(1, current.ice_servers.len());
+    assert_eq!("rotated", current.ice_servers[0].credential);
+    assert_eq!(ICETransportPolicy::Relay, current.ice_transport_policy);
+    assert_eq!(BundlePolicy::MaxCompat, current.bundle_policy);
+    assert_eq!(5, current.ice_candidate_pool_size);
+
+    // the pool size can't change once a local description was set
+    let offer = pc.create_offer(None).await?;
+    pc.set_local_description(offer).await?;
+    let result = pc
+        .set_configuration(Configuration {
+            ice_candidate_pool_size: 10,
+            ..Default::default()
+        })
+        .await;
+    assert!(Error::ErrModifyingICECandidatePoolSize.equal(&result.err().unwrap()));
+    pc.close().await?;
+
+    // nothing can be changed once closed
+    let result = pc.set_configuration(Configuration::default()).await;
+    assert!(Error::ErrConnectionClosed.equal(&result.err().unwrap()));
+
+    // the required rtcp-mux policy can't be relaxed to negotiate
+    let pc = api
+        .new_peer_connection(Configuration {
+            rtcp_mux_policy: RTCPMuxPolicy::Require,
+            ..Default::default()
+        })
+        .await?;
+    let result = pc
+        .set_configuration(Configuration {
+            rtcp_mux_policy: RTCPMuxPolicy::Negotiate,
+            ..Default::default()
+        })
+        .await;
+    assert!(Error::ErrModifyingRTCPMuxPolicy.equal(&result.err().unwrap()));
+    pc.set_configuration(Configuration {
+        ice_servers: vec![turn_server("rotated")],
+        rtcp_mux_policy: RTCPMuxPolicy::Require,
+        ..Default::default()
+    })
+    .await?;
+    let current = pc.get_configuration().await;
+    assert_eq!(RTCPMuxPolicy::Require, current.rtcp_mux_policy);
+    assert_eq!(1, current.ice_servers.len());
+    pc.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_set_configuration_ice_restart() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut pc_offer, mut pc_answer) = new_pair(&api).await?;
+
+    let (message_tx, mut message_rx) = mpsc::channel(16);
+    pc_answer
+        .on_data_channel(Box::new(move |d: Arc<DataChannel>| {
+            let message_tx = message_tx.clone();
+            Box::pin(async move {
+                d.on_message(Box::new(move |msg: DataChannelMessage| {
+                    let message_tx = message_tx.clone();
+                    Box::pin(async move {
+                        let _ = message_tx.send(msg.data).await;
+                    })
+                }))
+                .await;
+            })
+        }))
+        .await;
+    let dc = pc_offer.create_data_channel("data", None).await?;
+
+    signal_pair(&mut pc_offer, &mut pc_answer).await?;
+    while dc.ready_state() != DataChannelState::Open {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    // a STUN server only used once the configuration was updated
+    let stun_server = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+    pc_offer
+        .set_configuration(Configuration {
+            ice_servers: vec![ICEServer {
+                urls: vec![format!("stun:{}", stun_server.local_addr()?)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .await?;
+
+    let offer = pc_offer
+        .create_offer(Some(OfferOptions {
+            ice_restart: true,
+            ..Default::default()
+        }))
+        .await?;
+    let mut offer_gathering_complete = pc_offer.gathering_complete_promise().await;
+    pc_offer.set_local_description(offer).await?;
+
+    // the restarted ICE agent gathers with the new server
+    let mut buf = vec![0u8; 1500];
+    let (n, _) = tokio::time::timeout(Duration::from_secs(5), stun_server.recv_from(&mut buf))
+        .await
+        .expect("no binding request sent to the new STUN server")?;
+    assert!(n >= 20 && buf[..2] == [0x00, 0x01], "not a binding request");
+
+    let _ = offer_gathering_complete.recv().await;
+    pc_answer
+        .set_remote_description(pc_offer.local_description().await.unwrap())
+        .await?;
+    let answer = pc_answer.create_answer(None).await?;
+    let mut answer_gathering_complete = pc_answer.gathering_complete_promise().await;
+    pc_answer.set_local_description(answer).await?;
+    let _ = answer_gathering_complete.recv().await;
+    pc_offer
+        .set_remote_description(pc_answer.local_description().await.unwrap())
+        .await?;
+
+    // the data channel keeps working over the new ICE agent
+    let received = tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            dc.send_text("after restart".to_owned()).await?;
+            if let Ok(Some(data)) =
+                tokio::time::timeout(Duration::from_millis(500), message_rx.recv()).await
+            {
+                return Ok::<Bytes, anyhow::Error>(data);
+            }
+        }
+    })
+    .await
+    .expect("no message received after the ICE restart")?;
+    assert_eq!(Bytes::from_static(b"after restart"), received);
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_rollback() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (pc_offer, pc_answer) = new_pair(&api).await?;
+    let transceiver = pc_offer
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+
+    // nothing to roll back while stable
+    let rollback = SessionDescription {
+        serde: SessionDescriptionSerde {
+            sdp_type: SDPType::Rollback,
+            sdp: "".to_owned(),
+        },
+        parsed: None,
+    };
+    let result = pc_offer.set_local_description(rollback.clone()).await;
+    assert!(Error::ErrSignalingStateCannotRollback.equal(&result.err().unwrap()));
+
+    pc_offer.set_local_description_implicit().await?;
+    assert_eq!(SignalingState::HaveLocalOffer, pc_offer.signaling_state());
+    assert_eq!("0", transceiver.mid().await);
+    let offer = pc_offer.local_description().await.unwrap();
+
+    // a local offer can't be rolled back from the remote side
+    let result = pc_offer.set_remote_description(rollback.clone()).await;
+    assert!(Error::ErrSignalingStateProposedTransitionInvalid.equal(&result.err().unwrap()));
+
+    pc_offer.set_local_description(rollback.clone()).await?;
+    assert_eq!(SignalingState::Stable, pc_offer.signaling_state());
+    assert!(pc_offer.local_description().await.is_none());
+    assert_eq!("", transceiver.mid().await);
+    assert_eq!(1, pc_offer.get_transceivers().await.len());
+
+    pc_answer.set_remote_description(offer).await?;
+    assert_eq!(SignalingState::HaveRemoteOffer, pc_answer.signaling_state());
+    assert_eq!(1, pc_answer.get_transceivers().await.len());
+
+    pc_answer.set_remote_description(rollback).await?;
+    assert_eq!(SignalingState::Stable, pc_answer.signaling_state());
+    assert!(pc_answer.remote_description().await.is_none());
+    // the transceiver created for the remote offer is gone
+    assert!(pc_answer.get_transceivers().await.is_empty());
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+    Ok(())
+}
+
+/// negotiate_perfectly applies the perfect negotiation pattern to an offer the polite
+/// and impolite peers sent to each other at the same time.
+async fn negotiate_perfectly(polite: &PeerConnection, impolite: &PeerConnection) -> Result<()> {
+    let mut polite_gathering_complete = polite.gathering_complete_promise().await;
+    polite.set_local_description_implicit().await?;
+    let mut impolite_gathering_complete = impolite.gathering_complete_promise().await;
+    impolite.set_local_description_implicit().await?;
+    let _ = polite_gathering_complete.recv().await;
+    let _ = impolite_gathering_complete.recv().await;
+
+    let impolite_offer = impolite.local_description().await.unwrap();
+
+    // the impolite peer ignores the colliding offer
+    assert_eq!(SignalingState::HaveLocalOffer, impolite.signaling_state());
+
+    // the polite peer rolls back its own offer, and answers the other one
+    assert_eq!(SignalingState::HaveLocalOffer, polite.signaling_state());
+    polite
+        .set_local_description(SessionDescription {
+            serde: SessionDescriptionSerde {
+                sdp_type: SDPType::Rollback,
+                sdp: "".to_owned(),
+            },
+            parsed: None,
+        })
+        .await?;
+    polite.set_remote_description(impolite_offer).await?;
+    polite.set_local_description_implicit().await?;
+    assert_eq!(SignalingState::Stable, polite.signaling_state());
+
+    impolite
+        .set_remote_description(polite.local_description().await.unwrap())
+        .await?;
+    assert_eq!(SignalingState::Stable, impolite.signaling_state());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_perfect_negotiation() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut polite, mut impolite) = new_pair(&api).await?;
+    let polite_transceiver = polite
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+    impolite
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+
+    let wg = WaitGroup::new();
+    until_connection_state(&mut polite, &wg, PeerConnectionState::Connected).await;
+    until_connection_state(&mut impolite, &wg, PeerConnectionState::Connected).await;
+
+    negotiate_perfectly(&polite, &impolite).await?;
+    wg.wait().await;
+
+    // the polite peer reused its transceiver for the impolite peer's media section
+    assert_eq!(1, polite.get_transceivers().await.len());
+    assert_eq!(1, impolite.get_transceivers().await.len());
+    assert_eq!("0", polite_transceiver.mid().await);
+
+    // both renegotiate at the same time once connected
+    negotiate_perfectly(&polite, &impolite).await?;
+    assert_ne!(
+        RTPTransceiverDirection::Inactive,
+        polite_transceiver.direction()
+    );
+
+    close_pair_now(&polite, &impolite).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_bundle_and_rtcp_mux_policy() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let pc_offer = api
+        .new_peer_connection(Configuration {
+            bundle_policy: BundlePolicy::MaxBundle,
+            rtcp_mux_policy: RTCPMuxPolicy::Require,
+            ..Default::default()
+        })
+        .await?;
+    let pc_answer = api.new_peer_connection(Configuration::default()).await?;
+
+    pc_offer
+        .add_transceiver_from_kind(RTPCodecType::Audio, &[])
+        .await?;
+    pc_offer
+        .add_transceiver_from_kind(RTPCodecType::Video, &[])
+        .await?;
+
+    let offer = pc_offer.create_offer(None).await?;
+    assert!(offer.serde.sdp.contains("m=audio 9 "));
+    assert!(offer.serde.sdp.contains("m=video 0 "));
+    assert_eq!(1, offer.serde.sdp.matches("a=bundle-only").count());
+    assert_eq!(2, offer.serde.sdp.matches("a=rtcp-mux-only").count());
+
+    pc_answer.set_remote_description(offer.clone()).await?;
+    let answer = pc_answer.create_answer(None).await?;
+    assert!(!answer.serde.sdp.contains("a=bundle-only"));
+    assert!(!answer.serde.sdp.contains("a=rtcp-mux-only"));
+
+    pc_offer.set_local_description(offer.clone()).await?;
+    pc_offer.set_remote_description(answer).await?;
+
+    // a remote endpoint without rtcp-mux or BUNDLE is still accepted, whatever the policies
+    let mut offer_without_bundle = offer.clone();
+    offer_without_bundle.serde.sdp = offer_without_bundle
+        .serde
+        .sdp
+        .lines()
+        .filter(|l| !l.starts_with("a=group:BUNDLE"))
+        .map(|l| format!("{}\r\n", l))
+        .collect();
+    offer_without_bundle.parsed = None;
+
+    let mut offer_without_rtcp_mux = offer;
+    offer_without_rtcp_mux.serde.sdp = offer_without_rtcp_mux
+        .serde
+        .sdp
+        .replace("a=rtcp-mux\r\n", "");
+    offer_without_rtcp_mux.parsed = None;
+
+    let pc_require = api
+        .new_peer_connection(Configuration {
+            rtcp_mux_policy: RTCPMuxPolicy::Require,
+            ..Default::default()
+        })
+        .await?;
+    pc_require
+        .set_remote_description(offer_without_rtcp_mux)
+        .await?;
+
+    let pc_negotiate = api
+        .new_peer_connection(Configuration {
+            bundle_policy: BundlePolicy::MaxCompat,
+            ..Default::default()
+        })
+        .await?;
+    pc_negotiate
+        .set_remote_description(offer_without_bundle)
+        .await?;
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+    close_pair_now(&pc_require, &pc_negotiate).await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_ice_candidate_pool() -> Result<()> {
+    let api = APIBuilder::new().build();
+
+    // without a pool, candidates are gathered by set_local_description
+    let pc = api.new_peer_connection(Configuration::default()).await?;
+    assert_eq!(ICEGatheringState::New, pc.ice_gathering_state());
+    pc.create_data_channel("data", None).await?;
+    let offer = pc.create_offer(None).await?;
+    assert!(!offer.serde.sdp.contains("a=candidate"));
+    pc.close().await?;
+
+    let pc = api
+        .new_peer_connection(Configuration {
+            ice_candidate_pool_size: 1,
+            ..Default::default()
+        })
+        .await?;
+    // wait for the pool to be gathered, which is held back until a local description
+    // is set
+    loop {
+        {
+            let pool = pc.internal.ice_gatherer.pool.lock().await;
+            if matches!(pool.as_ref().and_then(|c| c.last()), Some(None)) {
+                break;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(ICEGatheringState::New, pc.ice_gathering_state());
+
+    let (candidate_tx, mut candidate_rx) = mpsc::channel(64);
+    pc.on_ice_candidate(Box::new(move |c: Option<ICECandidate>| {
+        let candidate_tx = candidate_tx.clone();
+        Box::pin(async move {
+            let _ = candidate_tx.send(c).await;
+        })
+    }))
+    .await;
+
+    // the first offer uses the pooled candidates, which are emitted once it is set
+    pc.create_data_channel("data", None).await?;
+    let offer = pc.create_offer(None).await?;
+    assert!(offer.serde.sdp.contains("a=candidate"));
+    let pooled = pc.internal.ice_gatherer.get_local_candidates().await?;
+    let mut gathering_complete = pc.gathering_complete_promise().await;
+    pc.set_local_description(offer).await?;
+    let _ = gathering_complete.recv().await;
+    assert_eq!(ICEGatheringState::Complete, pc.ice_gathering_state());
+    assert_eq!(
+        pooled,
+        pc.internal.ice_gatherer.get_local_candidates().await?
+    );
+
+    let mut emitted = vec![];
+    while let Some(c) = candidate_rx.recv().await.unwrap() {
+        emitted.push(c);
+    }
+    assert_eq!(pooled.len(), emitted.len());
+    assert!(emitted.iter().all(|c| pooled.contains(c)));
+
+    // closing releases the pool
+    pc.close().await?;
+    assert_eq!(ICEGathererState::Closed, pc.internal.ice_gatherer.state());
+    assert!(pc.internal.ice_gatherer.get_agent().await.is_none());
+
+    // enabling the pool later starts gathering
+    let pc = api.new_peer_connection(Configuration::default()).await?;
+    pc.set_configuration(Configuration {
+        ice_candidate_pool_size: 2,
+        ..Default::default()
+    })
+    .await?;
+    assert!(pc.internal.ice_gatherer.get_agent().await.is_some());
+    assert_eq!(ICEGatheringState::New, pc.ice_gathering_state());
+    pc.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_shared_certificate() -> Result<()> {
+    let api = APIBuilder::new().build();
+
+    let certificate =
+        Certificate::from_key_pair(KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?)?;
+    let fingerprint = certificate.get_fingerprint()?.value.to_uppercase();
+
+    // a clone is shared with one PeerConnection, a certificate restored from its
+    // pem is used by the other one
+    let restored = Certificate::from_pem_with_key(&certificate.pem()?)?;
+
+    let pc1 = api
+        .new_peer_connection(Configuration {
+            certificates: vec![certificate.clone()],
+            ..Default::default()
+        })
+        .await?;
+    let pc2 = api
+        .new_peer_connection(Configuration {
+            certificates: vec![restored],
+            ..Default::default()
+        })
+        .await?;
+
+    for pc in [&pc1, &pc2].iter() {
+        pc.create_data_channel("data", None).await?;
+        let offer = pc.create_offer(None).await?;
+        assert!(offer.serde.sdp.contains(&fingerprint));
+    }
+
+    close_pair_now(&pc1, &pc2).await;
+    Ok(())
+}
+
+/// LocalIdentityProvider stands in for an identity provider: the identities of
+/// its domain are asserted by signing the contents with a key it shares with
+/// the other peers.
+struct LocalIdentityProvider {
+    identity: String,
+    domain: String,
+    key: ring::hmac::Key,
+}
+
+impl LocalIdentityProvider {
+    fn new(user: &str) -> Arc<Self> {
+        Arc::new(LocalIdentityProvider {
+            identity: format!("{}@example.org", user),
+            domain: "example.org".to_owned(),
+            key: ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"shared secret"),
+        })
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for LocalIdentityProvider {
+    async fn generate_assertion(&self, contents: &str) -> Result<IdentityAssertion> {
+        let signed = format!("{}\n{}", self.identity, contents);
+        let tag = ring::hmac::sign(&self.key, signed.as_bytes());
+        Ok(IdentityAssertion {
+            idp: IdentityProviderDetails {
+                domain: self.domain.clone(),
+                protocol: "local".to_owned(),
+            },
+            assertion: serde_json::to_string(&(signed, base64::encode(tag.as_ref())))?,
+        })
+    }
+
+    async fn validate_assertion(
+        &self,
+        assertion: &IdentityAssertion,
+    ) -> Result<IdentityValidationResult> {
+        let (signed, tag): (String, String) = serde_json::from_str(&assertion.assertion)?;
+        ring::hmac::verify(&self.key, signed.as_bytes(), &base64::decode(tag)?)
+            .map_err(|_| Error::ErrInvalidIdentityAssertion)?;
+
+        let (identity, contents) = signed.split_once('\n').unwrap();
+        Ok(IdentityValidationResult {
+            identity: identity.to_owned(),
+            contents: contents.to_owned(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_peer_connection_identity() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let pc_offer = api.new_peer_connection(Configuration::default()).await?;
+    pc_offer
+        .set_identity_provider(LocalIdentityProvider::new("alice"))
+        .await;
+    let pc_answer = api
+        .new_peer_connection(Configuration {
+            peer_identity: "alice@example.org".to_owned(),
+            ..Default::default()
+        })
+        .await?;
+    pc_answer
+        .set_identity_provider(LocalIdentityProvider::new("bob"))
+        .await;
+
+    pc_offer.create_data_channel("data", None).await?;
+    let offer = pc_offer.create_offer(None).await?;
+    assert!(offer.serde.sdp.contains("a=identity:"));
+    pc_offer.set_local_description(offer.clone()).await?;
+
+    assert!(pc_answer.peer_identity().await.is_none());
+    pc_answer.set_remote_description(offer).await?;
+    assert_eq!(
+        Some("alice@example.org".to_owned()),
+        pc_answer.peer_identity().await
+    );
+
+    let answer = pc_answer.create_answer(None).await?;
+    pc_answer.set_local_description(answer.clone()).await?;
+    pc_offer.set_remote_description(answer).await?;
+    assert_eq!(
+        Some("bob@example.org".to_owned()),
+        pc_offer.peer_identity().await
+    );
+
+    close_pair_now(&pc_offer, &pc_answer).await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_identity_rejected() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let pc_offer = api.new_peer_connection(Configuration::default()).await?;
+    pc_offer.create_data_channel("data", None).await?;
+    let anonymous_offer = pc_offer.create_offer(None).await?;
+    pc_offer
+        .set_identity_provider(LocalIdentityProvider::new("mallory"))
+        .await;
+    let offer = pc_offer.create_offer(None).await?;
+
+    // the assertion is bound to the fingerprint of the certificate of the offerer
+    let mut other_certificate_offer = offer.clone();
+    let fingerprint = pc_offer
+        .internal
+        .dtls_transport
+        .get_local_parameters()?
+        .fingerprints[0]
+        .value
+        .to_uppercase();
+    other_certificate_offer.serde.sdp = other_certificate_offer.serde.sdp.replace(
+        &fingerprint,
+        &fingerprint.replace(|c: char| c.is_ascii_hexdigit(), "A"),
+    );
+    assert_ne!(offer.serde.sdp, other_certificate_offer.serde.sdp);
+
+    let tests = vec![
+        (
+            "no assertion",
+            anonymous_offer,
+            true,
+            Error::ErrIdentityAssertionMissing,
+        ),
+        (
+            "no identity provider",
+            offer.clone(),
+            false,
+            Error::ErrNoIdentityProvider,
+        ),
+        (
+            "other identity",
+            offer,
+            true,
+            Error::ErrPeerIdentityMismatch,
+        ),
+        (
+            "other certificate",
+            other_certificate_offer,
+            true,
+            Error::ErrIdentityAssertionFingerprintMismatch,
+        ),
+    ];
+
+    for (name, offer, with_identity_provider, expected_err) in tests {
+        let pc_answer = api
+            .new_peer_connection(Configuration {
+                peer_identity: "alice@example.org".to_owned(),
+                ..Default::default()
+            })
+            .await?;
+        if with_identity_provider {
+            pc_answer
+                .set_identity_provider(LocalIdentityProvider::new("bob"))
+                .await;
+        }
+
+        let result = pc_answer.set_remote_description(offer).await;
+        assert!(expected_err.equal(&result.err().unwrap()), "{}", name);
+        assert_eq!(
+            SignalingState::Stable,
+            pc_answer.signaling_state(),
+            "{}",
+            name
+        );
+        assert!(pc_answer.peer_identity().await.is_none(), "{}", name);
+
+        pc_answer.close().await?;
+    }
+
+    pc_offer.close().await?;
+
+    Ok(())
+}
+
+/// new_sending_pair connects a new pair of peer connections, the offerer sending
+/// video to the answerer until done_tx is dropped
+async fn new_sending_pair(
+    api: &API,
+) -> Result<(
+    PeerConnection,
+    PeerConnection,
+    Arc<RTPSender>,
+    mpsc::Sender<()>,
+)> {
+    let (mut offerer, mut answerer) = new_pair(api).await?;
+
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTPCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "webrtc-rs".to_owned(),
+    ));
+    let rtp_sender = offerer
+        .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+        .await?;
+
+    answerer
+        .on_track(Box::new(
+            move |track: Option<Arc<TrackRemote>>, _: Option<Arc<RTPReceiver>>| {
+                Box::pin(async move {
+                    if let Some(t) = track {
+                        while t.read_rtp().await.is_ok() {}
+                    }
+                })
+            },
+        ))
+        .await;
+
+    signal_pair(&mut offerer, &mut answerer).await?;
+
+    let (done_tx, done_rx) = mpsc::channel::<()>(1);
+    tokio::spawn(async move {
+        send_video_until_done(done_rx, vec![track], Bytes::from_static(&[0xAA; 100])).await;
+    });
+
+    Ok((offerer, answerer, rtp_sender, done_tx))
+}
+
+/// read_receiver_report reads the RTCP of rtp_sender until it gets a receiver
+/// report about the stream of the sender
+async fn read_receiver_report(rtp_sender: &RTPSender) -> Result<()> {
+    let ssrc = rtp_sender.get_parameters().await.encodings[0].ssrc;
+    let mut b = vec![0u8; RECEIVE_MTU];
+    loop {
+        let (n, _) = rtp_sender.read(&mut b).await?;
+        for pkt in unmarshal_rtcp(&b[..n])? {
+            if let Some(rr) = pkt
+                .as_any()
+                .downcast_ref::<rtcp::receiver_report::ReceiverReport>()
+            {
+                if rr.reports.iter().any(|r| r.ssrc == ssrc) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_peer_connection_interceptors_per_peer_connection() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new()
+        .with_media_engine(m)
+        .with_interceptor_registry(configure_rtcp_reports(Registry::new())?)
+        .build();
+
+    let (offerer1, answerer1, rtp_sender1, done_tx1) = new_sending_pair(&api).await?;
+    let (offerer2, answerer2, rtp_sender2, done_tx2) = new_sending_pair(&api).await?;
+
+    // each answerer reports about the stream it receives to its own offerer
+    tokio::time::timeout(Duration::from_secs(5), read_receiver_report(&rtp_sender1)).await??;
+    tokio::time::timeout(Duration::from_secs(5), read_receiver_report(&rtp_sender2)).await??;
+
+    // and closing a pair doesn't stop the interceptors of the other one
+    drop(done_tx1);
+    close_pair_now(&offerer1, &answerer1).await;
+    tokio::time::timeout(Duration::from_secs(5), read_receiver_report(&rtp_sender2)).await??;
+
+    drop(done_tx2);
+    close_pair_now(&offerer2, &answerer2).await;
+    Ok(())
+}
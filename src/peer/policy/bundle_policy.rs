@@ -5,6 +5,10 @@ use std::fmt;
 /// endpoint is not bundle-aware, and what ICE candidates are gathered. If the
 /// remote endpoint is bundle-aware, all media tracks and data channels are
 /// bundled onto the same transport.
+///
+/// Only a single transport is created for all media, whatever the policy and
+/// whether the remote endpoint is bundle-aware or not. MaxBundle offers every
+/// media section but the first one as bundle-only.
 #[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum BundlePolicy {
     Unspecified = 0,
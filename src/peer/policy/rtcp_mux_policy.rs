@@ -3,6 +3,10 @@ use std::fmt;
 
 /// RTCPMuxPolicy affects what ICE candidates are gathered to support
 /// non-multiplexed RTCP.
+///
+/// The ICE agent only gathers RTP candidates, so RTCP is always multiplexed with
+/// either policy, even if the remote endpoint doesn't support rtcp-mux. Require
+/// additionally offers rtcp-mux-only.
 #[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum RTCPMuxPolicy {
     Unspecified = 0,
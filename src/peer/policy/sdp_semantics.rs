@@ -104,14 +104,30 @@ mod test {
             .collect()
     }
 
+    /// extract_ssrc_list returns the SSRCs of the tracks of a media section,
+    /// leaving out the RTX repair flows
     fn extract_ssrc_list(md: &MediaDescription) -> Vec<String> {
+        let mut repair_ssrcs = HashSet::new();
+        for attr in &md.attributes {
+            if attr.key == "ssrc-group" {
+                if let Some(value) = &attr.value {
+                    let fields: Vec<&str> = value.split_whitespace().collect();
+                    if fields.len() == 3 && fields[0] == "FID" {
+                        repair_ssrcs.insert(fields[2]);
+                    }
+                }
+            }
+        }
+
         let mut ssrcs = HashSet::new();
         for attr in &md.attributes {
             if attr.key == SSRC_STR {
                 if let Some(value) = &attr.value {
                     let fields: Vec<&str> = value.split_whitespace().collect();
                     if let Some(ssrc) = fields.first() {
-                        ssrcs.insert(*ssrc);
+                        if !repair_ssrcs.contains(ssrc) {
+                            ssrcs.insert(*ssrc);
+                        }
                     }
                 }
             }
@@ -2,6 +2,7 @@
 mod sdp_test;
 
 use crate::api::media_engine::MediaEngine;
+use crate::data::sctp_transport::SCTP_MAX_MESSAGE_SIZE_UNSET_VALUE;
 use crate::error::Error;
 use crate::media::dtls_transport::dtls_fingerprint::DTLSFingerprint;
 use crate::media::rtp::rtp_codec::{RTPCodecCapability, RTPCodecParameters, RTPCodecType};
@@ -41,6 +42,7 @@ pub(crate) struct TrackDetails {
     pub(crate) stream_id: String,
     pub(crate) id: String,
     pub(crate) ssrc: SSRC,
+    pub(crate) repair_ssrc: SSRC,
     pub(crate) rids: Vec<String>,
 }
 
@@ -68,9 +70,7 @@ pub(crate) fn track_details_from_sdp(
         let mut track_id = "";
 
         // If media section is recvonly or inactive skip
-        if media.attribute(ATTR_KEY_RECV_ONLY).is_some()
-            || media.attribute(ATTR_KEY_INACTIVE).is_some()
-        {
+        if has_attribute(media, ATTR_KEY_RECV_ONLY) || has_attribute(media, ATTR_KEY_INACTIVE) {
             continue;
         }
 
@@ -95,10 +95,13 @@ pub(crate) fn track_details_from_sdp(
                             // as this declares that the second SSRC (632943048) is a rtx repair flow (RFC4588) for the first
                             // (2231627014) as specified in RFC5576
                             if split.len() == 3 {
-                                if let Err(err) = split[1].parse::<u32>() {
-                                    log::warn!("Failed to parse SSRC: {}", err);
-                                    continue;
-                                }
+                                let base_ssrc = match split[1].parse::<u32>() {
+                                    Ok(n) => n,
+                                    Err(err) => {
+                                        log::warn!("Failed to parse SSRC: {}", err);
+                                        continue;
+                                    }
+                                };
                                 let rtx_repair_flow = match split[2].parse::<u32>() {
                                     Ok(n) => n,
                                     Err(err) => {
@@ -106,7 +109,7 @@ pub(crate) fn track_details_from_sdp(
                                         continue;
                                     }
                                 };
-                                rtx_repair_flows.insert(rtx_repair_flow, true);
+                                rtx_repair_flows.insert(rtx_repair_flow, base_ssrc);
                                 // Remove if rtx was added as track before
                                 filter_track_with_ssrc(
                                     &mut incoming_tracks,
@@ -200,6 +203,15 @@ pub(crate) fn track_details_from_sdp(
         }
     }
 
+    // Attach every repair flow to the track of the SSRC it protects
+    for (repair_ssrc, base_ssrc) in &rtx_repair_flows {
+        for t in &mut incoming_tracks {
+            if t.ssrc == *base_ssrc {
+                t.repair_ssrc = *repair_ssrc;
+            }
+        }
+    }
+
     incoming_tracks
 }
 
@@ -256,9 +268,58 @@ pub(crate) async fn add_candidates_to_media_descriptions(
     Ok(m.with_property_attribute("end-of-candidates".to_owned()))
 }
 
+/// ATTR_KEY_RTCPMUX_ONLY offers a media section whose RTCP is only sent
+/// multiplexed with RTP, as described in https://tools.ietf.org/html/rfc8858
+pub(crate) const ATTR_KEY_RTCPMUX_ONLY: &str = "rtcp-mux-only";
+
+/// ATTR_KEY_BUNDLE_ONLY offers a media section which is only accepted if it
+/// is bundled, as described in https://tools.ietf.org/html/rfc8843#section-6
+pub(crate) const ATTR_KEY_BUNDLE_ONLY: &str = "bundle-only";
+
+/// ATTR_KEY_MAX_MESSAGE_SIZE advertises the size of the largest message the
+/// SCTP endpoint can receive, as described in https://tools.ietf.org/html/rfc8841#section-6
+pub(crate) const ATTR_KEY_MAX_MESSAGE_SIZE: &str = "max-message-size";
+
+/// with_bundle_only marks a media section as bundle-only, which also zeroes
+/// its port so that endpoints not aware of BUNDLE reject it.
+fn with_bundle_only(mut media: MediaDescription) -> MediaDescription {
+    media.media_name.port.value = 0;
+    media.with_property_attribute(ATTR_KEY_BUNDLE_ONLY.to_owned())
+}
+
+/// rejected_media_section returns a media section rejected with a zero port. It keeps
+/// its mid, so that later negotiations match the m-line and can reuse it.
+fn rejected_media_section(kind: String, mid_value: String) -> MediaDescription {
+    MediaDescription {
+        media_name: MediaName {
+            media: kind,
+            port: RangedPort {
+                value: 0,
+                range: None,
+            },
+            protos: vec![
+                "UDP".to_owned(),
+                "TLS".to_owned(),
+                "RTP".to_owned(),
+                "SAVPF".to_owned(),
+            ],
+            formats: vec!["0".to_owned()],
+        },
+        media_title: None,
+        connection_information: None,
+        bandwidth: vec![],
+        encryption_key: None,
+        attributes: vec![],
+    }
+    .with_value_attribute(ATTR_KEY_MID.to_owned(), mid_value)
+    .with_property_attribute(RTPTransceiverDirection::Inactive.to_string())
+}
+
 pub(crate) struct AddDataMediaSectionParams {
     should_add_candidates: bool,
+    bundle_only: bool,
     mid_value: String,
+    max_message_size: u32,
     ice_params: ICEParameters,
     dtls_role: ConnectionRole,
     ice_gathering_state: ICEGatheringState,
@@ -301,11 +362,19 @@ pub(crate) async fn add_data_media_section(
     .with_value_attribute(ATTR_KEY_MID.to_owned(), params.mid_value)
     .with_property_attribute(RTPTransceiverDirection::Sendrecv.to_string())
     .with_property_attribute("sctp-port:5000".to_owned())
+    .with_value_attribute(
+        ATTR_KEY_MAX_MESSAGE_SIZE.to_owned(),
+        params.max_message_size.to_string(),
+    )
     .with_ice_credentials(
         params.ice_params.username_fragment,
         params.ice_params.password,
     );
 
+    if params.bundle_only {
+        media = with_bundle_only(media);
+    }
+
     for f in dtls_fingerprints {
         media = media.with_fingerprint(f.algorithm.clone(), f.value.to_uppercase());
     }
@@ -338,15 +407,19 @@ pub(crate) async fn populate_local_candidates(
             Err(_) => return Some(sd.clone()),
         };
 
-        if !parsed.media_descriptions.is_empty() {
-            let mut m = parsed.media_descriptions.remove(0);
+        if let Some(i) = parsed
+            .media_descriptions
+            .iter()
+            .position(|m| !is_media_section_rejected(m))
+        {
+            let mut m = parsed.media_descriptions.remove(i);
             m = match add_candidates_to_media_descriptions(&candidates, m, ice_gathering_state)
                 .await
             {
                 Ok(m) => m,
                 Err(_) => return Some(sd.clone()),
             };
-            parsed.media_descriptions.insert(0, m);
+            parsed.media_descriptions.insert(i, m);
         }
 
         Some(session_description::SessionDescription {
@@ -364,6 +437,8 @@ pub(crate) async fn populate_local_candidates(
 pub(crate) struct AddTransceiverSdpParams {
     is_plan_b: bool,
     should_add_candidates: bool,
+    rtcp_mux_only: bool,
+    bundle_only: bool,
     mid_value: String,
     dtls_role: ConnectionRole,
     ice_gathering_state: ICEGatheringState,
@@ -392,6 +467,13 @@ pub(crate) async fn add_transceiver_sdp(
     let transceivers = &media_section.transceivers;
     // Use the first transceiver to generate the section attributes
     let t = &transceivers[0];
+
+    if media_section.is_rejected() {
+        return Ok((
+            d.with_media(rejected_media_section(t.kind.to_string(), mid_value)),
+            false,
+        ));
+    }
     let mut media = sdp::media_description::MediaDescription::new_jsep_media_description(
         t.kind.to_string(),
         vec![],
@@ -405,6 +487,13 @@ pub(crate) async fn add_transceiver_sdp(
     .with_property_attribute(ATTR_KEY_RTCPMUX.to_owned())
     .with_property_attribute(ATTR_KEY_RTCPRSIZE.to_owned());
 
+    if params.rtcp_mux_only {
+        media = media.with_property_attribute(ATTR_KEY_RTCPMUX_ONLY.to_owned());
+    }
+    if params.bundle_only {
+        media = with_bundle_only(media);
+    }
+
     let codecs = t.get_codecs().await;
     for codec in &codecs {
         let name = codec
@@ -477,29 +566,55 @@ pub(crate) async fn add_transceiver_sdp(
         });
     }
 
-    if !media_section.rid_map.is_empty() {
-        let mut recv_rids: Vec<String> = vec![];
-
-        for rid in media_section.rid_map.keys() {
-            media = media.with_value_attribute("rid".to_owned(), rid.to_owned() + " recv");
-            recv_rids.push(rid.to_owned());
-        }
-        // Simulcast
-        media = media.with_value_attribute(
-            "simulcast".to_owned(),
-            "recv ".to_owned() + recv_rids.join(";").as_str(),
-        );
+    let mut recv_rids: Vec<String> = vec![];
+    for rid in media_section.rid_map.keys() {
+        media = media.with_value_attribute("rid".to_owned(), rid.to_owned() + " recv");
+        recv_rids.push(rid.to_owned());
     }
 
+    let mut send_rids: Vec<String> = vec![];
     for mt in transceivers {
         if let Some(sender) = mt.sender().await {
             if let Some(track) = sender.track().await {
-                media = media.with_media_source(
-                    sender.ssrc,
-                    track.stream_id().to_owned(), /* cname */
-                    track.stream_id().to_owned(), /* streamLabel */
-                    track.id().to_owned(),
-                );
+                let send_parameters = sender.get_parameters().await;
+                if send_parameters.encodings.len() > 1 {
+                    // Simulcast layers are signalled by RID rather than by SSRC
+                    for encoding in &send_parameters.encodings {
+                        media = media
+                            .with_value_attribute("rid".to_owned(), encoding.rid.clone() + " send");
+                        send_rids.push(encoding.rid.clone());
+                    }
+                } else {
+                    for encoding in &send_parameters.encodings {
+                        if encoding.rtx.ssrc != 0 {
+                            media = media.with_value_attribute(
+                                ATTR_KEY_SSRCGROUP.to_owned(),
+                                format!(
+                                    "{} {} {}",
+                                    SEMANTIC_TOKEN_FLOW_IDENTIFICATION,
+                                    encoding.ssrc,
+                                    encoding.rtx.ssrc
+                                ),
+                            );
+                        }
+
+                        media = media.with_media_source(
+                            encoding.ssrc,
+                            track.stream_id().to_owned(), /* cname */
+                            track.stream_id().to_owned(), /* streamLabel */
+                            track.id().to_owned(),
+                        );
+
+                        if encoding.rtx.ssrc != 0 {
+                            media = media.with_media_source(
+                                encoding.rtx.ssrc,
+                                track.stream_id().to_owned(), /* cname */
+                                track.stream_id().to_owned(), /* streamLabel */
+                                track.id().to_owned(),
+                            );
+                        }
+                    }
+                }
                 if !is_plan_b {
                     media = media.with_property_attribute(
                         "msid:".to_owned() + track.stream_id() + " " + track.id(),
@@ -510,7 +625,24 @@ pub(crate) async fn add_transceiver_sdp(
         }
     }
 
-    media = media.with_property_attribute(t.direction().to_string());
+    // Simulcast
+    let mut simulcast = vec![];
+    if !send_rids.is_empty() {
+        simulcast.push("send ".to_owned() + send_rids.join(";").as_str());
+    }
+    if !recv_rids.is_empty() {
+        simulcast.push("recv ".to_owned() + recv_rids.join(";").as_str());
+    }
+    if !simulcast.is_empty() {
+        media = media.with_value_attribute("simulcast".to_owned(), simulcast.join(" "));
+    }
+
+    // An answer may only accept the directions the offer allows (RFC 8829 5.3.1)
+    let direction = match media_section.offered_direction {
+        Some(offered_direction) => t.direction().intersect(offered_direction.reverse()),
+        None => t.direction(),
+    };
+    media = media.with_property_attribute(direction.to_string());
 
     for fingerprint in dtls_fingerprints {
         media = media.with_fingerprint(
@@ -533,6 +665,17 @@ pub(crate) struct MediaSection {
     pub(crate) transceivers: Vec<Arc<RTPTransceiver>>,
     pub(crate) data: bool,
     pub(crate) rid_map: HashMap<String, String>,
+    /// offered_direction is the direction of the remote offer for this media section,
+    /// None when generating an offer
+    pub(crate) offered_direction: Option<RTPTransceiverDirection>,
+}
+
+impl MediaSection {
+    /// is_rejected reports whether the media section is rejected, as all of its
+    /// transceivers are stopping
+    pub(crate) fn is_rejected(&self) -> bool {
+        !self.transceivers.is_empty() && self.transceivers.iter().all(|t| t.stopping())
+    }
 }
 
 pub(crate) struct PopulateSdpParams {
@@ -541,6 +684,13 @@ pub(crate) struct PopulateSdpParams {
     pub(crate) is_icelite: bool,
     pub(crate) connection_role: ConnectionRole,
     pub(crate) ice_gathering_state: ICEGatheringState,
+    /// rtcp_mux_only offers the media sections without a separate RTCP component
+    pub(crate) rtcp_mux_only: bool,
+    /// bundle_only offers every media section but the first one as usable only
+    /// when bundled with it
+    pub(crate) bundle_only: bool,
+    /// max_message_size is the largest data channel message that can be received
+    pub(crate) max_message_size: u32,
 }
 
 /// populate_sdp serializes a PeerConnections state into an SDP
@@ -566,19 +716,29 @@ pub(crate) async fn populate_sdp(
         *count += 1;
     };
 
-    for (i, m) in media_sections.iter().enumerate() {
+    // The first media section not rejected carries the candidates, and is the one
+    // the bundle-only sections are bundled with
+    let mut have_first_section = false;
+    for m in media_sections {
         if m.data && !m.transceivers.is_empty() {
             return Err(Error::ErrSDPMediaSectionMediaDataChanInvalid.into());
         } else if !params.is_plan_b && m.transceivers.len() > 1 {
             return Err(Error::ErrSDPMediaSectionMultipleTrackInvalid.into());
         }
 
-        let should_add_candidates = i == 0;
+        let rejected = m.is_rejected();
+        let should_add_candidates = !rejected && !have_first_section;
+        let bundle_only = params.bundle_only && !rejected && have_first_section;
+        if !rejected {
+            have_first_section = true;
+        }
 
         let should_add_id = if m.data {
             let params = AddDataMediaSectionParams {
                 should_add_candidates,
+                bundle_only,
                 mid_value: m.id.clone(),
+                max_message_size: params.max_message_size,
                 ice_params: ice_params.clone(),
                 dtls_role: params.connection_role,
                 ice_gathering_state: params.ice_gathering_state,
@@ -589,6 +749,8 @@ pub(crate) async fn populate_sdp(
             let params = AddTransceiverSdpParams {
                 is_plan_b: params.is_plan_b,
                 should_add_candidates,
+                rtcp_mux_only: params.rtcp_mux_only,
+                bundle_only,
                 mid_value: m.id.clone(),
                 dtls_role: params.connection_role,
                 ice_gathering_state: params.ice_gathering_state,
@@ -670,37 +832,61 @@ pub(crate) fn get_peer_direction(
     RTPTransceiverDirection::Unspecified
 }
 
-pub(crate) fn extract_fingerprint(
+/// extract_fingerprints returns the fingerprints of the remote certificates. They can
+/// be set at the session level or in media sections, as long as they are the same
+/// wherever they are set.
+pub(crate) fn extract_fingerprints(
     desc: &sdp::session_description::SessionDescription,
-) -> Result<(String, String)> {
-    let mut fingerprints = vec![];
-
-    if let Some(fingerprint) = desc.attribute("fingerprint") {
-        fingerprints.push(fingerprint.clone());
-    }
+) -> Result<Vec<DTLSFingerprint>> {
+    let fingerprints_of = |attributes: &[sdp::common_description::Attribute]| {
+        attributes
+            .iter()
+            .filter(|a| a.key == "fingerprint")
+            .filter_map(|a| a.value.clone())
+            .collect::<Vec<String>>()
+    };
 
+    let mut fingerprints = vec![fingerprints_of(&desc.attributes)];
     for m in &desc.media_descriptions {
-        if let Some(fingerprint) = m.attribute("fingerprint") {
-            fingerprints.push(fingerprint.clone());
-        }
+        fingerprints.push(fingerprints_of(&m.attributes));
     }
+    fingerprints.retain(|f| !f.is_empty());
 
-    if fingerprints.is_empty() {
-        return Err(Error::ErrSessionDescriptionNoFingerprint.into());
-    }
+    let first = match fingerprints.first() {
+        Some(first) => first,
+        None => return Err(Error::ErrSessionDescriptionNoFingerprint.into()),
+    };
 
-    for m in 1..fingerprints.len() {
-        if fingerprints[m] != fingerprints[0] {
+    let sorted = |f: &[String]| {
+        let mut f = f.to_vec();
+        f.sort();
+        f.dedup();
+        f
+    };
+    let first_sorted = sorted(first);
+    for f in &fingerprints[1..] {
+        if sorted(f) != first_sorted {
             return Err(Error::ErrSessionDescriptionConflictingFingerprints.into());
         }
     }
 
-    let parts: Vec<&str> = fingerprints[0].split(' ').collect();
-    if parts.len() != 2 {
-        return Err(Error::ErrSessionDescriptionInvalidFingerprint.into());
+    let mut dtls_fingerprints = vec![];
+    for fingerprint in first {
+        let parts: Vec<&str> = fingerprint.split(' ').collect();
+        if parts.len() != 2 {
+            return Err(Error::ErrSessionDescriptionInvalidFingerprint.into());
+        }
+
+        let dtls_fingerprint = DTLSFingerprint {
+            algorithm: parts[0].to_owned(),
+            value: parts[1].to_owned(),
+        };
+        if !dtls_fingerprints.contains(&dtls_fingerprint) {
+            dtls_fingerprints.push(dtls_fingerprint);
+        }
     }
 
-    Ok((parts[1].to_owned(), parts[0].to_owned()))
+    Ok(dtls_fingerprints)
 }
 
 pub(crate) async fn extract_ice_details(
@@ -758,6 +944,19 @@ pub(crate) async fn extract_ice_details(
     Ok((remote_ufrags[0].clone(), remote_pwds[0].clone(), candidates))
 }
 
+/// have_end_of_candidates reports whether the description signals that the remote
+/// peer has no more candidates, either for the session or for a media section.
+/// All media sections share a single ICE transport.
+pub(crate) fn have_end_of_candidates(desc: &sdp::session_description::SessionDescription) -> bool {
+    desc.attributes
+        .iter()
+        .any(|a| a.key == ATTR_KEY_END_OF_CANDIDATES)
+        || desc
+            .media_descriptions
+            .iter()
+            .any(|m| !is_media_section_rejected(m) && has_attribute(m, ATTR_KEY_END_OF_CANDIDATES))
+}
+
 pub(crate) fn have_application_media_section(
     desc: &sdp::session_description::SessionDescription,
 ) -> bool {
@@ -770,6 +969,35 @@ pub(crate) fn have_application_media_section(
     false
 }
 
+/// get_max_message_size returns the max-message-size advertised in the application
+/// media section, or the size implied when the attribute is absent.
+pub(crate) fn get_max_message_size(desc: &sdp::session_description::SessionDescription) -> u32 {
+    for m in &desc.media_descriptions {
+        if m.media_name.media != MEDIA_SECTION_APPLICATION {
+            continue;
+        }
+        if let Some(value) = m.attribute(ATTR_KEY_MAX_MESSAGE_SIZE) {
+            if let Ok(max_message_size) = value.parse::<u32>() {
+                return max_message_size;
+            }
+        }
+    }
+
+    SCTP_MAX_MESSAGE_SIZE_UNSET_VALUE
+}
+
+/// is_media_section_rejected reports whether the media section is rejected, that is
+/// it has a zero port and is not bundle-only.
+pub(crate) fn is_media_section_rejected(media: &MediaDescription) -> bool {
+    media.media_name.port.value == 0 && !has_attribute(media, ATTR_KEY_BUNDLE_ONLY)
+}
+
+/// has_attribute reports whether the media section has the attribute, which
+/// may be a property attribute without a value.
+pub(crate) fn has_attribute(media: &MediaDescription, key: &str) -> bool {
+    media.attributes.iter().any(|a| a.key == key)
+}
+
 pub(crate) fn get_by_mid<'a, 'b>(
     search_mid: &'a str,
     desc: &'b session_description::SessionDescription,
@@ -23,9 +23,14 @@ fn test_extract_fingerprint() -> Result<()> {
             ..Default::default()
         };
 
-        let (fingerprint, hash) = extract_fingerprint(&s)?;
-        assert_eq!(fingerprint, "bar");
-        assert_eq!(hash, "foo");
+        let fingerprints = extract_fingerprints(&s)?;
+        assert_eq!(
+            fingerprints,
+            vec![DTLSFingerprint {
+                algorithm: "foo".to_owned(),
+                value: "bar".to_owned(),
+            }]
+        );
     }
 
     //"Good Media Fingerprint"
@@ -41,16 +46,21 @@ fn test_extract_fingerprint() -> Result<()> {
             ..Default::default()
         };
 
-        let (fingerprint, hash) = extract_fingerprint(&s)?;
-        assert_eq!(fingerprint, "bar");
-        assert_eq!(hash, "foo");
+        let fingerprints = extract_fingerprints(&s)?;
+        assert_eq!(
+            fingerprints,
+            vec![DTLSFingerprint {
+                algorithm: "foo".to_owned(),
+                value: "bar".to_owned(),
+            }]
+        );
     }
 
     //"No Fingerprint"
     {
         let s = sdp::session_description::SessionDescription::default();
 
-        if let Err(err) = extract_fingerprint(&s) {
+        if let Err(err) = extract_fingerprints(&s) {
             assert!(Error::ErrSessionDescriptionNoFingerprint.equal(&err));
         } else {
             assert!(false);
@@ -67,7 +77,7 @@ fn test_extract_fingerprint() -> Result<()> {
             ..Default::default()
         };
 
-        if let Err(err) = extract_fingerprint(&s) {
+        if let Err(err) = extract_fingerprints(&s) {
             assert!(Error::ErrSessionDescriptionInvalidFingerprint.equal(&err));
         } else {
             assert!(false);
@@ -91,7 +101,62 @@ fn test_extract_fingerprint() -> Result<()> {
             ..Default::default()
         };
 
-        if let Err(err) = extract_fingerprint(&s) {
+        if let Err(err) = extract_fingerprints(&s) {
+            assert!(Error::ErrSessionDescriptionConflictingFingerprints.equal(&err));
+        } else {
+            assert!(false);
+        }
+    }
+
+    let fingerprint_attributes = |values: &[&str]| {
+        values
+            .iter()
+            .map(|value| Attribute {
+                key: "fingerprint".to_owned(),
+                value: Some((*value).to_owned()),
+            })
+            .collect::<Vec<Attribute>>()
+    };
+
+    //"Multiple Fingerprints"
+    {
+        let s = sdp::session_description::SessionDescription {
+            attributes: fingerprint_attributes(&["sha-256 AA", "sha-1 BB"]),
+            media_descriptions: vec![sdp::media_description::MediaDescription {
+                attributes: fingerprint_attributes(&["sha-1 BB", "sha-256 AA"]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let fingerprints = extract_fingerprints(&s)?;
+        assert_eq!(
+            fingerprints,
+            vec![
+                DTLSFingerprint {
+                    algorithm: "sha-256".to_owned(),
+                    value: "AA".to_owned(),
+                },
+                DTLSFingerprint {
+                    algorithm: "sha-1".to_owned(),
+                    value: "BB".to_owned(),
+                },
+            ]
+        );
+    }
+
+    //"Conflicting Multiple Fingerprints"
+    {
+        let s = sdp::session_description::SessionDescription {
+            attributes: fingerprint_attributes(&["sha-256 AA", "sha-1 BB"]),
+            media_descriptions: vec![sdp::media_description::MediaDescription {
+                attributes: fingerprint_attributes(&["sha-256 AA"]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        if let Err(err) = extract_fingerprints(&s) {
             assert!(Error::ErrSessionDescriptionConflictingFingerprints.equal(&err));
         } else {
             assert!(false);
@@ -397,6 +462,7 @@ fn test_track_details_from_sdp() -> Result<()> {
             assert_eq!(RTPCodecType::Video, track.kind);
             assert_eq!(3000, track.ssrc);
             assert_eq!("video_trk_label", track.stream_id);
+            assert_eq!(4000, track.repair_ssrc);
         } else {
             assert!(false, "missing video track with ssrc:3000");
         }
@@ -527,6 +593,9 @@ async fn fingerprint_test(
         is_icelite: false,
         connection_role: ConnectionRole::Active,
         ice_gathering_state: ICEGatheringState::New,
+        rtcp_mux_only: false,
+        bundle_only: false,
+        max_message_size: 0,
     };
 
     let s = populate_sdp(
@@ -614,12 +683,12 @@ async fn test_media_description_fingerprints() -> Result<()> {
                     track,
                     Arc::new(DTLSTransport::default()),
                     Arc::clone(&api.media_engine),
-                    Arc::clone(&api.interceptor),
+                    api.interceptor_registry.build(),
                 )
                 .await,
             )))
             .await;
-        media[i].transceivers[0].set_direction(RTPTransceiverDirection::Sendonly);
+        media[i].transceivers[0].set_direction_internal(RTPTransceiverDirection::Sendonly);
     }
 
     //"Per-Media Description Fingerprints",
@@ -657,6 +726,7 @@ async fn test_populate_sdp() -> Result<()> {
             transceivers: vec![tr],
             data: false,
             rid_map,
+            offered_direction: None,
         }];
 
         let d = sdp::session_description::SessionDescription::default();
@@ -667,6 +737,9 @@ async fn test_populate_sdp() -> Result<()> {
             is_icelite: se.candidates.ice_lite,
             connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
             ice_gathering_state: ICEGatheringState::Complete,
+            rtcp_mux_only: false,
+            bundle_only: false,
+            max_message_size: 0,
         };
         let offer_sdp = populate_sdp(
             d,
@@ -737,6 +810,7 @@ async fn test_populate_sdp() -> Result<()> {
             transceivers: vec![tr],
             data: false,
             rid_map: HashMap::new(),
+            offered_direction: None,
         }];
 
         let d = sdp::session_description::SessionDescription::default();
@@ -747,6 +821,9 @@ async fn test_populate_sdp() -> Result<()> {
             is_icelite: se.candidates.ice_lite,
             connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
             ice_gathering_state: ICEGatheringState::Complete,
+            rtcp_mux_only: false,
+            bundle_only: false,
+            max_message_size: 0,
         };
         let offer_sdp = populate_sdp(
             d,
@@ -780,6 +857,122 @@ async fn test_populate_sdp() -> Result<()> {
         assert_eq!(true, found_vp8, "vp8 should be present in sdp");
     }
 
+    //"Policies"
+    {
+        let se = SettingEngine::default();
+        let mut me = MediaEngine::default();
+        me.register_default_codecs()?;
+        let me = Arc::new(me);
+
+        let mut media_sections = vec![];
+        for (id, kind, codecs) in [
+            ("0", RTPCodecType::Audio, &me.audio_codecs),
+            ("1", RTPCodecType::Video, &me.video_codecs),
+        ]
+        .iter()
+        {
+            let tr = RTPTransceiver::new(
+                None,
+                None,
+                RTPTransceiverDirection::Recvonly,
+                *kind,
+                codecs.to_vec(),
+                Arc::clone(&me),
+            )
+            .await;
+            media_sections.push(MediaSection {
+                id: id.to_string(),
+                transceivers: vec![tr],
+                ..Default::default()
+            });
+        }
+        media_sections.push(MediaSection {
+            id: "2".to_owned(),
+            data: true,
+            ..Default::default()
+        });
+
+        let d = sdp::session_description::SessionDescription::default();
+
+        let params = PopulateSdpParams {
+            is_plan_b: false,
+            media_description_fingerprint: se.sdp_media_level_fingerprints,
+            is_icelite: se.candidates.ice_lite,
+            connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
+            ice_gathering_state: ICEGatheringState::Complete,
+            rtcp_mux_only: true,
+            bundle_only: true,
+            max_message_size: 0,
+        };
+        let offer_sdp = populate_sdp(
+            d,
+            &[],
+            &me,
+            &[],
+            &ICEParameters::default(),
+            &media_sections,
+            params,
+        )
+        .await?;
+
+        assert_eq!(3, offer_sdp.media_descriptions.len());
+        for (i, desc) in offer_sdp.media_descriptions.iter().enumerate() {
+            let is_media = desc.media_name.media != MEDIA_SECTION_APPLICATION;
+            assert_eq!(is_media, has_attribute(desc, ATTR_KEY_RTCPMUX_ONLY));
+            assert_eq!(i != 0, has_attribute(desc, ATTR_KEY_BUNDLE_ONLY));
+            assert_eq!(i != 0, desc.media_name.port.value == 0);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_message_size() -> Result<()> {
+    let me = Arc::new(MediaEngine::default());
+    let media_sections = vec![MediaSection {
+        id: "data".to_owned(),
+        data: true,
+        ..Default::default()
+    }];
+
+    let params = PopulateSdpParams {
+        is_plan_b: false,
+        media_description_fingerprint: false,
+        is_icelite: false,
+        connection_role: DEFAULT_DTLS_ROLE_OFFER.to_connection_role(),
+        ice_gathering_state: ICEGatheringState::Complete,
+        rtcp_mux_only: false,
+        bundle_only: false,
+        max_message_size: 1048576,
+    };
+    let offer_sdp = populate_sdp(
+        sdp::session_description::SessionDescription::default(),
+        &[],
+        &me,
+        &[],
+        &ICEParameters::default(),
+        &media_sections,
+        params,
+    )
+    .await?;
+
+    assert_eq!(
+        offer_sdp.media_descriptions[0].attribute(ATTR_KEY_MAX_MESSAGE_SIZE),
+        Some(&"1048576".to_owned())
+    );
+    assert_eq!(get_max_message_size(&offer_sdp), 1048576);
+
+    // Without the attribute, the remote is assumed to accept 64KiB
+    let mut without_max_message_size = offer_sdp;
+    without_max_message_size.media_descriptions[0]
+        .attributes
+        .retain(|a| a.key != ATTR_KEY_MAX_MESSAGE_SIZE);
+    assert_eq!(
+        get_max_message_size(&without_max_message_size),
+        SCTP_MAX_MESSAGE_SIZE_UNSET_VALUE
+    );
+
     Ok(())
 }
 
@@ -165,6 +165,12 @@ pub(crate) fn check_next_signaling_state(
                     }
                     _ => {}
                 }
+            } else if sdp_type == SDPType::Offer && next == SignalingState::HaveLocalOffer {
+                // have-local-offer->SetLocal(offer)->have-local-offer
+                return Ok(next);
+            } else if sdp_type == SDPType::Rollback && next == SignalingState::Stable {
+                // have-local-offer->SetLocal(rollback)->stable
+                return Ok(next);
             }
         }
         SignalingState::HaveRemotePranswer => {
@@ -192,6 +198,9 @@ pub(crate) fn check_next_signaling_state(
                     }
                     _ => {}
                 }
+            } else if sdp_type == SDPType::Rollback && next == SignalingState::Stable {
+                // have-remote-offer->SetRemote(rollback)->stable
+                return Ok(next);
             }
         }
         SignalingState::HaveLocalPranswer => {
@@ -315,6 +324,30 @@ mod test {
                 SDPType::Answer,
                 None,
             ),
+            (
+                "have-local-offer->SetLocal(offer)->have-local-offer",
+                SignalingState::HaveLocalOffer,
+                SignalingState::HaveLocalOffer,
+                StateChangeOp::SetLocal,
+                SDPType::Offer,
+                None,
+            ),
+            (
+                "have-local-offer->SetLocal(rollback)->stable",
+                SignalingState::HaveLocalOffer,
+                SignalingState::Stable,
+                StateChangeOp::SetLocal,
+                SDPType::Rollback,
+                None,
+            ),
+            (
+                "have-remote-offer->SetRemote(rollback)->stable",
+                SignalingState::HaveRemoteOffer,
+                SignalingState::Stable,
+                StateChangeOp::SetRemote,
+                SDPType::Rollback,
+                None,
+            ),
             (
                 "(invalid) stable->SetRemote(pranswer)->have-remote-pranswer",
                 SignalingState::Stable,
@@ -331,6 +364,30 @@ mod test {
                 SDPType::Rollback,
                 Some(Error::ErrSignalingStateCannotRollback),
             ),
+            (
+                "(invalid) have-local-offer->SetRemote(rollback)->stable",
+                SignalingState::HaveLocalOffer,
+                SignalingState::Stable,
+                StateChangeOp::SetRemote,
+                SDPType::Rollback,
+                Some(Error::ErrSignalingStateProposedTransitionInvalid),
+            ),
+            (
+                "(invalid) have-remote-offer->SetLocal(rollback)->stable",
+                SignalingState::HaveRemoteOffer,
+                SignalingState::Stable,
+                StateChangeOp::SetLocal,
+                SDPType::Rollback,
+                Some(Error::ErrSignalingStateProposedTransitionInvalid),
+            ),
+            (
+                "(invalid) have-local-pranswer->SetLocal(rollback)->stable",
+                SignalingState::HaveLocalPranswer,
+                SignalingState::Stable,
+                StateChangeOp::SetLocal,
+                SDPType::Rollback,
+                Some(Error::ErrSignalingStateProposedTransitionInvalid),
+            ),
         ];
 
         for (desc, cur, next, op, sdp_type, expected_err) in tests {
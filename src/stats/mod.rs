@@ -1 +1,796 @@
+#[cfg(test)]
+mod stats_test;
 
+pub(crate) mod rtp_stream_stats;
+
+use crate::data::data_channel::data_channel_state::DataChannelState;
+use crate::media::dtls_transport::dtls_transport_state::DTLSTransportState;
+use crate::media::rtp::rtp_codec::RTPCodecType;
+use crate::media::rtp::{PayloadType, SSRC};
+use crate::peer::ice::ice_candidate::ice_candidate_type::ICECandidateType;
+use crate::peer::ice::ice_protocol::ICEProtocol;
+use crate::peer::ice::ice_role::ICERole;
+
+use ice::candidate::CandidatePairState;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// StatsTimestamp is a timestamp represented by the floating point number of
+/// milliseconds since the epoch.
+pub type StatsTimestamp = f64;
+
+/// stats_timestamp_now returns the StatsTimestamp of the current time.
+pub fn stats_timestamp_now() -> StatsTimestamp {
+    stats_timestamp_from(SystemTime::now())
+}
+
+/// stats_timestamp_from converts a SystemTime into a StatsTimestamp.
+pub fn stats_timestamp_from(t: SystemTime) -> StatsTimestamp {
+    t.duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64() * 1000.0)
+}
+
+/// stats_timestamp_from_instant converts a monotonic Instant, such as the ones
+/// reported by the ICE agent, into a StatsTimestamp.
+pub(crate) fn stats_timestamp_from_instant(t: Instant) -> StatsTimestamp {
+    let now = Instant::now();
+    let system_time = if t <= now {
+        SystemTime::now().checked_sub(now - t)
+    } else {
+        SystemTime::now().checked_add(t - now)
+    };
+    system_time.map_or(0.0, stats_timestamp_from)
+}
+
+/// StatsType indicates the type of the object that a Stats object represents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum StatsType {
+    /// Codec is used by CodecStats.
+    #[serde(rename = "codec")]
+    Codec,
+
+    /// InboundRTP is used by InboundRTPStreamStats.
+    #[serde(rename = "inbound-rtp")]
+    InboundRTP,
+
+    /// OutboundRTP is used by OutboundRTPStreamStats.
+    #[serde(rename = "outbound-rtp")]
+    OutboundRTP,
+
+    /// RemoteInboundRTP is used by RemoteInboundRTPStreamStats.
+    #[serde(rename = "remote-inbound-rtp")]
+    RemoteInboundRTP,
+
+    /// PeerConnection is used by PeerConnectionStats.
+    #[serde(rename = "peer-connection")]
+    PeerConnection,
+
+    /// DataChannel is used by DataChannelStats.
+    #[serde(rename = "data-channel")]
+    DataChannel,
+
+    /// Transport is used by TransportStats.
+    #[serde(rename = "transport")]
+    Transport,
+
+    /// CandidatePair is used by ICECandidatePairStats.
+    #[serde(rename = "candidate-pair")]
+    CandidatePair,
+
+    /// LocalCandidate is used by ICECandidateStats for the local candidate.
+    #[serde(rename = "local-candidate")]
+    LocalCandidate,
+
+    /// RemoteCandidate is used by ICECandidateStats for the remote candidate.
+    #[serde(rename = "remote-candidate")]
+    RemoteCandidate,
+
+    /// Certificate is used by CertificateStats.
+    #[serde(rename = "certificate")]
+    Certificate,
+}
+
+const STATS_TYPE_CODEC_STR: &str = "codec";
+const STATS_TYPE_INBOUND_RTP_STR: &str = "inbound-rtp";
+const STATS_TYPE_OUTBOUND_RTP_STR: &str = "outbound-rtp";
+const STATS_TYPE_REMOTE_INBOUND_RTP_STR: &str = "remote-inbound-rtp";
+const STATS_TYPE_PEER_CONNECTION_STR: &str = "peer-connection";
+const STATS_TYPE_DATA_CHANNEL_STR: &str = "data-channel";
+const STATS_TYPE_TRANSPORT_STR: &str = "transport";
+const STATS_TYPE_CANDIDATE_PAIR_STR: &str = "candidate-pair";
+const STATS_TYPE_LOCAL_CANDIDATE_STR: &str = "local-candidate";
+const STATS_TYPE_REMOTE_CANDIDATE_STR: &str = "remote-candidate";
+const STATS_TYPE_CERTIFICATE_STR: &str = "certificate";
+
+impl fmt::Display for StatsType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match *self {
+            StatsType::Codec => STATS_TYPE_CODEC_STR,
+            StatsType::InboundRTP => STATS_TYPE_INBOUND_RTP_STR,
+            StatsType::OutboundRTP => STATS_TYPE_OUTBOUND_RTP_STR,
+            StatsType::RemoteInboundRTP => STATS_TYPE_REMOTE_INBOUND_RTP_STR,
+            StatsType::PeerConnection => STATS_TYPE_PEER_CONNECTION_STR,
+            StatsType::DataChannel => STATS_TYPE_DATA_CHANNEL_STR,
+            StatsType::Transport => STATS_TYPE_TRANSPORT_STR,
+            StatsType::CandidatePair => STATS_TYPE_CANDIDATE_PAIR_STR,
+            StatsType::LocalCandidate => STATS_TYPE_LOCAL_CANDIDATE_STR,
+            StatsType::RemoteCandidate => STATS_TYPE_REMOTE_CANDIDATE_STR,
+            StatsType::Certificate => STATS_TYPE_CERTIFICATE_STR,
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// StatsICECandidatePairState is the state of an ICE candidate pair used in the
+/// ICECandidatePairStats object.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum StatsICECandidatePairState {
+    #[default]
+    Unspecified,
+
+    /// Frozen means a check for this pair hasn't been performed, and it can't
+    /// yet be performed until some other check succeeds.
+    #[serde(rename = "frozen")]
+    Frozen,
+
+    /// Waiting means a check has not been performed for this pair.
+    #[serde(rename = "waiting")]
+    Waiting,
+
+    /// InProgress means a check has been sent for this pair, but the
+    /// transaction is in progress.
+    #[serde(rename = "in-progress")]
+    InProgress,
+
+    /// Failed means a check for this pair was already done and failed.
+    #[serde(rename = "failed")]
+    Failed,
+
+    /// Succeeded means a check for this pair was already done and produced a
+    /// successful result.
+    #[serde(rename = "succeeded")]
+    Succeeded,
+}
+
+impl From<CandidatePairState> for StatsICECandidatePairState {
+    fn from(state: CandidatePairState) -> Self {
+        match state {
+            CandidatePairState::Waiting => StatsICECandidatePairState::Waiting,
+            CandidatePairState::InProgress => StatsICECandidatePairState::InProgress,
+            CandidatePairState::Failed => StatsICECandidatePairState::Failed,
+            CandidatePairState::Succeeded => StatsICECandidatePairState::Succeeded,
+            _ => StatsICECandidatePairState::Unspecified,
+        }
+    }
+}
+
+/// CodecStats contains statistics for a codec that is currently being used by RTP streams
+/// being sent or received by this PeerConnection object.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodecStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object. Two Stats objects will have the same ID if they were produced
+    /// by inspecting the same underlying object.
+    pub id: String,
+
+    /// payload_type as used in RTP encoding or decoding
+    pub payload_type: PayloadType,
+
+    /// mime_type is the codec MIME media type/subtype. e.g., video/vp8 or equivalent.
+    pub mime_type: String,
+
+    /// clock_rate represents the media sampling rate.
+    pub clock_rate: u32,
+
+    /// channels is 2 for stereo, missing for most other cases.
+    pub channels: u16,
+
+    /// sdp_fmtp_line is the a=fmtp line in the SDP corresponding to the codec,
+    /// i.e., after the colon following the PT.
+    pub sdp_fmtp_line: String,
+}
+
+/// InboundRTPStreamStats contains statistics for an inbound RTP stream
+/// that is currently received with this PeerConnection object.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboundRTPStreamStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// ssrc is the 32-bit unsigned integer value used to identify the source of the
+    /// stream of RTP packets that this stats object concerns.
+    pub ssrc: SSRC,
+
+    /// kind is either "audio" or "video"
+    pub kind: RTPCodecType,
+
+    /// transport_id is a unique identifier that is associated to the object that was inspected
+    /// to produce the TransportStats associated with this RTP stream.
+    pub transport_id: String,
+
+    /// codec_id is a unique identifier that is associated to the object that was inspected
+    /// to produce the CodecStats associated with this RTP stream.
+    pub codec_id: String,
+
+    /// track_identifier is the id of the TrackRemote this stream is delivered to.
+    pub track_identifier: String,
+
+    /// mid is the mid of the RTPTransceiver that owns the receiver of this stream.
+    pub mid: String,
+
+    /// rid is the RTP stream id of this stream when it is part of a simulcast group.
+    pub rid: String,
+
+    /// packets_received is the total number of RTP packets received for this SSRC.
+    pub packets_received: u64,
+
+    /// packets_lost is the total number of RTP packets lost for this SSRC. Note that
+    /// because of how this is estimated, it can be negative if more packets are received
+    /// than sent.
+    pub packets_lost: i64,
+
+    /// jitter is the packet jitter measured in seconds for this SSRC.
+    pub jitter: f64,
+
+    /// bytes_received is the total number of bytes received for this SSRC,
+    /// not including headers or padding.
+    pub bytes_received: u64,
+
+    /// header_bytes_received is the total number of RTP header and padding bytes
+    /// received for this SSRC.
+    pub header_bytes_received: u64,
+
+    /// last_packet_received_timestamp represents the timestamp at which the last packet
+    /// was received for this SSRC.
+    pub last_packet_received_timestamp: StatsTimestamp,
+}
+
+/// OutboundRTPStreamStats contains statistics for an outbound RTP stream
+/// that is currently sent with this PeerConnection object.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboundRTPStreamStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// ssrc is the 32-bit unsigned integer value used to identify the source of the
+    /// stream of RTP packets that this stats object concerns.
+    pub ssrc: SSRC,
+
+    /// kind is either "audio" or "video"
+    pub kind: RTPCodecType,
+
+    /// transport_id is a unique identifier that is associated to the object that was inspected
+    /// to produce the TransportStats associated with this RTP stream.
+    pub transport_id: String,
+
+    /// codec_id is a unique identifier that is associated to the object that was inspected
+    /// to produce the CodecStats associated with this RTP stream.
+    pub codec_id: String,
+
+    /// mid is the mid of the RTPTransceiver that owns the sender of this stream.
+    pub mid: String,
+
+    /// rid is the RTP stream identifier of this stream when it is a simulcast layer.
+    pub rid: String,
+
+    /// remote_id is used for looking up the remote RemoteInboundRTPStreamStats object
+    /// for the same SSRC.
+    pub remote_id: String,
+
+    /// packets_sent is the total number of RTP packets sent for this SSRC.
+    pub packets_sent: u64,
+
+    /// bytes_sent is the total number of bytes sent for this SSRC, not including
+    /// headers or padding.
+    pub bytes_sent: u64,
+
+    /// header_bytes_sent is the total number of RTP header and padding bytes sent
+    /// for this SSRC.
+    pub header_bytes_sent: u64,
+
+    /// last_packet_sent_timestamp represents the timestamp at which the last packet was
+    /// sent for this SSRC.
+    pub last_packet_sent_timestamp: StatsTimestamp,
+}
+
+/// RemoteInboundRTPStreamStats contains statistics for the remote endpoint's inbound
+/// RTP stream corresponding to an outbound stream that is currently sent with this
+/// PeerConnection object. It is measured at the remote endpoint and reported in an RTCP
+/// Receiver Report (RR) or RTCP Extended Report (XR).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteInboundRTPStreamStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// ssrc is the 32-bit unsigned integer value used to identify the source of the
+    /// stream of RTP packets that this stats object concerns.
+    pub ssrc: SSRC,
+
+    /// kind is either "audio" or "video"
+    pub kind: RTPCodecType,
+
+    /// transport_id is a unique identifier that is associated to the object that was inspected
+    /// to produce the TransportStats associated with this RTP stream.
+    pub transport_id: String,
+
+    /// codec_id is a unique identifier that is associated to the object that was inspected
+    /// to produce the CodecStats associated with this RTP stream.
+    pub codec_id: String,
+
+    /// local_id is used for looking up the local OutboundRTPStreamStats object for the same SSRC.
+    pub local_id: String,
+
+    /// packets_lost is the total number of RTP packets lost for this SSRC, as reported
+    /// by the remote endpoint.
+    pub packets_lost: i64,
+
+    /// jitter is the packet jitter measured in seconds for this SSRC, as reported
+    /// by the remote endpoint.
+    pub jitter: f64,
+
+    /// fraction_lost is the fraction packet loss reported for this SSRC in the last
+    /// reception report.
+    pub fraction_lost: f64,
+
+    /// round_trip_time is the latest round trip time in seconds, computed from the
+    /// LSR and DLSR fields of the last reception report.
+    pub round_trip_time: f64,
+
+    /// total_round_trip_time is the sum of all round trip time measurements in seconds.
+    pub total_round_trip_time: f64,
+
+    /// round_trip_time_measurements is the number of reception reports received for
+    /// this SSRC that contained a valid round trip time.
+    pub round_trip_time_measurements: u64,
+}
+
+/// PeerConnectionStats contains PeerConnection statistics.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerConnectionStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// data_channels_opened represents the number of unique DataChannels that have
+    /// entered the "open" state during their lifetime.
+    pub data_channels_opened: u32,
+
+    /// data_channels_closed represents the number of unique DataChannels that have
+    /// left the "open" state during their lifetime (due to being closed by either
+    /// end or the underlying transport being closed). DataChannels that transition
+    /// from "connecting" to "closing" or "closed" without ever being "open"
+    /// are not counted in this number.
+    pub data_channels_closed: u32,
+
+    /// data_channels_requested Represents the number of unique DataChannels returned
+    /// from a successful create_data_channel() call on the PeerConnection. If the
+    /// underlying data transport is not established, these may be in the "connecting" state.
+    pub data_channels_requested: u32,
+
+    /// data_channels_accepted represents the number of unique DataChannels signaled
+    /// in a "datachannel" event on the PeerConnection.
+    pub data_channels_accepted: u32,
+}
+
+/// DataChannelStats contains statistics related to each DataChannel ID.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataChannelStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// label is the "label" value of the DataChannel object.
+    pub label: String,
+
+    /// protocol is the "protocol" value of the DataChannel object.
+    pub protocol: String,
+
+    /// data_channel_identifier is the "id" attribute of the DataChannel object.
+    pub data_channel_identifier: u16,
+
+    /// transport_id the ID of the TransportStats object for transport used to carry this datachannel.
+    pub transport_id: String,
+
+    /// state is the "ready_state" value of the DataChannel object.
+    pub state: DataChannelState,
+
+    /// messages_sent represents the total number of API "message" events sent.
+    pub messages_sent: usize,
+
+    /// bytes_sent represents the total number of payload bytes sent on this
+    /// datachannel not including headers or padding.
+    pub bytes_sent: usize,
+
+    /// messages_received represents the total number of API "message" events received.
+    pub messages_received: usize,
+
+    /// bytes_received represents the total number of bytes received on this
+    /// datachannel not including headers or padding.
+    pub bytes_received: usize,
+}
+
+/// TransportStats contains transport statistics related to the PeerConnection object.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransportStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// bytes_sent represents the total number of payload bytes sent on this
+    /// transport, not including headers or padding.
+    pub bytes_sent: usize,
+
+    /// bytes_received represents the total number of payload bytes received on
+    /// this transport, not including headers or padding.
+    pub bytes_received: usize,
+
+    /// ice_role is set to the current value of the "role" attribute of the
+    /// underlying ICETransport.
+    pub ice_role: ICERole,
+
+    /// dtls_state is set to the current value of the "state" attribute of the
+    /// underlying DTLSTransport.
+    pub dtls_state: DTLSTransportState,
+
+    /// selected_candidate_pair_id is a unique identifier that is associated to the object
+    /// that was inspected to produce the ICECandidatePairStats associated with this transport.
+    pub selected_candidate_pair_id: String,
+
+    /// local_certificate_id is the ID of the CertificateStats for the local certificate.
+    /// Present only if DTLS is negotiated.
+    pub local_certificate_id: String,
+
+    /// remote_certificate_id is the ID of the CertificateStats for the remote certificate.
+    /// Present only if DTLS is negotiated.
+    pub remote_certificate_id: String,
+}
+
+/// ICECandidatePairStats contains ICE candidate pair statistics related
+/// to the ICETransport objects.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ICECandidatePairStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// transport_id is a unique identifier that is associated to the object that
+    /// was inspected to produce the TransportStats associated with this candidate pair.
+    pub transport_id: String,
+
+    /// local_candidate_id is a unique identifier that is associated to the object
+    /// that was inspected to produce the ICECandidateStats for the local candidate
+    /// associated with this candidate pair.
+    pub local_candidate_id: String,
+
+    /// remote_candidate_id is a unique identifier that is associated to the object
+    /// that was inspected to produce the ICECandidateStats for the remote candidate
+    /// associated with this candidate pair.
+    pub remote_candidate_id: String,
+
+    /// state represents the state of the checklist for the local and remote
+    /// candidates in a pair.
+    pub state: StatsICECandidatePairState,
+
+    /// nominated is true when this valid pair that should be used for media
+    /// if it is the highest-priority one amongst those whose nominated flag is set
+    pub nominated: bool,
+
+    /// packets_sent represents the total number of packets sent on this candidate pair.
+    pub packets_sent: u32,
+
+    /// packets_received represents the total number of packets received on this candidate pair.
+    pub packets_received: u32,
+
+    /// bytes_sent represents the total number of payload bytes sent on this candidate pair
+    /// not including headers or padding.
+    pub bytes_sent: u64,
+
+    /// bytes_received represents the total number of payload bytes received on this candidate pair
+    /// not including headers or padding.
+    pub bytes_received: u64,
+
+    /// last_packet_sent_timestamp represents the timestamp at which the last packet was
+    /// sent on this particular candidate pair, excluding STUN packets.
+    pub last_packet_sent_timestamp: StatsTimestamp,
+
+    /// last_packet_received_timestamp represents the timestamp at which the last packet
+    /// was received on this particular candidate pair, excluding STUN packets.
+    pub last_packet_received_timestamp: StatsTimestamp,
+
+    /// total_round_trip_time represents the sum of all round trip time measurements
+    /// in seconds since the beginning of the session, based on STUN connectivity
+    /// check responses.
+    pub total_round_trip_time: f64,
+
+    /// current_round_trip_time represents the latest round trip time measured in seconds,
+    /// computed from both STUN connectivity checks, including those that are sent
+    /// for consent verification.
+    pub current_round_trip_time: f64,
+
+    /// available_outgoing_bitrate is calculated by the underlying congestion control
+    /// by combining the available bitrate for all the outgoing RTP streams using
+    /// this candidate pair.
+    pub available_outgoing_bitrate: f64,
+
+    /// available_incoming_bitrate is calculated by the underlying congestion control
+    /// by combining the available bitrate for all the incoming RTP streams using
+    /// this candidate pair.
+    pub available_incoming_bitrate: f64,
+
+    /// requests_received represents the total number of connectivity check requests
+    /// received (including retransmissions).
+    pub requests_received: u64,
+
+    /// requests_sent represents the total number of connectivity check requests
+    /// sent (not including retransmissions).
+    pub requests_sent: u64,
+
+    /// responses_received represents the total number of connectivity check responses received.
+    pub responses_received: u64,
+
+    /// responses_sent represents the total number of connectivity check responses sent.
+    pub responses_sent: u64,
+
+    /// consent_requests_sent represents the total number of consent requests sent.
+    pub consent_requests_sent: u64,
+}
+
+/// ICECandidateStats contains ICE candidate statistics related to the ICETransport objects.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ICECandidateStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// transport_id is a unique identifier that is associated to the object that
+    /// was inspected to produce the TransportStats associated with this candidate.
+    pub transport_id: String,
+
+    /// address is the IP address of the candidate, allowing for IPv4 addresses and
+    /// IPv6 addresses.
+    pub address: String,
+
+    /// port is the port number of the candidate.
+    pub port: u16,
+
+    /// protocol is one of udp and tcp.
+    pub protocol: ICEProtocol,
+
+    /// candidate_type is the "Type" field of the ICECandidate.
+    pub candidate_type: ICECandidateType,
+
+    /// priority is the "priority" field of the ICECandidate.
+    pub priority: u32,
+
+    /// url is the url of the TURN or STUN server indicated in the that translated
+    /// this IP address. It is the url address surfaced in an PeerConnectionICEEvent.
+    pub url: String,
+
+    /// relay_protocol is the protocol used by the endpoint to communicate with the
+    /// TURN server. This is only present for local candidates.
+    pub relay_protocol: String,
+
+    /// deleted is true if the candidate has been deleted/freed. Only defined for
+    /// local candidates.
+    pub deleted: bool,
+}
+
+/// CertificateStats contains information about a certificate used by an ICETransport.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateStats {
+    /// timestamp is the timestamp associated with this object.
+    pub timestamp: StatsTimestamp,
+
+    /// stats_type is the object's StatsType
+    #[serde(rename = "type")]
+    pub stats_type: StatsType,
+
+    /// id is a unique id that is associated with the component inspected to produce
+    /// this Stats object.
+    pub id: String,
+
+    /// fingerprint is the fingerprint of the certificate.
+    pub fingerprint: String,
+
+    /// fingerprint_algorithm is the hash function used to compute the certificate fingerprint.
+    /// For instance, "sha-256".
+    pub fingerprint_algorithm: String,
+
+    /// base64_certificate is the DER-encoded base-64 representation of the certificate.
+    pub base64_certificate: String,
+
+    /// issuer_certificate_id refers to the stats object that contains the next
+    /// certificate in the certificate chain. If the current certificate is at the end
+    /// of the chain (i.e. a self-signed certificate), this will not be set.
+    pub issuer_certificate_id: String,
+}
+
+/// StatsReportType is one of the typed Stats objects contained in a StatsReport.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum StatsReportType {
+    Codec(CodecStats),
+    InboundRTP(InboundRTPStreamStats),
+    OutboundRTP(OutboundRTPStreamStats),
+    RemoteInboundRTP(RemoteInboundRTPStreamStats),
+    PeerConnection(PeerConnectionStats),
+    DataChannel(DataChannelStats),
+    Transport(TransportStats),
+    CandidatePair(ICECandidatePairStats),
+    LocalCandidate(ICECandidateStats),
+    RemoteCandidate(ICECandidateStats),
+    Certificate(CertificateStats),
+}
+
+impl StatsReportType {
+    /// id returns the id of the wrapped Stats object.
+    pub fn id(&self) -> &str {
+        match self {
+            StatsReportType::Codec(s) => &s.id,
+            StatsReportType::InboundRTP(s) => &s.id,
+            StatsReportType::OutboundRTP(s) => &s.id,
+            StatsReportType::RemoteInboundRTP(s) => &s.id,
+            StatsReportType::PeerConnection(s) => &s.id,
+            StatsReportType::DataChannel(s) => &s.id,
+            StatsReportType::Transport(s) => &s.id,
+            StatsReportType::CandidatePair(s) => &s.id,
+            StatsReportType::LocalCandidate(s) => &s.id,
+            StatsReportType::RemoteCandidate(s) => &s.id,
+            StatsReportType::Certificate(s) => &s.id,
+        }
+    }
+
+    /// stats_type returns the StatsType of the wrapped Stats object.
+    pub fn stats_type(&self) -> StatsType {
+        match self {
+            StatsReportType::Codec(s) => s.stats_type,
+            StatsReportType::InboundRTP(s) => s.stats_type,
+            StatsReportType::OutboundRTP(s) => s.stats_type,
+            StatsReportType::RemoteInboundRTP(s) => s.stats_type,
+            StatsReportType::PeerConnection(s) => s.stats_type,
+            StatsReportType::DataChannel(s) => s.stats_type,
+            StatsReportType::Transport(s) => s.stats_type,
+            StatsReportType::CandidatePair(s) => s.stats_type,
+            StatsReportType::LocalCandidate(s) => s.stats_type,
+            StatsReportType::RemoteCandidate(s) => s.stats_type,
+            StatsReportType::Certificate(s) => s.stats_type,
+        }
+    }
+}
+
+/// StatsReport collects Stats objects indexed by their ID.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct StatsReport {
+    pub reports: HashMap<String, StatsReportType>,
+}
+
+impl StatsReport {
+    /// get returns the Stats object with the given id, if any.
+    pub fn get(&self, id: &str) -> Option<&StatsReportType> {
+        self.reports.get(id)
+    }
+
+    /// get_by_type returns all the Stats objects of the given StatsType.
+    pub fn get_by_type(&self, stats_type: StatsType) -> Vec<&StatsReportType> {
+        self.reports
+            .values()
+            .filter(|s| s.stats_type() == stats_type)
+            .collect()
+    }
+
+    /// len returns the number of Stats objects in the report.
+    pub fn len(&self) -> usize {
+        self.reports.len()
+    }
+
+    /// is_empty returns true if the report contains no Stats objects.
+    pub fn is_empty(&self) -> bool {
+        self.reports.is_empty()
+    }
+}
+
+/// StatsCollector is handed to every subsystem of a PeerConnection, each of
+/// which feeds its own Stats objects into the report being built.
+#[derive(Default)]
+pub(crate) struct StatsCollector {
+    reports: Mutex<HashMap<String, StatsReportType>>,
+}
+
+impl StatsCollector {
+    pub(crate) fn new() -> Self {
+        StatsCollector::default()
+    }
+
+    /// collect adds a Stats object to the report, replacing any previous one with the same id.
+    pub(crate) async fn collect(&self, stats: StatsReportType) {
+        let mut reports = self.reports.lock().await;
+        reports.insert(stats.id().to_owned(), stats);
+    }
+
+    /// ready consumes the collector and returns the resulting StatsReport.
+    pub(crate) fn ready(self) -> StatsReport {
+        StatsReport {
+            reports: self.reports.into_inner(),
+        }
+    }
+}
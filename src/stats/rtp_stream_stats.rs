@@ -0,0 +1,169 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// InboundRTPStreamCounters accumulates the receive side statistics of a single
+/// RTP stream, following the algorithms of RFC 3550 Appendix A.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct InboundRTPStreamCounters {
+    pub(crate) packets_received: u64,
+    pub(crate) bytes_received: u64,
+    pub(crate) header_bytes_received: u64,
+    pub(crate) last_packet_received_timestamp: Option<SystemTime>,
+
+    started: bool,
+    base_seq: u16,
+    max_seq: u16,
+    cycles: u64,
+
+    /// interarrival jitter in seconds
+    jitter: f64,
+    last_transit: Option<f64>,
+}
+
+impl InboundRTPStreamCounters {
+    /// on_packet updates the counters with a received RTP packet.
+    pub(crate) fn on_packet(
+        &mut self,
+        header: &rtp::header::Header,
+        header_size: usize,
+        payload_size: usize,
+        clock_rate: u32,
+        now: SystemTime,
+    ) {
+        self.packets_received += 1;
+        self.header_bytes_received += header_size as u64;
+        self.bytes_received += payload_size as u64;
+        self.last_packet_received_timestamp = Some(now);
+
+        let seq = header.sequence_number;
+        if !self.started {
+            self.started = true;
+            self.base_seq = seq;
+            self.max_seq = seq;
+        } else {
+            let delta = seq.wrapping_sub(self.max_seq);
+            if delta != 0 && delta < 0x8000 {
+                if seq < self.max_seq {
+                    self.cycles += 1 << 16;
+                }
+                self.max_seq = seq;
+            }
+        }
+
+        if clock_rate != 0 {
+            let arrival = now
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_secs_f64());
+            let transit = arrival - header.timestamp as f64 / clock_rate as f64;
+            if let Some(last_transit) = self.last_transit {
+                let d = (transit - last_transit).abs();
+                // rtp timestamps wrap around, ignore the jump
+                if d < (u32::MAX / 2) as f64 / clock_rate as f64 {
+                    self.jitter += (d - self.jitter) / 16.0;
+                }
+            }
+            self.last_transit = Some(transit);
+        }
+    }
+
+    /// packets_lost returns the cumulative number of packets lost, which can
+    /// be negative if duplicates were received.
+    pub(crate) fn packets_lost(&self) -> i64 {
+        if !self.started {
+            return 0;
+        }
+        let expected = self.cycles + self.max_seq as u64 - self.base_seq as u64 + 1;
+        expected as i64 - self.packets_received as i64
+    }
+
+    /// jitter returns the interarrival jitter in seconds.
+    pub(crate) fn jitter(&self) -> f64 {
+        self.jitter
+    }
+}
+
+/// OutboundRTPStreamCounters accumulates the send side statistics of a single RTP stream.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct OutboundRTPStreamCounters {
+    pub(crate) packets_sent: u64,
+    pub(crate) bytes_sent: u64,
+    pub(crate) header_bytes_sent: u64,
+    pub(crate) last_packet_sent_timestamp: Option<SystemTime>,
+}
+
+impl OutboundRTPStreamCounters {
+    /// on_packet updates the counters with a sent RTP packet.
+    pub(crate) fn on_packet(&mut self, header_size: usize, payload_size: usize, now: SystemTime) {
+        self.packets_sent += 1;
+        self.header_bytes_sent += header_size as u64;
+        self.bytes_sent += payload_size as u64;
+        self.last_packet_sent_timestamp = Some(now);
+    }
+}
+
+/// RemoteInboundRTPStreamCounters keeps the last reception report received
+/// for one of our outbound RTP streams.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct RemoteInboundRTPStreamCounters {
+    pub(crate) received_report: bool,
+    pub(crate) packets_lost: i64,
+    pub(crate) fraction_lost: f64,
+    /// interarrival jitter in timestamp units
+    pub(crate) jitter: u32,
+    pub(crate) round_trip_time: f64,
+    pub(crate) total_round_trip_time: f64,
+    pub(crate) round_trip_time_measurements: u64,
+    pub(crate) last_report_timestamp: Option<SystemTime>,
+}
+
+impl RemoteInboundRTPStreamCounters {
+    /// on_reception_report updates the counters with a reception report sent
+    /// by the remote peer about one of our streams.
+    pub(crate) fn on_reception_report(
+        &mut self,
+        report: &rtcp::reception_report::ReceptionReport,
+        now: SystemTime,
+    ) {
+        self.received_report = true;
+        self.last_report_timestamp = Some(now);
+
+        // total_lost is a signed 24 bit integer
+        self.packets_lost = if report.total_lost & 0x80_0000 != 0 {
+            report.total_lost as i64 - 0x100_0000
+        } else {
+            report.total_lost as i64
+        };
+        self.fraction_lost = report.fraction_lost as f64 / 256.0;
+        self.jitter = report.jitter;
+
+        // RFC 3550 6.4.1: the round trip time is computed from the LSR and DLSR fields,
+        // expressed in units of 1/65536 seconds
+        if report.last_sender_report != 0 {
+            let now_compact = ntp_compact(now);
+            let rtt = now_compact
+                .wrapping_sub(report.last_sender_report)
+                .wrapping_sub(report.delay);
+            if rtt < 0x8000_0000 {
+                self.round_trip_time = rtt as f64 / 65536.0;
+                self.total_round_trip_time += self.round_trip_time;
+                self.round_trip_time_measurements += 1;
+            }
+        }
+    }
+}
+
+/// NTP epoch is 1900-01-01, 70 years before the unix epoch
+const NTP_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+/// ntp_time returns the 64 bit NTP timestamp for the given time.
+pub(crate) fn ntp_time(t: SystemTime) -> u64 {
+    let d = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = d.as_secs() + NTP_EPOCH_OFFSET;
+    let frac = ((d.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    (secs << 32) | frac
+}
+
+/// ntp_compact returns the middle 32 bits of the NTP timestamp for the given time,
+/// as used by the LSR and DLSR fields of RTCP reception reports.
+pub(crate) fn ntp_compact(t: SystemTime) -> u32 {
+    (ntp_time(t) >> 16) as u32
+}
@@ -0,0 +1,217 @@
+use super::rtp_stream_stats::*;
+use super::*;
+use crate::api::media_engine::MediaEngine;
+use crate::api::APIBuilder;
+use crate::peer::configuration::Configuration;
+use crate::peer::peer_connection::peer_connection_test::*;
+
+use anyhow::Result;
+use std::time::Duration;
+
+#[test]
+fn test_stats_type_string() {
+    let tests = vec![
+        (StatsType::Codec, "codec"),
+        (StatsType::InboundRTP, "inbound-rtp"),
+        (StatsType::OutboundRTP, "outbound-rtp"),
+        (StatsType::RemoteInboundRTP, "remote-inbound-rtp"),
+        (StatsType::PeerConnection, "peer-connection"),
+        (StatsType::DataChannel, "data-channel"),
+        (StatsType::Transport, "transport"),
+        (StatsType::CandidatePair, "candidate-pair"),
+        (StatsType::LocalCandidate, "local-candidate"),
+        (StatsType::RemoteCandidate, "remote-candidate"),
+        (StatsType::Certificate, "certificate"),
+    ];
+
+    for (stats_type, expected_string) in tests {
+        assert_eq!(expected_string, stats_type.to_string());
+        assert_eq!(
+            format!("\"{}\"", expected_string),
+            serde_json::to_string(&stats_type).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_stats_report_marshal_json() -> Result<()> {
+    let mut reports = HashMap::new();
+    reports.insert(
+        "PeerConnection-1".to_owned(),
+        StatsReportType::PeerConnection(PeerConnectionStats {
+            timestamp: 1000.0,
+            stats_type: StatsType::PeerConnection,
+            id: "PeerConnection-1".to_owned(),
+            data_channels_opened: 1,
+            data_channels_closed: 2,
+            data_channels_requested: 3,
+            data_channels_accepted: 4,
+        }),
+    );
+    let report = StatsReport { reports };
+
+    let value: serde_json::Value = serde_json::to_value(&report)?;
+    let expected = serde_json::json!({
+        "PeerConnection-1": {
+            "timestamp": 1000.0,
+            "type": "peer-connection",
+            "id": "PeerConnection-1",
+            "dataChannelsOpened": 1,
+            "dataChannelsClosed": 2,
+            "dataChannelsRequested": 3,
+            "dataChannelsAccepted": 4,
+        }
+    });
+    assert_eq!(expected, value);
+
+    Ok(())
+}
+
+fn rtp_header(sequence_number: u16, timestamp: u32) -> rtp::header::Header {
+    rtp::header::Header {
+        version: 2,
+        sequence_number,
+        timestamp,
+        ssrc: 5000,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_inbound_rtp_stream_counters_packets_lost() {
+    let mut counters = InboundRTPStreamCounters::default();
+    let now = SystemTime::now();
+
+    // 65534, 65535, [0 lost], 1, 2 across the sequence number wraparound
+    for seq in &[65534u16, 65535, 1, 2] {
+        counters.on_packet(&rtp_header(*seq, 0), 12, 100, 90000, now);
+    }
+
+    assert_eq!(4, counters.packets_received);
+    assert_eq!(400, counters.bytes_received);
+    assert_eq!(48, counters.header_bytes_received);
+    assert_eq!(1, counters.packets_lost());
+
+    // a late duplicate does not move the highest sequence number
+    counters.on_packet(&rtp_header(65535, 0), 12, 100, 90000, now);
+    assert_eq!(0, counters.packets_lost());
+}
+
+#[test]
+fn test_inbound_rtp_stream_counters_jitter() {
+    let mut counters = InboundRTPStreamCounters::default();
+    let start = SystemTime::now();
+
+    // packets sent every 20ms of media, received with a perfectly steady pace
+    for i in 0..10u32 {
+        let now = start + Duration::from_millis(20 * i as u64);
+        counters.on_packet(&rtp_header(i as u16, i * 960), 12, 100, 48000, now);
+    }
+    assert!(counters.jitter() < 1e-6);
+
+    // one packet arrives 10ms late
+    let now = start + Duration::from_millis(20 * 10 + 10);
+    counters.on_packet(&rtp_header(10, 10 * 960), 12, 100, 48000, now);
+    assert!((counters.jitter() - 0.010 / 16.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_remote_inbound_rtp_stream_counters() {
+    let mut counters = RemoteInboundRTPStreamCounters::default();
+    let now = SystemTime::now();
+
+    // the remote peer held our sender report for 1s and the network took 0.5s
+    let last_sender_report = ntp_compact(now - Duration::from_millis(1500));
+    counters.on_reception_report(
+        &rtcp::reception_report::ReceptionReport {
+            ssrc: 5000,
+            fraction_lost: 64,
+            total_lost: 0xFF_FFFF,
+            last_sequence_number: 100,
+            jitter: 90,
+            last_sender_report,
+            delay: 65536,
+        },
+        now,
+    );
+
+    assert!(counters.received_report);
+    assert_eq!(-1, counters.packets_lost);
+    assert!((counters.fraction_lost - 0.25).abs() < f64::EPSILON);
+    assert_eq!(90, counters.jitter);
+    assert!((counters.round_trip_time - 0.5).abs() < 0.001);
+    assert_eq!(1, counters.round_trip_time_measurements);
+}
+
+#[tokio::test]
+async fn test_peer_connection_get_stats() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let pc = api.new_peer_connection(Configuration::default()).await?;
+    pc.create_data_channel("data", None).await?;
+
+    let report = pc.get_stats().await;
+
+    assert_eq!(1, report.get_by_type(StatsType::PeerConnection).len());
+    assert_eq!(1, report.get_by_type(StatsType::DataChannel).len());
+    assert_eq!(1, report.get_by_type(StatsType::Certificate).len());
+    assert!(!report.get_by_type(StatsType::Codec).is_empty());
+
+    match report.get("ice_transport") {
+        Some(StatsReportType::Transport(stats)) => {
+            assert_eq!(0, stats.bytes_sent);
+            assert_eq!(0, stats.bytes_received);
+        }
+        _ => assert!(false, "missing ice transport stats"),
+    }
+    assert!(report.get("sctp_transport").is_some());
+
+    if let Some(StatsReportType::PeerConnection(stats)) =
+        report.get_by_type(StatsType::PeerConnection).first()
+    {
+        assert_eq!(1, stats.data_channels_requested);
+        assert_eq!(0, stats.data_channels_opened);
+    }
+
+    pc.close().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peer_connection_get_stats_connected() -> Result<()> {
+    let mut m = MediaEngine::default();
+    m.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let (mut offer_pc, mut answer_pc) = new_pair(&api).await?;
+    signal_pair(&mut offer_pc, &mut answer_pc).await?;
+
+    // wait for the initial data channel to go through the wire
+    let mut connected = false;
+    for _ in 0..100 {
+        let report = offer_pc.get_stats().await;
+        if let Some(StatsReportType::Transport(stats)) = report.get("ice_transport") {
+            if stats.bytes_sent > 0
+                && stats.bytes_received > 0
+                && !stats.selected_candidate_pair_id.is_empty()
+            {
+                connected = true;
+
+                assert!(report.get(&stats.selected_candidate_pair_id).is_some());
+                assert!(!report.get_by_type(StatsType::LocalCandidate).is_empty());
+                assert!(!report.get_by_type(StatsType::RemoteCandidate).is_empty());
+                assert_eq!(2, report.get_by_type(StatsType::Certificate).len());
+                break;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(connected, "transport never reported any traffic");
+
+    close_pair_now(&offer_pc, &answer_pc).await;
+
+    Ok(())
+}
@@ -30,3 +30,9 @@ pub fn flatten_errs(errs: Vec<anyhow::Error>) -> Result<()> {
         Err(Error::new(errs_strs.join("\n")).into())
     }
 }
+
+/// is_default reports whether a value equals its type's default, to leave
+/// unset fields out of serialized JSON
+pub(crate) fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
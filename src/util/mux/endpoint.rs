@@ -6,6 +6,7 @@ use async_trait::async_trait;
 use std::collections::HashMap;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -16,6 +17,7 @@ pub struct Endpoint {
     pub(crate) match_fn: MatchFunc,
     pub(crate) next_conn: Arc<dyn Conn + Send + Sync>,
     pub(crate) endpoints: Arc<Mutex<HashMap<usize, Arc<Endpoint>>>>,
+    pub(crate) bytes_sent: Arc<AtomicUsize>,
 }
 
 impl Endpoint {
@@ -50,7 +52,9 @@ impl Conn for Endpoint {
 
     /// writes bytes to the underlying conn
     async fn send(&self, buf: &[u8]) -> Result<usize> {
-        self.next_conn.send(buf).await
+        let n = self.next_conn.send(buf).await?;
+        self.bytes_sent.fetch_add(n, Ordering::SeqCst);
+        Ok(n)
     }
 
     async fn send_to(&self, _buf: &[u8], _target: SocketAddr) -> Result<usize> {
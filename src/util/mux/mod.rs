@@ -34,6 +34,8 @@ pub struct Mux {
     endpoints: Arc<Mutex<HashMap<usize, Arc<Endpoint>>>>,
     buffer_size: usize,
     closed_ch_tx: Option<mpsc::Sender<()>>,
+    bytes_sent: Arc<AtomicUsize>,
+    bytes_received: Arc<AtomicUsize>,
 }
 
 impl Mux {
@@ -45,13 +47,23 @@ impl Mux {
             endpoints: Arc::new(Mutex::new(HashMap::new())),
             buffer_size: config.buffer_size,
             closed_ch_tx: Some(closed_ch_tx),
+            bytes_sent: Arc::new(AtomicUsize::new(0)),
+            bytes_received: Arc::new(AtomicUsize::new(0)),
         };
 
         let buffer_size = m.buffer_size;
         let next_conn = Arc::clone(&m.next_conn);
         let endpoints = Arc::clone(&m.endpoints);
+        let bytes_received = Arc::clone(&m.bytes_received);
         tokio::spawn(async move {
-            Mux::read_loop(buffer_size, next_conn, closed_ch_rx, endpoints).await;
+            Mux::read_loop(
+                buffer_size,
+                next_conn,
+                closed_ch_rx,
+                endpoints,
+                bytes_received,
+            )
+            .await;
         });
 
         m
@@ -71,6 +83,7 @@ impl Mux {
             match_fn: f,
             next_conn: Arc::clone(&self.next_conn),
             endpoints: Arc::clone(&self.endpoints),
+            bytes_sent: Arc::clone(&self.bytes_sent),
         });
 
         endpoints.insert(e.id, Arc::clone(&e));
@@ -84,6 +97,16 @@ impl Mux {
         endpoints.remove(&e.id);
     }
 
+    /// bytes_sent returns the number of bytes written through all the Endpoints
+    pub fn bytes_sent(&self) -> usize {
+        self.bytes_sent.load(Ordering::SeqCst)
+    }
+
+    /// bytes_received returns the number of bytes read from the underlying conn
+    pub fn bytes_received(&self) -> usize {
+        self.bytes_received.load(Ordering::SeqCst)
+    }
+
     /// Close closes the Mux and all associated Endpoints.
     pub async fn close(&mut self) {
         self.closed_ch_tx.take();
@@ -97,6 +120,7 @@ impl Mux {
         next_conn: Arc<dyn Conn + Send + Sync>,
         mut closed_ch_rx: mpsc::Receiver<()>,
         endpoints: Arc<Mutex<HashMap<usize, Arc<Endpoint>>>>,
+        bytes_received: Arc<AtomicUsize>,
     ) {
         let mut buf = vec![0u8; buffer_size];
         let mut n = 0usize;
@@ -106,6 +130,7 @@ impl Mux {
                 result = next_conn.recv(&mut buf) => {
                     if let Ok(m) = result{
                         n = m;
+                        bytes_received.fetch_add(n, Ordering::SeqCst);
                     }
                 }
             };